@@ -0,0 +1,59 @@
+//! Sprite assembly on a 5,000-icon set, through the in-memory API and a
+//! directory build. Run with `cargo bench --bench sprite_assembly`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ICONS: usize = 5_000;
+const RUNS: usize = 10;
+
+/// A small icon with the usual mix of root attributes and path data.
+fn icon(n: usize) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\" fill=\"none\" stroke=\"currentColor\"><path d=\"M{n} 2l10 10-10 10z\"/><circle cx=\"12\" cy=\"12\" r=\"{}\"/></svg>",
+        n % 10 + 1
+    )
+}
+
+/// Median wall time of `RUNS` calls to `f`.
+fn median(mut f: impl FnMut()) -> Duration {
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let started = Instant::now();
+            f();
+            started.elapsed()
+        })
+        .collect();
+    times.sort();
+    times[RUNS / 2]
+}
+
+fn main() {
+    let sources: Vec<(String, String)> =
+        (0..ICONS).map(|n| (format!("icon-{n}"), icon(n))).collect();
+    let in_memory = median(|| {
+        black_box(svg_sheet::svg::build_from_sources(sources.clone()).unwrap());
+    });
+    println!("build_from_sources, {ICONS} icons: {in_memory:?}");
+
+    let dir = std::env::temp_dir().join("svg_sheet_bench_assembly");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, content) in &sources {
+        std::fs::write(dir.join(format!("{name}.svg")), content).unwrap();
+    }
+    let out = dir.join("sprite.svg");
+    let directory = median(|| {
+        let opts = svg_sheet::svg::RunOpts {
+            dry_run: true,
+            quiet: true,
+            ..Default::default()
+        };
+        black_box(
+            svg_sheet::svg::process_with_opts(dir.to_str().unwrap(), out.to_str().unwrap(), opts)
+                .unwrap(),
+        );
+    });
+    println!("process_with_opts, {ICONS} icons: {directory:?}");
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,228 @@
+//! Sprite generation from a Cargo build script (`build.rs`).
+//!
+//! `generate` builds the sprite into `OUT_DIR` next to a Rust module holding
+//! the sprite and one constant per icon id, and tells Cargo to rerun the
+//! script when an input changes:
+//!
+//! ```no_run
+//! // build.rs: build `svgs/` into `OUT_DIR/sprite.svg` and `OUT_DIR/icons.rs`
+//! svg_sheet::build_script::generate(Default::default()).unwrap();
+//! ```
+//!
+//! ```ignore
+//! // src/icons.rs
+//! include!(concat!(env!("OUT_DIR"), "/icons.rs"));
+//! ```
+
+use crate::error::AppError;
+use crate::svg::{self, RunOpts, summary::BuildSummary};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// What to build and where to put it inside `OUT_DIR`.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Input directory, relative to the crate root (`CARGO_MANIFEST_DIR`)
+    pub directory: PathBuf,
+    /// File name of the sprite written into `OUT_DIR`
+    pub sprite: String,
+    /// File name of the generated Rust module written into `OUT_DIR`
+    pub module: String,
+    /// Build options, as for the command line
+    pub run: RunOpts,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            directory: PathBuf::from("svgs"),
+            sprite: "sprite.svg".to_string(),
+            module: "icons.rs".to_string(),
+            run: RunOpts::default(),
+        }
+    }
+}
+
+/// Paths of the files `generate` wrote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generated {
+    pub sprite: PathBuf,
+    pub module: PathBuf,
+}
+
+/// Build the sprite and its constants module into `OUT_DIR` and print
+/// `cargo:rerun-if-changed` lines for the input directory, every input, and
+/// any `--input-iconify` collection or template. Call it from `build.rs`.
+pub fn generate(opts: Options) -> Result<Generated, AppError> {
+    let out_dir = std::env::var_os("OUT_DIR").ok_or_else(|| AppError::WriteFile {
+        path: "OUT_DIR".into(),
+        source: std::io::Error::other("not set; call generate from a build script"),
+    })?;
+    let crate_dir = std::env::var_os("CARGO_MANIFEST_DIR").map_or_else(PathBuf::new, PathBuf::from);
+    let mut stdout = std::io::stdout().lock();
+    generate_in(opts, Path::new(&out_dir), &crate_dir, &mut stdout)
+}
+
+/// `generate` with explicit directories, writing Cargo directives to `cargo`.
+fn generate_in(
+    opts: Options,
+    out_dir: &Path,
+    crate_dir: &Path,
+    cargo: &mut impl Write,
+) -> Result<Generated, AppError> {
+    let directory = crate_dir.join(&opts.directory);
+    let sprite = out_dir.join(&opts.sprite);
+    let module = out_dir.join(&opts.module);
+    let run = RunOpts {
+        quiet: true,
+        ..opts.run
+    };
+    let built = svg::build(
+        &directory.display().to_string(),
+        &sprite.display().to_string(),
+        &run,
+    )?;
+
+    let mut rerun: Vec<PathBuf> = vec![directory];
+    rerun.extend(
+        built
+            .files
+            .iter()
+            .map(|f| PathBuf::from(&f.path))
+            .filter(|p| p.is_file()),
+    );
+    rerun.extend(run.iconify.iter().map(|p| crate_dir.join(p)));
+    rerun.extend(run.template.iter().map(|p| crate_dir.join(p)));
+    let to_err = |e| AppError::WriteFile {
+        path: "stdout".into(),
+        source: e,
+    };
+    for path in rerun {
+        writeln!(cargo, "cargo:rerun-if-changed={}", path.display()).map_err(to_err)?;
+    }
+
+    let source = module_source(&built, &opts.sprite);
+    if std::fs::read_to_string(&module).ok().as_deref() != Some(source.as_str()) {
+        std::fs::write(&module, source).map_err(|e| AppError::WriteFile {
+            path: module.display().to_string(),
+            source: e,
+        })?;
+    }
+    Ok(Generated { sprite, module })
+}
+
+/// Rust source exposing the sprite (`SPRITE`), every id (`ICONS`), and one
+/// constant per id.
+fn module_source(built: &BuildSummary, sprite_file: &str) -> String {
+    let mut out = String::from("// Generated by svg_sheet. Do not edit.\n\n");
+    out.push_str("/// The sprite sheet markup.\n");
+    out.push_str(&format!(
+        "pub const SPRITE: &str = include_str!(concat!(env!(\"OUT_DIR\"), \"/{}\"));\n\n",
+        sprite_file.escape_default()
+    ));
+    out.push_str("/// Every icon id in the sprite, in output order.\n");
+    out.push_str("pub const ICONS: &[&str] = &[\n");
+    for f in &built.files {
+        out.push_str(&format!("    \"{}\",\n", f.name.escape_default()));
+    }
+    out.push_str("];\n");
+    let mut taken = HashSet::new();
+    for f in &built.files {
+        let name = const_name(&f.name, &mut taken);
+        out.push_str(&format!(
+            "\n/// Id of the `{0}` icon.\npub const {name}: &str = \"{0}\";\n",
+            f.name.escape_default()
+        ));
+    }
+    out
+}
+
+/// SCREAMING_SNAKE_CASE constant name for an icon id, made unique among
+/// `taken` (and the module's own constants) with a numeric suffix.
+fn const_name(id: &str, taken: &mut HashSet<String>) -> String {
+    let snake = svg::naming::apply_case(id, svg::naming::IdCase::Snake);
+    let mut base: String = snake
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !base.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        base = format!("ICON_{base}");
+    }
+    let mut name = base.clone();
+    let mut n = 2;
+    while ["SPRITE", "ICONS"].contains(&name.as_str()) || !taken.insert(name.clone()) {
+        name = format!("{base}_{n}");
+        n += 1;
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn generates_sprite_module_and_rerun_lines() {
+        let root = std::env::temp_dir().join("svg_build_script");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("icons")).unwrap();
+        fs::create_dir_all(root.join("out")).unwrap();
+        for name in ["arrow-left", "1st", "sprite"] {
+            fs::write(
+                root.join(format!("icons/{name}.svg")),
+                "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>",
+            )
+            .unwrap();
+        }
+        let opts = Options {
+            directory: "icons".into(),
+            ..Default::default()
+        };
+        let mut cargo = Vec::new();
+        let generated = generate_in(opts, &root.join("out"), &root, &mut cargo).unwrap();
+
+        assert!(
+            fs::read_to_string(&generated.sprite)
+                .unwrap()
+                .contains("<pattern id=\"arrow-left\"")
+        );
+        let module = fs::read_to_string(&generated.module).unwrap();
+        assert!(module.contains(
+            "pub const SPRITE: &str = include_str!(concat!(env!(\"OUT_DIR\"), \"/sprite.svg\"));"
+        ));
+        assert!(module.contains(
+            "pub const ICONS: &[&str] = &[\n    \"1st\",\n    \"arrow-left\",\n    \"sprite\",\n];"
+        ));
+        assert!(module.contains("pub const ARROW_LEFT: &str = \"arrow-left\";"));
+        assert!(module.contains("pub const ICON_1ST: &str = \"1st\";"));
+        assert!(module.contains("pub const SPRITE_2: &str = \"sprite\";"));
+
+        let cargo = String::from_utf8(cargo).unwrap();
+        assert!(cargo.starts_with(&format!(
+            "cargo:rerun-if-changed={}\n",
+            root.join("icons").display()
+        )));
+        assert!(cargo.contains(&format!(
+            "cargo:rerun-if-changed={}\n",
+            root.join("icons/1st.svg").display()
+        )));
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn const_names_are_unique_identifiers() {
+        let mut taken = HashSet::new();
+        assert_eq!(const_name("arrow-left", &mut taken), "ARROW_LEFT");
+        assert_eq!(const_name("arrow_left", &mut taken), "ARROW_LEFT_2");
+        assert_eq!(const_name("icons", &mut taken), "ICONS_2");
+        assert_eq!(const_name("social.x", &mut taken), "SOCIAL_X");
+    }
+}
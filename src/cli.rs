@@ -1,3 +1,4 @@
+use clap::builder::BoolishValueParser;
 use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum, value_parser};
 use std::path::PathBuf;
 
@@ -8,11 +9,18 @@ pub enum Shell {
     Fish,
     Pwsh,
     Elvish,
+    Nushell,
+    /// Fig (Amazon Q) completion spec, written as svg_sheet.ts
+    Fig,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum Commands {
-    Watch,
+    Watch {
+        /// Print machine-readable progress events on stdout
+        #[arg(long, value_enum)]
+        events: Option<EventFormat>,
+    },
     Build,
     /// Generate shell completions for a given shell
     Completions {
@@ -22,6 +30,31 @@ pub enum Commands {
         #[arg(short, long)]
         out_dir: Option<PathBuf>,
     },
+    /// List icons that no file under --src references
+    Unused {
+        /// Source directory to scan for icon references; repeatable
+        #[arg(long, required = true, value_name = "DIR")]
+        src: Vec<PathBuf>,
+        /// Exit non-zero when any icon is unused
+        #[arg(long, action = ArgAction::SetTrue)]
+        fail: bool,
+    },
+    /// Report references under --src to icons the sprite does not contain
+    Missing {
+        /// Source directory to scan for `<sprite>#<id>` references; repeatable
+        #[arg(long, required = true, value_name = "DIR")]
+        src: Vec<PathBuf>,
+    },
+    /// Download icons from a remote source into the input directory
+    Fetch {
+        #[command(subcommand)]
+        source: FetchSource,
+    },
+    /// Inspect the config file format
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
     /// Generate a man page
     Man {
         /// Output directory to write the man page
@@ -30,6 +63,97 @@ pub enum Commands {
     },
 }
 
+#[derive(Debug, Subcommand)]
+pub enum FetchSource {
+    /// Export every component of a Figma file as SVG
+    Figma {
+        /// Key of the Figma file (the id in its URL after /file/ or /design/)
+        #[arg(long)]
+        file_key: String,
+        /// Figma personal access token
+        #[arg(long, env = "FIGMA_TOKEN", hide_env_values = true)]
+        token: String,
+    },
+    /// Download the SVGs listed in a file, one `[name] URL [sha256:HEX]` per line;
+    /// downloads are cached under --cache-dir when it is set
+    Urls {
+        /// File listing the icons to download
+        list: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print a JSON Schema for svg_sheet.toml, for editor validation and completion
+    Schema,
+}
+
+/// Casing applied to filename-derived pattern ids
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum IdCase {
+    /// Keep the filename stem unchanged
+    #[default]
+    Keep,
+    /// arrow-left
+    Kebab,
+    /// arrowLeft
+    Camel,
+    /// arrow_left
+    Snake,
+}
+
+/// Handling of `<image>` elements embedding raster data
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum RasterImages {
+    /// Keep them silently
+    Allow,
+    /// Keep them and warn with their type and size
+    #[default]
+    Warn,
+    /// Remove them
+    Strip,
+}
+
+/// Handling of percentage width/height on icon roots
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum PercentDims {
+    /// Drop them and rely on the viewBox
+    Strip,
+    /// Keep them as percentages
+    Keep,
+    /// Fail the build
+    #[default]
+    Error,
+}
+
+/// How `--chunk-by` assigns icons to sprite sheets
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ChunkBy {
+    /// One sheet per top-level subdirectory of the input directory
+    Dir,
+    /// Sheets of at most --chunk-size bytes, filled in input order
+    Size,
+}
+
+/// Handling of the root `<svg id>` of each input
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum RootId {
+    /// Keep it as an id on a <g> wrapping the icon content
+    Keep,
+    /// Move it to a data-id attribute
+    #[default]
+    DataId,
+    /// Remove it
+    Drop,
+}
+
+/// Format of watch-mode progress events
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum EventFormat {
+    /// One JSON object per line
+    Json,
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
 pub enum LogLevel {
     Error,
@@ -39,43 +163,377 @@ pub enum LogLevel {
     Trace,
 }
 
+/// Format of log lines written to stderr
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum LogFormat {
+    /// Multi-line, human-readable records
+    Pretty,
+    /// One human-readable line per record
+    Compact,
+    /// One JSON object per line, for log aggregation
+    Json,
+}
+
 #[derive(Debug, Parser)]
 #[clap(author, version, about, long_about = None)]
 pub struct Args {
-    #[arg(short, long, default_value = "sprite.svg")]
+    /// Output sprite path, or `-` for stdout
+    #[arg(short, long, default_value = "sprite.svg", env = "SVG_SHEET_FILE")]
     pub file: String,
-    #[arg(short, long, default_value = "svgs")]
+    /// Input directory, or `-` to read SVG content from stdin
+    #[arg(short, long, default_value = "svgs", env = "SVG_SHEET_DIRECTORY")]
     pub directory: String,
+    /// Read one SVG (or several concatenated) from stdin instead of a directory
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub stdin: bool,
+    /// Build only the SVG files listed in FILE, one path per line (`-` for
+    /// stdin, e.g. from `git diff --name-only`), instead of the whole
+    /// directory
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "stdin",
+        env = "SVG_SHEET_FILES_FROM"
+    )]
+    pub files_from: Option<String>,
+    /// Also build the icons of an Iconify JSON collection (e.g. mdi.json);
+    /// repeatable. The directory may then be absent
+    #[arg(long, value_name = "FILE")]
+    pub input_iconify: Vec<PathBuf>,
+    /// Also build the SVGs of an installed npm package directory, found in
+    /// node_modules (e.g. heroicons/24/outline); ids are prefixed with the
+    /// package name unless =PREFIX is given. Repeatable
+    #[arg(long, value_name = "PACKAGE[/DIR][=PREFIX]")]
+    pub from_package: Vec<crate::svg::packages::PackageSource>,
+    /// Also build the SVGs in DIR, replacing icons of the same id from the
+    /// input directory and earlier inputs (e.g. local overrides of vendor
+    /// icons). Repeatable; later directories win
+    #[arg(long, value_name = "DIR")]
+    pub overlay: Vec<PathBuf>,
 
     /// Use filesystem polling instead of event-based watching
-    #[arg(long, action = ArgAction::SetTrue)]
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_POLL")]
     pub poll: bool,
-    /// Debounce interval in milliseconds for event-based watch
-    #[arg(long, default_value_t = 300, value_parser = value_parser!(u64))]
+    /// Delay in milliseconds between directory scans with --poll
+    #[arg(long, value_name = "N", default_value_t = 500, value_parser = value_parser!(u64).range(1..), env = "SVG_SHEET_POLL_INTERVAL_MS")]
+    pub poll_interval_ms: u64,
+    /// Rebuild a running watcher whenever FILE is created or touched, as
+    /// SIGUSR1 does on Unix
+    #[arg(long, value_name = "FILE", env = "SVG_SHEET_REBUILD_TRIGGER")]
+    pub rebuild_trigger: Option<PathBuf>,
+    /// Quiet period in milliseconds after the last change before an
+    /// event-based watch rebuilds; bursts of changes become one rebuild
+    #[arg(long, default_value_t = 300, value_parser = value_parser!(u64), env = "SVG_SHEET_DEBOUNCE_MS")]
     pub debounce_ms: u64,
+    /// Parse inputs on N threads [default: one per logical CPU]
+    #[arg(long, value_name = "N", env = "SVG_SHEET_JOBS")]
+    pub jobs: Option<std::num::NonZeroUsize>,
 
     /// Suppress non-error output
-    #[arg(long, action = ArgAction::SetTrue)]
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_QUIET")]
     pub quiet: bool,
     /// Increase verbosity (info-level messages)
-    #[arg(long, action = ArgAction::SetTrue)]
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_VERBOSE")]
     pub verbose: bool,
     /// Parse/validate but do not write output files
-    #[arg(long, action = ArgAction::SetTrue)]
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_DRY_RUN")]
     pub dry_run: bool,
+    /// Print a unified diff of each output against what would be written;
+    /// implies --dry-run
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_DIFF")]
+    pub diff: bool,
+    /// Compare the sprite with an earlier one and list the icons added,
+    /// removed, or changed in the build summary
+    #[arg(long, value_name = "FILE", env = "SVG_SHEET_BASELINE")]
+    pub baseline: Option<PathBuf>,
     /// Treat warnings as errors
-    #[arg(long, action = ArgAction::SetTrue)]
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_FAIL_ON_WARN")]
     pub fail_on_warn: bool,
+    /// Strictest checks: every warning fails (ignoring --allow and the
+    /// baseline), and unknown root attributes or ids that would be sanitized
+    /// are errors
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_STRICT")]
+    pub strict: bool,
+    /// Don't report warnings of this rule (name or code, e.g. missing-viewbox);
+    /// repeatable
+    #[arg(long, value_name = "RULE")]
+    pub allow: Vec<crate::error::WarningKind>,
+    /// Fail the build on warnings of this rule even without --fail-on-warn;
+    /// repeatable, and wins over --allow
+    #[arg(long, value_name = "RULE")]
+    pub deny: Vec<crate::error::WarningKind>,
+    /// Directory for a persistent build cache reused across runs
+    #[arg(long, value_name = "DIR", env = "SVG_SHEET_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// Also honor .gitignore files when selecting inputs
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_GITIGNORE")]
+    pub gitignore: bool,
+    /// Follow symlinked inputs (broken links and loops are skipped with a warning)
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_FOLLOW_SYMLINKS")]
+    pub follow_symlinks: bool,
+    /// Only include these icons; repeatable, or a file with one name per line
+    #[arg(long, value_name = "NAME|FILE")]
+    pub only: Vec<String>,
+    /// Casing applied to filename-derived ids (e.g. ArrowLeft.svg -> arrow-left)
+    #[arg(long, value_enum, default_value_t = IdCase::Keep, env = "SVG_SHEET_ID_CASE")]
+    pub id_case: IdCase,
+    /// Include SVGs in subdirectories, prefixing ids with the directory names
+    #[arg(short, long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_RECURSIVE")]
+    pub recursive: bool,
+    /// Separator between subdirectory names and the filename in ids
+    #[arg(
+        long,
+        default_value = "-",
+        value_name = "SEP",
+        env = "SVG_SHEET_ID_SEPARATOR"
+    )]
+    pub id_separator: String,
+    /// What to do with each input's root <svg id>
+    #[arg(long, value_enum, default_value_t = RootId::DataId, env = "SVG_SHEET_ROOT_ID")]
+    pub root_id: RootId,
+    /// Set this preserveAspectRatio on every icon (e.g. "xMidYMid meet")
+    #[arg(long, value_name = "VALUE", value_parser = parse_preserve_aspect_ratio)]
+    pub force_preserve_aspect_ratio: Option<String>,
+    /// Set an attribute on the sprite's root <svg> (e.g. class=svg-sprite);
+    /// repeatable, and overrides the config's [root] table
+    #[arg(long, value_name = "NAME=VALUE", value_parser = parse_root_attr)]
+    pub root_attr: Vec<(String, String)>,
+    /// What to do with width="100%"-style dimensions on icon roots
+    #[arg(long, value_enum, default_value_t = PercentDims::Error, env = "SVG_SHEET_PERCENT_DIMS")]
+    pub percent_dims: PercentDims,
+    /// Pixels per em/rem when converting em and rem dimensions on icon roots
+    #[arg(long, value_name = "PX", default_value_t = 16.0, value_parser = parse_em_size, env = "SVG_SHEET_EM_SIZE")]
+    pub em_size: f64,
+    /// Decimal places kept in icon width/height and viewBox numbers
+    #[arg(long, value_name = "DIGITS", default_value_t = 4, value_parser = value_parser!(u8).range(0..=12), env = "SVG_SHEET_PRECISION")]
+    pub precision: u8,
+    /// Also write an artifact from the same build; repeatable.
+    /// KIND is one of: sprite, manifest, preview, ts, assets-manifest, tailwind,
+    /// storybook, iconify, react-native, search-index, markdown,
+    /// esm, icons-json, inline-html
+    #[arg(long, value_name = "KIND=PATH")]
+    pub emit: Vec<crate::svg::emit::EmitTarget>,
+    /// Also write a Markdown gallery of the icons (same as --emit markdown=FILE)
+    #[arg(long, value_name = "FILE", env = "SVG_SHEET_MARKDOWN_OUT")]
+    pub markdown_out: Option<String>,
+    /// Also write a JSON object of each icon's standalone SVG (same as
+    /// --emit icons-json=FILE)
+    #[arg(long, value_name = "FILE", env = "SVG_SHEET_JSON_ICONS")]
+    pub json_icons: Option<String>,
+    /// Render the sprite with this Tera template instead of the built-in markup
+    #[arg(long, value_name = "FILE", env = "SVG_SHEET_TEMPLATE")]
+    pub template: Option<PathBuf>,
+    /// Synonym groups for the search-index artifact, one comma-separated
+    /// group per line
+    #[arg(long, value_name = "FILE", env = "SVG_SHEET_SYNONYMS")]
+    pub synonyms: Option<PathBuf>,
+    /// JSON file mapping icon ids to titles and descriptions, added as
+    /// `<title>`/`<desc>` and listed in the manifest
+    #[arg(long, value_name = "FILE", env = "SVG_SHEET_TITLES")]
+    pub titles: Option<PathBuf>,
+    /// Precede each icon with a comment naming its source file and content hash
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_SOURCE_COMMENTS")]
+    pub source_comments: bool,
+    /// Stamp each icon with data-hash="<short sha>" of its emitted content,
+    /// so caches and visual-regression tools can tell which icons changed
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_HASH_ATTRS")]
+    pub hash_attrs: bool,
+    /// Start the sprite with a comment naming the generator, its version, and
+    /// the build time (SOURCE_DATE_EPOCH when set)
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_GENERATOR_COMMENT")]
+    pub generator_comment: bool,
+    /// Write only the <defs> element, without the outer <svg>, for splicing
+    /// into an inline <svg> in a page layout
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), conflicts_with_all = ["template", "chunk_by"], env = "SVG_SHEET_FRAGMENT")]
+    pub fragment: bool,
+    /// Leave the build time out of the generator comment
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_NO_TIMESTAMP")]
+    pub no_timestamp: bool,
+    /// Keep license/copyright comments from inputs in one block atop the sprite
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_KEEP_LICENSE_COMMENTS")]
+    pub keep_license_comments: bool,
+    /// Emit byte-identical icons once and alias the copies to the first
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_DEDUPE")]
+    pub dedupe: bool,
+    /// Warn about icons that are identical after normalizing whitespace,
+    /// numeric precision and attribute order
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_DETECT_DUPLICATES")]
+    pub detect_duplicates: bool,
+    /// Verify the output is up to date instead of writing it; exits non-zero
+    /// with a summary of the differences otherwise
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_CHECK")]
+    pub check: bool,
+    /// Copy an output to PATTERN before overwriting it; `{file}` is the
+    /// output's file name [default when given without a value: {file}.bak]
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "{file}.bak",
+        env = "SVG_SHEET_BACKUP"
+    )]
+    pub backup: Option<String>,
+    /// Also copy every sprite written into DIR under a timestamped, hashed name
+    #[arg(long, value_name = "DIR", env = "SVG_SHEET_ARCHIVE_DIR")]
+    pub archive_dir: Option<PathBuf>,
+    /// Record each icon's content hash in a lockfile beside the sprite
+    /// (sprite.lock) and fail when an icon's content no longer matches it
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_LOCK")]
+    pub lock: bool,
+    /// Accept changed icons into the lockfile (implies --lock)
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_UPDATE_LOCK")]
+    pub update_lock: bool,
+    /// Reject input files larger than SIZE (bytes, or with a K/M/G suffix)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, env = "SVG_SHEET_MAX_FILE_SIZE")]
+    pub max_file_size: Option<u64>,
+    /// Reject icons whose emitted markup is larger than SIZE (bytes, or K/M/G)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, env = "SVG_SHEET_MAX_ICON_BYTES")]
+    pub max_icon_bytes: Option<u64>,
+    /// Warn (or fail with --fail-on-warn) about each icon larger than SIZE,
+    /// breaking down its path data, embedded images, and definitions
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, env = "SVG_SHEET_MAX_ICON_SIZE")]
+    pub max_icon_size: Option<u64>,
+    /// Warn (or fail with --fail-on-warn) when the sprite is larger than SIZE,
+    /// listing the largest icons
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, env = "SVG_SHEET_MAX_OUTPUT_SIZE")]
+    pub max_output_size: Option<u64>,
+    /// Handling of <image> elements embedding raster data (data: URIs)
+    #[arg(long, value_enum, default_value_t = RasterImages::Warn, env = "SVG_SHEET_RASTER_IMAGES")]
+    pub raster_images: RasterImages,
+    /// Apply --raster-images only to embedded images of at least SIZE
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "0", env = "SVG_SHEET_RASTER_THRESHOLD")]
+    pub raster_threshold: u64,
+    /// Apply translate/scale/matrix transforms on paths and groups to the
+    /// path coordinates and drop the attributes (changes markup structure)
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_FLATTEN_TRANSFORMS")]
+    pub flatten_transforms: bool,
+    /// Rewrite path data in its shortest equivalent form: relative commands
+    /// where shorter, H/V for axis-aligned lines, implicit repeated commands
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_COMPACT_PATHS")]
+    pub compact_paths: bool,
+    /// Hoist gradients and filters into the sprite's root <defs>, emitting
+    /// structurally identical definitions once
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_SHARED_DEFS")]
+    pub shared_defs: bool,
+    /// Rewrite `xlink:href` as SVG 2 `href`, dropping the xlink namespace
+    /// once nothing else uses it
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_PLAIN_HREF")]
+    pub plain_href: bool,
+    /// Drop comments, <metadata>, empty groups and indentation from icon
+    /// markup; license comments (<!--! ... -->) and text are kept
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_OPTIMIZE")]
+    pub optimize: bool,
+    /// Run the WebAssembly transform in FILE on every icon after the
+    /// built-in passes (see Plugins in the README). Repeatable; plugins run
+    /// in order
+    #[cfg(feature = "plugins")]
+    #[arg(long, value_name = "FILE")]
+    pub plugin: Vec<PathBuf>,
+    /// Rewrite colors to var(--icon-<role>, <original>) so the sprite can be
+    /// themed with CSS variables; roles come from the config's [roles] table
+    #[arg(long, action = ArgAction::SetTrue, value_parser = BoolishValueParser::new(), env = "SVG_SHEET_THEMABLE")]
+    pub themable: bool,
+    /// Split the sprite into several sheets (sprite-<chunk>.svg) plus a
+    /// sprite.chunks.json manifest mapping each icon to its sheet
+    #[arg(long, value_enum, value_name = "STRATEGY", env = "SVG_SHEET_CHUNK_BY")]
+    pub chunk_by: Option<ChunkBy>,
+    /// Maximum size of each sheet under --chunk-by size
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "100K", env = "SVG_SHEET_CHUNK_SIZE")]
+    pub chunk_size: u64,
+
+    /// Config file to read instead of ./svg_sheet.toml
+    #[arg(long, value_name = "FILE", env = "SVG_SHEET_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Apply the config file's [profile.NAME] switches (e.g. dev, prod)
+    #[arg(long, value_name = "NAME", env = "SVG_SHEET_PROFILE")]
+    pub profile: Option<String>,
 
     /// Global log level when RUST_LOG is not set
     /// One of: error, warn, info, debug, trace
-    #[arg(long, value_enum)]
+    #[arg(long, value_enum, env = "SVG_SHEET_LOG")]
     pub log_level: Option<LogLevel>,
+    /// Log line format; pretty when stderr is a terminal, json otherwise
+    #[arg(long, value_enum, env = "SVG_SHEET_LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
 
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+/// Validate and normalize a `--force-preserve-aspect-ratio` value.
+fn parse_preserve_aspect_ratio(v: &str) -> Result<String, String> {
+    crate::svg::normalize::normalize_preserve_aspect_ratio(v)
+        .ok_or_else(|| "expected '<align> [meet|slice]' or 'none'".to_string())
+}
+
+/// Parse an `--em-size` value: a positive number of pixels.
+fn parse_em_size(v: &str) -> Result<f64, String> {
+    v.trim()
+        .trim_end_matches("px")
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite() && *n > 0.0)
+        .ok_or_else(|| format!("invalid size '{v}'; expected a positive number of pixels"))
+}
+
+/// Parse a `--root-attr` value: `NAME=VALUE`.
+fn parse_root_attr(v: &str) -> Result<(String, String), String> {
+    let (name, value) = v
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{v}'"))?;
+    let name = name.trim();
+    check_root_attr(name)?;
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Check that `name` can be set on the sprite's root: an XML attribute name,
+/// other than the `xmlns` every sprite declares.
+///
+/// Example:
+/// ```
+/// use svg_sheet::cli::check_root_attr;
+/// assert!(check_root_attr("xmlns:xlink").is_ok());
+/// assert!(check_root_attr("xmlns").is_err());
+/// assert!(check_root_attr("data x").is_err());
+/// ```
+pub fn check_root_attr(name: &str) -> Result<(), String> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    if !valid {
+        return Err(format!("invalid attribute name '{name}'"));
+    }
+    if name == "xmlns" {
+        return Err("'xmlns' is always set on the sprite's root".to_string());
+    }
+    Ok(())
+}
+
+/// Parse a byte size such as `4096`, `512K`, `2M`, or `1G` (binary units).
+fn parse_size(v: &str) -> Result<u64, String> {
+    let v = v.trim();
+    let lower = v.to_ascii_lowercase();
+    let digits = lower.trim_end_matches(['b', 'i']);
+    let (digits, unit) = match digits.chars().last() {
+        Some('k') => (&digits[..digits.len() - 1], 1u64 << 10),
+        Some('m') => (&digits[..digits.len() - 1], 1 << 20),
+        Some('g') => (&digits[..digits.len() - 1], 1 << 30),
+        _ => (digits, 1),
+    };
+    digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .ok_or_else(|| format!("invalid size '{v}'; expected bytes or a K/M/G suffix"))
+}
+
 /// Parse CLI arguments from the process arguments using clap.
 ///
 /// Example (constructing `Args` directly for testing):
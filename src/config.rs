@@ -0,0 +1,246 @@
+//! Project configuration file (`svg_sheet.toml`).
+//!
+//! Settings that are awkward as flags (tables, long lists) live here. The file
+//! is read from the working directory unless `--config` names another one.
+
+use crate::error::AppError;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// File name looked up in the working directory when `--config` is not given.
+pub const CONFIG_FILE: &str = "svg_sheet.toml";
+
+/// Parsed contents of the config file.
+///
+/// Example:
+/// ```
+/// let config = svg_sheet::config::Config::parse(
+///     "emit = [\"ts=src/icons.ts\"]\n[colors]\n\"#1a1a1a\" = \"currentColor\"\n",
+/// )
+/// .unwrap();
+/// assert_eq!(config.emit, vec!["ts=src/icons.ts".to_string()]);
+/// assert_eq!(config.colors["#1a1a1a"], "currentColor");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+#[schemars(
+    title = "svg_sheet.toml",
+    description = "Project configuration for svg_sheet"
+)]
+pub struct Config {
+    /// Extra artifacts as `kind=path`, added to any `--emit` flags
+    pub emit: Vec<String>,
+    /// Passes run on each icon, in order: `normalize-dims`,
+    /// `strip-attributes`, `plain-href`, `raster-images`, `colors`,
+    /// `themable`, `flatten-transforms`, `compact-paths`, `optimize`,
+    /// `shared-defs`, `plugins`, `scope-ids`, `a11y`. Stages left out are
+    /// skipped; listed ones run without their flag
+    pub pipeline: Option<Vec<String>>,
+    /// Attributes dropped from every icon's root and children
+    pub attributes: AttributesConfig,
+    /// Color replacements applied to fills, strokes and stop colors
+    pub colors: BTreeMap<String, String>,
+    /// Theme roles for `--themable`: role name to the colors that play it
+    pub roles: BTreeMap<String, Vec<String>>,
+    /// Per-rule warning levels and the tolerated warning count
+    pub warnings: WarningsConfig,
+    /// Extra ids pointing at an icon (alias to icon id), so renamed icons
+    /// keep their old ids
+    pub aliases: BTreeMap<String, String>,
+    /// Deprecated icons, with a message such as the replacement to use
+    /// (may be empty)
+    pub deprecated: BTreeMap<String, String>,
+    /// Tags for each icon, listed in the manifest and preview
+    pub tags: BTreeMap<String, Vec<String>>,
+    /// Attributes set on the sprite's root `<svg>`, such as `class`
+    pub root: BTreeMap<String, String>,
+    /// Named sets of build switches, selected with `--profile`
+    pub profile: BTreeMap<String, Profile>,
+}
+
+/// A `[profile.<name>]` table: switches turned on, on top of the command-line
+/// flags, when `--profile <name>` selects it.
+///
+/// Example:
+/// ```
+/// let config = svg_sheet::config::Config::parse(
+///     "[profile.prod]\nstrict = true\nemit = [\"assets-manifest=dist/assets.json\"]\n",
+/// )
+/// .unwrap();
+/// let prod = &config.profile["prod"];
+/// assert!(prod.strict && !prod.source_comments);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Profile {
+    /// Extra artifacts as `kind=path`, added to the top-level `emit`
+    pub emit: Vec<String>,
+    /// Replaces the top-level `pipeline`
+    pub pipeline: Option<Vec<String>>,
+    /// `--source-comments`
+    pub source_comments: bool,
+    /// `--hash-attrs`
+    pub hash_attrs: bool,
+    /// `--keep-license-comments`
+    pub keep_license_comments: bool,
+    /// `--dedupe`
+    pub dedupe: bool,
+    /// `--detect-duplicates`
+    pub detect_duplicates: bool,
+    /// `--compact-paths`
+    pub compact_paths: bool,
+    /// `--shared-defs`
+    pub shared_defs: bool,
+    /// `--plain-href`
+    pub plain_href: bool,
+    /// `--optimize`
+    pub optimize: bool,
+    /// `--flatten-transforms`
+    pub flatten_transforms: bool,
+    /// `--fail-on-warn`
+    pub fail_on_warn: bool,
+    /// `--strict`
+    pub strict: bool,
+    /// `--lock`
+    pub lock: bool,
+}
+
+/// The `[warnings]` table: a level for each rule, keyed by name or code, plus
+/// an optional `baseline`.
+///
+/// Example:
+/// ```
+/// use svg_sheet::config::{Config, WarningLevel};
+/// let config = Config::parse(
+///     "[warnings]\nbaseline = 12\nmissing-viewbox = \"allow\"\nW0006 = \"error\"\n",
+/// )
+/// .unwrap();
+/// assert_eq!(config.warnings.baseline, Some(12));
+/// assert_eq!(config.warnings.rules["W0006"], WarningLevel::Error);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct WarningsConfig {
+    /// Number of failing warnings tolerated before the build fails, so legacy
+    /// icon sets can adopt `--fail-on-warn` and `error` rules incrementally
+    pub baseline: Option<usize>,
+    /// Level of each warning rule, by name (`missing-viewbox`) or code (`W0004`)
+    #[serde(flatten)]
+    pub rules: BTreeMap<String, WarningLevel>,
+}
+
+/// The `[attributes]` table: attribute names or patterns, where `*` matches
+/// any run of characters, to drop from every icon.
+///
+/// Example:
+/// ```
+/// let config = svg_sheet::config::Config::parse(
+///     "[attributes]\nstrip = [\"data-figma-*\", \"xml:space\"]\n",
+/// )
+/// .unwrap();
+/// assert_eq!(config.attributes.strip, ["data-figma-*", "xml:space"]);
+/// assert!(config.attributes.keep.is_empty());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttributesConfig {
+    /// Attributes removed wherever they appear
+    pub strip: Vec<String>,
+    /// When not empty, the only attributes kept; all others are removed
+    pub keep: Vec<String>,
+}
+
+/// How a warning rule is treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WarningLevel {
+    /// Not reported (`--allow`)
+    Allow,
+    /// Reported; fails the build only under `--fail-on-warn`
+    Warn,
+    /// Fails the build (`--deny`)
+    Error,
+}
+
+impl Config {
+    /// Parse config text; the error message is the TOML parser's.
+    pub fn parse(text: &str) -> Result<Config, String> {
+        toml::from_str(text).map_err(|e| e.message().to_string())
+    }
+
+    /// JSON Schema of the config file, derived from this struct.
+    ///
+    /// Example:
+    /// ```
+    /// let schema: serde_json::Value =
+    ///     serde_json::from_str(&svg_sheet::config::Config::schema()).unwrap();
+    /// assert_eq!(schema["additionalProperties"], false);
+    /// assert!(schema["properties"]["colors"].is_object());
+    /// ```
+    pub fn schema() -> String {
+        let schema = schemars::schema_for!(Config);
+        serde_json::to_string_pretty(&schema).expect("schema serializes")
+    }
+
+    /// Load `explicit` if given (it must exist), else `svg_sheet.toml` in the
+    /// working directory if present. Returns `None` when there is no file.
+    pub fn discover(explicit: Option<&Path>) -> Result<Option<Config>, AppError> {
+        let path = match explicit {
+            Some(path) => path,
+            None if Path::new(CONFIG_FILE).is_file() => Path::new(CONFIG_FILE),
+            None => return Ok(None),
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| AppError::ReadFile {
+            path: path.display().to_string(),
+            source: e,
+        })?;
+        let config = Config::parse(&text).map_err(|message| AppError::Config {
+            path: path.display().to_string(),
+            message,
+        })?;
+        tracing::debug!(path = %path.display(), "Loaded config");
+        Ok(Some(config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unknown_keys_and_bad_types() {
+        assert!(Config::parse("").unwrap() == Config::default());
+        let err = Config::parse("colours = {}").unwrap_err();
+        assert!(err.contains("unknown field `colours`"), "{err}");
+        assert!(Config::parse("[colors]\nred = 1").is_err());
+        assert!(Config::parse("[roles]\nprimary = \"#000\"").is_err());
+        assert!(Config::parse("[warnings]\nmissing-viewbox = \"fatal\"").is_err());
+        assert!(Config::parse("[warnings]\nbaseline = -1").is_err());
+        assert!(Config::parse("[profile.dev]\nsource_comments = true").is_err());
+        assert!(Config::parse("[attributes]\ndrop = [\"id\"]").is_err());
+    }
+
+    #[test]
+    fn parses_profiles() {
+        let config = Config::parse(
+            "[profile.dev]\nsource-comments = true\n\n[profile.prod]\ncompact-paths = true\nlock = true\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.profile["dev"],
+            Profile {
+                source_comments: true,
+                ..Default::default()
+            }
+        );
+        assert!(config.profile["prod"].compact_paths && config.profile["prod"].lock);
+    }
+
+    #[test]
+    fn explicit_path_must_exist() {
+        let err = Config::discover(Some(Path::new("/nonexistent/svg_sheet.toml"))).unwrap_err();
+        assert!(matches!(err, AppError::ReadFile { .. }));
+    }
+}
@@ -1,7 +1,10 @@
 use std::error::Error as StdError;
 use std::fmt;
 
+/// Every failure the library and CLI report. New variants may be added in
+/// minor releases; match on [`AppError::code`] for a stable identifier.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum AppError {
     ReadDir {
         path: String,
@@ -18,6 +21,8 @@ pub enum AppError {
     ParseSvg {
         path: String,
         message: String,
+        /// 1-based line and column of the problem, when known
+        location: Option<(usize, usize)>,
     },
     NoSvgFiles {
         path: String,
@@ -49,10 +54,379 @@ pub enum AppError {
         path: String,
         value: String,
     },
+    /// Root `preserveAspectRatio` does not match the SVG grammar
+    InvalidPreserveAspectRatio {
+        path: String,
+        value: String,
+    },
+    /// A user-supplied output template failed to parse or render
+    Template {
+        path: String,
+        message: String,
+    },
     /// Warnings were emitted and --fail-on-warn was set
     WarningsPresent {
-        count: usize,
+        warnings: Vec<Warning>,
+    },
+    /// Icons requested via --only have no matching input file
+    UnknownIcons {
+        names: Vec<String>,
+    },
+    /// Icons that no scanned source file references (`unused --fail`)
+    UnusedIcons {
+        names: Vec<String>,
+    },
+    /// Source files reference sprite ids that no input produces (`missing`)
+    MissingIcons {
+        references: usize,
+        names: Vec<String>,
+    },
+    /// `--check` found an output that differs from what a build would write
+    OutOfDate {
+        path: String,
+        diff: String,
+    },
+    /// An input or its emitted pattern exceeds a `--max-*` size limit
+    InputTooLarge {
+        path: String,
+        flag: &'static str,
+        size: u64,
+        limit: u64,
+    },
+    /// The config file could not be parsed or has invalid values
+    Config {
+        path: String,
+        message: String,
+    },
+    /// A remote source could not be downloaded or returned an unexpected response
+    Fetch {
+        url: String,
+        message: String,
+    },
+    /// An `--input-iconify` file is not a valid Iconify JSON collection
+    Iconify {
+        path: String,
+        message: String,
+    },
+    /// A `--from-package` directory is not in any `node_modules` above `from`
+    PackageNotFound {
+        package: String,
+        from: String,
     },
+    /// A config `[aliases]` entry points at an icon the build does not contain
+    UnknownAliasTarget {
+        alias: String,
+        target: String,
+    },
+    /// Icons whose content no longer matches the lockfile (`--lock`)
+    LockMismatch {
+        path: String,
+        names: Vec<String>,
+    },
+    /// An input passes the normal checks but not `--strict`
+    StrictViolation {
+        path: String,
+        message: String,
+    },
+    /// An icon's `.svg.toml` sidecar file could not be parsed
+    Sidecar {
+        path: String,
+        message: String,
+    },
+    /// A `--plugin` module could not be loaded, failed, or returned an invalid result
+    Plugin {
+        path: String,
+        message: String,
+    },
+}
+
+impl AppError {
+    /// Stable code of this kind of error (`E0006` for an id collision), kept
+    /// across releases even when the message wording changes.
+    ///
+    /// Example:
+    /// ```
+    /// use svg_sheet::error::AppError;
+    /// let e = AppError::NoSvgFiles { path: "icons".into() };
+    /// assert_eq!(e.code(), "E0005");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::ReadDir { .. } => "E0001",
+            AppError::ReadFile { .. } => "E0002",
+            AppError::WriteFile { .. } => "E0003",
+            AppError::ParseSvg { .. } => "E0004",
+            AppError::NoSvgFiles { .. } => "E0005",
+            AppError::IdCollision { .. } => "E0006",
+            AppError::RootIdReferenced { .. } => "E0007",
+            AppError::InvalidIdAfterSanitize { .. } => "E0008",
+            AppError::InvalidDimension { .. } => "E0009",
+            AppError::InvalidViewBox { .. } => "E0010",
+            AppError::InvalidPreserveAspectRatio { .. } => "E0011",
+            AppError::Template { .. } => "E0012",
+            AppError::WarningsPresent { .. } => "E0013",
+            AppError::UnknownIcons { .. } => "E0014",
+            AppError::UnusedIcons { .. } => "E0015",
+            AppError::MissingIcons { .. } => "E0016",
+            AppError::OutOfDate { .. } => "E0017",
+            AppError::InputTooLarge { .. } => "E0018",
+            AppError::Config { .. } => "E0019",
+            AppError::Fetch { .. } => "E0020",
+            AppError::Iconify { .. } => "E0021",
+            AppError::PackageNotFound { .. } => "E0022",
+            AppError::UnknownAliasTarget { .. } => "E0023",
+            AppError::LockMismatch { .. } => "E0024",
+            AppError::StrictViolation { .. } => "E0025",
+            AppError::Sidecar { .. } => "E0026",
+            AppError::Plugin { .. } => "E0027",
+        }
+    }
+
+    /// The input or output file the error is about, if any. For an id
+    /// collision this is the second file defining the id.
+    ///
+    /// Example:
+    /// ```
+    /// use svg_sheet::error::AppError;
+    /// let e = AppError::IdCollision {
+    ///     id: "arrow".into(),
+    ///     first_path: "a/arrow.svg".into(),
+    ///     second_path: "b/arrow.svg".into(),
+    /// };
+    /// assert_eq!(e.path(), Some("b/arrow.svg"));
+    /// assert_eq!(e.id(), Some("arrow"));
+    /// ```
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            AppError::ReadDir { path, .. }
+            | AppError::ReadFile { path, .. }
+            | AppError::WriteFile { path, .. }
+            | AppError::ParseSvg { path, .. }
+            | AppError::NoSvgFiles { path }
+            | AppError::RootIdReferenced { path, .. }
+            | AppError::InvalidIdAfterSanitize { path, .. }
+            | AppError::InvalidDimension { path, .. }
+            | AppError::InvalidViewBox { path, .. }
+            | AppError::InvalidPreserveAspectRatio { path, .. }
+            | AppError::Template { path, .. }
+            | AppError::OutOfDate { path, .. }
+            | AppError::InputTooLarge { path, .. }
+            | AppError::Config { path, .. }
+            | AppError::Iconify { path, .. }
+            | AppError::LockMismatch { path, .. }
+            | AppError::StrictViolation { path, .. }
+            | AppError::Sidecar { path, .. }
+            | AppError::Plugin { path, .. } => Some(path),
+            AppError::IdCollision { second_path, .. } => Some(second_path),
+            _ => None,
+        }
+    }
+
+    /// The 1-based line and column in [`AppError::path`] the error points
+    /// at, if known.
+    ///
+    /// Example:
+    /// ```
+    /// use svg_sheet::error::AppError;
+    /// let e = AppError::ParseSvg {
+    ///     path: "a.svg".into(),
+    ///     message: "expected '>' after attributes".into(),
+    ///     location: Some((12, 8)),
+    /// };
+    /// assert_eq!(e.location(), Some((12, 8)));
+    /// ```
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            AppError::ParseSvg { location, .. } => *location,
+            _ => None,
+        }
+    }
+
+    /// The pattern id the error is about, if any (the original id for one
+    /// that sanitized to nothing).
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            AppError::IdCollision { id, .. } | AppError::RootIdReferenced { id, .. } => Some(id),
+            AppError::UnknownAliasTarget { alias, .. } => Some(alias),
+            AppError::InvalidIdAfterSanitize { original, .. } => Some(original),
+            _ => None,
+        }
+    }
+
+    /// The warnings that aborted a `--fail-on-warn` build; empty for every
+    /// other error.
+    pub fn warnings(&self) -> &[Warning] {
+        match self {
+            AppError::WarningsPresent { warnings } => warnings,
+            _ => &[],
+        }
+    }
+
+    /// Icon names listed by `--only`, `unused`, `missing`, and `--lock` errors; empty
+    /// for every other error.
+    pub fn names(&self) -> &[String] {
+        match self {
+            AppError::UnknownIcons { names }
+            | AppError::UnusedIcons { names }
+            | AppError::MissingIcons { names, .. }
+            | AppError::LockMismatch { names, .. } => names,
+            _ => &[],
+        }
+    }
+}
+
+/// Kind of a non-fatal issue found while building.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum WarningKind {
+    /// The root `<svg id>` was moved to `data-id`, dropped, or sanitized
+    RootId,
+    /// The root `<svg>` has no `width`
+    MissingWidth,
+    /// The root `<svg>` has no `height`
+    MissingHeight,
+    /// The root `<svg>` has no `viewBox`
+    MissingViewBox,
+    /// The filename is not valid UTF-8
+    LossyFilename,
+    /// An embedded raster image was kept (`--raster-images warn`)
+    RasterImage,
+    /// An icon nearly duplicates another (`--detect-duplicates`)
+    NearDuplicate,
+    /// The sprite is over `--max-output-size`
+    OutputBudget,
+    /// An icon references an id defined neither in it nor in `_defs.svg`
+    UnresolvedReference,
+    /// An icon is marked deprecated
+    DeprecatedIcon,
+    /// An icon is over `--max-icon-size`
+    IconBudget,
+}
+
+impl WarningKind {
+    const ALL: [WarningKind; 11] = [
+        WarningKind::RootId,
+        WarningKind::MissingWidth,
+        WarningKind::MissingHeight,
+        WarningKind::MissingViewBox,
+        WarningKind::LossyFilename,
+        WarningKind::RasterImage,
+        WarningKind::NearDuplicate,
+        WarningKind::OutputBudget,
+        WarningKind::UnresolvedReference,
+        WarningKind::DeprecatedIcon,
+        WarningKind::IconBudget,
+    ];
+
+    /// Stable code of this kind of warning, like [`AppError::code`].
+    pub fn code(self) -> &'static str {
+        match self {
+            WarningKind::RootId => "W0001",
+            WarningKind::MissingWidth => "W0002",
+            WarningKind::MissingHeight => "W0003",
+            WarningKind::MissingViewBox => "W0004",
+            WarningKind::LossyFilename => "W0005",
+            WarningKind::RasterImage => "W0006",
+            WarningKind::NearDuplicate => "W0007",
+            WarningKind::OutputBudget => "W0008",
+            WarningKind::UnresolvedReference => "W0009",
+            WarningKind::DeprecatedIcon => "W0010",
+            WarningKind::IconBudget => "W0011",
+        }
+    }
+
+    /// Rule name used by `--allow` and `--deny` (`missing-viewbox`).
+    pub fn name(self) -> &'static str {
+        match self {
+            WarningKind::RootId => "root-id",
+            WarningKind::MissingWidth => "missing-width",
+            WarningKind::MissingHeight => "missing-height",
+            WarningKind::MissingViewBox => "missing-viewbox",
+            WarningKind::LossyFilename => "lossy-filename",
+            WarningKind::RasterImage => "embedded-raster",
+            WarningKind::NearDuplicate => "near-duplicate",
+            WarningKind::OutputBudget => "output-budget",
+            WarningKind::UnresolvedReference => "unresolved-reference",
+            WarningKind::DeprecatedIcon => "deprecated-icon",
+            WarningKind::IconBudget => "icon-budget",
+        }
+    }
+
+    /// The kind with the given `code`, if any.
+    ///
+    /// Example:
+    /// ```
+    /// use svg_sheet::error::WarningKind;
+    /// assert_eq!(WarningKind::from_code("W0004"), Some(WarningKind::MissingViewBox));
+    /// assert_eq!(WarningKind::from_code("E0004"), None);
+    /// ```
+    pub fn from_code(code: &str) -> Option<WarningKind> {
+        WarningKind::ALL.into_iter().find(|k| k.code() == code)
+    }
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parses a rule name or code (`missing-viewbox` or `W0004`).
+///
+/// Example:
+/// ```
+/// use svg_sheet::error::WarningKind;
+/// assert_eq!("embedded-raster".parse(), Ok(WarningKind::RasterImage));
+/// assert_eq!("W0004".parse(), Ok(WarningKind::MissingViewBox));
+/// assert!("missing-title".parse::<WarningKind>().is_err());
+/// ```
+impl std::str::FromStr for WarningKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        WarningKind::ALL
+            .into_iter()
+            .find(|k| k.name().eq_ignore_ascii_case(s) || k.code().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<&str> = WarningKind::ALL.iter().map(|k| k.name()).collect();
+                format!(
+                    "unknown warning rule '{s}'; expected one of {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// A non-fatal issue found while building: which kind, where, and the
+/// human-readable message that is logged for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// Input the warning is about (the output for `OutputBudget`)
+    pub path: String,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Warning {
+            kind,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Stable code of the warning's kind (`W0004` for a missing `viewBox`).
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
 }
 
 impl fmt::Display for AppError {
@@ -61,7 +435,12 @@ impl fmt::Display for AppError {
             AppError::ReadDir { path, .. } => write!(f, "failed to read directory: {path}"),
             AppError::ReadFile { path, .. } => write!(f, "failed to read file: {path}"),
             AppError::WriteFile { path, .. } => write!(f, "failed to write file: {path}"),
-            AppError::ParseSvg { path, message } => {
+            AppError::ParseSvg {
+                path,
+                message,
+                location: Some((line, column)),
+            } => write!(f, "{path}:{line}:{column}: failed to parse svg: {message}"),
+            AppError::ParseSvg { path, message, .. } => {
                 write!(f, "failed to parse svg ({path}): {message}")
             }
             AppError::NoSvgFiles { path } => write!(f, "no SVG files found in directory: {path}"),
@@ -75,23 +454,86 @@ impl fmt::Display for AppError {
             ),
             AppError::RootIdReferenced { path, id } => write!(
                 f,
-                "root <svg> id '{id}' in {path} is referenced inside the document; root ids are moved to data-id"
+                "root <svg> id '{id}' in {path} is referenced inside the document; use --root-id keep to preserve it"
             ),
             AppError::InvalidIdAfterSanitize { path, original } => {
                 write!(f, "id '{original}' in {path} is empty after sanitization")
             }
-            AppError::InvalidDimension { path, attr, value } => write!(
-                f,
-                "invalid {attr}='{value}' in {path}; expected positive number (optionally 'px')"
-            ),
+            AppError::InvalidDimension { path, attr, value } => {
+                write!(
+                    f,
+                    "invalid {attr}='{value}' in {path}; expected positive length (unitless, px, pt, pc, mm, cm, in, em or rem)"
+                )?;
+                if value.trim_end().ends_with('%') {
+                    f.write_str("; pass --percent-dims strip or keep to accept percentages")?;
+                }
+                Ok(())
+            }
             AppError::InvalidViewBox { path, value } => write!(
                 f,
                 "invalid viewBox='{value}' in {path}; expected four numbers with positive width/height"
             ),
-            AppError::WarningsPresent { count } => write!(
+            AppError::InvalidPreserveAspectRatio { path, value } => write!(
+                f,
+                "invalid preserveAspectRatio='{value}' in {path}; expected '<align> [meet|slice]' or 'none'"
+            ),
+            AppError::Template { path, message } => {
+                write!(f, "failed to render template {path}: {message}")
+            }
+            AppError::WarningsPresent { warnings } => write!(
+                f,
+                "aborting due to {} warning(s) (use --no-fail-on-warn to ignore)",
+                warnings.len()
+            ),
+            AppError::UnknownIcons { names } => {
+                write!(f, "requested icon(s) not found: {}", names.join(", "))
+            }
+            AppError::UnusedIcons { names } => {
+                write!(f, "{} unused icon(s): {}", names.len(), names.join(", "))
+            }
+            AppError::MissingIcons { references, names } => write!(
+                f,
+                "{references} reference(s) to missing icon(s): {}",
+                names.join(", ")
+            ),
+            AppError::OutOfDate { path, diff } => write!(
                 f,
-                "aborting due to {count} warning(s) (use --no-fail-on-warn to ignore)"
+                "{path} is out of date ({diff}); rerun without --check to update it"
             ),
+            AppError::InputTooLarge {
+                path,
+                flag,
+                size,
+                limit,
+            } => write!(
+                f,
+                "{path} is {size} bytes, over the {flag} limit of {limit} bytes"
+            ),
+            AppError::Config { path, message } => write!(f, "invalid config ({path}): {message}"),
+            AppError::Fetch { url, message } => write!(f, "failed to fetch {url}: {message}"),
+            AppError::Iconify { path, message } => {
+                write!(f, "invalid Iconify collection ({path}): {message}")
+            }
+            AppError::PackageNotFound { package, from } => write!(
+                f,
+                "package path '{package}' not found in any node_modules above {from}"
+            ),
+            AppError::UnknownAliasTarget { alias, target } => {
+                write!(f, "alias '{alias}' points to unknown icon '{target}'")
+            }
+            AppError::LockMismatch { path, names } => write!(
+                f,
+                "{} icon(s) changed since {path} was written: {} (rerun with --update-lock to accept)",
+                names.len(),
+                names.join(", ")
+            ),
+            AppError::StrictViolation { path, message } => {
+                write!(f, "{path}: {message} (rejected by --strict)")
+            }
+            AppError::Sidecar { path, message } => {
+                write!(f, "invalid sidecar metadata ({path}): {message}")
+            }
+            AppError::Plugin { path, message } => write!(f, "plugin {path} failed: {message}"),
         }
     }
 }
@@ -140,10 +582,21 @@ mod tests {
         let e = AppError::ParseSvg {
             path: "p.svg".into(),
             message: "bad".into(),
+            location: None,
         };
         let s = e.to_string();
         assert!(s.contains("failed to parse svg"));
 
+        let e = AppError::ParseSvg {
+            path: "p.svg".into(),
+            message: "expected '>' after attributes".into(),
+            location: Some((12, 8)),
+        };
+        assert_eq!(
+            e.to_string(),
+            "p.svg:12:8: failed to parse svg: expected '>' after attributes"
+        );
+
         let e = AppError::NoSvgFiles { path: "dir".into() };
         let s = e.to_string();
         assert!(s.contains("no SVG files"));
@@ -169,6 +622,16 @@ mod tests {
             original: "💥".into(),
         };
         assert!(e.to_string().contains("empty after sanitization"));
+
+        let e = AppError::UnknownAliasTarget {
+            alias: "chevron-right".into(),
+            target: "arow-right".into(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "alias 'chevron-right' points to unknown icon 'arow-right'"
+        );
+        assert_eq!((e.code(), e.id()), ("E0023", Some("chevron-right")));
     }
 
     #[test]
@@ -179,6 +642,16 @@ mod tests {
             value: "0".into(),
         };
         assert!(e.to_string().contains("invalid width='0'"));
+        assert!(!e.to_string().contains("--percent-dims"));
+        let e = AppError::InvalidDimension {
+            path: "p.svg".into(),
+            attr: "width".into(),
+            value: "100%".into(),
+        };
+        assert!(
+            e.to_string()
+                .ends_with("pass --percent-dims strip or keep to accept percentages")
+        );
 
         let e = AppError::InvalidViewBox {
             path: "p.svg".into(),
@@ -186,7 +659,171 @@ mod tests {
         };
         assert!(e.to_string().contains("invalid viewBox"));
 
-        let e = AppError::WarningsPresent { count: 3 };
+        let e = AppError::InvalidPreserveAspectRatio {
+            path: "p.svg".into(),
+            value: "middle".into(),
+        };
+        assert!(
+            e.to_string()
+                .contains("invalid preserveAspectRatio='middle'")
+        );
+
+        let warning = Warning::new(
+            WarningKind::MissingWidth,
+            "a.svg",
+            "Missing width on root <svg>",
+        );
+        let e = AppError::WarningsPresent {
+            warnings: vec![warning.clone(); 3],
+        };
         assert!(e.to_string().contains("aborting due to 3 warning(s)"));
+        assert_eq!(e.warnings()[0].code(), "W0002");
+        assert_eq!(warning.to_string(), "a.svg: Missing width on root <svg>");
+
+        let e = AppError::UnknownIcons {
+            names: vec!["a".into(), "b".into()],
+        };
+        assert_eq!(e.to_string(), "requested icon(s) not found: a, b");
+
+        let e = AppError::UnusedIcons {
+            names: vec!["a".into(), "b".into()],
+        };
+        assert_eq!(e.to_string(), "2 unused icon(s): a, b");
+
+        let e = AppError::MissingIcons {
+            references: 3,
+            names: vec!["clsoe".into()],
+        };
+        assert_eq!(e.to_string(), "3 reference(s) to missing icon(s): clsoe");
+
+        let e = AppError::OutOfDate {
+            path: "sprite.svg".into(),
+            diff: "added: a".into(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "sprite.svg is out of date (added: a); rerun without --check to update it"
+        );
+
+        let e = AppError::InputTooLarge {
+            path: "big.svg".into(),
+            flag: "--max-file-size",
+            size: 2048,
+            limit: 1024,
+        };
+        assert_eq!(
+            e.to_string(),
+            "big.svg is 2048 bytes, over the --max-file-size limit of 1024 bytes"
+        );
+
+        let e = AppError::Config {
+            path: "svg_sheet.toml".into(),
+            message: "unknown field `colours`".into(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "invalid config (svg_sheet.toml): unknown field `colours`"
+        );
+
+        let e = AppError::Fetch {
+            url: "https://example.com/a.svg".into(),
+            message: "HTTP 404".into(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "failed to fetch https://example.com/a.svg: HTTP 404"
+        );
+
+        let e = AppError::Iconify {
+            path: "mdi.json".into(),
+            message: "alias `up` has unknown parent `arrow`".into(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "invalid Iconify collection (mdi.json): alias `up` has unknown parent `arrow`"
+        );
+
+        let e = AppError::Sidecar {
+            path: "svgs/home.svg.toml".into(),
+            message: "unknown field `titel`".into(),
+        };
+        assert_eq!(
+            (e.to_string().as_str(), e.code(), e.path()),
+            (
+                "invalid sidecar metadata (svgs/home.svg.toml): unknown field `titel`",
+                "E0026",
+                Some("svgs/home.svg.toml")
+            )
+        );
+
+        let e = AppError::Plugin {
+            path: "stamp.wasm".into(),
+            message: "svgs/home.svg: no license".into(),
+        };
+        assert_eq!(
+            (e.to_string().as_str(), e.code(), e.path()),
+            (
+                "plugin stamp.wasm failed: svgs/home.svg: no license",
+                "E0027",
+                Some("stamp.wasm")
+            )
+        );
+
+        let e = AppError::PackageNotFound {
+            package: "heroicons/24/outline".into(),
+            from: "/app".into(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "package path 'heroicons/24/outline' not found in any node_modules above /app"
+        );
+    }
+
+    #[test]
+    fn codes_and_accessors() {
+        let e = AppError::IdCollision {
+            id: "dup".into(),
+            first_path: "a.svg".into(),
+            second_path: "b.svg".into(),
+        };
+        assert_eq!(
+            (e.code(), e.path(), e.id()),
+            ("E0006", Some("b.svg"), Some("dup"))
+        );
+
+        let e = AppError::InvalidIdAfterSanitize {
+            path: "p.svg".into(),
+            original: "---".into(),
+        };
+        assert_eq!((e.code(), e.id()), ("E0008", Some("---")));
+
+        let e = AppError::MissingIcons {
+            references: 2,
+            names: vec!["bell".into()],
+        };
+        assert_eq!(
+            (e.code(), e.path(), e.names()),
+            ("E0016", None, &["bell".to_string()][..])
+        );
+
+        let e = AppError::PackageNotFound {
+            package: "heroicons".into(),
+            from: "/app".into(),
+        };
+        assert_eq!(e.code(), "E0022");
+        assert!(e.names().is_empty());
+
+        let e = AppError::LockMismatch {
+            path: "sprite.lock".into(),
+            names: vec!["bell".into()],
+        };
+        assert_eq!(
+            e.to_string(),
+            "1 icon(s) changed since sprite.lock was written: bell (rerun with --update-lock to accept)"
+        );
+        assert_eq!(
+            (e.code(), e.path(), e.names()),
+            ("E0024", Some("sprite.lock"), &["bell".to_string()][..])
+        );
     }
 }
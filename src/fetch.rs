@@ -0,0 +1,107 @@
+//! Downloading icons from remote sources into the input directory.
+
+use crate::error::AppError;
+use std::path::{Path, PathBuf};
+
+pub mod figma;
+pub mod urls;
+
+/// GET `url` with extra request headers, returning the response body.
+/// Non-2xx responses and transport failures become `AppError::Fetch`.
+pub(crate) fn get(url: &str, headers: &[(&str, &str)]) -> Result<Vec<u8>, AppError> {
+    let fetch_err = |message: String| AppError::Fetch {
+        url: url.to_string(),
+        message,
+    };
+    let mut request = ureq::get(url);
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    let response = request.call().map_err(|e| match e {
+        ureq::Error::Status(code, response) => {
+            let body = response.into_string().unwrap_or_default();
+            fetch_err(format!("HTTP {code}: {}", body.trim()))
+        }
+        other => fetch_err(other.to_string()),
+    })?;
+    let mut body = Vec::new();
+    std::io::Read::read_to_end(&mut response.into_reader(), &mut body)
+        .map_err(|e| fetch_err(e.to_string()))?;
+    tracing::debug!(url, bytes = body.len(), "Fetched");
+    Ok(body)
+}
+
+/// File stem for a downloaded icon: `name` sanitized like an id, made unique
+/// among `taken` by a numeric suffix. Returns `None` when nothing usable remains.
+///
+/// Example:
+/// ```
+/// use svg_sheet::fetch::icon_stem;
+/// let mut taken = Vec::new();
+/// assert_eq!(icon_stem("arrows/Arrow Left", &mut taken).as_deref(), Some("arrows-Arrow-Left"));
+/// assert_eq!(icon_stem("arrows/Arrow Left", &mut taken).as_deref(), Some("arrows-Arrow-Left-2"));
+/// assert_eq!(icon_stem("🙂", &mut taken), None);
+/// ```
+pub fn icon_stem(name: &str, taken: &mut Vec<String>) -> Option<String> {
+    let stem = crate::svg::sanitize::sanitize_id(name);
+    if stem.is_empty() {
+        return None;
+    }
+    let mut unique = stem.clone();
+    let mut n = 1;
+    while taken.contains(&unique) {
+        n += 1;
+        unique = format!("{stem}-{n}");
+    }
+    taken.push(unique.clone());
+    Some(unique)
+}
+
+/// Write `bytes` to `<dir>/<stem>.svg`, creating `dir` if needed.
+pub(crate) fn write_icon(dir: &Path, stem: &str, bytes: &[u8]) -> Result<PathBuf, AppError> {
+    std::fs::create_dir_all(dir).map_err(|e| AppError::WriteFile {
+        path: dir.display().to_string(),
+        source: e,
+    })?;
+    let path = dir.join(format!("{stem}.svg"));
+    std::fs::write(&path, bytes).map_err(|e| AppError::WriteFile {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+    Ok(path)
+}
+
+/// A minimal HTTP server answering requests from a fixed `path -> body` table
+/// (404 otherwise), for exercising fetchers without the network. `{base}` in
+/// a body is replaced by the server's own URL.
+#[cfg(test)]
+pub(crate) fn serve(routes: Vec<(&'static str, &'static str)>) -> String {
+    use std::io::{BufRead, BufReader, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let base_url = base.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            let _ = reader.read_line(&mut request_line);
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                line.clear();
+            }
+            let path = request_line.split_whitespace().nth(1).unwrap_or("");
+            let (status, body) = match routes.iter().find(|(p, _)| *p == path) {
+                Some((_, body)) => ("200 OK", body.replace("{base}", &base_url)),
+                None => ("404 Not Found", "not found".to_string()),
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(body.as_bytes());
+        }
+    });
+    base
+}
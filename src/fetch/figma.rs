@@ -0,0 +1,170 @@
+//! Figma REST API import: every component of a file, exported as SVG.
+
+use super::{get, icon_stem, write_icon};
+use crate::error::AppError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Base URL of the Figma REST API.
+pub const API: &str = "https://api.figma.com/v1";
+
+/// Node ids requested per image export call, keeping URLs short.
+const BATCH: usize = 50;
+
+#[derive(Deserialize)]
+struct ComponentsResponse {
+    meta: ComponentsMeta,
+}
+
+#[derive(Deserialize)]
+struct ComponentsMeta {
+    components: Vec<Component>,
+}
+
+#[derive(Deserialize)]
+struct Component {
+    node_id: String,
+    name: String,
+    #[serde(default)]
+    containing_frame: Option<ContainingFrame>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContainingFrame {
+    #[serde(default)]
+    containing_component_set: Option<ComponentSet>,
+}
+
+#[derive(Deserialize)]
+struct ComponentSet {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ImagesResponse {
+    #[serde(default)]
+    err: Option<String>,
+    #[serde(default)]
+    images: HashMap<String, Option<String>>,
+}
+
+impl Component {
+    /// Icon name: the component's, prefixed by its variant set when it has one.
+    fn icon_name(&self) -> String {
+        match self
+            .containing_frame
+            .as_ref()
+            .and_then(|f| f.containing_component_set.as_ref())
+        {
+            Some(set) => format!("{}/{}", set.name, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Download every component of Figma file `file_key` as `<name>.svg` into
+/// `out_dir`, authenticating with a personal access `token`. Returns the
+/// written paths in the file's component order.
+pub fn fetch(file_key: &str, token: &str, out_dir: &Path) -> Result<Vec<PathBuf>, AppError> {
+    fetch_from(API, file_key, token, out_dir)
+}
+
+/// `fetch` against the API at `api`.
+pub(crate) fn fetch_from(
+    api: &str,
+    file_key: &str,
+    token: &str,
+    out_dir: &Path,
+) -> Result<Vec<PathBuf>, AppError> {
+    let auth = [("X-Figma-Token", token)];
+    let url = format!("{api}/files/{file_key}/components");
+    let components: ComponentsResponse = parse_json(&url, &get(&url, &auth)?)?;
+    let components = components.meta.components;
+    tracing::info!(
+        file_key,
+        count = components.len(),
+        "Listed Figma components"
+    );
+
+    let mut taken = Vec::new();
+    let mut written = Vec::with_capacity(components.len());
+    for batch in components.chunks(BATCH) {
+        let ids: Vec<&str> = batch.iter().map(|c| c.node_id.as_str()).collect();
+        let url = format!("{api}/images/{file_key}?ids={}&format=svg", ids.join(","));
+        let images: ImagesResponse = parse_json(&url, &get(&url, &auth)?)?;
+        if let Some(message) = images.err {
+            return Err(AppError::Fetch { url, message });
+        }
+        for component in batch {
+            let name = component.icon_name();
+            let Some(Some(image_url)) = images.images.get(&component.node_id) else {
+                tracing::warn!(component = %name, "Figma rendered no SVG for component; skipping");
+                continue;
+            };
+            let Some(stem) = icon_stem(&name, &mut taken) else {
+                tracing::warn!(component = %name, "Component name has no usable characters; skipping");
+                continue;
+            };
+            let svg = get(image_url, &[])?;
+            written.push(write_icon(out_dir, &stem, &svg)?);
+        }
+    }
+    Ok(written)
+}
+
+/// Deserialize a JSON response body from `url`.
+fn parse_json<T: serde::de::DeserializeOwned>(url: &str, body: &[u8]) -> Result<T, AppError> {
+    serde_json::from_slice(body).map_err(|e| AppError::Fetch {
+        url: url.to_string(),
+        message: format!("unexpected response: {e}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downloads_components_as_named_svgs() {
+        let base = crate::fetch::serve(vec![
+            (
+                "/files/KEY/components",
+                r#"{"meta":{"components":[
+                    {"node_id":"1:2","name":"arrow-left"},
+                    {"node_id":"1:3","name":"Style=Filled","containing_frame":{"containingComponentSet":{"name":"heart"}}},
+                    {"node_id":"1:4","name":"blank"}
+                ]}}"#,
+            ),
+            (
+                "/images/KEY?ids=1:2,1:3,1:4&format=svg",
+                r#"{"err":null,"images":{"1:2":"{base}/a.svg","1:3":"{base}/b.svg","1:4":null}}"#,
+            ),
+            ("/a.svg", "<svg id=\"a\"/>"),
+            ("/b.svg", "<svg id=\"b\"/>"),
+        ]);
+        let dir = std::env::temp_dir().join(format!("svg_figma_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let written = fetch_from(&base, "KEY", "t", &dir).unwrap();
+        assert_eq!(
+            written,
+            vec![
+                dir.join("arrow-left.svg"),
+                dir.join("heart-Style-Filled.svg")
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(&written[1]).unwrap(),
+            "<svg id=\"b\"/>"
+        );
+
+        let err = fetch_from(&base, "NOPE", "t", &dir).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("failed to fetch {base}/files/NOPE/components: HTTP 404: not found")
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,214 @@
+//! Icons downloaded from a list of URLs, optionally pinned to a checksum.
+//!
+//! Each non-empty line of the list names one SVG: its URL, optionally an icon
+//! name, and optionally a `sha256:<hex>` pin, separated by whitespace in any
+//! order. `#` starts a comment.
+
+use super::{get, icon_stem, write_icon};
+use crate::error::AppError;
+use std::path::{Path, PathBuf};
+
+/// One line of a URL list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlEntry {
+    pub url: String,
+    /// Icon name; defaults to the file stem of the URL's path
+    pub name: Option<String>,
+    /// Expected lowercase SHA-256 of the content
+    pub sha256: Option<String>,
+}
+
+/// Outcome for one downloaded icon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fetched {
+    pub path: PathBuf,
+    /// SHA-256 of the content, for pinning unpinned entries
+    pub sha256: String,
+    pub pinned: bool,
+    /// Whether the content came from the cache rather than the network
+    pub cached: bool,
+}
+
+/// Parse a URL list; errors name the offending line.
+///
+/// Example:
+/// ```
+/// let pin = "AB".repeat(32);
+/// let text = format!("# icons\nhttps://cdn.example.com/arrow.svg\nclose https://cdn.example.com/x.svg sha256:{pin}\n");
+/// let entries = svg_sheet::fetch::urls::parse_list(&text).unwrap();
+/// assert_eq!(entries[0].name, None);
+/// assert_eq!(entries[1].name.as_deref(), Some("close"));
+/// assert_eq!(entries[1].sha256, Some(pin.to_lowercase()));
+/// ```
+pub fn parse_list(text: &str) -> Result<Vec<UrlEntry>, String> {
+    let mut entries = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (mut url, mut name, mut sha256) = (None, None, None);
+        for token in line.split_whitespace() {
+            let slot = if let Some(hex) = token.strip_prefix("sha256:") {
+                if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!("line {}: invalid checksum '{token}'", i + 1));
+                }
+                (&mut sha256, hex.to_ascii_lowercase())
+            } else if token.starts_with("http://") || token.starts_with("https://") {
+                (&mut url, token.to_string())
+            } else {
+                (&mut name, token.to_string())
+            };
+            if slot.0.replace(slot.1).is_some() {
+                return Err(format!("line {}: unexpected '{token}'", i + 1));
+            }
+        }
+        let Some(url) = url else {
+            return Err(format!("line {}: missing http(s) URL", i + 1));
+        };
+        entries.push(UrlEntry { url, name, sha256 });
+    }
+    Ok(entries)
+}
+
+/// Download every entry of `list` into `out_dir` as `<name>.svg`. With a
+/// `cache_dir`, downloads are kept under `<cache_dir>/fetch` and reused; a
+/// pinned entry whose destination already has the pinned content is not
+/// downloaded at all. Content that does not match its pin is rejected.
+pub fn fetch(
+    list: &Path,
+    out_dir: &Path,
+    cache_dir: Option<&Path>,
+) -> Result<Vec<Fetched>, AppError> {
+    let text = std::fs::read_to_string(list).map_err(|e| AppError::ReadFile {
+        path: list.display().to_string(),
+        source: e,
+    })?;
+    let entries = parse_list(&text).map_err(|message| AppError::Config {
+        path: list.display().to_string(),
+        message,
+    })?;
+    let mut taken = Vec::new();
+    let mut fetched = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        let name = entry.name.clone().unwrap_or_else(|| url_stem(&entry.url));
+        let Some(stem) = icon_stem(&name, &mut taken) else {
+            return Err(AppError::Fetch {
+                url: entry.url.clone(),
+                message: format!("cannot derive an icon name from '{name}'; name it explicitly"),
+            });
+        };
+        let dest = out_dir.join(format!("{stem}.svg"));
+        let cached_path = cache_dir.map(|dir| {
+            let key = crate::svg::source::sha256_hex(entry.url.as_bytes());
+            dir.join("fetch").join(format!("{}.svg", &key[..16]))
+        });
+        let reusable = |path: &Path| {
+            let bytes = std::fs::read(path).ok()?;
+            let hash = crate::svg::source::sha256_hex(&bytes);
+            match &entry.sha256 {
+                Some(pin) if *pin != hash => None,
+                None if path == dest => None,
+                _ => Some((bytes, hash)),
+            }
+        };
+        let (bytes, sha256, cached) = match std::iter::once(dest.as_path())
+            .chain(cached_path.as_deref())
+            .find_map(reusable)
+        {
+            Some((bytes, hash)) => (bytes, hash, true),
+            None => {
+                let bytes = get(&entry.url, &[])?;
+                let hash = crate::svg::source::sha256_hex(&bytes);
+                if let Some(pin) = &entry.sha256
+                    && *pin != hash
+                {
+                    return Err(AppError::Fetch {
+                        url: entry.url.clone(),
+                        message: format!(
+                            "checksum mismatch: expected sha256:{pin}, got sha256:{hash}"
+                        ),
+                    });
+                }
+                if let Some(path) = &cached_path {
+                    let dir = path.parent().expect("cache path has a parent");
+                    let stem = path.file_stem().and_then(|s| s.to_str()).expect("hex stem");
+                    write_icon(dir, stem, &bytes)?;
+                }
+                (bytes, hash, false)
+            }
+        };
+        if entry.sha256.is_none() {
+            tracing::info!(url = %entry.url, sha256 = %sha256, "Fetched unpinned icon");
+        }
+        fetched.push(Fetched {
+            path: write_icon(out_dir, &stem, &bytes)?,
+            sha256,
+            pinned: entry.sha256.is_some(),
+            cached,
+        });
+    }
+    Ok(fetched)
+}
+
+/// File stem of a URL's path, ignoring query and fragment.
+fn url_stem(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let last = path.rsplit('/').next().unwrap_or(path);
+    last.strip_suffix(".svg").unwrap_or(last).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert_eq!(
+            parse_list("arrow").unwrap_err(),
+            "line 1: missing http(s) URL"
+        );
+        assert_eq!(
+            parse_list("\nhttps://a/x.svg https://a/y.svg").unwrap_err(),
+            "line 2: unexpected 'https://a/y.svg'"
+        );
+        assert!(parse_list("https://a/x.svg sha256:xyz").is_err());
+        assert_eq!(url_stem("https://a/b/arrow.svg?v=2"), "arrow");
+    }
+
+    #[test]
+    fn downloads_verifies_and_caches() {
+        const BODY: &str = "<svg id=\"a\"/>";
+        let base = crate::fetch::serve(vec![("/arrow.svg", BODY), ("/other.svg", BODY)]);
+        let root = std::env::temp_dir().join(format!("svg_fetch_urls_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let hash = crate::svg::source::sha256_hex(BODY.as_bytes());
+        let list = root.join("icons.txt");
+        std::fs::write(
+            &list,
+            format!("{base}/arrow.svg sha256:{hash}\nclose {base}/other.svg\n"),
+        )
+        .unwrap();
+        let (out, cache) = (root.join("svgs"), root.join("cache"));
+
+        let first = fetch(&list, &out, Some(&cache)).unwrap();
+        assert_eq!(first[0].path, out.join("arrow.svg"));
+        assert_eq!(first[1].path, out.join("close.svg"));
+        assert!(first[0].pinned && !first[1].pinned);
+        assert!(first.iter().all(|f| !f.cached && f.sha256 == hash));
+
+        // Served from the destination (pinned) and the cache (unpinned).
+        let again = fetch(&list, &out, Some(&cache)).unwrap();
+        assert!(again.iter().all(|f| f.cached));
+
+        std::fs::write(
+            &list,
+            format!("{base}/arrow.svg sha256:{}\n", "0".repeat(64)),
+        )
+        .unwrap();
+        let err = fetch(&list, &root.join("fresh"), None).unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"), "{err}");
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,133 @@
+//! Single-key commands while `watch` runs in a terminal: `r` rebuilds, `c`
+//! clears the screen, and `q` quits.
+//!
+//! On Unix the terminal is switched to non-canonical, no-echo input so keys
+//! arrive without Enter; signals and output processing are left alone, so
+//! Ctrl+C and log lines behave as usual. Elsewhere keys are not read.
+
+use crate::svg;
+
+/// A watch-mode key command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// Rebuild now, re-reading every input
+    Rebuild,
+    /// Clear the terminal
+    Clear,
+    /// Stop watching, as on Ctrl+C
+    Quit,
+}
+
+impl Key {
+    /// The command bound to an input byte, case-insensitively.
+    ///
+    /// Example:
+    /// ```
+    /// use svg_sheet::keys::Key;
+    /// assert_eq!(Key::from_byte(b'R'), Some(Key::Rebuild));
+    /// assert_eq!(Key::from_byte(b'x'), None);
+    /// ```
+    pub fn from_byte(byte: u8) -> Option<Key> {
+        match byte.to_ascii_lowercase() {
+            b'r' => Some(Key::Rebuild),
+            b'c' => Some(Key::Clear),
+            b'q' => Some(Key::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// One-line help for the key commands.
+pub const HINT: &str = "Press r to rebuild, c to clear the screen, q to quit";
+
+/// Start reading key commands from stdin on a background thread. Returns
+/// false, reading nothing, when stdin is not a terminal or keys are not
+/// supported on this platform.
+pub fn listen() -> bool {
+    use std::io::IsTerminal;
+    if !std::io::stdin().is_terminal() || !terminal::enter() {
+        return false;
+    }
+    std::thread::spawn(|| {
+        use std::io::Read;
+        for byte in std::io::stdin().lock().bytes() {
+            let Ok(byte) = byte else { break };
+            match Key::from_byte(byte) {
+                Some(Key::Rebuild) => svg::request_rebuild(),
+                Some(Key::Clear) => clear_screen(),
+                Some(Key::Quit) => {
+                    svg::request_shutdown();
+                    break;
+                }
+                None => {}
+            }
+        }
+    });
+    true
+}
+
+/// Put the terminal back the way `listen` found it. Safe to call more than
+/// once, or when `listen` changed nothing.
+pub fn restore() {
+    terminal::restore();
+}
+
+fn clear_screen() {
+    use std::io::Write;
+    let mut out = std::io::stdout().lock();
+    let _ = out.write_all(b"\x1b[2J\x1b[H");
+    let _ = out.flush();
+}
+
+#[cfg(unix)]
+mod terminal {
+    use rustix::termios::{self, LocalModes, OptionalActions, SpecialCodeIndex, Termios};
+    use std::sync::Mutex;
+
+    /// Terminal settings before `enter`, restored by `restore`.
+    static SAVED: Mutex<Option<Termios>> = Mutex::new(None);
+
+    pub(super) fn enter() -> bool {
+        let stdin = std::io::stdin();
+        let Ok(saved) = termios::tcgetattr(&stdin) else {
+            return false;
+        };
+        let mut keys = saved.clone();
+        keys.local_modes &= !(LocalModes::ICANON | LocalModes::ECHO);
+        keys.special_codes[SpecialCodeIndex::VMIN] = 1;
+        keys.special_codes[SpecialCodeIndex::VTIME] = 0;
+        if termios::tcsetattr(&stdin, OptionalActions::Now, &keys).is_err() {
+            return false;
+        }
+        *SAVED.lock().unwrap_or_else(|e| e.into_inner()) = Some(saved);
+        true
+    }
+
+    pub(super) fn restore() {
+        if let Some(saved) = SAVED.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            let _ = termios::tcsetattr(std::io::stdin(), OptionalActions::Now, &saved);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod terminal {
+    pub(super) fn enter() -> bool {
+        false
+    }
+
+    pub(super) fn restore() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_keys_case_insensitively() {
+        assert_eq!(Key::from_byte(b'r'), Some(Key::Rebuild));
+        assert_eq!(Key::from_byte(b'C'), Some(Key::Clear));
+        assert_eq!(Key::from_byte(b'q'), Some(Key::Quit));
+        assert_eq!(Key::from_byte(b'\n'), None);
+    }
+}
@@ -1,7 +1,22 @@
 //! svg_sheet library interface.
 //!
 //! This exposes the core modules for reuse and for doctests.
+//!
+//! Without default features only the in-memory core is built: parsing,
+//! normalization, and sprite rendering (`svg::build_from_sources`), which
+//! compiles to `wasm32-unknown-unknown`. The `fs` feature adds directory
+//! inputs, file outputs, watch mode, and `build_script` for `build.rs`, `fetch` the HTTP downloads, and
+//! `cli` (the default) the command-line interface and config file.
 
+#[cfg(feature = "fs")]
+pub mod build_script;
+#[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(feature = "cli")]
+pub mod config;
 pub mod error;
+#[cfg(feature = "fetch")]
+pub mod fetch;
+#[cfg(feature = "cli")]
+pub mod keys;
 pub mod svg;
@@ -1,34 +1,22 @@
-mod cli;
-mod error;
-mod svg;
-
-use crate::error::AppError;
-use cli::{Args, Commands, LogLevel, Shell};
 use std::error::Error as _;
+use svg_sheet::cli::{
+    self, Args, ChunkBy, Commands, ConfigCommand, EventFormat, FetchSource, IdCase, LogFormat,
+    LogLevel, PercentDims, RasterImages, RootId, Shell,
+};
+use svg_sheet::config::{self, Config, WarningLevel};
+use svg_sheet::error::{AppError, WarningKind};
+use svg_sheet::svg;
+use svg_sheet::svg::pipeline::Stage;
 
 /// Program entry point. Dispatches CLI commands and prints user-friendly errors.
 fn main() {
     let args = cli::parse();
     init_tracing(&args);
 
-    let result: Result<(), AppError> = match &args.command {
-        None => svg::process_with_opts(&args.directory, &args.file, to_run_opts(&args)),
-        Some(Commands::Build) => {
-            svg::process_with_opts(&args.directory, &args.file, to_run_opts(&args))
-        }
-        Some(Commands::Watch) => {
-            svg::watch_with_opts(&args.directory, &args.file, to_run_opts(&args))
-        }
-        Some(Commands::Completions { shell, out_dir }) => {
-            generate_completions(*shell, out_dir.clone())
-        }
-        Some(Commands::Man { out_dir }) => generate_man(out_dir.clone()),
-    };
-
-    match result {
+    match run(&args) {
         Ok(_) => std::process::exit(0),
         Err(e) => {
-            eprintln!("\x1b[1;31mError:\x1b[0m {e}");
+            eprintln!("\x1b[1;31mError[{}]:\x1b[0m {e}", e.code());
             if let Some(source) = e.source() {
                 eprintln!("Caused by: {source}");
             }
@@ -37,40 +25,419 @@ fn main() {
     }
 }
 
-/// Convert parsed CLI `Args` into `svg::RunOpts` used by core routines.
-fn to_run_opts(args: &Args) -> svg::RunOpts {
-    svg::RunOpts {
+/// Stop watching cleanly on the first SIGINT/SIGTERM; a second one exits
+/// immediately with status 130.
+fn install_shutdown_handler() {
+    let signalled = std::sync::atomic::AtomicBool::new(false);
+    let installed = ctrlc::set_handler(move || {
+        if signalled.swap(true, std::sync::atomic::Ordering::SeqCst) {
+            svg_sheet::keys::restore();
+            std::process::exit(130);
+        }
+        svg::request_shutdown();
+    });
+    if let Err(e) = installed {
+        tracing::warn!(error = %e, "Could not install the shutdown handler");
+    }
+}
+
+/// Rebuild a running watcher on SIGUSR1 (`kill -USR1 <pid>`), as the `r`
+/// key does.
+#[cfg(unix)]
+fn install_rebuild_handler() {
+    use nix::sys::signal::{SaFlags, SigAction, SigHandler, SigSet, Signal, sigaction};
+    extern "C" fn on_usr1(_: std::ffi::c_int) {
+        svg::request_rebuild();
+    }
+    let action = SigAction::new(
+        SigHandler::Handler(on_usr1),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    // SAFETY: the handler only stores to an atomic flag, which is
+    // async-signal-safe.
+    if let Err(e) = unsafe { sigaction(Signal::SIGUSR1, &action) } {
+        tracing::warn!(error = %e, "Could not install the SIGUSR1 rebuild handler");
+    }
+}
+
+/// Signals are not used for rebuilds outside Unix; see `--rebuild-trigger`.
+#[cfg(not(unix))]
+fn install_rebuild_handler() {}
+
+/// The paths listed by `--files-from`, one per line. The list is read once,
+/// since stdin can only be consumed by the first build of a session.
+fn files_from(args: &Args) -> Result<Option<Vec<std::path::PathBuf>>, AppError> {
+    static FILES: std::sync::OnceLock<Vec<std::path::PathBuf>> = std::sync::OnceLock::new();
+    let Some(source) = &args.files_from else {
+        return Ok(None);
+    };
+    if let Some(files) = FILES.get() {
+        return Ok(Some(files.clone()));
+    }
+    let text = if source == svg::STDIO {
+        std::io::read_to_string(std::io::stdin().lock())
+    } else {
+        std::fs::read_to_string(source)
+    }
+    .map_err(|e| AppError::ReadFile {
+        path: source.clone(),
+        source: e,
+    })?;
+    let files = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(std::path::PathBuf::from)
+        .collect();
+    Ok(Some(FILES.get_or_init(|| files).clone()))
+}
+
+/// Dispatch the selected command.
+fn run(args: &Args) -> Result<(), AppError> {
+    let stdin = args.stdin || args.directory == svg::STDIO;
+    if stdin && args.files_from.as_deref() == Some(svg::STDIO) {
+        return Err(AppError::ReadFile {
+            path: svg::STDIO.into(),
+            source: std::io::Error::other(
+                "cannot read both SVG content and --files-from from stdin",
+            ),
+        });
+    }
+    if files_from(args)?.is_some_and(|files| !files.iter().any(|p| p.is_file())) {
+        // A pre-commit hook whose diff only touched other files, or only
+        // deleted icons, has nothing to check.
+        if !args.quiet {
+            eprintln!("No listed input files exist; nothing to build");
+        }
+        return Ok(());
+    }
+    match &args.command {
+        None | Some(Commands::Build) if stdin => {
+            // Warnings were already logged as they were found.
+            svg::process_stream(std::io::stdin().lock(), &args.file, to_run_opts(args)?).map(drop)
+        }
+        None | Some(Commands::Build) => {
+            svg::process_with_opts(&args.directory, &args.file, to_run_opts(args)?).map(drop)
+        }
+        Some(Commands::Watch { .. }) if stdin => Err(AppError::ReadDir {
+            path: svg::STDIO.into(),
+            source: std::io::Error::other("cannot watch stdin"),
+        }),
+        Some(Commands::Watch { .. }) => {
+            install_shutdown_handler();
+            install_rebuild_handler();
+            let opts = to_run_opts(args)?;
+            if svg_sheet::keys::listen() && !opts.quiet && !opts.json_events {
+                eprintln!("{}", svg_sheet::keys::HINT);
+            }
+            let config = args
+                .config
+                .clone()
+                .unwrap_or_else(|| config::CONFIG_FILE.into());
+            let watched =
+                svg::watch_with_config(&args.directory, &args.file, opts, &config, || {
+                    to_run_opts(args)
+                });
+            svg_sheet::keys::restore();
+            watched
+        }
+        Some(Commands::Unused { src, fail }) => {
+            svg::unused_with_opts(&args.directory, &args.file, src, *fail, to_run_opts(args)?)
+        }
+        Some(Commands::Missing { src }) => {
+            svg::missing_with_opts(&args.directory, &args.file, src, to_run_opts(args)?)
+        }
+        Some(Commands::Completions { shell, out_dir }) => {
+            generate_completions(*shell, out_dir.clone())
+        }
+        Some(Commands::Fetch {
+            source: FetchSource::Figma { file_key, token },
+        }) => {
+            let dir = std::path::Path::new(&args.directory);
+            let written = svg_sheet::fetch::figma::fetch(file_key, token, dir)?;
+            if !args.quiet {
+                println!("Fetched {} icon(s) into {}", written.len(), dir.display());
+            }
+            Ok(())
+        }
+        Some(Commands::Fetch {
+            source: FetchSource::Urls { list },
+        }) => {
+            let dir = std::path::Path::new(&args.directory);
+            let fetched = svg_sheet::fetch::urls::fetch(list, dir, args.cache_dir.as_deref())?;
+            if !args.quiet {
+                let cached = fetched.iter().filter(|f| f.cached).count();
+                let unpinned = fetched.iter().filter(|f| !f.pinned).count();
+                println!(
+                    "Fetched {} icon(s) into {} ({cached} cached, {unpinned} unpinned)",
+                    fetched.len(),
+                    dir.display()
+                );
+            }
+            Ok(())
+        }
+        Some(Commands::Config {
+            command: ConfigCommand::Schema,
+        }) => print_config_schema(),
+        Some(Commands::Man { out_dir }) => generate_man(out_dir.clone()),
+    }
+}
+
+/// Convert parsed CLI `Args`, merged with the config file, into the
+/// `svg::RunOpts` used by core routines.
+fn to_run_opts(args: &Args) -> Result<svg::RunOpts, AppError> {
+    let config = Config::discover(args.config.as_deref())?.unwrap_or_default();
+    let config_path = || {
+        args.config
+            .as_ref()
+            .map_or(config::CONFIG_FILE.to_string(), |p| p.display().to_string())
+    };
+    let profile = match &args.profile {
+        Some(name) => {
+            let Some(profile) = config.profile.get(name) else {
+                let names: Vec<&str> = config.profile.keys().map(String::as_str).collect();
+                return Err(AppError::Config {
+                    path: config_path(),
+                    message: format!(
+                        "unknown profile '{name}' (defined: {})",
+                        if names.is_empty() {
+                            "none".to_string()
+                        } else {
+                            names.join(", ")
+                        }
+                    ),
+                });
+            };
+            tracing::debug!(profile = %name, "Using config profile");
+            profile.clone()
+        }
+        None => config::Profile::default(),
+    };
+    let strict = args.strict || profile.strict;
+    let mut emit = args.emit.clone();
+    if let Some(path) = &args.markdown_out {
+        emit.push(svg::emit::EmitTarget {
+            kind: svg::emit::EmitKind::Markdown,
+            path: path.clone(),
+        });
+    }
+    if let Some(path) = &args.json_icons {
+        emit.push(svg::emit::EmitTarget {
+            kind: svg::emit::EmitKind::IconsJson,
+            path: path.clone(),
+        });
+    }
+    for target in config.emit.iter().chain(&profile.emit) {
+        emit.push(target.parse().map_err(|message| AppError::Config {
+            path: config_path(),
+            message,
+        })?);
+    }
+    let configured = match profile.pipeline.as_ref().or(config.pipeline.as_ref()) {
+        Some(names) => Some(
+            svg::pipeline::parse(names).map_err(|message| AppError::Config {
+                path: config_path(),
+                message,
+            })?,
+        ),
+        None => None,
+    };
+    // A stage listed in a configured pipeline turns on its pass.
+    let listed = |stage| configured.as_ref().is_some_and(|p| p.contains(&stage));
+    #[cfg(not(feature = "plugins"))]
+    if listed(Stage::Plugins) {
+        return Err(AppError::Config {
+            path: config_path(),
+            message: "stage 'plugins' requires the plugins feature".to_string(),
+        });
+    }
+    let pipeline = configured
+        .clone()
+        .unwrap_or_else(|| Stage::DEFAULT.to_vec());
+    // Config levels first, then the flags: a flag overrides the config's level
+    // for its rule.
+    let (mut allow, mut deny) = (Vec::new(), Vec::new());
+    for (rule, level) in &config.warnings.rules {
+        let kind: WarningKind = rule.parse().map_err(|message| AppError::Config {
+            path: config_path(),
+            message,
+        })?;
+        match level {
+            WarningLevel::Allow => allow.push(kind),
+            WarningLevel::Warn => {}
+            WarningLevel::Error => deny.push(kind),
+        }
+    }
+    for alias in config.aliases.keys() {
+        if svg::sanitize::sanitize_id(alias) != *alias {
+            return Err(AppError::Config {
+                path: config_path(),
+                message: format!("alias '{alias}' is not a valid id"),
+            });
+        }
+    }
+    // Config root attributes first; a flag replaces the value of its name.
+    let mut root_attrs = Vec::new();
+    for (name, value) in config.root.iter().map(|(k, v)| (k.clone(), v.clone())) {
+        cli::check_root_attr(&name).map_err(|message| AppError::Config {
+            path: config_path(),
+            message,
+        })?;
+        root_attrs.push((name, value));
+    }
+    for (name, value) in &args.root_attr {
+        match root_attrs.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value.clone(),
+            None => root_attrs.push((name.clone(), value.clone())),
+        }
+    }
+    deny.retain(|kind| !args.allow.contains(kind));
+    allow.extend(&args.allow);
+    deny.extend(&args.deny);
+    if strict {
+        allow.clear();
+    }
+    Ok(svg::RunOpts {
         quiet: args.quiet,
         verbose: args.verbose,
-        dry_run: args.dry_run,
-        fail_on_warn: args.fail_on_warn,
+        dry_run: args.dry_run || args.diff,
+        diff: args.diff,
+        fail_on_warn: args.fail_on_warn || profile.fail_on_warn || strict,
+        strict,
+        allow,
+        deny,
+        warning_baseline: match strict {
+            true => 0,
+            false => config.warnings.baseline.unwrap_or(0),
+        },
         debounce_ms: args.debounce_ms,
+        poll_interval_ms: args.poll_interval_ms,
+        rebuild_trigger: args.rebuild_trigger.clone(),
+        jobs: args.jobs,
         poll: args.poll,
-    }
+        cache_dir: args.cache_dir.clone(),
+        use_gitignore: args.gitignore,
+        follow_symlinks: args.follow_symlinks,
+        only: args.only.clone(),
+        id_case: match args.id_case {
+            IdCase::Keep => svg::naming::IdCase::Keep,
+            IdCase::Kebab => svg::naming::IdCase::Kebab,
+            IdCase::Camel => svg::naming::IdCase::Camel,
+            IdCase::Snake => svg::naming::IdCase::Snake,
+        },
+        recursive: args.recursive,
+        id_separator: args.id_separator.clone(),
+        root_id: match args.root_id {
+            RootId::Keep => svg::ids::RootIdPolicy::Keep,
+            RootId::DataId => svg::ids::RootIdPolicy::DataId,
+            RootId::Drop => svg::ids::RootIdPolicy::Drop,
+        },
+        force_preserve_aspect_ratio: args.force_preserve_aspect_ratio.clone(),
+        percent_dims: match args.percent_dims {
+            PercentDims::Strip => svg::normalize::PercentDims::Strip,
+            PercentDims::Keep => svg::normalize::PercentDims::Keep,
+            PercentDims::Error => svg::normalize::PercentDims::Error,
+        },
+        em_px: args.em_size,
+        precision: args.precision.into(),
+        emit,
+        template: args.template.clone(),
+        synonyms: args.synonyms.clone(),
+        titles: args.titles.clone(),
+        baseline: args.baseline.clone(),
+        source_comments: args.source_comments || profile.source_comments,
+        hash_attrs: args.hash_attrs || profile.hash_attrs,
+        generator_comment: args.generator_comment,
+        fragment: args.fragment,
+        root_attrs,
+        no_timestamp: args.no_timestamp,
+        keep_license_comments: args.keep_license_comments || profile.keep_license_comments,
+        dedupe: args.dedupe || profile.dedupe,
+        detect_duplicates: args.detect_duplicates || profile.detect_duplicates,
+        check: args.check,
+        backup: args.backup.clone(),
+        archive_dir: args.archive_dir.clone(),
+        lock: args.lock || args.update_lock || profile.lock,
+        update_lock: args.update_lock,
+        max_file_size: args.max_file_size,
+        max_icon_bytes: args.max_icon_bytes,
+        max_icon_size: args.max_icon_size,
+        max_output_size: args.max_output_size,
+        raster_images: match args.raster_images {
+            RasterImages::Allow => svg::raster::RasterPolicy::Allow,
+            RasterImages::Warn => svg::raster::RasterPolicy::Warn,
+            RasterImages::Strip => svg::raster::RasterPolicy::Strip,
+        },
+        raster_threshold: args.raster_threshold,
+        flatten_transforms: args.flatten_transforms
+            || profile.flatten_transforms
+            || listed(Stage::FlattenTransforms),
+        compact_paths: args.compact_paths || profile.compact_paths || listed(Stage::CompactPaths),
+        shared_defs: args.shared_defs || profile.shared_defs || listed(Stage::SharedDefs),
+        plain_href: args.plain_href || profile.plain_href || listed(Stage::PlainHref),
+        optimize: args.optimize || profile.optimize || listed(Stage::Optimize),
+        pipeline,
+        #[cfg(feature = "plugins")]
+        plugins: args
+            .plugin
+            .iter()
+            .map(|path| svg::plugin::Plugin::load(path))
+            .collect::<Result<_, _>>()?,
+        strip_attributes: config.attributes.strip,
+        keep_attributes: config.attributes.keep,
+        colors: config.colors.into_iter().collect(),
+        themable: args.themable || listed(Stage::Themable),
+        roles: config
+            .roles
+            .into_iter()
+            .flat_map(|(role, colors)| colors.into_iter().map(move |c| (c, role.clone())))
+            .collect(),
+        aliases: config.aliases.into_iter().collect(),
+        deprecated: config.deprecated.into_iter().collect(),
+        tags: config.tags.into_iter().collect(),
+        chunk_by: args.chunk_by.map(|by| match by {
+            ChunkBy::Dir => svg::chunk::ChunkBy::Dir,
+            ChunkBy::Size => svg::chunk::ChunkBy::Size(args.chunk_size),
+        }),
+        iconify: args.input_iconify.clone(),
+        packages: args.from_package.clone(),
+        overlays: args.overlay.clone(),
+        files: files_from(args)?,
+        json_events: matches!(
+            args.command,
+            Some(Commands::Watch {
+                events: Some(EventFormat::Json)
+            })
+        ),
+    })
 }
 
 /// Generate shell completion files into an output directory.
 fn generate_completions(shell: Shell, out_dir: Option<std::path::PathBuf>) -> Result<(), AppError> {
     use clap_complete::{Shell as ClapShell, generate_to};
+    use clap_complete_fig::Fig;
+    use clap_complete_nushell::Nushell;
     let mut cmd = cli::command();
-    let shell = match shell {
-        Shell::Bash => ClapShell::Bash,
-        Shell::Zsh => ClapShell::Zsh,
-        Shell::Fish => ClapShell::Fish,
-        Shell::Pwsh => ClapShell::PowerShell,
-        Shell::Elvish => ClapShell::Elvish,
-    };
     let out_dir = out_dir.unwrap_or_else(|| std::env::current_dir().unwrap());
     std::fs::create_dir_all(&out_dir).map_err(|e| AppError::WriteFile {
         path: out_dir.display().to_string(),
         source: e,
     })?;
     let bin_name = env!("CARGO_PKG_NAME");
-    let _path =
-        generate_to(shell, &mut cmd, bin_name, &out_dir).map_err(|e| AppError::WriteFile {
-            path: out_dir.display().to_string(),
-            source: std::io::Error::other(e.to_string()),
-        })?;
+    let generated = match shell {
+        Shell::Nushell => generate_to(Nushell, &mut cmd, bin_name, &out_dir),
+        Shell::Fig => generate_to(Fig, &mut cmd, bin_name, &out_dir),
+        Shell::Bash => generate_to(ClapShell::Bash, &mut cmd, bin_name, &out_dir),
+        Shell::Zsh => generate_to(ClapShell::Zsh, &mut cmd, bin_name, &out_dir),
+        Shell::Fish => generate_to(ClapShell::Fish, &mut cmd, bin_name, &out_dir),
+        Shell::Pwsh => generate_to(ClapShell::PowerShell, &mut cmd, bin_name, &out_dir),
+        Shell::Elvish => generate_to(ClapShell::Elvish, &mut cmd, bin_name, &out_dir),
+    };
+    let _path = generated.map_err(|e| AppError::WriteFile {
+        path: out_dir.display().to_string(),
+        source: std::io::Error::other(e.to_string()),
+    })?;
     println!(
         "Generated completions for {bin_name} in {}",
         out_dir.display()
@@ -78,6 +445,15 @@ fn generate_completions(shell: Shell, out_dir: Option<std::path::PathBuf>) -> Re
     Ok(())
 }
 
+/// Print the config file's JSON Schema to stdout.
+fn print_config_schema() -> Result<(), AppError> {
+    use std::io::Write;
+    writeln!(std::io::stdout().lock(), "{}", Config::schema()).map_err(|e| AppError::WriteFile {
+        path: "<stdout>".to_string(),
+        source: e,
+    })
+}
+
 /// Generate a man page for the CLI into an output directory.
 fn generate_man(out_dir: Option<std::path::PathBuf>) -> Result<(), AppError> {
     let cmd = cli::command();
@@ -103,7 +479,9 @@ fn generate_man(out_dir: Option<std::path::PathBuf>) -> Result<(), AppError> {
 
 /// Initialize tracing subscriber based on environment and CLI verbosity flags.
 fn init_tracing(args: &Args) {
-    use tracing_subscriber::{EnvFilter, Registry, fmt, prelude::*};
+    use std::io::IsTerminal;
+    use tracing_subscriber::fmt::format::FmtSpan;
+    use tracing_subscriber::{EnvFilter, Layer, Registry, fmt, prelude::*};
 
     // If RUST_LOG is set, prefer it; otherwise derive from CLI flags
     let env_filter = match EnvFilter::try_from_default_env() {
@@ -129,12 +507,29 @@ fn init_tracing(args: &Args) {
         }
     };
 
-    let fmt_layer = fmt::layer()
+    let terminal = std::io::stderr().is_terminal();
+    let format = args.log_format.unwrap_or(if terminal {
+        LogFormat::Pretty
+    } else {
+        LogFormat::Json
+    });
+    let base = fmt::layer()
         .with_target(false)
         .with_file(false)
         .with_line_number(false)
         .with_timer(fmt::time::uptime())
-        .json();
+        .with_ansi(terminal)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(std::io::stderr);
+    let fmt_layer = match format {
+        LogFormat::Pretty => base
+            .pretty()
+            .with_file(false)
+            .with_line_number(false)
+            .boxed(),
+        LogFormat::Compact => base.compact().boxed(),
+        LogFormat::Json => base.json().boxed(),
+    };
 
     let subscriber = Registry::default().with(env_filter).with(fmt_layer);
     let _ = tracing::subscriber::set_global_default(subscriber);
@@ -1,28 +1,339 @@
-use crate::error::AppError;
+use crate::error::{AppError, Warning, WarningKind};
+use pipeline::Stage;
+use std::borrow::Cow;
+#[cfg(feature = "fs")]
 use std::collections::hash_map::DefaultHasher;
+#[cfg(feature = "fs")]
 use std::hash::{Hash, Hasher};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+#[cfg(feature = "fs")]
+use std::time::{Duration, Instant};
+use std::time::{SystemTime, UNIX_EPOCH};
 use winnow::Parser;
 
+#[cfg(feature = "fs")]
+mod archive;
+#[cfg(feature = "fs")]
+mod budget;
+#[cfg(feature = "fs")]
+pub(crate) mod cache;
+#[cfg(feature = "fs")]
+pub mod chunk;
+mod colors;
+mod defs;
+#[cfg(feature = "fs")]
+mod deprecation;
+#[cfg(feature = "fs")]
+mod diff;
+#[cfg(feature = "fs")]
+pub mod emit;
+#[cfg(feature = "fs")]
+mod events;
+mod flatten;
+#[cfg(feature = "fs")]
+mod generator;
+mod href;
+#[cfg(feature = "fs")]
+mod iconify;
 pub mod ids;
+#[cfg(feature = "fs")]
+mod inputs;
+#[cfg(feature = "fs")]
+mod lock;
+mod markup;
+mod namespaces;
+pub mod naming;
 pub mod normalize;
+mod optimize;
+#[cfg(feature = "fs")]
+mod overlays;
+#[cfg(feature = "fs")]
+pub mod packages;
 mod parsing;
+mod path_data;
+pub mod pipeline;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod raster;
+#[cfg(feature = "fs")]
+mod reload;
 pub mod sanitize;
+#[cfg(feature = "fs")]
+mod shared_defs;
+#[cfg(feature = "fs")]
+mod sidecar;
+#[cfg(feature = "fs")]
+mod similar;
+pub(crate) mod source;
+#[cfg(feature = "fs")]
+mod sprites;
+mod strict;
+mod strip_attrs;
+pub mod summary;
+#[cfg(feature = "fs")]
+mod template;
+#[cfg(feature = "fs")]
+mod titles;
 mod transform;
+#[cfg(feature = "fs")]
+pub mod usage;
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone)]
 pub struct RunOpts {
     pub quiet: bool,
     pub verbose: bool,
     pub dry_run: bool,
+    /// Under `dry_run`, print a unified diff of each output against what
+    /// would be written
+    pub diff: bool,
     pub fail_on_warn: bool,
+    /// Reject unknown root attributes and ids that sanitization would change
+    pub strict: bool,
+    /// Warning rules that are not reported (`--allow`)
+    pub allow: Vec<WarningKind>,
+    /// Warning rules that fail the build (`--deny`); wins over `allow`
+    pub deny: Vec<WarningKind>,
+    /// Number of failing warnings tolerated before the build fails
+    pub warning_baseline: usize,
+    /// Quiet period in milliseconds after the last change before the event
+    /// watcher rebuilds
     pub debounce_ms: u64,
+    /// Threads parsing inputs; `None` uses one per logical CPU
+    pub jobs: Option<std::num::NonZeroUsize>,
     pub poll: bool,
+    /// Delay in milliseconds between directory scans of the polling watcher
+    pub poll_interval_ms: u64,
+    /// File whose creation or modification forces a full watch rebuild
+    pub rebuild_trigger: Option<std::path::PathBuf>,
+    /// Directory for the persistent build cache; disabled when `None`
+    pub cache_dir: Option<std::path::PathBuf>,
+    /// Also honor `.gitignore` files when listing inputs
+    pub use_gitignore: bool,
+    /// Resolve symlinked inputs instead of skipping them
+    pub follow_symlinks: bool,
+    /// Icon names (or files listing names) to include; empty includes all
+    pub only: Vec<String>,
+    /// Casing applied to filename-derived pattern ids
+    pub id_case: naming::IdCase,
+    /// Descend into subdirectories of the input directory
+    pub recursive: bool,
+    /// Separator joining subdirectory names and the filename in pattern ids
+    pub id_separator: String,
+    /// Handling of the root `<svg id>` of each input
+    pub root_id: ids::RootIdPolicy,
+    /// Normalized `preserveAspectRatio` applied to every icon, replacing its own
+    pub force_preserve_aspect_ratio: Option<String>,
+    /// Handling of percentage `width`/`height` on icon roots
+    pub percent_dims: normalize::PercentDims,
+    /// Pixels per `em`/`rem` when converting root `width`/`height`
+    pub em_px: f64,
+    /// Decimal places kept in root `width`, `height`, and `viewBox` numbers
+    pub precision: usize,
+    /// Extra artifacts rendered from the same parse pass as the sprite
+    #[cfg(feature = "fs")]
+    pub emit: Vec<emit::EmitTarget>,
+    /// Tera template rendering the sprite instead of the built-in markup
+    pub template: Option<std::path::PathBuf>,
+    /// Synonyms file for the `search-index` artifact
+    pub synonyms: Option<std::path::PathBuf>,
+    /// JSON file mapping icon ids to titles and descriptions
+    pub titles: Option<std::path::PathBuf>,
+    /// Earlier sprite the build summary lists added, removed, and changed
+    /// icons against
+    pub baseline: Option<std::path::PathBuf>,
+    /// Print watch progress as JSON lines on stdout instead of summaries
+    pub json_events: bool,
+    /// Precede each pattern with a comment naming its source file and hash
+    pub source_comments: bool,
+    /// Stamp each pattern with a `data-hash` of its emitted content
+    pub hash_attrs: bool,
+    /// Start the sprite with a comment naming the generator and build time
+    pub generator_comment: bool,
+    /// Write only the `<defs>` element, without the `<svg>` wrapper; ignored
+    /// with `template` and `chunk_by`
+    pub fragment: bool,
+    /// Attributes set on the sprite's root `<svg>` after its `xmlns`
+    pub root_attrs: Vec<(String, String)>,
+    /// Leave the build time out of the generator comment
+    pub no_timestamp: bool,
+    /// Hoist license/copyright comments from inputs into one block atop the sprite
+    pub keep_license_comments: bool,
+    /// Emit identical icons once, aliasing later copies to the first
+    pub dedupe: bool,
+    /// Warn about icons that match after normalizing whitespace, precision and attribute order
+    pub detect_duplicates: bool,
+    /// Compare outputs with the files on disk instead of writing them
+    pub check: bool,
+    /// Copy an existing output here before overwriting it; `{file}` is the
+    /// output's file name, and relative paths are beside the output
+    pub backup: Option<String>,
+    /// Directory receiving a timestamped copy of every sprite written
+    pub archive_dir: Option<std::path::PathBuf>,
+    /// Check icons against the lockfile beside the sprite, and keep it current
+    pub lock: bool,
+    /// Accept icon content changes into the lockfile instead of failing
+    pub update_lock: bool,
+    /// Reject input files (or stdin documents) larger than this many bytes
+    pub max_file_size: Option<u64>,
+    /// Reject icons whose emitted pattern markup is larger than this many bytes
+    pub max_icon_bytes: Option<u64>,
+    /// Warn about icons whose markup, with their shared definitions, is
+    /// larger than this many bytes
+    pub max_icon_size: Option<u64>,
+    /// Warn when the whole sprite is larger than this many bytes
+    pub max_output_size: Option<u64>,
+    /// Handling of `<image>` elements embedding raster data
+    pub raster_images: raster::RasterPolicy,
+    /// Apply `raster_images` only to data URIs of at least this many bytes
+    pub raster_threshold: u64,
+    /// Fold `transform` attributes on paths and groups into the path data
+    pub flatten_transforms: bool,
+    /// Rewrite path data in its shortest form (relative commands, implicit repeats)
+    pub compact_paths: bool,
+    /// Hoist gradients and filters into the sprite's root `<defs>`, merging identical copies
+    pub shared_defs: bool,
+    /// Rewrite `xlink:href` as SVG 2 `href`
+    pub plain_href: bool,
+    /// Drop comments, metadata, empty groups and indentation from icons
+    pub optimize: bool,
+    /// Markup passes run on each icon, in order; stages left out are skipped
+    pub pipeline: Vec<Stage>,
+    /// WebAssembly transforms run, in order, by the `plugins` stage
+    #[cfg(feature = "plugins")]
+    pub plugins: Vec<plugin::Plugin>,
+    /// Attribute names or `*` patterns dropped from every icon
+    pub strip_attributes: Vec<String>,
+    /// When not empty, the only attribute names or `*` patterns icons keep
+    pub keep_attributes: Vec<String>,
+    /// Color replacements (`from`, `to`) for fills, strokes and stop colors
+    pub colors: Vec<(String, String)>,
+    /// Wrap literal colors in `var(--icon-<role>, <original>)`
+    pub themable: bool,
+    /// Theme roles (`color`, `role`) used by `themable`; unlisted colors take
+    /// the property name as their role
+    pub roles: Vec<(String, String)>,
+    /// Extra ids (`alias`, `icon`) rendered as patterns pointing at an icon,
+    /// so renamed icons keep their old ids
+    pub aliases: Vec<(String, String)>,
+    /// Icons marked deprecated in the config, as `(icon, message)`
+    pub deprecated: Vec<(String, String)>,
+    /// Tags of icons from the config, as `(icon, tags)`
+    pub tags: Vec<(String, Vec<String>)>,
+    /// Split the sprite into several sheets plus a combined manifest
+    #[cfg(feature = "fs")]
+    pub chunk_by: Option<chunk::ChunkBy>,
+    /// Iconify JSON collections whose icons are built alongside the directory's
+    pub iconify: Vec<std::path::PathBuf>,
+    /// Installed npm packages whose SVGs are built alongside the directory's
+    #[cfg(feature = "fs")]
+    pub packages: Vec<packages::PackageSource>,
+    /// Directories whose icons replace same-named icons of earlier inputs
+    pub overlays: Vec<std::path::PathBuf>,
+    /// Explicit input files (`--files-from`) used instead of listing the
+    /// input directory, which still names icons in subdirectories
+    pub files: Option<Vec<std::path::PathBuf>>,
+}
+
+impl Default for RunOpts {
+    fn default() -> Self {
+        RunOpts {
+            quiet: false,
+            verbose: false,
+            dry_run: false,
+            diff: false,
+            fail_on_warn: false,
+            strict: false,
+            allow: Vec::new(),
+            deny: Vec::new(),
+            warning_baseline: 0,
+            debounce_ms: 0,
+            poll_interval_ms: 500,
+            rebuild_trigger: None,
+            jobs: None,
+            poll: false,
+            cache_dir: None,
+            use_gitignore: false,
+            follow_symlinks: false,
+            only: Vec::new(),
+            id_case: naming::IdCase::default(),
+            recursive: false,
+            id_separator: "-".to_string(),
+            root_id: ids::RootIdPolicy::default(),
+            force_preserve_aspect_ratio: None,
+            percent_dims: normalize::PercentDims::default(),
+            em_px: normalize::DEFAULT_EM_PX,
+            precision: normalize::DEFAULT_PRECISION,
+            #[cfg(feature = "fs")]
+            emit: Vec::new(),
+            template: None,
+            synonyms: None,
+            titles: None,
+            baseline: None,
+            json_events: false,
+            source_comments: false,
+            hash_attrs: false,
+            generator_comment: false,
+            fragment: false,
+            root_attrs: Vec::new(),
+            no_timestamp: false,
+            keep_license_comments: false,
+            dedupe: false,
+            detect_duplicates: false,
+            check: false,
+            backup: None,
+            archive_dir: None,
+            lock: false,
+            update_lock: false,
+            max_file_size: None,
+            max_icon_bytes: None,
+            max_icon_size: None,
+            max_output_size: None,
+            raster_images: raster::RasterPolicy::default(),
+            raster_threshold: 0,
+            flatten_transforms: false,
+            compact_paths: false,
+            shared_defs: false,
+            plain_href: false,
+            optimize: false,
+            pipeline: Stage::DEFAULT.to_vec(),
+            #[cfg(feature = "plugins")]
+            plugins: Vec::new(),
+            strip_attributes: Vec::new(),
+            keep_attributes: Vec::new(),
+            colors: Vec::new(),
+            themable: false,
+            roles: Vec::new(),
+            aliases: Vec::new(),
+            deprecated: Vec::new(),
+            tags: Vec::new(),
+            #[cfg(feature = "fs")]
+            chunk_by: None,
+            iconify: Vec::new(),
+            #[cfg(feature = "fs")]
+            packages: Vec::new(),
+            overlays: Vec::new(),
+            files: None,
+        }
+    }
+}
+
+impl RunOpts {
+    /// Whether warnings of `kind` are reported, i.e. not `--allow`ed.
+    fn reports(&self, kind: WarningKind) -> bool {
+        self.deny.contains(&kind) || !self.allow.contains(&kind)
+    }
+
+    /// Whether a reported warning of `kind` fails the build.
+    #[cfg(feature = "fs")]
+    fn fails_on(&self, kind: WarningKind) -> bool {
+        self.fail_on_warn || self.deny.contains(&kind)
+    }
 }
 
 // Cached representation of a processed SVG used by incremental watch builds
+// (in-memory builds only read the markup)
 #[derive(Clone)]
-struct CacheEntry {
+#[cfg_attr(not(feature = "fs"), allow(dead_code))]
+pub(crate) struct CacheEntry {
     mtime: SystemTime,
     len: u64,
     name: String,
@@ -30,37 +341,63 @@ struct CacheEntry {
     children: String,
     child_ids: Vec<String>,
     path_str: String,
-    warnings: usize,
+    warnings: Vec<Warning>,
+    /// SHA-256 of the raw input content
+    hash: String,
+    /// License/copyright comments found before the root `<svg>`
+    licenses: Vec<String>,
+    /// Definitions hoisted into the sprite's root `<defs>` as `(id, markup)`
+    shared_defs: Vec<(String, String)>,
+    /// Number of colors replaced through the config's `[colors]` table
+    colors_remapped: usize,
+    /// Attributes removed through the config's `[attributes]` table, as
+    /// `(attribute, count)`
+    attributes_stripped: Vec<(String, usize)>,
+    /// CSS variables introduced by `--themable` as `(name, default)`
+    theme_vars: Vec<(String, String)>,
+    /// Source of the same-named icon this `--overlay` icon replaced
+    overrides: Option<String>,
 }
 
-/// A struct to represent a SVG file
-#[cfg_attr(not(test), allow(dead_code))]
-struct SvgSprite {
+/// A struct to represent a SVG file, borrowing from the entry it was parsed
+/// into where it can
+struct SvgSprite<'a> {
     /// The name of the SVG file
-    name: String,
+    name: Cow<'a, str>,
     /// The attributes of the svg tag
-    attributes: Vec<(String, String)>,
+    attributes: Cow<'a, [(String, String)]>,
     /// The children of the svg tag
-    children: String,
+    children: Cow<'a, str>,
 }
 
-impl SvgSprite {
-    #[cfg(test)]
+impl<'a> From<&'a CacheEntry> for SvgSprite<'a> {
+    fn from(entry: &'a CacheEntry) -> Self {
+        SvgSprite {
+            name: Cow::Borrowed(&entry.name),
+            attributes: Cow::Borrowed(&entry.out_attrs),
+            children: Cow::Borrowed(&entry.children),
+        }
+    }
+}
+
+impl SvgSprite<'static> {
+    #[cfg(all(test, feature = "fs"))]
     pub fn new(name: String, attributes: Vec<(&str, &str)>, children: String) -> Self {
-        let attributes = attributes
+        let attributes: Vec<(String, String)> = attributes
             .iter()
             .map(|(a, b)| (a.to_string(), b.to_string()))
             .collect();
         SvgSprite {
-            name,
-            attributes,
-            children,
+            name: Cow::Owned(name),
+            attributes: Cow::Owned(attributes),
+            children: Cow::Owned(children),
         }
     }
 }
 
-/// Parse input SVGs in `directory` and stream-write a sprite to `file`.
-/// Minimizes memory usage by writing patterns as they are parsed.
+/// Parse input SVGs in `directory` and write a sprite to `file`, returning
+/// the warnings of the build. The sprite is rendered in memory first; an
+/// existing output with identical content is left untouched.
 ///
 /// Example (dry run):
 /// ```
@@ -71,198 +408,641 @@ impl SvgSprite {
 /// fs::write(tmp.join("a.svg"), "<svg width='1' height='1'><g/></svg>").unwrap();
 /// let opts = svg_sheet::svg::RunOpts { dry_run: true, ..Default::default() };
 /// let out = tmp.join("sprite.svg");
-/// svg_sheet::svg::process_with_opts(tmp.to_str().unwrap(), out.to_str().unwrap(), opts).unwrap();
+/// let warnings = svg_sheet::svg::process_with_opts(tmp.to_str().unwrap(), out.to_str().unwrap(), opts).unwrap();
+/// assert_eq!(warnings[0].code(), "W0004"); // no viewBox
 /// let _ = fs::remove_dir_all(tmp);
 /// ```
-pub fn process_with_opts(directory: &str, file: &str, opts: RunOpts) -> Result<(), AppError> {
-    // Collect candidate SVG file entries first to detect empty inputs without creating output.
-    let out_basename = std::path::Path::new(file)
-        .file_name()
-        .and_then(|s| s.to_str())
-        .map(|s| s.to_string());
-    let entries: Vec<std::path::PathBuf> = std::fs::read_dir(directory)
-        .map_err(|e| AppError::ReadDir {
-            path: directory.to_string(),
-            source: e,
-        })?
-        .filter_map(|e| e.ok().map(|de| de.path()))
-        .filter(|p| p.extension().is_some_and(|ext| ext == "svg"))
-        .filter(|p| {
-            if let Some(ref base) = out_basename {
-                return p.file_name().and_then(|s| s.to_str()) != Some(base.as_str());
-            }
-            true
-        })
-        .collect();
+#[cfg(feature = "fs")]
+pub fn process_with_opts(
+    directory: &str,
+    file: &str,
+    opts: RunOpts,
+) -> Result<Vec<Warning>, AppError> {
+    let built = build(directory, file, &opts)?;
+    summary::print(&built, &opts);
+    Ok(built.warnings)
+}
+
+/// Build `directory` into `file` once, returning the summary instead of
+/// printing it.
+#[cfg(feature = "fs")]
+pub(crate) fn build(
+    directory: &str,
+    file: &str,
+    opts: &RunOpts,
+) -> Result<summary::BuildSummary, AppError> {
+    let started = Instant::now();
+    let root = std::path::Path::new(directory);
+    let (entries, collected) = input_files(directory, file, opts)?;
+
+    let mut disk_cache = opts
+        .cache_dir
+        .as_deref()
+        .map(|dir| cache::BuildCache::load(dir, opts));
+    let parsed = parse_inputs(root, &entries, disk_cache.as_mut(), opts)?;
+    for entry in &parsed {
+        log_warnings(entry, opts);
+    }
+    if let Some(dc) = disk_cache.as_mut() {
+        let live = entries.iter().map(|p| p.display().to_string()).collect();
+        dc.retain(&live);
+        dc.save()?;
+    }
+    for entry in &collected {
+        log_warnings(entry, opts);
+    }
+
+    let refs: Vec<&CacheEntry> = parsed.iter().chain(&collected).collect();
+    let mut built = write_entries(directory, file, &refs, opts)?;
+    built.duration = started.elapsed();
+    Ok(built)
+}
 
-    if entries.is_empty() {
+/// List the inputs selected for a build of `directory` into `file` (see
+/// `select_inputs`). Fails with `NoSvgFiles` when nothing remains.
+#[cfg(feature = "fs")]
+fn input_files(
+    directory: &str,
+    file: &str,
+    opts: &RunOpts,
+) -> Result<(Vec<std::path::PathBuf>, Vec<CacheEntry>), AppError> {
+    inputs::warn_if_output_in_inputs(directory, file, opts);
+    let (entries, collected) = select_inputs(directory, file, opts)?;
+    if entries.is_empty() && collected.is_empty() {
         return Err(AppError::NoSvgFiles {
             path: directory.to_string(),
         });
     }
+    Ok((entries, collected))
+}
 
-    // Choose output sink based on dry_run
-    let writer: Box<dyn std::io::Write> = if opts.dry_run {
-        Box::new(std::io::BufWriter::new(std::io::sink()))
-    } else {
-        let file_opt = std::fs::File::create(file).map_err(|e| AppError::WriteFile {
-            path: file.to_string(),
-            source: e,
-        })?;
-        Box::new(std::io::BufWriter::new(file_opt))
-    };
-    let mut writer = writer;
+/// Select the inputs of a build of `directory` into `file`: every `.svg`
+/// except the outputs themselves, and the parsed icons of sprite files in the
+/// directory, `--input-iconify` collections, `--from-package` packages, and
+/// `--overlay` directories (replacing same-named icons), all narrowed by
+/// `--only`. Without the directory, only the other sources are read.
+#[cfg(feature = "fs")]
+fn select_inputs(
+    directory: &str,
+    file: &str,
+    opts: &RunOpts,
+) -> Result<(Vec<std::path::PathBuf>, Vec<CacheEntry>), AppError> {
+    let _span = tracing::debug_span!("list_inputs", directory).entered();
+    let root = std::path::Path::new(directory);
+    let (mut entries, mut collected) =
+        sprites::split_inputs(root, listed_inputs(directory, file, opts)?, opts)?;
+    collected.extend(iconify::load_all(opts)?);
+    collected.extend(packages::load_all(opts)?);
+    let overlay = overlays::load_all(opts)?;
+    overlays::apply(root, &mut entries, &mut collected, overlay, opts);
+    let entries = inputs::select_only(root, entries, &collected, opts)?;
+    tracing::debug!(count = entries.len() + collected.len(), "Selected inputs");
+    Ok((entries, collected))
+}
 
-    use std::io::Write as _;
-    writer
-        .write_all(b"<svg xmlns=\"http://www.w3.org/2000/svg\"><defs>")
-        .map_err(|e| AppError::WriteFile {
-            path: file.to_string(),
-            source: e,
-        })?;
+/// The input files of a build of `directory` into `file`: the `--files-from`
+/// list when given, otherwise the listing of `directory`.
+#[cfg(feature = "fs")]
+fn listed_inputs(
+    directory: &str,
+    file: &str,
+    opts: &RunOpts,
+) -> Result<Vec<std::path::PathBuf>, AppError> {
+    match &opts.files {
+        Some(files) => Ok(inputs::list_files(directory, file, files, opts)),
+        None if lists_directory(directory, opts) => inputs::list_inputs(directory, file, opts),
+        None => Ok(Vec::new()),
+    }
+}
 
-    // Warning counter for this run
-    let mut warn_count: usize = 0;
+/// Whether `directory` should be listed for a build: always, unless icons
+/// come from collections, packages, or overlays and the directory does not
+/// exist.
+#[cfg(feature = "fs")]
+fn lists_directory(directory: &str, opts: &RunOpts) -> bool {
+    (opts.iconify.is_empty() && opts.packages.is_empty() && opts.overlays.is_empty())
+        || std::path::Path::new(directory).exists()
+}
 
-    for path in entries {
-        let file_name = match path.file_name().and_then(|n| n.to_str()) {
-            Some(s) => s.to_string(),
-            None => continue,
-        };
-        let name = file_name.trim_end_matches(".svg").to_string();
-        let content = std::fs::read_to_string(&path).map_err(|e| AppError::ReadFile {
-            path: path.display().to_string(),
-            source: e,
-        })?;
+/// List the icons built from `directory` that no file under `src` mentions,
+/// in build order.
+///
+/// A file mentions an icon when its id appears as a whole identifier-like token
+/// (`#arrow`, `"arrow"`) or as the tail of one (`icon-arrow`). The sprite
+/// `file`, the icon directory, and `--emit` artifacts are not scanned.
+///
+/// Example:
+/// ```
+/// use std::fs;
+/// let tmp = std::env::temp_dir().join("svg_sheet_unused");
+/// let _ = fs::remove_dir_all(&tmp);
+/// fs::create_dir_all(tmp.join("icons")).unwrap();
+/// fs::create_dir_all(tmp.join("src")).unwrap();
+/// fs::write(tmp.join("icons/a.svg"), "<svg width='1'><g/></svg>").unwrap();
+/// fs::write(tmp.join("icons/b.svg"), "<svg width='1'><g/></svg>").unwrap();
+/// fs::write(tmp.join("src/app.js"), "use('#a')").unwrap();
+/// let unused = svg_sheet::svg::unused_icons(
+///     tmp.join("icons").to_str().unwrap(),
+///     tmp.join("sprite.svg").to_str().unwrap(),
+///     &[tmp.join("src")],
+///     &Default::default(),
+/// ).unwrap();
+/// assert_eq!(unused, vec!["b".to_string()]);
+/// let _ = fs::remove_dir_all(tmp);
+/// ```
+#[cfg(feature = "fs")]
+pub fn unused_icons(
+    directory: &str,
+    file: &str,
+    src: &[std::path::PathBuf],
+    opts: &RunOpts,
+) -> Result<Vec<String>, AppError> {
+    let names = icon_names(directory, file, opts)?;
+    let set = names.iter().cloned().collect();
+    let used = usage::referenced_names(src, &set, &scan_skips(directory, file, opts), opts)?;
+    Ok(names.into_iter().filter(|n| !used.contains(n)).collect())
+}
 
-        let pre = preprocess_svg_content(&content);
-        let mut s = pre.as_str();
-        let (attributes, children) = match parsing::parse_svg.parse_next(&mut s) {
-            Ok(ok) => ok,
-            Err(e) => {
-                return Err(AppError::ParseSvg {
-                    path: path.display().to_string(),
-                    message: format!("{e:?}"),
-                });
-            }
-        };
+/// Print the icons no file under `src` references, one per line on stdout.
+/// With `fail`, any unused icon is an `UnusedIcons` error.
+#[cfg(feature = "fs")]
+pub fn unused_with_opts(
+    directory: &str,
+    file: &str,
+    src: &[std::path::PathBuf],
+    fail: bool,
+    opts: RunOpts,
+) -> Result<(), AppError> {
+    let unused = unused_icons(directory, file, src, &opts)?;
+    for name in &unused {
+        println!("{name}");
+    }
+    if !opts.quiet {
+        eprintln!("{} unused icon(s)", unused.len());
+    }
+    if fail && !unused.is_empty() {
+        return Err(AppError::UnusedIcons { names: unused });
+    }
+    Ok(())
+}
 
-        // Convert attributes and handle root <svg id> policy: move id -> data-id after sanitization
-        let mut out_attrs: Vec<(String, String)> = Vec::new();
-        let mut root_id_raw: Option<&str> = None;
-        let mut pending_viewbox: Option<String> = None;
-        let mut saw_width = false;
-        let mut saw_height = false;
-        let mut saw_viewbox = false;
-        for (k, v) in &attributes {
-            if *k == "id" {
-                root_id_raw = Some(v);
-            } else if *k == "width" || *k == "height" {
-                match normalize::normalize_length(v) {
-                    Some(nv) => out_attrs.push(((*k).to_string(), nv)),
-                    None => {
-                        return Err(AppError::InvalidDimension {
-                            path: path.display().to_string(),
-                            attr: (*k).to_string(),
-                            value: (*v).to_string(),
-                        });
-                    }
-                }
-                if *k == "width" {
-                    saw_width = true;
-                } else {
-                    saw_height = true;
-                }
-            } else if *k == "viewBox" {
-                match normalize::normalize_viewbox(v) {
-                    Some(vb) => pending_viewbox = Some(vb),
-                    None => {
-                        return Err(AppError::InvalidViewBox {
-                            path: path.display().to_string(),
-                            value: (*v).to_string(),
-                        });
-                    }
-                }
-                saw_viewbox = true;
-            } else {
-                out_attrs.push(((*k).to_string(), (*v).to_string()));
-            }
-        }
+/// Find `<sprite>#<id>` references under `src` whose id is not an icon built
+/// from `directory`, where `<sprite>` is the file name of `file`.
+///
+/// Example:
+/// ```
+/// use std::fs;
+/// let tmp = std::env::temp_dir().join("svg_sheet_missing");
+/// let _ = fs::remove_dir_all(&tmp);
+/// fs::create_dir_all(tmp.join("icons")).unwrap();
+/// fs::create_dir_all(tmp.join("src")).unwrap();
+/// fs::write(tmp.join("icons/arrow.svg"), "<svg width='1'><g/></svg>").unwrap();
+/// fs::write(tmp.join("src/app.css"), "a { fill: url(sprite.svg#arow) }").unwrap();
+/// let missing = svg_sheet::svg::missing_icons(
+///     tmp.join("icons").to_str().unwrap(),
+///     tmp.join("sprite.svg").to_str().unwrap(),
+///     &[tmp.join("src")],
+///     &Default::default(),
+/// ).unwrap();
+/// assert_eq!(missing[0].name, "arow");
+/// let _ = fs::remove_dir_all(tmp);
+/// ```
+#[cfg(feature = "fs")]
+pub fn missing_icons(
+    directory: &str,
+    file: &str,
+    src: &[std::path::PathBuf],
+    opts: &RunOpts,
+) -> Result<Vec<usage::IconReference>, AppError> {
+    let names: std::collections::HashSet<String> =
+        icon_names(directory, file, opts)?.into_iter().collect();
+    Ok(scan_references(directory, file, src, opts)?
+        .into_iter()
+        .filter(|r| !names.contains(&r.name))
+        .collect())
+}
 
-        if let Some(idv) = root_id_raw {
-            let sanitized = sanitize::sanitize_id(idv);
-            if sanitized.is_empty() {
-                return Err(AppError::InvalidIdAfterSanitize {
-                    path: path.display().to_string(),
-                    original: idv.to_string(),
-                });
-            }
-            if ids::references_id(children, idv) {
-                return Err(AppError::RootIdReferenced {
-                    path: path.display().to_string(),
-                    id: idv.to_string(),
-                });
-            }
-            // Warning: moved root id to data-id
-            warn_count += 1;
-            tracing::warn!(path = %path.display(), original_id = %idv, sanitized_id = %sanitized, "Root <svg id> moved to data-id");
-            out_attrs.push(("data-id".to_string(), sanitized));
-        }
-        if let Some(vb) = pending_viewbox {
-            out_attrs.push(("viewBox".to_string(), vb));
-        }
+/// Find `<sprite>#<id>` references under `src` to icons marked deprecated
+/// (see `deprecated_icons`), each with its deprecation message.
+///
+/// Example:
+/// ```
+/// use std::fs;
+/// let tmp = std::env::temp_dir().join("svg_sheet_deprecated_refs");
+/// let _ = fs::remove_dir_all(&tmp);
+/// fs::create_dir_all(tmp.join("icons")).unwrap();
+/// fs::create_dir_all(tmp.join("src")).unwrap();
+/// fs::write(tmp.join("icons/old.svg"), "<svg width='1' data-deprecated='Use new'><g/></svg>").unwrap();
+/// fs::write(tmp.join("src/app.css"), "a { fill: url(sprite.svg#old) }").unwrap();
+/// let refs = svg_sheet::svg::deprecated_references(
+///     tmp.join("icons").to_str().unwrap(),
+///     tmp.join("sprite.svg").to_str().unwrap(),
+///     &[tmp.join("src")],
+///     &Default::default(),
+/// ).unwrap();
+/// assert_eq!((refs[0].0.name.as_str(), refs[0].1.as_str()), ("old", "Use new"));
+/// let _ = fs::remove_dir_all(tmp);
+/// ```
+#[cfg(feature = "fs")]
+pub fn deprecated_references(
+    directory: &str,
+    file: &str,
+    src: &[std::path::PathBuf],
+    opts: &RunOpts,
+) -> Result<Vec<(usage::IconReference, String)>, AppError> {
+    let deprecated: std::collections::HashMap<String, String> =
+        deprecated_icons(directory, file, opts)?
+            .into_iter()
+            .collect();
+    if deprecated.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(scan_references(directory, file, src, opts)?
+        .into_iter()
+        .filter_map(|r| {
+            let message = deprecated.get(&r.name)?.clone();
+            Some((r, message))
+        })
+        .collect())
+}
 
-        // Rewrite internal ids -> data-id and ensure no per-file duplicates
-        let (rewritten_children, _data_ids) = ids::rewrite_ids_to_data_ids(children);
+/// Every `<sprite>#<id>` reference under `src`, where `<sprite>` is the file
+/// name of `file`.
+#[cfg(feature = "fs")]
+fn scan_references(
+    directory: &str,
+    file: &str,
+    src: &[std::path::PathBuf],
+    opts: &RunOpts,
+) -> Result<Vec<usage::IconReference>, AppError> {
+    let sprite_name = std::path::Path::new(file)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| file.to_string());
+    usage::sprite_references(src, &sprite_name, &scan_skips(directory, file, opts), opts)
+}
 
-        // Warnings for missing dims/viewBox
-        if !saw_width {
-            warn_count += 1;
-            tracing::warn!(path = %path.display(), file = %file_name, "Missing width on root <svg>");
+/// Print each reference to a missing icon as `path:line: name` on stdout and
+/// fail with `MissingIcons` when there is any. References to deprecated icons
+/// are reported on stderr without failing.
+#[cfg(feature = "fs")]
+pub fn missing_with_opts(
+    directory: &str,
+    file: &str,
+    src: &[std::path::PathBuf],
+    opts: RunOpts,
+) -> Result<(), AppError> {
+    let missing = missing_icons(directory, file, src, &opts)?;
+    for r in &missing {
+        println!("{}:{}: {}", r.path.display(), r.line, r.name);
+    }
+    let deprecated = deprecated_references(directory, file, src, &opts)?;
+    for (r, message) in &deprecated {
+        eprintln!(
+            "{}:{}: warning: {}",
+            r.path.display(),
+            r.line,
+            deprecation::describe(&r.name, message)
+        );
+    }
+    if !opts.quiet && !deprecated.is_empty() {
+        eprintln!("{} reference(s) to deprecated icon(s)", deprecated.len());
+    }
+    if missing.is_empty() {
+        if !opts.quiet {
+            eprintln!("0 missing icon reference(s)");
         }
-        if !saw_height {
-            warn_count += 1;
-            tracing::warn!(path = %path.display(), file = %file_name, "Missing height on root <svg>");
+        return Ok(());
+    }
+    let names: std::collections::BTreeSet<String> =
+        missing.iter().map(|r| r.name.clone()).collect();
+    Err(AppError::MissingIcons {
+        references: missing.len(),
+        names: names.into_iter().collect(),
+    })
+}
+
+/// Icons a build of `directory` into `file` would mark deprecated, as
+/// `(name, message)`. Only the root element of each input file is read.
+#[cfg(feature = "fs")]
+fn deprecated_icons(
+    directory: &str,
+    file: &str,
+    opts: &RunOpts,
+) -> Result<Vec<(String, String)>, AppError> {
+    let root = std::path::Path::new(directory);
+    let (paths, collected) = input_files(directory, file, opts)?;
+    let mut found = Vec::new();
+    for path in &paths {
+        let (name, doc) = read_source(root, path, opts)?;
+        let mut body: &str = &doc.body;
+        let attrs = parsing::parse_svg
+            .parse_next(&mut body)
+            .map(|(attrs, _)| attrs)
+            .unwrap_or_default();
+        let sidecar = sidecar::load(path)?;
+        if let Some(message) = deprecation::message(&name, &attrs, sidecar.as_ref(), opts) {
+            found.push((name, message.to_string()));
         }
-        if !saw_viewbox {
-            warn_count += 1;
-            tracing::warn!(path = %path.display(), file = %file_name, "Missing viewBox on root <svg>");
+    }
+    for entry in &collected {
+        let sidecar = sidecar::load(std::path::Path::new(&entry.path_str))?;
+        if let Some(message) =
+            deprecation::message(&entry.name, &entry.out_attrs, sidecar.as_ref(), opts)
+        {
+            found.push((entry.name.clone(), message.to_string()));
         }
+    }
+    Ok(found)
+}
 
-        let attrs = out_attrs
-            .iter()
-            .map(|(k, v)| format!(r#" {k}="{v}""#))
-            .collect::<String>();
-        let pattern = format!(r#"<pattern id="{name}"{attrs}>{rewritten_children}</pattern>"#);
-        writer
-            .write_all(pattern.as_bytes())
-            .map_err(|e| AppError::WriteFile {
-                path: file.to_string(),
-                source: e,
-            })?;
+/// Pattern ids of the icons a build of `directory` into `file` would contain.
+#[cfg(feature = "fs")]
+fn icon_names(directory: &str, file: &str, opts: &RunOpts) -> Result<Vec<String>, AppError> {
+    let root = std::path::Path::new(directory);
+    let (entries, collected) = input_files(directory, file, opts)?;
+    let mut names: Vec<String> = entries
+        .iter()
+        .filter_map(|p| inputs::icon_name(root, p, opts))
+        .chain(collected.into_iter().map(|e| e.name))
+        .collect();
+    for (alias, target) in &opts.aliases {
+        if names.contains(target) {
+            names.push(alias.clone());
+        }
     }
+    Ok(names)
+}
 
-    writer
-        .write_all(b"</defs></svg>")
-        .and_then(|_| writer.flush())
-        .map_err(|e| AppError::WriteFile {
-            path: file.to_string(),
+/// Paths a source scan must not read: the icon directory, the sprite, and
+/// `--emit` artifacts all list every icon.
+#[cfg(feature = "fs")]
+fn scan_skips(directory: &str, file: &str, opts: &RunOpts) -> Vec<std::path::PathBuf> {
+    let mut skip = vec![
+        std::path::PathBuf::from(directory),
+        std::path::PathBuf::from(file),
+    ];
+    skip.extend(opts.emit.iter().map(|t| std::path::PathBuf::from(&t.path)));
+    skip
+}
+
+/// Path value (`-d -` / `-f -`) selecting stdin for input or stdout for output.
+pub const STDIO: &str = "-";
+
+/// Read one SVG, or several concatenated SVG documents, from `reader` and
+/// write the sprite to `file` (`-` for stdout), returning the warnings of the
+/// build.
+///
+/// Each document's pattern id is its sanitized root `id`; documents without
+/// one are named `icon-1`, `icon-2`, ... in stream order.
+///
+/// Example:
+/// ```
+/// let input = "<svg id='a' width='1' height='1' viewBox='0 0 1 1'><g/></svg><svg width='1'><g/></svg>";
+/// let out = std::env::temp_dir().join("svg_sheet_stream.svg");
+/// let opts = svg_sheet::svg::RunOpts { quiet: true, ..Default::default() };
+/// svg_sheet::svg::process_stream(input.as_bytes(), out.to_str().unwrap(), opts).unwrap();
+/// let sprite = std::fs::read_to_string(&out).unwrap();
+/// assert!(sprite.contains("<pattern id=\"a\"") && sprite.contains("<pattern id=\"icon-2\""));
+/// let _ = std::fs::remove_file(out);
+/// ```
+#[cfg(feature = "fs")]
+pub fn process_stream<R: std::io::Read>(
+    mut reader: R,
+    file: &str,
+    opts: RunOpts,
+) -> Result<Vec<Warning>, AppError> {
+    const ORIGIN: &str = "<stdin>";
+    let started = Instant::now();
+    let mut input = String::new();
+    reader
+        .read_to_string(&mut input)
+        .map_err(|e| AppError::ReadFile {
+            path: ORIGIN.to_string(),
             source: e,
         })?;
+    let documents = split_svg_stream(&input, ORIGIN)?;
+    if documents.is_empty() {
+        return Err(AppError::NoSvgFiles {
+            path: ORIGIN.to_string(),
+        });
+    }
 
-    if opts.fail_on_warn && warn_count > 0 {
-        return Err(AppError::WarningsPresent { count: warn_count });
+    let mut parsed: Vec<CacheEntry> = Vec::with_capacity(documents.len());
+    for (i, (doc, root_id)) in documents.iter().enumerate() {
+        let name = root_id
+            .as_deref()
+            .map(sanitize::sanitize_id)
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| format!("icon-{}", i + 1));
+        let origin = format!("{ORIGIN}#{}", i + 1);
+        if let Some(limit) = opts.max_file_size {
+            check_size(&origin, "--max-file-size", doc.len() as u64, limit)?;
+        }
+        let entry = parse_icon(&name, &origin, doc, &opts)?;
+        log_warnings(&entry, &opts);
+        parsed.push(entry);
     }
 
-    Ok(())
+    let refs: Vec<&CacheEntry> = parsed.iter().collect();
+    let mut built = write_entries("", file, &refs, &opts)?;
+    built.duration = started.elapsed();
+    summary::print(&built, &opts);
+    Ok(built.warnings)
 }
 
-/// Watch a directory for changes and rebuild the sprite when inputs change.
+/// Split concatenated SVG documents into `(document, root id)` pairs.
+/// XML prologs and comments between documents are skipped.
+#[cfg(feature = "fs")]
+fn split_svg_stream(input: &str, origin: &str) -> Result<Vec<(String, Option<String>)>, AppError> {
+    let mut documents = Vec::new();
+    let mut rest = preprocess_svg_content(input);
+    while !rest.trim().is_empty() {
+        let mut cursor = rest;
+        let (attributes, _) = parsing::parse_svg.parse_next(&mut cursor).map_err(|_| {
+            parsing::located_error(
+                &format!("{origin}#{}", documents.len() + 1),
+                rest,
+                parsing::line_column(input, input.len() - rest.len()),
+            )
+        })?;
+        let root_id = attributes
+            .iter()
+            .find(|(k, _)| *k == "id")
+            .map(|(_, v)| v.to_string());
+        let consumed = rest.len() - cursor.len();
+        documents.push((rest[..consumed].to_string(), root_id));
+        rest = preprocess_svg_content(cursor);
+    }
+    Ok(documents)
+}
+
+/// Build a sprite entirely in memory from `(name, content)` pairs.
+///
+/// Each `name` is sanitized into a pattern id (`arrow left` becomes
+/// `arrow-left`, see `sanitize::sanitize_id`); a name with nothing left fails
+/// with `InvalidIdAfterSanitize`. `content` runs through the same parsing,
+/// validation, and normalization as files read from disk. Warnings are logged
+/// via `tracing`. Names giving the same id are rejected with `IdCollision`.
+///
+/// Example:
+/// ```
+/// let sprite = svg_sheet::svg::build_from_sources([
+///     ("dot".to_string(), "<svg width='1' height='1' viewBox='0 0 1 1'><circle r='1'/></svg>".to_string()),
+/// ])
+/// .unwrap();
+/// assert!(sprite.contains("<pattern id=\"dot\""));
+/// ```
+pub fn build_from_sources<I>(sources: I) -> Result<String, AppError>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut seen: std::collections::HashMap<String, String> = std::collections::HashMap::new();
+    let mut entries: Vec<CacheEntry> = Vec::new();
+    for (name, content) in sources {
+        let id = sanitize::sanitize_id(&name);
+        if id.is_empty() {
+            return Err(AppError::InvalidIdAfterSanitize {
+                path: name.clone(),
+                original: name,
+            });
+        }
+        if id != name {
+            tracing::debug!(name = %name, id = %id, "Sanitized icon name");
+        }
+        if let Some(first) = seen.get(&id) {
+            return Err(AppError::IdCollision {
+                id,
+                first_path: first.clone(),
+                second_path: name,
+            });
+        }
+        seen.insert(id.clone(), name.clone());
+        let opts = RunOpts::default();
+        let entry = parse_icon(&id, &name, &content, &opts)?;
+        log_warnings(&entry, &opts);
+        entries.push(entry);
+    }
+    check_ids(&entries.iter().collect::<Vec<_>>())?;
+    let sprites: Vec<SvgSprite> = entries.iter().map(SvgSprite::from).collect();
+    Ok(transform::transform(&sprites))
+}
+
+/// Set by `request_shutdown`; watch loops stop once it is.
+#[cfg(feature = "fs")]
+static SHUTDOWN: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Ask a running watch loop to stop after any build in progress, report the
+/// session, and return `Ok(())`. Safe to call from a signal handler.
+#[cfg(feature = "fs")]
+pub fn request_shutdown() {
+    SHUTDOWN.store(true, std::sync::atomic::Ordering::SeqCst);
+}
+
+#[cfg(feature = "fs")]
+fn shutdown_requested() -> bool {
+    SHUTDOWN.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+/// Set by `request_rebuild`; cleared when a watch loop picks it up.
+#[cfg(feature = "fs")]
+static REBUILD: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Ask a running watch loop to rebuild now, re-reading every input even if
+/// nothing changed on disk. Safe to call from a signal handler.
+#[cfg(feature = "fs")]
+pub fn request_rebuild() {
+    REBUILD.store(true, std::sync::atomic::Ordering::SeqCst);
+}
+
+/// Whether a rebuild was requested since the last call.
+#[cfg(feature = "fs")]
+fn take_rebuild_request() -> bool {
+    REBUILD.swap(false, std::sync::atomic::Ordering::SeqCst)
+}
+
+/// Sleep for `duration`, waking early on `request_shutdown` or
+/// `request_rebuild`.
+#[cfg(feature = "fs")]
+fn idle(duration: Duration) {
+    let until = Instant::now() + duration;
+    while !shutdown_requested() && !REBUILD.load(std::sync::atomic::Ordering::SeqCst) {
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        std::thread::sleep(left.min(SHUTDOWN_POLL));
+    }
+}
+
+/// How often an idle watch loop checks for `request_shutdown` and
+/// `request_rebuild`.
+#[cfg(feature = "fs")]
+const SHUTDOWN_POLL: Duration = Duration::from_millis(100);
+
+/// Reload the watched config file if it changed and report the new settings.
+/// Returns true when `opts` was replaced and the sprite should be rebuilt.
+#[cfg(feature = "fs")]
+fn reload_config(config: &mut Option<reload::ConfigWatch<'_>>, opts: &mut RunOpts) -> bool {
+    let Some(watch) = config.as_mut() else {
+        return false;
+    };
+    let Some(result) = watch.poll() else {
+        return false;
+    };
+    let path = watch.path().display().to_string();
+    match result {
+        Ok(new) => {
+            let changed = reload::changed_settings(opts, &new);
+            *opts = new;
+            if opts.json_events {
+                events::emit(
+                    opts,
+                    events::WatchEvent::ConfigReloaded {
+                        path: &path,
+                        changed,
+                    },
+                );
+            } else if !opts.quiet {
+                if changed.is_empty() {
+                    println!("Reloaded {path}: no settings changed");
+                } else {
+                    println!("Reloaded {path}: {} changed", changed.join(", "));
+                }
+            }
+            true
+        }
+        Err(e) => {
+            tracing::error!(
+                code = e.code(),
+                error = %e,
+                "Config not reloaded; keeping the previous settings"
+            );
+            events::emit(opts, events::WatchEvent::failed(&e));
+            false
+        }
+    }
+}
+
+/// Print (or emit as an event) the totals of a stopped watch session.
+#[cfg(feature = "fs")]
+fn report_session(mut totals: summary::WatchTotals, started: Instant, opts: &RunOpts) {
+    totals.duration = started.elapsed();
+    if opts.json_events {
+        events::emit(opts, events::WatchEvent::stopped(&totals));
+    } else if let Some(text) = summary::render_session(&totals, opts) {
+        println!("{text}");
+    }
+}
+
+/// Watch a directory for changes and rebuild the sprite when inputs change,
+/// until `request_shutdown` is called.
 ///
 /// Example:
 /// ```no_run
 /// svg_sheet::svg::watch_with_opts("svgs", "sprite.svg", Default::default()).unwrap();
 /// ```
+#[cfg(feature = "fs")]
 pub fn watch_with_opts(directory: &str, file: &str, opts: RunOpts) -> Result<(), AppError> {
     if opts.poll {
         return watch_poll(directory, file, opts);
@@ -270,13 +1050,56 @@ pub fn watch_with_opts(directory: &str, file: &str, opts: RunOpts) -> Result<(),
     watch_event(directory, file, opts)
 }
 
+/// Like `watch_with_opts`, but also watch the config file at `config`: when
+/// it is created, edited, or removed, `load` is called for fresh options,
+/// the changed settings are reported, and the sprite is rebuilt with them.
+/// If `load` fails, the error is reported and the previous options are kept.
+///
+/// Example:
+/// ```no_run
+/// use svg_sheet::svg::{RunOpts, watch_with_config};
+/// watch_with_config("svgs", "sprite.svg", RunOpts::default(), "svg_sheet.toml".as_ref(), || {
+///     Ok(RunOpts::default())
+/// })
+/// .unwrap();
+/// ```
+#[cfg(feature = "fs")]
+pub fn watch_with_config(
+    directory: &str,
+    file: &str,
+    opts: RunOpts,
+    config: &std::path::Path,
+    load: impl FnMut() -> Result<RunOpts, AppError>,
+) -> Result<(), AppError> {
+    let config = Some(reload::ConfigWatch::new(config, Box::new(load)));
+    if opts.poll {
+        return watch_poll_with(directory, file, opts, config);
+    }
+    watch_event_with(directory, file, opts, config)
+}
+
 /// Event-based watcher using native file notifications.
 ///
 /// Example:
 /// ```no_run
 /// svg_sheet::svg::watch_event("svgs", "sprite.svg", Default::default()).unwrap();
 /// ```
+#[cfg(feature = "fs")]
 pub fn watch_event(directory: &str, file: &str, opts: RunOpts) -> Result<(), AppError> {
+    watch_event_with(directory, file, opts, None)
+}
+
+#[cfg(feature = "fs")]
+fn watch_event_with(
+    directory: &str,
+    file: &str,
+    mut opts: RunOpts,
+    mut config: Option<reload::ConfigWatch<'_>>,
+) -> Result<(), AppError> {
+    SHUTDOWN.store(false, std::sync::atomic::Ordering::SeqCst);
+    REBUILD.store(false, std::sync::atomic::Ordering::SeqCst);
+    let started = Instant::now();
+    let mut totals = summary::WatchTotals::default();
     if !opts.quiet {
         tracing::info!(
             directory,
@@ -285,6 +1108,15 @@ pub fn watch_event(directory: &str, file: &str, opts: RunOpts) -> Result<(), App
             "Watching for changes (Ctrl+C to stop)"
         );
     }
+    events::emit(
+        &opts,
+        events::WatchEvent::WatchStarted {
+            directory,
+            file,
+            mode: "event",
+        },
+    );
+    inputs::warn_if_output_in_inputs(directory, file, &opts);
     use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
     use std::sync::mpsc::channel;
 
@@ -299,50 +1131,78 @@ pub fn watch_event(directory: &str, file: &str, opts: RunOpts) -> Result<(), App
         path: "watch".into(),
         source: std::io::Error::other(e.to_string()),
     })?;
-    watcher
-        .watch(std::path::Path::new(directory), RecursiveMode::NonRecursive)
-        .map_err(|e| AppError::ReadDir {
-            path: directory.into(),
+    if lists_directory(directory, &opts) {
+        watcher
+            .watch(
+                std::path::Path::new(directory),
+                if opts.recursive {
+                    RecursiveMode::Recursive
+                } else {
+                    RecursiveMode::NonRecursive
+                },
+            )
+            .map_err(|e| AppError::ReadDir {
+                path: directory.into(),
+                source: std::io::Error::other(e.to_string()),
+            })?;
+    }
+    for collection in &opts.iconify {
+        watcher
+            .watch(collection, RecursiveMode::NonRecursive)
+            .map_err(|e| AppError::ReadFile {
+                path: collection.display().to_string(),
+                source: std::io::Error::other(e.to_string()),
+            })?;
+    }
+    for dir in &opts.overlays {
+        let mode = if opts.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher.watch(dir, mode).map_err(|e| AppError::ReadDir {
+            path: dir.display().to_string(),
             source: std::io::Error::other(e.to_string()),
         })?;
+    }
 
     let mut cache: std::collections::HashMap<String, CacheEntry> = std::collections::HashMap::new();
-    let debounce = Duration::from_millis(if opts.debounce_ms == 0 {
-        1
-    } else {
-        opts.debounce_ms
-    });
-    let mut last_trigger = SystemTime::now();
-    let mut pending = false;
-    loop {
-        match rx.recv() {
+    let mut trigger = opts.rebuild_trigger.as_deref().map(reload::Trigger::new);
+    // Each change restarts the quiet period, so a burst of changes (a save
+    // touching several files, a branch checkout) becomes a single rebuild.
+    let mut deadline: Option<Instant> = None;
+    while !shutdown_requested() {
+        let wait = deadline.map_or(SHUTDOWN_POLL, |d| {
+            d.saturating_duration_since(Instant::now())
+                .min(SHUTDOWN_POLL)
+        });
+        match rx.recv_timeout(wait) {
             Ok(Ok(_evt)) => {
-                pending = true;
-                let elapsed = last_trigger.elapsed().unwrap_or(Duration::ZERO);
-                if elapsed >= debounce {
-                    if let Err(e) = rebuild_once(directory, file, &mut cache, opts) {
-                        tracing::error!(error = %e, "Rebuild failed");
-                    }
-                    last_trigger = SystemTime::now();
-                    pending = false;
-                }
+                deadline = Some(Instant::now() + Duration::from_millis(opts.debounce_ms));
             }
             Ok(Err(e)) => {
                 tracing::error!(error = %e, "Watch error");
             }
-            Err(_disconnected) => break,
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        if reload_config(&mut config, &mut opts)
+            | take_rebuild_request()
+            | trigger.as_mut().is_some_and(reload::Trigger::fired)
+        {
+            cache.clear();
+            deadline = Some(Instant::now());
         }
 
-        if pending {
-            std::thread::sleep(debounce);
-            if let Err(e) = rebuild_once(directory, file, &mut cache, opts) {
-                tracing::error!(error = %e, "Rebuild failed");
-            }
-            last_trigger = SystemTime::now();
-            pending = false;
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            deadline = None;
+            events::emit(&opts, events::WatchEvent::BuildStarted);
+            let result = rebuild_once(directory, file, &mut cache, &opts);
+            report_rebuild(result, &opts, &mut totals);
         }
     }
 
+    report_session(totals, started, &opts);
     Ok(())
 }
 
@@ -352,7 +1212,22 @@ pub fn watch_event(directory: &str, file: &str, opts: RunOpts) -> Result<(), App
 /// ```no_run
 /// svg_sheet::svg::watch_poll("svgs", "sprite.svg", Default::default()).unwrap();
 /// ```
+#[cfg(feature = "fs")]
 pub fn watch_poll(directory: &str, file: &str, opts: RunOpts) -> Result<(), AppError> {
+    watch_poll_with(directory, file, opts, None)
+}
+
+#[cfg(feature = "fs")]
+fn watch_poll_with(
+    directory: &str,
+    file: &str,
+    mut opts: RunOpts,
+    mut config: Option<reload::ConfigWatch<'_>>,
+) -> Result<(), AppError> {
+    SHUTDOWN.store(false, std::sync::atomic::Ordering::SeqCst);
+    REBUILD.store(false, std::sync::atomic::Ordering::SeqCst);
+    let started = Instant::now();
+    let mut totals = summary::WatchTotals::default();
     if !opts.quiet {
         tracing::info!(
             directory,
@@ -361,38 +1236,55 @@ pub fn watch_poll(directory: &str, file: &str, opts: RunOpts) -> Result<(), AppE
             "Watching for changes (Ctrl+C to stop)"
         );
     }
+    events::emit(
+        &opts,
+        events::WatchEvent::WatchStarted {
+            directory,
+            file,
+            mode: "poll",
+        },
+    );
+    inputs::warn_if_output_in_inputs(directory, file, &opts);
     let mut cache: std::collections::HashMap<String, CacheEntry> = std::collections::HashMap::new();
     let mut last_state: Option<u64> = None;
+    let mut trigger = opts.rebuild_trigger.as_deref().map(reload::Trigger::new);
 
-    loop {
-        let state = dir_state_hash(directory)?;
+    while !shutdown_requested() {
+        if reload_config(&mut config, &mut opts)
+            | take_rebuild_request()
+            | trigger.as_mut().is_some_and(reload::Trigger::fired)
+        {
+            cache.clear();
+            last_state = None;
+        }
+        let interval = Duration::from_millis(opts.poll_interval_ms);
+        let state = dir_state_hash(directory, file, &opts)?;
         if last_state.as_ref().is_some_and(|s| *s == state) {
-            std::thread::sleep(Duration::from_millis(500));
+            idle(interval);
             continue;
         }
 
-        let out_basename = std::path::Path::new(file)
-            .file_name()
-            .and_then(|s| s.to_str())
-            .map(|s| s.to_string());
-        let paths: Vec<std::path::PathBuf> = std::fs::read_dir(directory)
-            .map_err(|e| AppError::ReadDir {
-                path: directory.to_string(),
-                source: e,
-            })?
-            .filter_map(|e| e.ok().map(|de| de.path()))
-            .filter(|p| p.extension().is_some_and(|ext| ext == "svg"))
-            .filter(|p| {
-                if let Some(ref base) = out_basename {
-                    return p.file_name().and_then(|s| s.to_str()) != Some(base.as_str());
-                }
-                true
-            })
-            .collect();
+        let build_started = Instant::now();
+        events::emit(&opts, events::WatchEvent::BuildStarted);
+        let (paths, collected) = match select_inputs(directory, file, &opts) {
+            Ok(selected) => selected,
+            Err(e) => {
+                report_rebuild(Err(e), &opts, &mut totals);
+                last_state = Some(state);
+                idle(interval);
+                continue;
+            }
+        };
 
-        if paths.is_empty() {
+        if paths.is_empty() && collected.is_empty() {
             tracing::warn!(directory, "No SVG files found");
-            std::thread::sleep(Duration::from_millis(500));
+            events::emit(
+                &opts,
+                events::WatchEvent::BuildSkipped {
+                    reason: "no SVG files found".into(),
+                },
+            );
+            idle(interval);
             continue;
         }
 
@@ -414,8 +1306,9 @@ pub fn watch_poll(directory: &str, file: &str, opts: RunOpts) -> Result<(), AppE
                 None => true,
             };
             if needs {
-                match build_cache_entry(p) {
+                match build_cache_entry(std::path::Path::new(directory), p, &opts) {
                     Ok(mut ce) => {
+                        log_warnings(&ce, &opts);
                         ce.mtime = mtime;
                         ce.len = len;
                         ce.path_str = key.clone();
@@ -424,7 +1317,7 @@ pub fn watch_poll(directory: &str, file: &str, opts: RunOpts) -> Result<(), AppE
                     Err(e) => {
                         eprintln!("Skip {}: {e}", p.display());
                         // skip writing this round
-                        std::thread::sleep(Duration::from_millis(500));
+                        idle(interval);
                         continue;
                     }
                 }
@@ -432,39 +1325,49 @@ pub fn watch_poll(directory: &str, file: &str, opts: RunOpts) -> Result<(), AppE
         }
 
         // Write sprite from cache in sorted order
-        if let Err(e) = write_sprite_from_cache(file, &cache, &paths, opts) {
-            eprintln!("Write failed: {e}");
-            if let Some(src) = std::error::Error::source(&e) {
-                eprintln!("Caused by: {src}");
-            }
-        } else if !opts.quiet || opts.verbose {
-            tracing::info!("Rebuilt sprite");
-        }
+        let result = write_sprite_from_cache(directory, file, &cache, &paths, &collected, &opts)
+            .map(|mut built| {
+                built.duration = build_started.elapsed();
+                Some(built)
+            });
+        report_rebuild(result, &opts, &mut totals);
         last_state = Some(state);
-        std::thread::sleep(Duration::from_millis(500));
+        idle(interval);
     }
-}
 
-/// Compute a stable hash of the directory state considering `.svg` file names,
-/// sizes, and modification times.
-fn dir_state_hash(directory: &str) -> Result<u64, AppError> {
-    let entries = std::fs::read_dir(directory).map_err(|e| AppError::ReadDir {
-        path: directory.to_string(),
-        source: e,
-    })?;
+    report_session(totals, started, &opts);
+    Ok(())
+}
+
+/// Compute a stable hash of the directory state considering the input `.svg`
+/// files' paths, sizes, and modification times, plus the ignore files,
+/// `_defs.svg`, `--input-iconify` collections, and `--overlay` icons. The output `file` is excluded so writing it
+/// does not retrigger a build.
+#[cfg(feature = "fs")]
+fn dir_state_hash(directory: &str, file: &str, opts: &RunOpts) -> Result<u64, AppError> {
+    let paths = listed_inputs(directory, file, opts)?;
+    let defs = std::path::Path::new(directory).join(shared_defs::DEFS_FILE);
+    let mut overlay = Vec::new();
+    for dir in &opts.overlays {
+        overlay.extend(inputs::list_svg_files(&dir.display().to_string(), opts)?);
+    }
+    let sidecars: Vec<std::path::PathBuf> = paths
+        .iter()
+        .chain(&overlay)
+        .map(|p| sidecar::path_of(p))
+        .collect();
     let mut hasher = DefaultHasher::new();
-    for entry in entries {
-        let Ok(entry) = entry else { continue };
-        let _path = entry.path();
-        let file_name = entry.file_name();
-        let Ok(name_str) = file_name.into_string() else {
-            continue;
-        };
-        if !name_str.ends_with(".svg") {
-            continue;
-        }
-        name_str.hash(&mut hasher);
-        if let Ok(md) = entry.metadata() {
+    for path in paths
+        .iter()
+        .chain(&inputs::ignore_files(directory, &paths, opts))
+        .chain(std::iter::once(&defs))
+        .chain(&opts.iconify)
+        .chain(&overlay)
+        .chain(&sidecars)
+        .chain(&opts.titles)
+    {
+        path.hash(&mut hasher);
+        if let Ok(md) = inputs::metadata(path, opts) {
             md.len().hash(&mut hasher);
             if let Ok(modified) = md.modified() {
                 hash_time(&modified, &mut hasher);
@@ -475,6 +1378,7 @@ fn dir_state_hash(directory: &str) -> Result<u64, AppError> {
 }
 
 /// Hash a `SystemTime` into the provided hasher using seconds and nanos.
+#[cfg(feature = "fs")]
 fn hash_time(t: &SystemTime, hasher: &mut DefaultHasher) {
     if let Ok(dur) = t.duration_since(UNIX_EPOCH) {
         dur.as_secs().hash(hasher);
@@ -483,7 +1387,8 @@ fn hash_time(t: &SystemTime, hasher: &mut DefaultHasher) {
 }
 
 /// Strip BOM, leading XML prolog, and comments before the root `<svg>` tag.
-fn preprocess_svg_content(input: &str) -> String {
+#[cfg(feature = "fs")]
+fn preprocess_svg_content(input: &str) -> &str {
     let mut s = input.trim_start_matches('\u{feff}');
     // Iteratively skip whitespace + XML declarations or comments before <svg
     loop {
@@ -504,7 +1409,7 @@ fn preprocess_svg_content(input: &str) -> String {
         s = trimmed;
         break;
     }
-    s.to_string()
+    s
 }
 
 // Sanitize an id by dropping leading invalid chars and replacing internal
@@ -519,25 +1424,28 @@ fn preprocess_svg_content(input: &str) -> String {
 // sprite rendering moved to svg::transform
 
 /// Recompute cache entries for changed inputs and write out the sprite once.
+/// Returns `None` when the build was skipped (no inputs or an unparsable file).
+#[cfg(feature = "fs")]
 fn rebuild_once(
     directory: &str,
     file: &str,
     cache: &mut std::collections::HashMap<String, CacheEntry>,
-    opts: RunOpts,
-) -> Result<(), AppError> {
+    opts: &RunOpts,
+) -> Result<Option<summary::BuildSummary>, AppError> {
+    let started = Instant::now();
     // Collect current svg files
-    let paths: Vec<std::path::PathBuf> = std::fs::read_dir(directory)
-        .map_err(|e| AppError::ReadDir {
-            path: directory.to_string(),
-            source: e,
-        })?
-        .filter_map(|e| e.ok().map(|de| de.path()))
-        .filter(|p| p.extension().is_some_and(|ext| ext == "svg"))
-        .collect();
+    let root = std::path::Path::new(directory);
+    let (paths, collected) = select_inputs(directory, file, opts)?;
 
-    if paths.is_empty() {
+    if paths.is_empty() && collected.is_empty() {
         tracing::warn!(directory, "No SVG files found");
-        return Ok(());
+        events::emit(
+            opts,
+            events::WatchEvent::BuildSkipped {
+                reason: "no SVG files found".into(),
+            },
+        );
+        return Ok(None);
     }
 
     // Remove deleted entries
@@ -546,6 +1454,8 @@ fn rebuild_once(
     cache.retain(|k, _| live.contains(k));
 
     // Update or add changed files
+    let mut changed = Vec::new();
+    let mut stats = Vec::new();
     for p in &paths {
         let meta = match std::fs::metadata(p) {
             Ok(m) => m,
@@ -553,25 +1463,36 @@ fn rebuild_once(
         };
         let mtime = meta.modified().unwrap_or(UNIX_EPOCH);
         let len = meta.len();
-        let key = p.display().to_string();
-        let needs = match cache.get(&key) {
+        let needs = match cache.get(&p.display().to_string()) {
             Some(c) => c.mtime != mtime || c.len != len,
             None => true,
         };
         if needs {
-            match build_cache_entry(p) {
-                Ok(mut ce) => {
-                    ce.mtime = mtime;
-                    ce.len = len;
-                    ce.path_str = key.clone();
-                    cache.insert(key, ce);
-                }
-                Err(e) => {
-                    tracing::warn!(path = %p.display(), error = %e, "Skipping file due to error");
-                    return Ok(());
-                }
+            changed.push(p.clone());
+            stats.push((mtime, len));
+        }
+    }
+    match parse_inputs(root, &changed, None, opts) {
+        Ok(parsed) => {
+            for ((p, (mtime, len)), mut ce) in changed.iter().zip(stats).zip(parsed) {
+                log_warnings(&ce, opts);
+                let key = p.display().to_string();
+                ce.mtime = mtime;
+                ce.len = len;
+                ce.path_str = key.clone();
+                cache.insert(key, ce);
             }
         }
+        Err(e) => {
+            tracing::warn!(path = ?e.path(), error = %e, "Skipping file due to error");
+            events::emit(
+                opts,
+                events::WatchEvent::BuildSkipped {
+                    reason: e.to_string(),
+                },
+            );
+            return Ok(None);
+        }
     }
 
     // Check global id collisions
@@ -590,187 +1511,1195 @@ fn rebuild_once(
         }
     }
 
-    write_sprite_from_cache(file, cache, &paths, opts)
+    let mut built = write_sprite_from_cache(directory, file, cache, &paths, &collected, opts)?;
+    built.duration = started.elapsed();
+    Ok(Some(built))
 }
 
-/// Parse and normalize a single SVG file into a cache entry.
-fn build_cache_entry(path: &std::path::Path) -> Result<CacheEntry, AppError> {
-    let file_name = path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or_else(|| AppError::ReadFile {
-            path: path.display().to_string(),
-            source: std::io::Error::other("invalid filename"),
-        })?
-        .to_string();
-    let name = file_name.trim_end_matches(".svg").to_string();
-    let content = std::fs::read_to_string(path).map_err(|e| AppError::ReadFile {
+/// Report the outcome of a watch rebuild as a summary line or JSON event.
+#[cfg(feature = "fs")]
+fn report_rebuild(
+    result: Result<Option<summary::BuildSummary>, AppError>,
+    opts: &RunOpts,
+    totals: &mut summary::WatchTotals,
+) {
+    if !matches!(result, Ok(None)) {
+        totals.rebuilds += 1;
+    }
+    match result {
+        Ok(Some(built)) if opts.json_events => {
+            events::emit(opts, events::WatchEvent::succeeded(&built));
+        }
+        Ok(Some(built)) => summary::print(&built, opts),
+        Ok(None) => {}
+        Err(e) => {
+            totals.failures += 1;
+            tracing::error!(error = %e, code = e.code(), "Rebuild failed");
+            events::emit(opts, events::WatchEvent::failed(&e));
+        }
+    }
+}
+
+/// Parse and normalize a single SVG file below `root` into a cache entry.
+#[cfg(feature = "fs")]
+fn build_cache_entry(
+    root: &std::path::Path,
+    path: &std::path::Path,
+    opts: &RunOpts,
+) -> Result<CacheEntry, AppError> {
+    let (name, doc) = read_source(root, path, opts)?;
+    let mut entry = parse_document(&name, &path.display().to_string(), &doc, opts)?;
+    entry.warnings.extend(inputs::filename_warning(root, path));
+    Ok(entry)
+}
+
+/// Parse the input files `paths` below `root`, in order, on up to `--jobs`
+/// threads. With a persistent cache, files whose content hash is unchanged
+/// reuse their cached entry, and fresh entries are recorded in it. Fails with
+/// the error of the first failing file.
+#[cfg(feature = "fs")]
+fn parse_inputs(
+    root: &std::path::Path,
+    paths: &[std::path::PathBuf],
+    mut disk_cache: Option<&mut cache::BuildCache>,
+    opts: &RunOpts,
+) -> Result<Vec<CacheEntry>, AppError> {
+    let cached = disk_cache.as_deref();
+    let parse = |path: &std::path::PathBuf| {
+        let (name, doc) = read_source(root, path, opts)?;
+        let key = path.display().to_string();
+        if let Some(hit) = cached.and_then(|dc| dc.get(&key, &doc.hash)) {
+            tracing::debug!(path = %key, "Build cache hit");
+            return Ok((hit, None));
+        }
+        let mut entry = parse_document(&name, &key, &doc, opts)?;
+        entry.warnings.extend(inputs::filename_warning(root, path));
+        Ok((entry, Some(doc.hash)))
+    };
+    let jobs = parse_jobs(opts, paths.len());
+    let results: Vec<Result<(CacheEntry, Option<String>), AppError>> = if jobs == 1 {
+        paths.iter().map(parse).collect()
+    } else {
+        tracing::debug!(jobs, inputs = paths.len(), "Parsing in parallel");
+        let next = std::sync::atomic::AtomicUsize::new(0);
+        let mut slots: Vec<Option<_>> = paths.iter().map(|_| None).collect();
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..jobs)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        loop {
+                            let n = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            let Some(path) = paths.get(n) else {
+                                return done;
+                            };
+                            done.push((n, parse(path)));
+                        }
+                    })
+                })
+                .collect();
+            for worker in workers {
+                for (n, result) in worker.join().expect("parser thread panicked") {
+                    slots[n] = Some(result);
+                }
+            }
+        });
+        slots
+            .into_iter()
+            .map(|slot| slot.expect("every input is parsed"))
+            .collect()
+    };
+    let mut parsed = Vec::with_capacity(results.len());
+    for (path, result) in paths.iter().zip(results) {
+        let (entry, fresh) = result?;
+        if let (Some(dc), Some(hash)) = (disk_cache.as_mut(), fresh) {
+            dc.insert(&path.display().to_string(), hash, &entry);
+        }
+        parsed.push(entry);
+    }
+    Ok(parsed)
+}
+
+/// Threads for parsing `inputs` files: `--jobs`, or one per logical CPU, and
+/// never more than there are files. `--verbose` parses on one thread so the
+/// per-file logs keep input order.
+#[cfg(feature = "fs")]
+fn parse_jobs(opts: &RunOpts, inputs: usize) -> usize {
+    if opts.verbose {
+        return 1;
+    }
+    let jobs = opts
+        .jobs
+        .or_else(|| std::thread::available_parallelism().ok());
+    jobs.map_or(1, |n| n.get()).clamp(1, inputs.max(1))
+}
+
+/// Read an input file, returning its filename-derived name and document.
+/// The file is streamed, so only its root element is buffered.
+#[cfg(feature = "fs")]
+fn read_source(
+    root: &std::path::Path,
+    path: &std::path::Path,
+    opts: &RunOpts,
+) -> Result<(String, source::SourceDoc<'static>), AppError> {
+    let name = inputs::icon_name(root, path, opts).ok_or_else(|| AppError::ReadFile {
         path: path.display().to_string(),
-        source: e,
+        source: std::io::Error::other("invalid filename"),
     })?;
-    let pre = preprocess_svg_content(&content);
-    let mut s = pre.as_str();
-    let (attributes, children) =
-        parsing::parse_svg
-            .parse_next(&mut s)
-            .map_err(|e| AppError::ParseSvg {
-                path: path.display().to_string(),
-                message: format!("{e:?}"),
-            })?;
+    let read_err = |e| AppError::ReadFile {
+        path: path.display().to_string(),
+        source: e,
+    };
+    let file = std::fs::File::open(path).map_err(read_err)?;
+    if let Some(limit) = opts.max_file_size {
+        let size = file.metadata().map_err(read_err)?.len();
+        check_size(&path.display().to_string(), "--max-file-size", size, limit)?;
+    }
+    let doc = source::read_document(file).map_err(read_err)?;
+    tracing::debug!(file = %path.display(), icon = %name, bytes = doc.body.len(), "Read source");
+    Ok((name, doc))
+}
+
+/// Fail with `InputTooLarge` when `size` exceeds the `flag` limit.
+#[cfg(feature = "fs")]
+fn check_size(path: &str, flag: &'static str, size: u64, limit: u64) -> Result<(), AppError> {
+    if size > limit {
+        return Err(AppError::InputTooLarge {
+            path: path.to_string(),
+            flag,
+            size,
+            limit,
+        });
+    }
+    Ok(())
+}
+
+/// Parse, validate, and normalize one in-memory SVG document into a cache entry.
+/// `origin` names the source (a path or logical name) in errors and warnings.
+fn parse_icon(
+    name: &str,
+    origin: &str,
+    content: &str,
+    opts: &RunOpts,
+) -> Result<CacheEntry, AppError> {
+    parse_document(name, origin, &source::document(content), opts)
+}
 
+/// Parse, validate, and normalize a document read by `source::read_document`.
+#[tracing::instrument(level = "debug", skip_all, fields(file = origin, icon = name, bytes = doc.body.len()))]
+fn parse_document(
+    name: &str,
+    origin: &str,
+    doc: &source::SourceDoc<'_>,
+    opts: &RunOpts,
+) -> Result<CacheEntry, AppError> {
+    let mut s: &str = &doc.body;
+    let (attributes, children) = parsing::parse_svg
+        .parse_next(&mut s)
+        .map_err(|_| parsing::located_error(origin, &doc.body, doc.start))?;
+
+    if opts.strict {
+        strict::check(origin, &attributes, children)?;
+    }
+
+    // Apply the root <svg id> policy after sanitization
+    let normalize_span = tracing::debug_span!("normalize").entered();
     let mut out_attrs: Vec<(String, String)> = Vec::new();
     let mut root_id_raw: Option<&str> = None;
-    let mut pending_viewbox: Option<String> = None;
-    let mut warnings = 0usize;
-    let mut saw_width = false;
-    let mut saw_height = false;
-    let mut saw_viewbox = false;
+    let mut root_id_kept: Option<String> = None;
+    let mut warnings: Vec<Warning> = Vec::new();
+    let warning = |kind, message: String| Warning::new(kind, origin, message);
     for (k, v) in &attributes {
         if *k == "id" {
             root_id_raw = Some(v);
-        } else if *k == "width" || *k == "height" {
-            match normalize::normalize_length(v) {
-                Some(nv) => out_attrs.push(((*k).to_string(), nv)),
-                None => {
-                    return Err(AppError::InvalidDimension {
-                        path: path.display().to_string(),
-                        attr: (*k).to_string(),
-                        value: (*v).to_string(),
-                    });
-                }
-            }
-            if *k == "width" {
-                saw_width = true;
-            } else {
-                saw_height = true;
-            }
-        } else if *k == "viewBox" {
-            match normalize::normalize_viewbox(v) {
-                Some(vb) => pending_viewbox = Some(vb),
-                None => {
-                    return Err(AppError::InvalidViewBox {
-                        path: path.display().to_string(),
-                        value: (*v).to_string(),
-                    });
-                }
-            }
-            saw_viewbox = true;
         } else {
             out_attrs.push(((*k).to_string(), (*v).to_string()));
         }
     }
+    let saw = |name| attributes.iter().any(|(k, _)| *k == name);
+    let (saw_width, saw_height, saw_viewbox) = (saw("width"), saw("height"), saw("viewBox"));
     if let Some(idv) = root_id_raw {
         let sanitized = sanitize::sanitize_id(idv);
         if sanitized.is_empty() {
             return Err(AppError::InvalidIdAfterSanitize {
-                path: path.display().to_string(),
+                path: origin.to_string(),
                 original: idv.to_string(),
             });
         }
-        if ids::references_id(children, idv) {
+        if opts.root_id != ids::RootIdPolicy::Keep && ids::references_id(children, idv) {
             return Err(AppError::RootIdReferenced {
-                path: path.display().to_string(),
+                path: origin.to_string(),
                 id: idv.to_string(),
             });
         }
-        warnings += 1;
-        out_attrs.push(("data-id".to_string(), sanitized));
+        match opts.root_id {
+            ids::RootIdPolicy::Keep => {
+                if sanitized != idv {
+                    warnings.push(warning(
+                        WarningKind::RootId,
+                        format!("Root <svg id> '{idv}' sanitized to '{sanitized}'"),
+                    ));
+                }
+                root_id_kept = Some(sanitized);
+            }
+            ids::RootIdPolicy::DataId => {
+                warnings.push(warning(
+                    WarningKind::RootId,
+                    format!("Root <svg id> '{idv}' moved to data-id '{sanitized}'"),
+                ));
+                out_attrs.push(("data-id".to_string(), sanitized));
+            }
+            ids::RootIdPolicy::Drop => {
+                warnings.push(warning(
+                    WarningKind::RootId,
+                    format!("Root <svg id> '{idv}' dropped"),
+                ));
+            }
+        }
     }
-    if let Some(vb) = pending_viewbox {
-        out_attrs.push(("viewBox".to_string(), vb));
+    normalize_span.exit();
+
+    let _rewrite_span = tracing::debug_span!("rewrite_children").entered();
+    let mut children: Cow<str> = Cow::Borrowed(children);
+    let mut colors_remapped = 0;
+    let mut attributes_stripped = Vec::new();
+    let mut theme_vars = Vec::new();
+    let mut shared_defs = Vec::new();
+    let mut data_ids = Vec::new();
+    for stage in &opts.pipeline {
+        let _stage_span = tracing::trace_span!("stage", name = %stage).entered();
+        // Each pass borrows the markup; only a rewrite replaces it.
+        let rewritten = match stage {
+            Stage::NormalizeDims => {
+                normalize_dims(&mut out_attrs, origin, opts)?;
+                None
+            }
+            Stage::StripAttributes => {
+                let rules = strip_attrs::Rules {
+                    strip: &opts.strip_attributes,
+                    keep: &opts.keep_attributes,
+                };
+                if rules.is_empty() {
+                    None
+                } else {
+                    let (out, removed) = strip_attrs::apply(&mut out_attrs, &children, rules);
+                    let count: usize = removed.iter().map(|(_, n)| n).sum();
+                    if count > 0 {
+                        tracing::info!(path = %origin, count, "Stripped attributes");
+                    }
+                    attributes_stripped = removed;
+                    owned(out)
+                }
+            }
+            Stage::PlainHref if opts.plain_href => owned(href::plain(&children, &mut out_attrs)),
+            Stage::RasterImages => {
+                let (out, raster_warnings) =
+                    raster::apply(&children, opts.raster_images, opts.raster_threshold);
+                warnings.extend(
+                    raster_warnings
+                        .into_iter()
+                        .map(|m| warning(WarningKind::RasterImage, m)),
+                );
+                owned(out)
+            }
+            Stage::Colors => {
+                let (out, recolored) = colors::remap(&children, &opts.colors);
+                colors_remapped = colors::remap_attrs(&mut out_attrs, &opts.colors) + recolored;
+                if colors_remapped > 0 {
+                    tracing::info!(path = %origin, count = colors_remapped, "Remapped colors");
+                }
+                owned(out)
+            }
+            Stage::Themable if opts.themable => {
+                let (out, vars) = colors::themable(&children, &mut out_attrs, &opts.roles);
+                theme_vars = vars;
+                owned(out)
+            }
+            Stage::FlattenTransforms if opts.flatten_transforms => owned(flatten::flatten(
+                &children,
+                flatten::sets_stroke(&out_attrs),
+            )),
+            Stage::CompactPaths if opts.compact_paths => {
+                owned(markup::rewrite_path_data(&children, |d| {
+                    path_data::compact(d).filter(|c| c.len() < d.len())
+                }))
+            }
+            Stage::Optimize if opts.optimize => owned(optimize::optimize(&children)),
+            Stage::SharedDefs if opts.shared_defs => {
+                let (out, defs) = defs::hoist(&children);
+                shared_defs = defs;
+                owned(out)
+            }
+            Stage::Plugins => {
+                #[cfg(feature = "plugins")]
+                for plugin in &opts.plugins {
+                    children = Cow::Owned(plugin.run(name, origin, &mut out_attrs, &children)?);
+                }
+                None
+            }
+            Stage::ScopeIds => {
+                // Rewrite internal ids -> data-id and ensure no per-file duplicates
+                let (scoped, def_ids) = ids::scope_def_ids(&children, name);
+                let (rewritten, ids) = ids::rewrite_ids_to_data_ids_except(&scoped, &def_ids);
+                data_ids = ids;
+                let changed = matches!(scoped, Cow::Owned(_)) || matches!(rewritten, Cow::Owned(_));
+                changed.then(|| rewritten.into_owned())
+            }
+            // Sidecar and `--titles` labels are added when the sprite is assembled.
+            Stage::A11y => None,
+            Stage::PlainHref
+            | Stage::Themable
+            | Stage::FlattenTransforms
+            | Stage::CompactPaths
+            | Stage::Optimize
+            | Stage::SharedDefs => None,
+        };
+        if let Some(markup) = rewritten {
+            children = Cow::Owned(markup);
+        }
     }
-
-    let (rewritten_children, data_ids) = ids::rewrite_ids_to_data_ids(children);
+    // The entry owns its markup: the one copy of an untouched body.
+    let rewritten_children = match root_id_kept {
+        Some(id) => {
+            let wrapped = format!("<g id=\"{id}\">{children}</g>");
+            data_ids.push(id);
+            wrapped
+        }
+        None => children.into_owned(),
+    };
 
     if !saw_width {
-        warnings += 1;
+        warnings.push(warning(
+            WarningKind::MissingWidth,
+            "Missing width on root <svg>".to_string(),
+        ));
     }
     if !saw_height {
-        warnings += 1;
+        warnings.push(warning(
+            WarningKind::MissingHeight,
+            "Missing height on root <svg>".to_string(),
+        ));
     }
     if !saw_viewbox {
-        warnings += 1;
+        warnings.push(warning(
+            WarningKind::MissingViewBox,
+            "Missing viewBox on root <svg>".to_string(),
+        ));
     }
 
     Ok(CacheEntry {
         mtime: UNIX_EPOCH,
         len: 0,
-        name,
+        name: name.to_string(),
         out_attrs,
         children: rewritten_children,
         child_ids: data_ids,
-        path_str: path.display().to_string(),
+        path_str: origin.to_string(),
         warnings,
+        hash: doc.hash.clone(),
+        licenses: doc.licenses.clone(),
+        shared_defs,
+        colors_remapped,
+        attributes_stripped,
+        theme_vars,
+        overrides: None,
     })
 }
 
-/// Write the sprite file from cached entries in the provided order.
+/// The `normalize-dims` stage: root `width`/`height` in pixels (percentages
+/// per `percent_dims`), `viewBox` and `preserveAspectRatio` in canonical form,
+/// with the `viewBox` and any forced `preserveAspectRatio` moved last.
+fn normalize_dims(
+    attrs: &mut Vec<(String, String)>,
+    origin: &str,
+    opts: &RunOpts,
+) -> Result<(), AppError> {
+    let mut out = Vec::with_capacity(attrs.len() + 1);
+    let mut viewbox = None;
+    for (k, v) in attrs.drain(..) {
+        match k.as_str() {
+            "width" | "height" => {
+                let percent = || normalize::normalize_percent(&v);
+                match normalize::normalize_length_with(&v, opts.em_px, opts.precision) {
+                    Some(nv) => out.push((k, nv)),
+                    None => match opts.percent_dims {
+                        normalize::PercentDims::Keep if let Some(pv) = percent() => {
+                            out.push((k, pv));
+                        }
+                        normalize::PercentDims::Strip if percent().is_some() => {
+                            tracing::debug!(path = %origin, attr = %k, value = %v, "Dropped percentage dimension");
+                        }
+                        _ => {
+                            return Err(AppError::InvalidDimension {
+                                path: origin.to_string(),
+                                attr: k,
+                                value: v,
+                            });
+                        }
+                    },
+                }
+            }
+            "viewBox" => match normalize::normalize_viewbox_with(&v, opts.precision) {
+                Some(vb) => viewbox = Some(vb),
+                None => {
+                    return Err(AppError::InvalidViewBox {
+                        path: origin.to_string(),
+                        value: v,
+                    });
+                }
+            },
+            "preserveAspectRatio" => {
+                let Some(par) = normalize::normalize_preserve_aspect_ratio(&v) else {
+                    return Err(AppError::InvalidPreserveAspectRatio {
+                        path: origin.to_string(),
+                        value: v,
+                    });
+                };
+                if opts.force_preserve_aspect_ratio.is_none() {
+                    out.push((k, par));
+                }
+            }
+            _ => out.push((k, v)),
+        }
+    }
+    if let Some(vb) = viewbox {
+        out.push(("viewBox".to_string(), vb));
+    }
+    if let Some(par) = &opts.force_preserve_aspect_ratio {
+        out.push(("preserveAspectRatio".to_string(), par.clone()));
+    }
+    *attrs = out;
+    Ok(())
+}
+
+/// The markup a pipeline stage rewrote, or `None` when it left it as is.
+fn owned(markup: Cow<str>) -> Option<String> {
+    match markup {
+        Cow::Owned(markup) => Some(markup),
+        Cow::Borrowed(_) => None,
+    }
+}
+
+/// Emit the reported warnings collected for an entry through `tracing`.
+fn log_warnings(entry: &CacheEntry, opts: &RunOpts) {
+    for warning in entry.warnings.iter().filter(|w| opts.reports(w.kind)) {
+        tracing::warn!(path = %entry.path_str, icon = %entry.name, code = warning.code(), "{}", warning.message);
+    }
+}
+
+/// Write the sprite file from cached entries in the provided order, followed
+/// by the `collected` icons of collections and packages.
+/// Returns a summary of what was written (duration is left for the caller).
+#[cfg(feature = "fs")]
 fn write_sprite_from_cache(
+    directory: &str,
     file: &str,
     cache: &std::collections::HashMap<String, CacheEntry>,
     order: &[std::path::PathBuf],
-    opts: RunOpts,
-) -> Result<(), AppError> {
-    use std::io::Write as _;
-    // Use dry-run sink when requested
-    let writer: Box<dyn std::io::Write> = if opts.dry_run {
-        Box::new(std::io::BufWriter::new(std::io::sink()))
+    collected: &[CacheEntry],
+    opts: &RunOpts,
+) -> Result<summary::BuildSummary, AppError> {
+    let entries: Vec<&CacheEntry> = order
+        .iter()
+        .filter_map(|p| cache.get(&p.display().to_string()))
+        .chain(collected)
+        .collect();
+    write_entries(directory, file, &entries, opts)
+}
+
+/// Reject ids used twice in one sprite: pattern ids that collide, e.g.
+/// `ArrowLeft.svg` and `arrow-left.svg` under `--id-case kebab`, and the real
+/// ids icons keep in their markup (scoped definitions, kept root ids) that
+/// match a pattern id or each other.
+fn check_ids(entries: &[&CacheEntry]) -> Result<(), AppError> {
+    let mut seen: std::collections::HashMap<String, &str> = std::collections::HashMap::new();
+    let kept = entries.iter().flat_map(|entry| {
+        ids::defined_ids(&entry.children)
+            .unwrap_or_default()
+            .into_iter()
+            .map(move |id| (id, *entry))
+    });
+    let all = entries.iter().map(|entry| (entry.name.clone(), *entry));
+    for (id, entry) in all.chain(kept) {
+        if let Some(first) = seen.get(&id) {
+            return Err(AppError::IdCollision {
+                id,
+                first_path: first.to_string(),
+                second_path: entry.path_str.clone(),
+            });
+        }
+        seen.insert(id, &entry.path_str);
+    }
+    Ok(())
+}
+
+/// Check the config `[aliases]` against the built icons: an alias must not
+/// reuse an icon's id, and its target must be an icon (unless `--only` left
+/// it out, in which case the alias is dropped).
+#[cfg(feature = "fs")]
+fn check_aliases(entries: &[&CacheEntry], opts: &RunOpts) -> Result<(), AppError> {
+    for (alias, target) in &opts.aliases {
+        if let Some(entry) = entries.iter().find(|e| &e.name == alias) {
+            return Err(AppError::IdCollision {
+                id: alias.clone(),
+                first_path: entry.path_str.clone(),
+                second_path: "[aliases]".into(),
+            });
+        }
+        if opts.only.is_empty() && !entries.iter().any(|e| &e.name == target) {
+            return Err(AppError::UnknownAliasTarget {
+                alias: alias.clone(),
+                target: target.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Config aliases whose target is among `entries`, with that entry.
+#[cfg(feature = "fs")]
+fn config_aliases<'a>(
+    entries: &[&'a CacheEntry],
+    opts: &'a RunOpts,
+) -> Vec<(&'a str, &'a CacheEntry)> {
+    opts.aliases
+        .iter()
+        .filter_map(|(alias, target)| {
+            let entry = entries.iter().find(|e| &e.name == target)?;
+            Some((alias.as_str(), *entry))
+        })
+        .collect()
+}
+
+/// A sprite rendered from a set of entries, before it is written.
+#[cfg(feature = "fs")]
+struct Rendered {
+    sprite: String,
+    files: Vec<summary::FileSummary>,
+    warnings: Vec<Warning>,
+    aliases: std::collections::HashMap<String, String>,
+}
+
+/// Attributes of the sprite's root `<svg>` after its `xmlns`: the namespace
+/// declarations the icons use, then `--root-attr`, which wins on a clash.
+#[cfg(feature = "fs")]
+fn sprite_root(entries: &[&CacheEntry], opts: &RunOpts) -> Vec<(String, String)> {
+    let mut root = namespaces::hoist(
+        entries
+            .iter()
+            .map(|e| (e.out_attrs.as_slice(), e.children.as_str())),
+    );
+    root.retain(|(k, _)| !opts.root_attrs.iter().any(|(r, _)| r == k));
+    root.extend(opts.root_attrs.iter().cloned());
+    root
+}
+
+/// Render entries into sprite markup (or through `--template`), with the
+/// contents of `_defs.svg` in the root `<defs>`.
+#[tracing::instrument(level = "debug", skip_all, fields(icons = entries.len(), bytes = tracing::field::Empty))]
+#[cfg(feature = "fs")]
+fn render_sprite(
+    entries: &[&CacheEntry],
+    shared: Option<&shared_defs::SharedDefs>,
+    opts: &RunOpts,
+) -> Result<Rendered, AppError> {
+    let extra_defs = shared.map_or("", |d| d.markup.as_str());
+    if let Some(tpl) = &opts.template {
+        let rendered = render_template(&tpl.display().to_string(), entries, extra_defs, opts)?;
+        tracing::Span::current().record("bytes", rendered.sprite.len());
+        return Ok(rendered);
+    }
+    let mut sprite = String::new();
+    if opts.generator_comment {
+        sprite.push_str(&generator::comment(opts)?);
+    }
+    if opts.keep_license_comments {
+        sprite.push_str(&license_block(entries));
+    }
+    let root = sprite_root(entries, opts);
+    sprite.push_str(&transform::sprite_open(&root));
+    sprite.push_str(&defs::collect(entries));
+    sprite.push_str(extra_defs);
+    let aliases = if opts.dedupe {
+        find_duplicates(entries)
+    } else {
+        std::collections::HashMap::new()
+    };
+    sprite.reserve(patterns_len(entries, opts) + transform::SPRITE_CLOSE.len());
+    let mut warnings = Vec::new();
+    let mut files = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let alias_of = aliases.get(&entry.name);
+        if opts.source_comments {
+            sprite.push_str(&source_comment(entry));
+        }
+        let start = sprite.len();
+        let out_attrs = namespaces::pattern_attrs(&entry.out_attrs, &entry.children, &root);
+        match alias_of {
+            // Patterns inherit content and attributes through `href`.
+            Some(target) => {
+                let mut attrs = vec![("href".to_string(), format!("#{target}"))];
+                attrs.extend(opts.hash_attrs.then(|| hash_attr(entry)));
+                transform::write_pattern(&mut sprite, &entry.name, &attrs, "");
+            }
+            None if opts.hash_attrs => {
+                let mut attrs: Vec<(String, String)> = out_attrs
+                    .iter()
+                    .filter(|(k, _)| k != "data-hash")
+                    .cloned()
+                    .collect();
+                attrs.push(hash_attr(entry));
+                transform::write_pattern(&mut sprite, &entry.name, &attrs, &entry.children);
+            }
+            None => transform::write_pattern(&mut sprite, &entry.name, &out_attrs, &entry.children),
+        }
+        let bytes = sprite.len() - start;
+        files.push(icon_summary(entry, bytes, alias_of, &mut warnings, opts)?);
+    }
+    for (alias, target) in config_aliases(entries, opts) {
+        let start = sprite.len();
+        let mut attrs = vec![("href".to_string(), format!("#{}", target.name))];
+        attrs.extend(opts.hash_attrs.then(|| hash_attr(target)));
+        transform::write_pattern(&mut sprite, alias, &attrs, "");
+        files.push(summary::FileSummary {
+            name: alias.to_string(),
+            path: target.path_str.clone(),
+            bytes: sprite.len() - start,
+            warnings: 0,
+            alias_of: Some(target.name.clone()),
+            colors_remapped: 0,
+            attributes_stripped: Vec::new(),
+            overrides: None,
+        });
+    }
+    sprite.push_str(transform::SPRITE_CLOSE);
+    tracing::Span::current().record("bytes", sprite.len());
+    Ok(Rendered {
+        sprite,
+        files,
+        warnings,
+        aliases,
+    })
+}
+
+/// `render_sprite` through the `--template` at `path`. Size limits apply to
+/// the markup the template emits for each icon; config aliases and
+/// `--dedupe` are left to the template.
+#[cfg(feature = "fs")]
+fn render_template(
+    path: &str,
+    entries: &[&CacheEntry],
+    extra_defs: &str,
+    opts: &RunOpts,
+) -> Result<Rendered, AppError> {
+    let template = template::Template::load(path)?;
+    let empty = template.render(&[], "")?.len();
+    let mut warnings = Vec::new();
+    let mut files = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let bytes = template.icon_len(entry, empty)?;
+        files.push(icon_summary(entry, bytes, None, &mut warnings, opts)?);
+    }
+    Ok(Rendered {
+        sprite: template.render(entries, extra_defs)?,
+        files,
+        warnings,
+        aliases: std::collections::HashMap::new(),
+    })
+}
+
+/// Check `entry`'s `bytes` of emitted markup against the size limits and
+/// summarize it, adding its reported warnings (and any budget warning) to
+/// `warnings`.
+#[cfg(feature = "fs")]
+fn icon_summary(
+    entry: &CacheEntry,
+    bytes: usize,
+    alias_of: Option<&String>,
+    warnings: &mut Vec<Warning>,
+    opts: &RunOpts,
+) -> Result<summary::FileSummary, AppError> {
+    let before = warnings.len();
+    warnings.extend(
+        entry
+            .warnings
+            .iter()
+            .filter(|w| opts.reports(w.kind))
+            .cloned(),
+    );
+    if let Some(limit) = opts.max_icon_bytes {
+        check_size(&entry.path_str, "--max-icon-bytes", bytes as u64, limit)?;
+    }
+    if let Some(limit) = opts.max_icon_size
+        && alias_of.is_none()
+        && let Some(message) = budget::over_budget(entry, bytes, limit)
+        && opts.reports(WarningKind::IconBudget)
+    {
+        tracing::warn!(path = %entry.path_str, "{message}");
+        warnings.push(Warning::new(
+            WarningKind::IconBudget,
+            &entry.path_str,
+            message,
+        ));
+    }
+    Ok(summary::FileSummary {
+        name: entry.name.clone(),
+        path: entry.path_str.clone(),
+        bytes,
+        warnings: warnings.len() - before,
+        alias_of: alias_of.cloned(),
+        colors_remapped: entry.colors_remapped,
+        attributes_stripped: entry.attributes_stripped.clone(),
+        overrides: entry.overrides.clone(),
+    })
+}
+
+/// `--hash-attrs`: the `data-hash` attribute of a pattern showing `entry`'s
+/// content, the first 8 hex digits of the hash its lockfile entry records.
+#[cfg(feature = "fs")]
+fn hash_attr(entry: &CacheEntry) -> (String, String) {
+    let hash = lock::content_hash(entry);
+    ("data-hash".to_string(), hash[..8].to_string())
+}
+
+/// Bytes the patterns of `entries` (and their `--source-comments`) add to a
+/// sprite, to reserve before rendering. Deduplicated icons and aliases are
+/// shorter than their full pattern, so this errs on the large side.
+#[cfg(feature = "fs")]
+fn patterns_len(entries: &[&CacheEntry], opts: &RunOpts) -> usize {
+    let comments: usize = if opts.source_comments {
+        entries
+            .iter()
+            .map(|e| "<!-- source:  (sha256:) -->".len() + e.path_str.len() + e.hash.len())
+            .sum()
+    } else {
+        0
+    };
+    let patterns: usize = entries
+        .iter()
+        .map(|e| transform::pattern_len(&e.name, &e.out_attrs, &e.children))
+        .sum();
+    let aliases: usize = opts
+        .aliases
+        .iter()
+        .map(|(alias, target)| {
+            transform::pattern_len(alias, &[], "") + " href=\"#\"".len() + target.len()
+        })
+        .sum();
+    let hashes = if opts.hash_attrs {
+        (entries.len() + opts.aliases.len()) * r#" data-hash="01234567""#.len()
+    } else {
+        0
+    };
+    comments + patterns + aliases + hashes
+}
+
+/// Render entries into sprite markup and write it to `file`, or to one file
+/// per chunk under `--chunk-by`, after applying the icons' sidecar files and
+/// `--titles`. `root` is the input directory. Skips the write on `--dry-run`
+/// or when the existing file is byte-identical.
+#[cfg(feature = "fs")]
+fn write_entries(
+    root: &str,
+    file: &str,
+    entries: &[&CacheEntry],
+    opts: &RunOpts,
+) -> Result<summary::BuildSummary, AppError> {
+    let mut sidecars = sidecar::load_all(entries)?;
+    if let Some(path) = &opts.titles {
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        titles::apply(&mut sidecars, titles::load(path)?, &names);
+    }
+    let patched = sidecar::patch(entries, &sidecars, opts.pipeline.contains(&Stage::A11y));
+    let entries: Vec<&CacheEntry> = entries
+        .iter()
+        .map(|e| patched.iter().find(|p| p.name == e.name).unwrap_or(e))
+        .collect();
+    let entries = entries.as_slice();
+    check_ids(entries)?;
+    check_aliases(entries, opts)?;
+    let shared = shared_defs::load(root)?;
+    if let Some(defs) = &shared {
+        shared_defs::check_collisions(defs, entries)?;
+    }
+    let Rendered {
+        sprite,
+        files,
+        mut warnings,
+        aliases,
+    } = render_sprite(entries, shared.as_ref(), opts)?;
+    if let Some(defs) = &shared
+        && opts.reports(WarningKind::UnresolvedReference)
+    {
+        for warning in shared_defs::unresolved(defs, entries) {
+            tracing::warn!(path = %warning.path, "{}", warning.message);
+            warnings.push(warning);
+        }
+    }
+    let deprecated = deprecation::collect(entries, &sidecars, opts);
+    if opts.reports(WarningKind::DeprecatedIcon) {
+        for warning in deprecation::warnings(entries, &deprecated) {
+            tracing::warn!(path = %warning.path, "{}", warning.message);
+            warnings.push(warning);
+        }
+    }
+    if opts.detect_duplicates {
+        for (first, other) in similar::near_duplicates(entries) {
+            // Byte-identical copies already aliased by `--dedupe` are not news.
+            if aliases.contains_key(&other) || !opts.reports(WarningKind::NearDuplicate) {
+                continue;
+            }
+            let message = format!("Icon '{other}' is a near-duplicate of '{first}'");
+            tracing::warn!(icon = %other, similar_to = %first, "{message}");
+            let path = entries
+                .iter()
+                .find(|e| e.name == other)
+                .map_or(other.as_str(), |e| e.path_str.as_str());
+            warnings.push(Warning::new(WarningKind::NearDuplicate, path, message));
+        }
+    }
+    if let Some(limit) = opts.max_output_size
+        && let Some(message) = summary::over_budget(sprite.len() as u64, limit, &files)
+        && opts.reports(WarningKind::OutputBudget)
+    {
+        tracing::warn!(output = %file, "{message}");
+        warnings.push(Warning::new(WarningKind::OutputBudget, file, message));
+    }
+
+    let failing: Vec<Warning> = warnings
+        .iter()
+        .filter(|w| opts.fails_on(w.kind))
+        .cloned()
+        .collect();
+    if failing.len() > opts.warning_baseline {
+        return Err(AppError::WarningsPresent { warnings: failing });
+    }
+    if !failing.is_empty() {
+        tracing::info!(
+            count = failing.len(),
+            baseline = opts.warning_baseline,
+            "Failing warnings are within the baseline"
+        );
+    }
+    if opts.lock {
+        lock::verify(file, entries, opts)?;
+    }
+
+    let baseline = match &opts.baseline {
+        Some(path) => Some(diff::baseline(path, &sprite)?),
+        None => None,
+    };
+
+    // Artifacts inlining the sprite still get the standalone `<svg>`.
+    let markup = match opts.fragment && opts.template.is_none() {
+        true => Cow::Owned(transform::fragment(&sprite)),
+        false => Cow::Borrowed(sprite.as_str()),
+    };
+    let (unchanged, bytes, chunks) = match opts.chunk_by {
+        Some(by) => write_chunks(root, file, entries, &files, shared.as_ref(), by, opts)?,
+        None => (
+            write_output(file, markup.as_bytes(), opts)?,
+            markup.len() as u64,
+            0,
+        ),
+    };
+    if let Some(dir) = &opts.archive_dir
+        && !(unchanged || opts.dry_run || opts.check)
+    {
+        archive::archive(dir, file, &markup)?;
+    }
+    let deduplicated = aliases.len();
+    let synonyms = match &opts.synonyms {
+        Some(path) => emit::load_synonyms(path)?,
+        None => Vec::new(),
+    };
+    let ctx = emit::EmitContext {
+        entries,
+        sprite: &sprite,
+        sprite_path: file,
+        aliases,
+        config_aliases: config_aliases(entries, opts),
+        deprecated,
+        root,
+        tags: &sidecar::merge_tags(&opts.tags, &sidecars),
+        synonyms: &synonyms,
+        sidecars: &sidecars,
+    };
+    for target in &opts.emit {
+        let artifact = emit::render(target.kind, &ctx, &target.path);
+        if target.path != STDIO
+            && let Some(parent) = std::path::Path::new(&target.path).parent()
+            && !parent.as_os_str().is_empty()
+            && !opts.dry_run
+            && !opts.check
+        {
+            std::fs::create_dir_all(parent).map_err(|e| AppError::WriteFile {
+                path: target.path.clone(),
+                source: e,
+            })?;
+        }
+        write_output(&target.path, artifact.as_bytes(), opts)?;
+        if target.kind == emit::EmitKind::AssetsManifest {
+            // The manifest's URLs resolve against a hashed copy beside it.
+            let dir = std::path::Path::new(&target.path)
+                .parent()
+                .filter(|_| target.path != STDIO)
+                .unwrap_or(std::path::Path::new(""));
+            let hashed = dir.join(emit::hashed_name(file, &sprite));
+            write_output(&hashed.display().to_string(), sprite.as_bytes(), opts)?;
+        }
+        tracing::info!(kind = %target.kind, path = %target.path, "Emitted artifact");
+    }
+
+    Ok(summary::BuildSummary {
+        output: file.to_string(),
+        icons: files.len(),
+        warnings,
+        bytes,
+        duration: Duration::ZERO,
+        dry_run: opts.dry_run,
+        unchanged,
+        deduplicated,
+        colors_remapped: files.iter().map(|f| f.colors_remapped).sum(),
+        attributes_stripped: files
+            .iter()
+            .flat_map(|f| &f.attributes_stripped)
+            .map(|(_, n)| n)
+            .sum(),
+        chunks,
+        files,
+        baseline,
+    })
+}
+
+/// Write one sprite per chunk next to `file`, plus the combined manifest.
+/// `files` holds each entry's summary, in the same order, and `shared` goes
+/// into every chunk. Returns whether every output was unchanged, the total
+/// sprite size, and the chunk count.
+#[cfg(feature = "fs")]
+fn write_chunks(
+    root: &str,
+    file: &str,
+    entries: &[&CacheEntry],
+    files: &[summary::FileSummary],
+    shared: Option<&shared_defs::SharedDefs>,
+    by: chunk::ChunkBy,
+    opts: &RunOpts,
+) -> Result<(bool, u64, usize), AppError> {
+    if file == STDIO {
+        return Err(AppError::WriteFile {
+            path: STDIO.into(),
+            source: std::io::Error::other("cannot write chunks to stdout"),
+        });
+    }
+    let sizes: Vec<usize> = files.iter().map(|f| f.bytes).collect();
+    let chunks: Vec<(String, String, Vec<&CacheEntry>)> = chunk::split(entries, &sizes, by, root)
+        .into_iter()
+        .map(|(name, members)| {
+            let path = chunk::chunk_path(file, &name);
+            (name, path, members)
+        })
+        .collect();
+    let mut unchanged = true;
+    let mut bytes = 0u64;
+    for (name, path, members) in &chunks {
+        let rendered = render_sprite(members, shared, opts)?;
+        unchanged &= write_output(path, rendered.sprite.as_bytes(), opts)?;
+        bytes += rendered.sprite.len() as u64;
+        tracing::info!(chunk = %name, path = %path, icons = members.len(), "Wrote chunk");
+    }
+    unchanged &= write_output(
+        &chunk::manifest_path(file),
+        chunk::manifest(&chunks, &opts.aliases).as_bytes(),
+        opts,
+    )?;
+    Ok((unchanged, bytes, chunks.len()))
+}
+
+/// Map each icon whose normalized attributes and content are identical to an
+/// earlier icon onto that first icon's name. The root `data-id` is ignored.
+#[cfg(feature = "fs")]
+fn find_duplicates(entries: &[&CacheEntry]) -> std::collections::HashMap<String, String> {
+    let mut first = std::collections::HashMap::<_, &str>::new();
+    let mut aliases = std::collections::HashMap::new();
+    for entry in entries {
+        let attrs: Vec<&(String, String)> = entry
+            .out_attrs
+            .iter()
+            .filter(|(k, _)| k != "data-id")
+            .collect();
+        match first.entry((attrs, entry.children.as_str())) {
+            std::collections::hash_map::Entry::Occupied(o) => {
+                tracing::info!(icon = %entry.name, alias_of = %o.get(), "Deduplicated identical icon");
+                aliases.insert(entry.name.clone(), o.get().to_string());
+            }
+            std::collections::hash_map::Entry::Vacant(v) => {
+                v.insert(entry.name.as_str());
+            }
+        }
+    }
+    aliases
+}
+
+/// Consolidate the distinct license comments of all entries into one block,
+/// or nothing when no input carried one.
+#[cfg(feature = "fs")]
+fn license_block(entries: &[&CacheEntry]) -> String {
+    let mut texts: Vec<&str> = Vec::new();
+    for text in entries.iter().flat_map(|e| &e.licenses) {
+        if !texts.contains(&text.as_str()) {
+            texts.push(text);
+        }
+    }
+    if texts.is_empty() {
+        return String::new();
+    }
+    format!("<!--\n{}\n-->\n", texts.join("\n\n"))
+}
+
+/// Provenance comment for `--source-comments`. `--` is broken up so paths
+/// cannot terminate the comment early.
+#[cfg(feature = "fs")]
+fn source_comment(entry: &CacheEntry) -> String {
+    format!(
+        "<!-- source: {} (sha256:{}) -->",
+        entry.path_str.replace("--", "- -"),
+        entry.hash
+    )
+}
+
+/// Write an output to `file` (`-` for stdout), honoring `--dry-run`.
+/// Returns `true` when the write was skipped because nothing changed.
+#[tracing::instrument(level = "debug", skip_all, fields(file = file, bytes = bytes.len()))]
+#[cfg(feature = "fs")]
+fn write_output(file: &str, bytes: &[u8], opts: &RunOpts) -> Result<bool, AppError> {
+    if opts.check {
+        check_output(file, bytes).map(|_| true)
+    } else if opts.dry_run {
+        if opts.diff && file != STDIO {
+            print_diff(file, bytes)?;
+        }
+        Ok(false)
+    } else if file == STDIO {
+        write_stdout(bytes)?;
+        Ok(false)
     } else {
-        let file_opt = std::fs::File::create(file).map_err(|e| AppError::WriteFile {
-            path: file.to_string(),
-            source: e,
-        })?;
-        Box::new(std::io::BufWriter::new(file_opt))
+        write_if_changed(file, bytes, opts.backup.as_deref())
+    }
+}
+
+/// Print a unified diff from the current contents of `file` to `bytes`
+/// (`--diff`); nothing when they are equal.
+#[cfg(feature = "fs")]
+fn print_diff(file: &str, bytes: &[u8]) -> Result<(), AppError> {
+    let existing = match std::fs::read(file) {
+        Ok(existing) => Some(existing),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(AppError::ReadFile {
+                path: file.to_string(),
+                source: e,
+            });
+        }
     };
-    let mut writer = writer;
-    writer
-        .write_all(b"<svg xmlns=\"http://www.w3.org/2000/svg\"><defs>")
-        .map_err(|e| AppError::WriteFile {
-            path: file.to_string(),
-            source: e,
-        })?;
+    let old = existing.as_deref().map(String::from_utf8_lossy);
+    let patch = diff::unified(file, old.as_deref(), &String::from_utf8_lossy(bytes));
+    write_stdout(patch.as_bytes())
+}
 
-    let mut warn_count = 0usize;
-    for p in order {
-        let key = p.display().to_string();
-        if let Some(entry) = cache.get(&key) {
-            warn_count += entry.warnings;
-            let attrs = entry
-                .out_attrs
-                .iter()
-                .map(|(k, v)| format!(r#" {k}="{v}""#))
-                .collect::<String>();
-            writer
-                .write_all(
-                    format!(
-                        r#"<pattern id="{}"{}>{}</pattern>"#,
-                        entry.name, attrs, entry.children
-                    )
-                    .as_bytes(),
-                )
-                .map_err(|e| AppError::WriteFile {
-                    path: file.to_string(),
-                    source: e,
-                })?;
+/// Verify that `file` already holds exactly `bytes` (`--check`), failing with
+/// `OutOfDate` and a per-icon summary of the difference otherwise.
+#[cfg(feature = "fs")]
+fn check_output(file: &str, bytes: &[u8]) -> Result<(), AppError> {
+    if file == STDIO {
+        return Err(AppError::ReadFile {
+            path: STDIO.into(),
+            source: std::io::Error::other("cannot check stdout"),
+        });
+    }
+    let existing = match std::fs::read(file) {
+        Ok(existing) => existing,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(AppError::OutOfDate {
+                path: file.to_string(),
+                diff: "file does not exist".into(),
+            });
+        }
+        Err(e) => {
+            return Err(AppError::ReadFile {
+                path: file.to_string(),
+                source: e,
+            });
         }
+    };
+    if existing == bytes {
+        return Ok(());
     }
+    Err(AppError::OutOfDate {
+        path: file.to_string(),
+        diff: diff::summarize(
+            &String::from_utf8_lossy(&existing),
+            &String::from_utf8_lossy(bytes),
+        ),
+    })
+}
 
-    writer
-        .write_all(b"</defs></svg>")
-        .and_then(|_| writer.flush())
-        .map_err(|e| AppError::WriteFile {
-            path: file.to_string(),
+/// Write `bytes` to `file` unless it already holds identical content, first
+/// copying the old file to the `backup` pattern if given.
+/// Returns `true` when the write was skipped because nothing changed, so
+/// downstream watchers are not retriggered by an identical rewrite.
+#[cfg(feature = "fs")]
+fn write_if_changed(file: &str, bytes: &[u8], backup: Option<&str>) -> Result<bool, AppError> {
+    let existing = std::fs::read(file).ok();
+    if existing.as_deref() == Some(bytes) {
+        tracing::info!(file, "Output unchanged; skipping write");
+        return Ok(true);
+    }
+    if let (Some(pattern), Some(existing)) = (backup, &existing) {
+        let path = backup_path(file, pattern);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| AppError::WriteFile {
+                path: path.display().to_string(),
+                source: e,
+            })?;
+        }
+        std::fs::write(&path, existing).map_err(|e| AppError::WriteFile {
+            path: path.display().to_string(),
             source: e,
         })?;
-
-    if opts.fail_on_warn && warn_count > 0 {
-        return Err(AppError::WarningsPresent { count: warn_count });
+        tracing::info!(file, backup = %path.display(), "Backed up previous output");
     }
+    std::fs::write(file, bytes).map_err(|e| AppError::WriteFile {
+        path: file.to_string(),
+        source: e,
+    })?;
+    Ok(false)
+}
 
-    Ok(())
+/// Where `--backup` copies `file`: `pattern` with `{file}` replaced by the
+/// file name, relative to the file's directory unless absolute.
+#[cfg(feature = "fs")]
+fn backup_path(file: &str, pattern: &str) -> std::path::PathBuf {
+    let path = std::path::Path::new(file);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let dir = path.parent().unwrap_or(std::path::Path::new(""));
+    dir.join(pattern.replace("{file}", &name))
+}
+
+/// Write the sprite to stdout.
+#[cfg(feature = "fs")]
+fn write_stdout(bytes: &[u8]) -> Result<(), AppError> {
+    use std::io::Write;
+    let mut out = std::io::stdout().lock();
+    out.write_all(bytes)
+        .and_then(|_| out.flush())
+        .map_err(|e| AppError::WriteFile {
+            path: "<stdout>".to_string(),
+            source: e,
+        })
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "fs"))]
 mod tests {
     use super::*;
     use proptest::prelude::*;
@@ -808,7 +2737,7 @@ mod tests {
             ),
             SvgSprite::new("two".into(), vec![("fill", "#000")], "<circle/>".into()),
         ];
-        let out = crate::svg::transform::transform(svgs);
+        let out = crate::svg::transform::transform(&svgs);
         assert!(out.starts_with("<svg"));
         assert!(out.contains("<defs>"));
         assert!(out.contains("<pattern id=\"one\" width=\"24\" height=\"24\"><g/>"));
@@ -816,6 +2745,133 @@ mod tests {
         assert!(out.ends_with("</defs></svg>"));
     }
 
+    #[test]
+    fn pattern_len_matches_written_pattern() {
+        let attributes = [
+            ("width".to_string(), "24".to_string()),
+            ("data-id".into(), "\u{e9}t\u{e9}".into()),
+        ];
+        let mut out = String::from("<defs>");
+        transform::write_pattern(&mut out, "sun", &attributes, "<circle r=\"4\"/>");
+        assert_eq!(
+            &out[6..],
+            "<pattern id=\"sun\" width=\"24\" data-id=\"\u{e9}t\u{e9}\"><circle r=\"4\"/></pattern>"
+        );
+        assert_eq!(
+            transform::pattern_len("sun", &attributes, "<circle r=\"4\"/>"),
+            out.len() - 6
+        );
+    }
+
+    #[test]
+    fn sprite_root_carries_extra_attributes() {
+        let open = transform::sprite_open(&[
+            ("class".into(), "sprite".into()),
+            ("data-x".into(), "<a & \"b\">".into()),
+        ]);
+        assert_eq!(
+            open,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"sprite\" data-x=\"&lt;a &amp; &quot;b&quot;&gt;\"><defs>"
+        );
+        let sprite = format!("<!-- c -->{open}<pattern id=\"a\"></pattern></defs></svg>");
+        assert_eq!(
+            transform::split_root(&sprite),
+            Some((
+                "<!-- c -->",
+                " class=\"sprite\" data-x=\"&lt;a &amp; &quot;b&quot;&gt;\"",
+                "<pattern id=\"a\"></pattern></defs></svg>"
+            ))
+        );
+        assert_eq!(
+            transform::fragment(&sprite),
+            "<!-- c --><defs><pattern id=\"a\"></pattern></defs>"
+        );
+    }
+
+    #[test]
+    fn build_from_sources_normalizes_without_filesystem() {
+        let sprite = build_from_sources([
+            (
+                "a".to_string(),
+                "<svg id='root' width='24px' viewBox='0,0,24,24'><g id='x'/></svg>".to_string(),
+            ),
+            ("b".to_string(), "<svg height='2'><rect/></svg>".to_string()),
+        ])
+        .expect("build ok");
+        assert!(sprite.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\"><defs>"));
+        assert!(sprite.contains(
+            "<pattern id=\"a\" width=\"24\" data-id=\"root\" viewBox=\"0 0 24 24\"><g data-id='x'/>"
+        ));
+        assert!(sprite.contains("<pattern id=\"b\" height=\"2\"><rect/></pattern>"));
+    }
+
+    #[test]
+    fn build_from_sources_sanitizes_names_into_ids() {
+        let sprite = build_from_sources([(
+            "arrow \"left\" <x>".to_string(),
+            "<svg width='1'><g/></svg>".to_string(),
+        )])
+        .expect("build ok");
+        assert!(sprite.contains("<pattern id=\"arrow-left-x\" width=\"1\"><g/></pattern>"));
+    }
+
+    #[test]
+    fn build_from_sources_rejects_duplicates_and_bad_input() {
+        let svg = "<svg width='1'></svg>".to_string();
+        let err = build_from_sources([("a".to_string(), svg.clone()), ("a".to_string(), svg)])
+            .expect_err("duplicate names");
+        assert!(matches!(err, AppError::IdCollision { .. }));
+
+        let err = build_from_sources([
+            ("a b".to_string(), "<svg width='1'></svg>".to_string()),
+            ("a-b".to_string(), "<svg width='1'></svg>".to_string()),
+        ])
+        .expect_err("names sanitizing to one id");
+        match err {
+            AppError::IdCollision {
+                id,
+                first_path,
+                second_path,
+            } => assert_eq!(
+                (id, first_path, second_path),
+                ("a-b".into(), "a b".into(), "a-b".into())
+            ),
+            other => panic!("unexpected error: {other}"),
+        }
+        let err = build_from_sources([
+            (
+                "a".to_string(),
+                "<svg width='1'><clipPath id='b-c'/><g clip-path='url(#b-c)'/></svg>".to_string(),
+            ),
+            (
+                "a-b".to_string(),
+                "<svg width='1'><clipPath id='c'/><g clip-path='url(#c)'/></svg>".to_string(),
+            ),
+        ])
+        .expect_err("scoped def ids colliding across icons");
+        match err {
+            AppError::IdCollision {
+                id,
+                first_path,
+                second_path,
+            } => assert_eq!(
+                (id, first_path, second_path),
+                ("a-b-c".into(), "a".into(), "a-b".into())
+            ),
+            other => panic!("unexpected error: {other}"),
+        }
+        let err = build_from_sources([("\" <>".to_string(), "<svg width='1'></svg>".to_string())])
+            .expect_err("name with no valid characters");
+        assert!(matches!(err, AppError::InvalidIdAfterSanitize { .. }));
+
+        let err =
+            build_from_sources([("bad".to_string(), "nope".to_string())]).expect_err("parse error");
+        match err {
+            AppError::ParseSvg { path, .. } => assert_eq!(path, "bad"),
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
     #[test]
     fn process_empty_directory_yields_error() {
         let tmp = TempDir::new("svg_sheet_empty");
@@ -859,11 +2915,13 @@ mod tests {
         let tmp = TempDir::new("svg_sheet_hash");
         let dir = tmp.path();
         fs::write(dir.join("c.svg"), "<svg id=\"c\"></svg>").unwrap();
-        let h1 = dir_state_hash(dir.to_str().unwrap()).expect("hash1");
+        let h1 = dir_state_hash(dir.to_str().unwrap(), "sprite.svg", &RunOpts::default())
+            .expect("hash1");
         // Touch file update
         std::thread::sleep(std::time::Duration::from_millis(10));
         fs::write(dir.join("c.svg"), "<svg id=\"c2\"></svg>").unwrap();
-        let h2 = dir_state_hash(dir.to_str().unwrap()).expect("hash2");
+        let h2 = dir_state_hash(dir.to_str().unwrap(), "sprite.svg", &RunOpts::default())
+            .expect("hash2");
         assert_ne!(h1, h2);
     }
 
@@ -922,6 +2980,107 @@ mod tests {
         }
     }
 
+    #[test]
+    fn percent_dimensions_follow_the_policy() {
+        let src = "<svg width='100%' height=' 50.0 %' viewBox='0 0 24 24'><g/></svg>";
+        assert!(matches!(
+            parse_icon("a", "a.svg", src, &RunOpts::default()),
+            Err(AppError::InvalidDimension { .. })
+        ));
+
+        let policy = |percent_dims| RunOpts {
+            percent_dims,
+            ..Default::default()
+        };
+        let kept = parse_icon("a", "a.svg", src, &policy(normalize::PercentDims::Keep)).unwrap();
+        assert_eq!(
+            kept.out_attrs,
+            [
+                ("width".to_string(), "100%".to_string()),
+                ("height".into(), "50%".into()),
+                ("viewBox".into(), "0 0 24 24".into()),
+            ]
+        );
+        let stripped =
+            parse_icon("a", "a.svg", src, &policy(normalize::PercentDims::Strip)).unwrap();
+        assert_eq!(
+            stripped.out_attrs,
+            [("viewBox".to_string(), "0 0 24 24".to_string())]
+        );
+        assert!(stripped.warnings.is_empty());
+
+        // Other units still fail.
+        let em = "<svg width='2ex' height='24' viewBox='0 0 24 24'><g/></svg>";
+        assert!(matches!(
+            parse_icon("a", "a.svg", em, &policy(normalize::PercentDims::Strip)),
+            Err(AppError::InvalidDimension { .. })
+        ));
+    }
+
+    #[test]
+    fn preserve_aspect_ratio_validated_and_forced() {
+        let src = "<svg width='1' height='1' viewBox='0 0 1 1' preserveAspectRatio=' xMinYMin  slice'><g/></svg>";
+        let entry = parse_icon("a", "a.svg", src, &RunOpts::default()).expect("valid");
+        assert!(
+            entry
+                .out_attrs
+                .contains(&("preserveAspectRatio".into(), "xMinYMin slice".into()))
+        );
+
+        let forced = RunOpts {
+            force_preserve_aspect_ratio: Some("none".into()),
+            ..Default::default()
+        };
+        let entry = parse_icon("a", "a.svg", src, &forced).expect("forced");
+        let pars: Vec<_> = entry
+            .out_attrs
+            .iter()
+            .filter(|(k, _)| k == "preserveAspectRatio")
+            .collect();
+        assert_eq!(
+            pars,
+            vec![&("preserveAspectRatio".to_string(), "none".to_string())]
+        );
+
+        let bad = "<svg width='1' height='1' preserveAspectRatio='center'><g/></svg>";
+        assert!(matches!(
+            parse_icon("a", "a.svg", bad, &RunOpts::default()),
+            Err(AppError::InvalidPreserveAspectRatio { .. })
+        ));
+    }
+
+    #[test]
+    fn root_id_policies() {
+        let src =
+            "<svg id=\"root\" width='1' height='1' viewBox='0 0 1 1'><use href=\"#root\"/></svg>";
+        let keep = RunOpts {
+            root_id: ids::RootIdPolicy::Keep,
+            ..Default::default()
+        };
+        let entry = parse_icon("r", "r.svg", src, &keep).expect("keep allows references");
+        assert!(entry.children.starts_with("<g id=\"root\">"));
+        assert!(entry.child_ids.contains(&"root".to_string()));
+        assert!(!entry.out_attrs.iter().any(|(k, _)| k == "data-id"));
+
+        let drop = RunOpts {
+            root_id: ids::RootIdPolicy::Drop,
+            ..Default::default()
+        };
+        let plain = "<svg id='root' width='1' height='1' viewBox='0 0 1 1'><g/></svg>";
+        let entry = parse_icon("r", "r.svg", plain, &drop).expect("drop");
+        assert!(
+            !entry
+                .out_attrs
+                .iter()
+                .any(|(k, _)| k == "id" || k == "data-id")
+        );
+        assert_eq!(entry.children, "<g/>");
+        assert!(matches!(
+            parse_icon("r", "r.svg", src, &drop),
+            Err(AppError::RootIdReferenced { .. })
+        ));
+    }
+
     #[test]
     fn ids_rewritten_to_data_ids_and_no_cross_file_collision() {
         let tmp = TempDir::new("id_rewrite");
@@ -940,6 +3099,28 @@ mod tests {
         assert!(!sprite.contains(" id=\"dup\""));
     }
 
+    #[test]
+    fn definition_ids_are_scoped_per_icon() {
+        let tmp = TempDir::new("scoped_def_ids");
+        let dir = tmp.path();
+        let src = "<svg width='1'><clipPath id=\"clip0\"><rect/></clipPath><g clip-path=\"url(#clip0)\"/></svg>";
+        fs::write(dir.join("a.svg"), src).unwrap();
+        fs::write(dir.join("b.svg"), src).unwrap();
+        let mut cache = std::collections::HashMap::new();
+        super::rebuild_once(
+            dir.to_str().unwrap(),
+            &dir.join("sprite.svg").to_string_lossy(),
+            &mut cache,
+            &RunOpts::default(),
+        )
+        .expect("scoped ids do not collide");
+        let sprite = fs::read_to_string(dir.join("sprite.svg")).unwrap();
+        for icon in ["a", "b"] {
+            assert!(sprite.contains(&format!("<clipPath id=\"{icon}-clip0\">")));
+            assert!(sprite.contains(&format!("clip-path=\"url(#{icon}-clip0)\"")));
+        }
+    }
+
     #[test]
     fn handles_bom_xml_prolog_and_leading_comment() {
         let tmp = TempDir::new("svg_preamble");
@@ -1104,6 +3285,58 @@ mod tests {
         matches!(err, AppError::WarningsPresent { .. });
     }
 
+    #[test]
+    fn allow_and_deny_tune_individual_warning_rules() {
+        let tmp = TempDir::new("svg_allow_deny");
+        let dir = tmp.path();
+        fs::write(
+            dir.join("warn.svg"),
+            "<svg width=\"1\" height=\"1\"><g/></svg>",
+        )
+        .unwrap();
+        let out = dir.join("sprite.svg");
+        let run =
+            |opts: RunOpts| process_with_opts(dir.to_str().unwrap(), out.to_str().unwrap(), opts);
+        let quiet = RunOpts {
+            quiet: true,
+            ..Default::default()
+        };
+
+        let warnings = run(quiet.clone()).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, WarningKind::MissingViewBox);
+
+        let allowed = RunOpts {
+            allow: vec![WarningKind::MissingViewBox],
+            fail_on_warn: true,
+            ..quiet.clone()
+        };
+        assert!(run(allowed).unwrap().is_empty());
+
+        let denied = RunOpts {
+            deny: vec![WarningKind::MissingViewBox],
+            allow: vec![WarningKind::MissingViewBox],
+            ..quiet.clone()
+        };
+        match run(denied) {
+            Err(AppError::WarningsPresent { warnings }) => assert_eq!(warnings.len(), 1),
+            other => panic!("unexpected: {other:?}"),
+        }
+
+        let within_baseline = RunOpts {
+            fail_on_warn: true,
+            warning_baseline: 1,
+            ..quiet.clone()
+        };
+        assert_eq!(run(within_baseline).unwrap().len(), 1);
+
+        let other_denied = RunOpts {
+            deny: vec![WarningKind::RasterImage],
+            ..quiet
+        };
+        assert_eq!(run(other_denied).unwrap().len(), 1);
+    }
+
     #[test]
     fn process_write_file_error_when_output_is_directory() {
         let tmp = TempDir::new("svg_write_dir");
@@ -1139,7 +3372,7 @@ mod tests {
             dir.to_str().unwrap(),
             &dir.join("sprite.svg").to_string_lossy(),
             &mut cache,
-            RunOpts {
+            &RunOpts {
                 dry_run: true,
                 ..Default::default()
             },
@@ -1152,7 +3385,8 @@ mod tests {
     fn dir_state_hash_nonexistent_dir_errors() {
         let tmp = TempDir::new("svg_hash_bad_dir");
         let bad = tmp.path().join("missing");
-        let err = super::dir_state_hash(bad.to_str().unwrap()).expect_err("expected error");
+        let err = super::dir_state_hash(bad.to_str().unwrap(), "sprite.svg", &RunOpts::default())
+            .expect_err("expected error");
         matches!(err, AppError::ReadDir { .. });
     }
 
@@ -1255,35 +3489,86 @@ mod tests {
         let mut cache: std::collections::HashMap<String, CacheEntry> =
             std::collections::HashMap::new();
         let out_path = dir.join("sprite.svg");
-        super::rebuild_once(
+        let built = super::rebuild_once(
             dir.to_str().unwrap(),
             &out_path.to_string_lossy(),
             &mut cache,
-            RunOpts {
+            &RunOpts {
                 dry_run: false,
                 ..Default::default()
             },
         )
         .expect("rebuild returns ok when skipping");
+        assert!(built.is_none(), "skipped rebuild reports no build");
         assert!(!out_path.exists(), "no sprite should be written on skip");
     }
 
+    #[test]
+    fn parallel_parsing_keeps_input_order() {
+        let tmp = TempDir::new("svg_parse_jobs");
+        let dir = tmp.path();
+        let paths: Vec<std::path::PathBuf> = (0..12)
+            .map(|n| {
+                let path = dir.join(format!("i{n:02}.svg"));
+                fs::write(
+                    &path,
+                    format!("<svg viewBox=\"0 0 {} 1\"><g/></svg>", n + 1),
+                )
+                .unwrap();
+                path
+            })
+            .collect();
+        let names = |jobs| {
+            let opts = RunOpts {
+                jobs: std::num::NonZeroUsize::new(jobs),
+                ..Default::default()
+            };
+            parse_inputs(dir, &paths, None, &opts)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(4), names(1));
+        assert_eq!(names(4)[11], "i11");
+
+        let opts = RunOpts {
+            jobs: std::num::NonZeroUsize::new(8),
+            ..Default::default()
+        };
+        assert_eq!(parse_jobs(&opts, 3), 3);
+        let verbose = RunOpts {
+            verbose: true,
+            ..opts
+        };
+        assert_eq!(parse_jobs(&verbose, 3), 1);
+
+        fs::write(&paths[5], "<svg").unwrap();
+        fs::write(&paths[9], "<svg").unwrap();
+        let Err(err) = parse_inputs(dir, &paths, None, &RunOpts::default()) else {
+            panic!("broken inputs parsed");
+        };
+        assert_eq!(err.path(), Some(paths[5].display().to_string().as_str()));
+    }
+
     #[test]
     fn write_sprite_from_cache_respects_fail_on_warn() {
         let tmp = TempDir::new("svg_write_warn");
         let dir = tmp.path();
         std::fs::write(dir.join("w.svg"), "<svg ><g/></svg>").unwrap();
-        let entry = build_cache_entry(&dir.join("w.svg")).expect("entry");
-        assert!(entry.warnings > 0);
+        let entry = build_cache_entry(dir, &dir.join("w.svg"), &RunOpts::default()).expect("entry");
+        assert!(!entry.warnings.is_empty());
         let mut cache: std::collections::HashMap<String, CacheEntry> =
             std::collections::HashMap::new();
         cache.insert(dir.join("w.svg").display().to_string(), entry);
         let order = vec![dir.join("w.svg")];
         let err = write_sprite_from_cache(
+            &dir.to_string_lossy(),
             &dir.join("out.svg").to_string_lossy(),
             &cache,
             &order,
-            RunOpts {
+            &[],
+            &RunOpts {
                 fail_on_warn: true,
                 ..Default::default()
             },
@@ -1292,13 +3577,376 @@ mod tests {
         matches!(err, AppError::WarningsPresent { .. });
     }
 
+    #[test]
+    fn id_case_renames_patterns_and_detects_collisions() {
+        let tmp = TempDir::new("svg_id_case");
+        let dir = tmp.path().join("in");
+        fs::create_dir_all(&dir).unwrap();
+        let icon = "<svg width='1' height='1' viewBox='0 0 1 1'><g/></svg>";
+        fs::write(dir.join("ArrowLeft.svg"), icon).unwrap();
+        let out = tmp.path().join("sprite.svg");
+        let opts = RunOpts {
+            quiet: true,
+            id_case: naming::IdCase::Kebab,
+            ..Default::default()
+        };
+        process_with_opts(dir.to_str().unwrap(), out.to_str().unwrap(), opts.clone())
+            .expect("build");
+        assert!(
+            fs::read_to_string(&out)
+                .unwrap()
+                .contains("id=\"arrow-left\"")
+        );
+
+        fs::write(dir.join("arrow_left.svg"), icon).unwrap();
+        let err = process_with_opts(dir.to_str().unwrap(), out.to_str().unwrap(), opts)
+            .expect_err("collision");
+        match err {
+            AppError::IdCollision { id, .. } => assert_eq!(id, "arrow-left"),
+            other => panic!("unexpected: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn split_svg_stream_handles_prologs_and_comments() {
+        let input = "<?xml version='1.0'?>\n<svg id='a'><g/></svg>\n<!-- next -->\n<svg width='1'><path/></svg>\n";
+        let docs = split_svg_stream(input, "<stdin>").expect("split");
+        assert_eq!(docs.len(), 2);
+        assert_eq!(
+            docs[0],
+            ("<svg id='a'><g/></svg>".to_string(), Some("a".to_string()))
+        );
+        assert_eq!(docs[1], ("<svg width='1'><path/></svg>".to_string(), None));
+        assert!(split_svg_stream("  \n", "<stdin>").unwrap().is_empty());
+        let commented = "<svg width='1'><!-- </svg> --><path d=\"M0 0\"/></svg>";
+        let nested = "<svg id='n'><svg width='2'><g/></svg><path/></svg>";
+        let docs = split_svg_stream(&format!("{commented}\n{nested}"), "<stdin>").expect("split");
+        assert_eq!(
+            docs,
+            [
+                (commented.to_string(), None),
+                (nested.to_string(), Some("n".to_string()))
+            ]
+        );
+        match split_svg_stream("<svg id='a'><g/></svg><svg", "<stdin>") {
+            Err(AppError::ParseSvg { path, .. }) => assert_eq!(path, "<stdin>#2"),
+            other => panic!("unexpected: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn emit_targets_share_one_build() {
+        let tmp = TempDir::new("svg_emit");
+        let dir = tmp.path().join("in");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("a.svg"),
+            "<svg width='1' height='1' viewBox='0 0 1 1'><g/></svg>",
+        )
+        .unwrap();
+        let out = tmp.path().join("sprite.svg");
+        let manifest = tmp.path().join("dist/icons.json");
+        let ts = tmp.path().join("dist/icons.ts");
+        let assets = tmp.path().join("dist/assets-manifest.json");
+        let opts = RunOpts {
+            quiet: true,
+            emit: vec![
+                format!("manifest={}", manifest.display()).parse().unwrap(),
+                format!("ts={}", ts.display()).parse().unwrap(),
+                format!("assets-manifest={}", assets.display())
+                    .parse()
+                    .unwrap(),
+            ],
+            ..Default::default()
+        };
+        process_with_opts(dir.to_str().unwrap(), out.to_str().unwrap(), opts).expect("build");
+        assert!(out.exists());
+        assert!(
+            fs::read_to_string(&manifest)
+                .unwrap()
+                .contains("\"id\": \"a\"")
+        );
+        assert!(fs::read_to_string(&ts).unwrap().contains("\"a\","));
+        let sprite = fs::read_to_string(&out).unwrap();
+        let hashed = emit::hashed_name(out.to_str().unwrap(), &sprite);
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("dist").join(&hashed)).unwrap(),
+            sprite
+        );
+        assert!(
+            fs::read_to_string(&assets)
+                .unwrap()
+                .contains(&format!("\"a\": \"{hashed}#a\""))
+        );
+    }
+
+    #[test]
+    fn license_comments_hoisted_once_when_enabled() {
+        let tmp = TempDir::new("svg_license_comments");
+        let dir = tmp.path().join("in");
+        fs::create_dir_all(&dir).unwrap();
+        for n in ["a", "b"] {
+            fs::write(
+                dir.join(format!("{n}.svg")),
+                "<!-- Copyright Acme --><svg width='1' height='1' viewBox='0 0 1 1'><g/></svg>",
+            )
+            .unwrap();
+        }
+        let out = tmp.path().join("sprite.svg");
+        let opts = RunOpts {
+            quiet: true,
+            keep_license_comments: true,
+            ..Default::default()
+        };
+        process_with_opts(dir.to_str().unwrap(), out.to_str().unwrap(), opts).unwrap();
+        let sprite = fs::read_to_string(&out).unwrap();
+        assert!(sprite.starts_with("<!--\nCopyright Acme\n-->\n<svg"));
+        assert_eq!(sprite.matches("Copyright Acme").count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_filenames_are_built_with_a_warning() {
+        use std::os::unix::ffi::OsStrExt;
+        let tmp = TempDir::new("svg_non_utf8");
+        let dir = tmp.path().join("in");
+        fs::create_dir_all(&dir).unwrap();
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9.svg");
+        if fs::write(
+            dir.join(name),
+            "<svg width='1' height='1' viewBox='0 0 1 1'><g/></svg>",
+        )
+        .is_err()
+        {
+            // Some filesystems (e.g. APFS) reject non-UTF-8 names outright.
+            return;
+        }
+        let out = tmp.path().join("sprite.svg");
+        let opts = RunOpts {
+            quiet: true,
+            ..Default::default()
+        };
+        process_with_opts(dir.to_str().unwrap(), out.to_str().unwrap(), opts.clone())
+            .expect("build");
+        assert!(
+            fs::read_to_string(&out)
+                .unwrap()
+                .contains("<pattern id=\"caf\"")
+        );
+
+        let strict = RunOpts {
+            fail_on_warn: true,
+            ..opts
+        };
+        match process_with_opts(dir.to_str().unwrap(), out.to_str().unwrap(), strict) {
+            Err(AppError::WarningsPresent { warnings }) => {
+                assert_eq!(warnings.len(), 1);
+                assert_eq!(warnings[0].kind, WarningKind::LossyFilename);
+            }
+            other => panic!("unexpected: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn dedupe_aliases_identical_icons() {
+        let tmp = TempDir::new("svg_dedupe");
+        let dir = tmp.path().join("in");
+        fs::create_dir_all(&dir).unwrap();
+        let icon = "<svg width='1' height='1' viewBox='0 0 1 1'><path d='M0 0'/></svg>";
+        fs::write(dir.join("a.svg"), icon).unwrap();
+        fs::write(dir.join("b.svg"), icon.replace("<svg ", "<svg id='copy' ")).unwrap();
+        fs::write(dir.join("c.svg"), icon.replace("M0 0", "M1 1")).unwrap();
+        let out = tmp.path().join("sprite.svg");
+        let manifest = tmp.path().join("m.json");
+        let opts = RunOpts {
+            quiet: true,
+            dedupe: true,
+            emit: vec![format!("manifest={}", manifest.display()).parse().unwrap()],
+            ..Default::default()
+        };
+        process_with_opts(dir.to_str().unwrap(), out.to_str().unwrap(), opts).unwrap();
+        let sprite = fs::read_to_string(&out).unwrap();
+        assert!(sprite.contains("<pattern id=\"b\" href=\"#a\"></pattern>"));
+        assert_eq!(sprite.matches("M0 0").count(), 1);
+        assert!(sprite.contains("M1 1"));
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&manifest).unwrap()).unwrap();
+        assert_eq!(json["icons"][1]["aliasOf"], "a");
+        assert!(json["icons"][2].get("aliasOf").is_none());
+    }
+
+    #[test]
+    fn source_comments_precede_patterns_only_when_enabled() {
+        let tmp = TempDir::new("svg_source_comments");
+        let dir = tmp.path().join("in");
+        fs::create_dir_all(&dir).unwrap();
+        let content = "<svg width='1' height='1' viewBox='0 0 1 1'><g/></svg>";
+        fs::write(dir.join("a.svg"), content).unwrap();
+        let out = tmp.path().join("sprite.svg");
+        let run = |source_comments| {
+            let opts = RunOpts {
+                quiet: true,
+                source_comments,
+                ..Default::default()
+            };
+            process_with_opts(dir.to_str().unwrap(), out.to_str().unwrap(), opts).unwrap();
+            fs::read_to_string(&out).unwrap()
+        };
+        assert!(!run(false).contains("<!--"));
+        let expected = format!(
+            "<!-- source: {} (sha256:{}) --><pattern id=\"a\"",
+            dir.join("a.svg").display(),
+            source::sha256_hex(content.as_bytes())
+        );
+        assert!(run(true).contains(&expected));
+    }
+
+    #[test]
+    fn hash_attrs_change_only_for_changed_icons() {
+        let tmp = TempDir::new("svg_hash_attrs");
+        let dir = tmp.path().join("in");
+        fs::create_dir_all(&dir).unwrap();
+        let icon = "<svg width='1' height='1' viewBox='0 0 1 1' data-hash='x'><g/></svg>";
+        fs::write(dir.join("a.svg"), icon).unwrap();
+        fs::write(dir.join("b.svg"), icon).unwrap();
+        let out = tmp.path().join("sprite.svg");
+        let run = || {
+            let opts = RunOpts {
+                quiet: true,
+                hash_attrs: true,
+                aliases: vec![("c".into(), "b".into())],
+                ..Default::default()
+            };
+            process_with_opts(dir.to_str().unwrap(), out.to_str().unwrap(), opts).unwrap();
+            let sprite = fs::read_to_string(&out).unwrap();
+            let hash = |id: &str| {
+                let pattern = sprite
+                    .split(&format!("<pattern id=\"{id}\""))
+                    .nth(1)
+                    .unwrap();
+                let tag = pattern.split('>').next().unwrap();
+                assert_eq!(tag.matches("data-hash").count(), 1);
+                let value = tag.split("data-hash=\"").nth(1).unwrap();
+                value[..8].to_string()
+            };
+            (hash("a"), hash("b"), hash("c"))
+        };
+        let (a, b, c) = run();
+        assert_eq!((a.len(), &a), (8, &b));
+        assert_eq!(b, c, "aliases carry their target's hash");
+        fs::write(dir.join("b.svg"), icon.replace("<g/>", "<path d='M0 0'/>")).unwrap();
+        let (a2, b2, c2) = run();
+        assert_eq!(a2, a);
+        assert_ne!(b2, b);
+        assert_eq!(c2, b2);
+    }
+
+    #[test]
+    fn write_if_changed_skips_identical_output() {
+        let tmp = TempDir::new("svg_write_unchanged");
+        let out = tmp.path().join("sprite.svg");
+        let out_str = out.to_str().unwrap();
+        assert!(!write_if_changed(out_str, b"<svg/>", None).expect("first write"));
+        let before = fs::metadata(&out).unwrap().modified().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(write_if_changed(out_str, b"<svg/>", None).expect("second write"));
+        let after = fs::metadata(&out).unwrap().modified().unwrap();
+        assert_eq!(before, after, "identical output must not be rewritten");
+        assert!(!write_if_changed(out_str, b"<svg></svg>", None).expect("changed write"));
+        assert_eq!(fs::read_to_string(&out).unwrap(), "<svg></svg>");
+    }
+
+    #[test]
+    fn backup_copies_the_previous_output_before_a_change() {
+        let tmp = TempDir::new("svg_write_backup");
+        let out = tmp.path().join("sprite.svg");
+        let out_str = out.to_str().unwrap();
+        let backup = Some("old/{file}.bak");
+        write_if_changed(out_str, b"<svg/>", backup).unwrap();
+        assert!(!tmp.path().join("old").exists(), "nothing to back up yet");
+        write_if_changed(out_str, b"<svg/>", backup).unwrap();
+        assert!(!tmp.path().join("old").exists(), "unchanged output is kept");
+        write_if_changed(out_str, b"<svg></svg>", backup).unwrap();
+        let saved = tmp.path().join("old/sprite.svg.bak");
+        assert_eq!(fs::read_to_string(saved).unwrap(), "<svg/>");
+        assert_eq!(
+            backup_path("/x/sprite.svg", "/backups/{file}"),
+            std::path::Path::new("/backups/sprite.svg")
+        );
+    }
+
+    #[test]
+    fn process_with_cache_dir_reuses_and_invalidates_entries() {
+        let tmp = TempDir::new("svg_disk_cache");
+        let dir = tmp.path().join("in");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.svg"), "<svg width=\"1\"><g/></svg>").unwrap();
+        let out = tmp.path().join("sprite.svg");
+        let opts = RunOpts {
+            cache_dir: Some(tmp.path().join("cache")),
+            ..Default::default()
+        };
+        let run = || {
+            process_with_opts(dir.to_str().unwrap(), out.to_str().unwrap(), opts.clone())
+                .expect("build ok");
+            fs::read_to_string(&out).unwrap()
+        };
+        let first = run();
+        assert!(tmp.path().join("cache/svg_sheet-cache.json").exists());
+        assert_eq!(run(), first);
+
+        fs::write(dir.join("a.svg"), "<svg width=\"2\"><g/></svg>").unwrap();
+        assert!(
+            run().contains("width=\"2\""),
+            "changed content must be re-parsed"
+        );
+    }
+
+    #[test]
+    fn dir_state_hash_tracks_spriteignore_and_skips_ignored_files() {
+        let tmp = TempDir::new("svg_hash_spriteignore");
+        let dir = tmp.path();
+        let d = dir.to_str().unwrap();
+        fs::write(dir.join(inputs::IGNORE_FILE), "drafts-*\n").unwrap();
+        let h1 = super::dir_state_hash(d, "sprite.svg", &RunOpts::default()).expect("hash");
+        fs::write(dir.join("drafts-x.svg"), "<svg></svg>").unwrap();
+        let h2 = super::dir_state_hash(d, "sprite.svg", &RunOpts::default()).expect("hash2");
+        assert_eq!(h1, h2, "ignored files must not affect the watch hash");
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        fs::write(dir.join(inputs::IGNORE_FILE), "other\n").unwrap();
+        let h3 = super::dir_state_hash(d, "sprite.svg", &RunOpts::default()).expect("hash3");
+        assert_ne!(h2, h3, "editing .spriteignore must trigger a rebuild");
+    }
+
+    #[test]
+    fn dir_state_hash_ignores_output_inside_input_dir() {
+        let tmp = TempDir::new("svg_hash_output");
+        let dir = tmp.path();
+        fs::write(dir.join("a.svg"), "<svg width='1'><g/></svg>").unwrap();
+        let out = dir.join("sprite.svg").display().to_string();
+        let d = dir.to_str().unwrap();
+        let h1 = super::dir_state_hash(d, &out, &RunOpts::default()).expect("hash");
+        let opts = RunOpts {
+            quiet: true,
+            ..Default::default()
+        };
+        process_with_opts(d, &out, opts.clone()).expect("build");
+        let h2 = super::dir_state_hash(d, &out, &RunOpts::default()).expect("hash2");
+        assert_eq!(h1, h2, "writing the sprite must not retrigger a build");
+        // A second build must not ingest the sprite as an icon.
+        process_with_opts(d, &out, opts).expect("rebuild");
+        assert!(!fs::read_to_string(&out).unwrap().contains("id=\"sprite\""));
+    }
+
     #[test]
     fn dir_state_hash_ignores_non_svg_changes() {
         let tmp = TempDir::new("svg_hash_ignore");
         let dir = tmp.path();
-        let h1 = super::dir_state_hash(dir.to_str().unwrap()).expect("hash");
+        let h1 = super::dir_state_hash(dir.to_str().unwrap(), "sprite.svg", &RunOpts::default())
+            .expect("hash");
         std::fs::write(dir.join("note.txt"), "hello").unwrap();
-        let h2 = super::dir_state_hash(dir.to_str().unwrap()).expect("hash2");
+        let h2 = super::dir_state_hash(dir.to_str().unwrap(), "sprite.svg", &RunOpts::default())
+            .expect("hash2");
         assert_eq!(h1, h2, "non-svg changes should not affect dir_state_hash");
     }
 }
@@ -0,0 +1,87 @@
+//! `--archive-dir`: a timestamped, content-addressed copy of every sprite a
+//! build writes, as an audit trail of which sheet was generated when.
+
+use crate::error::AppError;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Copy `sprite`, just written to `file`, into `dir` as
+/// `<stem>-<UTC timestamp>.<hash>.<ext>`, returning the archive path.
+pub(crate) fn archive(dir: &Path, file: &str, sprite: &str) -> Result<PathBuf, AppError> {
+    let output = Path::new(file);
+    let stem = output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|s| *s != super::STDIO)
+        .unwrap_or("sprite");
+    let ext = output.extension().and_then(|s| s.to_str()).unwrap_or("svg");
+    let stamp = utc_timestamp(SystemTime::now());
+    let hash = super::source::sha256_hex(sprite.as_bytes());
+    let path = dir.join(format!("{stem}-{stamp}.{}.{ext}", &hash[..8]));
+    let to_err = |e| AppError::WriteFile {
+        path: path.display().to_string(),
+        source: e,
+    };
+    std::fs::create_dir_all(dir).map_err(to_err)?;
+    std::fs::write(&path, sprite).map_err(to_err)?;
+    tracing::info!(path = %path.display(), "Archived sprite");
+    Ok(path)
+}
+
+/// `time` as a compact ISO 8601 UTC timestamp (`20240131T235959Z`), safe in
+/// file names on every platform.
+fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Gregorian `(year, month, day)` of a day count since 1970-01-01 (Howard
+/// Hinnant's `civil_from_days`).
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn formats_utc_timestamps() {
+        let at = |secs| utc_timestamp(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "19700101T000000Z");
+        assert_eq!(at(951_827_696), "20000229T123456Z");
+        assert_eq!(at(1_735_689_599), "20241231T235959Z");
+    }
+
+    #[test]
+    fn archives_under_a_timestamped_hashed_name() {
+        let dir = std::env::temp_dir().join("svg_archive_names");
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = archive(&dir, "public/my.icons.svg", "<svg/>").unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        let hash = &super::super::source::sha256_hex(b"<svg/>")[..8];
+        assert!(
+            name.starts_with("my.icons-") && name.ends_with(&format!("Z.{hash}.svg")),
+            "{name}"
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "<svg/>");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,149 @@
+//! `--max-icon-size`: a warning for each icon whose markup is over budget,
+//! with a breakdown of its size so the bulk is easy to find.
+
+use super::CacheEntry;
+use super::markup::{self, Token};
+use super::summary::format_bytes;
+
+/// Bytes of an icon's markup by what they hold.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Breakdown {
+    /// Values of `d` attributes outside definitions
+    path_data: usize,
+    /// `<image>` elements, with their data URIs
+    images: usize,
+    /// `<defs>` elements and hoisted shared definitions
+    defs: usize,
+}
+
+/// Measure `entry`'s markup. Markup that does not tokenize is all "other".
+fn breakdown(entry: &CacheEntry) -> Breakdown {
+    let mut out = Breakdown {
+        defs: entry.shared_defs.iter().map(|(_, m)| m.len()).sum(),
+        ..Default::default()
+    };
+    // Depth inside `<defs>` or `<image>`, and which of the two it is.
+    let mut inside: Option<(&str, usize)> = None;
+    for token in markup::tokenize(&entry.children).unwrap_or_default() {
+        let len = token.raw().len();
+        match (&mut inside, &token) {
+            (Some((name, depth)), _) => {
+                if *name == "defs" {
+                    out.defs += len;
+                } else {
+                    out.images += len;
+                }
+                match token {
+                    Token::Open { empty: false, .. } => *depth += 1,
+                    Token::Close(_) if *depth == 1 => inside = None,
+                    Token::Close(_) => *depth -= 1,
+                    _ => {}
+                }
+            }
+            (None, Token::Open { name, raw, empty }) => match *name {
+                "defs" => {
+                    out.defs += len;
+                    inside = (!empty).then_some(("defs", 1));
+                }
+                "image" => {
+                    out.images += len;
+                    inside = (!empty).then_some(("image", 1));
+                }
+                _ => {
+                    out.path_data += markup::attributes(raw)
+                        .unwrap_or_default()
+                        .iter()
+                        .filter(|(k, ..)| *k == "d")
+                        .map(|(_, v, _)| v.len())
+                        .sum::<usize>();
+                }
+            },
+            (None, _) => {}
+        }
+    }
+    out
+}
+
+/// Warning for an icon over the `--max-icon-size` budget `limit`, naming
+/// what its bytes are; `None` within budget. The icon's size is its
+/// `pattern_bytes` of emitted markup plus the shared definitions it hoisted
+/// into the sprite's root.
+pub(crate) fn over_budget(entry: &CacheEntry, pattern_bytes: usize, limit: u64) -> Option<String> {
+    let shared: usize = entry.shared_defs.iter().map(|(_, m)| m.len()).sum();
+    let bytes = pattern_bytes + shared;
+    if bytes as u64 <= limit {
+        return None;
+    }
+    let b = breakdown(entry);
+    let other = bytes.saturating_sub(b.path_data + b.images + b.defs);
+    let mut parts = [
+        ("path data", b.path_data),
+        ("embedded images", b.images),
+        ("definitions", b.defs),
+        ("other markup", other),
+    ];
+    parts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+    let listed: Vec<String> = parts
+        .iter()
+        .filter(|(_, n)| *n > 0)
+        .map(|(label, n)| format!("{label} {}", format_bytes(*n as u64)))
+        .collect();
+    Some(format!(
+        "Icon '{}' is {}, over the {} budget; {}",
+        entry.name,
+        format_bytes(bytes as u64),
+        format_bytes(limit),
+        listed.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn icon(children: &str) -> CacheEntry {
+        CacheEntry {
+            mtime: std::time::UNIX_EPOCH,
+            len: 0,
+            name: "map".into(),
+            out_attrs: vec![],
+            children: children.into(),
+            child_ids: vec![],
+            path_str: "map.svg".into(),
+            warnings: vec![],
+            hash: String::new(),
+            licenses: vec![],
+            shared_defs: vec![("g".into(), "<linearGradient id=\"g\"/>".into())],
+            colors_remapped: 0,
+            attributes_stripped: vec![],
+            theme_vars: vec![],
+            overrides: None,
+        }
+    }
+
+    #[test]
+    fn breaks_down_paths_images_and_defs() {
+        let entry = icon(
+            "<defs><clipPath id=\"c\"><path d=\"M0 0\"/></clipPath></defs><g><path d=\"M1 1h2\"/></g><image href=\"data:image/png;base64,AAAA\"></image>",
+        );
+        assert_eq!(
+            breakdown(&entry),
+            Breakdown {
+                path_data: 6,
+                images: 41 + 8,
+                defs: 24 + 57,
+            }
+        );
+    }
+
+    #[test]
+    fn warns_over_budget_listing_the_largest_parts_first() {
+        let entry = icon(&format!("<path d=\"{}\"/>", "M0 0".repeat(600)));
+        let bytes = entry.children.len() + 40;
+        assert!(over_budget(&entry, bytes, bytes as u64 + 24).is_none());
+        assert_eq!(
+            over_budget(&entry, bytes, 1024).unwrap(),
+            "Icon 'map' is 2.4 KiB, over the 1.0 KiB budget; path data 2.3 KiB, other markup 52 B, definitions 24 B"
+        );
+    }
+}
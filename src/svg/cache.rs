@@ -0,0 +1,249 @@
+//! Persistent on-disk build cache keyed by input path and content hash.
+//!
+//! Stores the parsed/normalized fragment of every input so repeated CLI runs
+//! only re-process files whose content changed. The whole cache is discarded
+//! when the crate version or any output-affecting option differs.
+
+use super::source::sha256_hex;
+use super::{CacheEntry, RunOpts};
+use crate::error::{AppError, Warning, WarningKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// File name of the cache inside `--cache-dir`.
+const CACHE_FILE: &str = "svg_sheet-cache.json";
+
+/// Serialized form of the cache file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DiskCache {
+    fingerprint: String,
+    entries: HashMap<String, DiskEntry>,
+}
+
+/// One cached input, valid while its content hash matches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DiskEntry {
+    hash: String,
+    name: String,
+    out_attrs: Vec<(String, String)>,
+    children: String,
+    child_ids: Vec<String>,
+    /// `(code, message)` of each warning
+    warnings: Vec<(String, String)>,
+    #[serde(default)]
+    licenses: Vec<String>,
+    #[serde(default)]
+    shared_defs: Vec<(String, String)>,
+    #[serde(default)]
+    colors_remapped: usize,
+    #[serde(default)]
+    attributes_stripped: Vec<(String, usize)>,
+    #[serde(default)]
+    theme_vars: Vec<(String, String)>,
+}
+
+/// Loaded cache plus bookkeeping for saving it back after a build.
+pub(crate) struct BuildCache {
+    path: PathBuf,
+    disk: DiskCache,
+    dirty: bool,
+}
+
+impl BuildCache {
+    /// Load the cache from `dir`, starting empty if it is missing, unreadable,
+    /// or was written with a different fingerprint.
+    pub(crate) fn load(dir: &Path, opts: &RunOpts) -> Self {
+        let path = dir.join(CACHE_FILE);
+        let fingerprint = fingerprint(opts);
+        let disk = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<DiskCache>(&bytes).ok())
+            .filter(|d| d.fingerprint == fingerprint);
+        let dirty = disk.is_none();
+        let disk = disk.unwrap_or(DiskCache {
+            fingerprint,
+            entries: HashMap::new(),
+        });
+        BuildCache { path, disk, dirty }
+    }
+
+    /// Return the cached entry for `path` if its content hash still matches.
+    pub(crate) fn get(&self, path: &str, hash: &str) -> Option<CacheEntry> {
+        let e = self.disk.entries.get(path).filter(|e| e.hash == hash)?;
+        Some(CacheEntry {
+            mtime: UNIX_EPOCH,
+            len: 0,
+            name: e.name.clone(),
+            out_attrs: e.out_attrs.clone(),
+            children: e.children.clone(),
+            child_ids: e.child_ids.clone(),
+            path_str: path.to_string(),
+            warnings: e
+                .warnings
+                .iter()
+                .filter_map(|(code, message)| {
+                    let kind = WarningKind::from_code(code)?;
+                    Some(Warning::new(kind, path, message.clone()))
+                })
+                .collect(),
+            hash: e.hash.clone(),
+            licenses: e.licenses.clone(),
+            shared_defs: e.shared_defs.clone(),
+            colors_remapped: e.colors_remapped,
+            attributes_stripped: e.attributes_stripped.clone(),
+            theme_vars: e.theme_vars.clone(),
+            overrides: None,
+        })
+    }
+
+    /// Record a freshly parsed entry for `path`.
+    pub(crate) fn insert(&mut self, path: &str, hash: String, entry: &CacheEntry) {
+        self.disk.entries.insert(
+            path.to_string(),
+            DiskEntry {
+                hash,
+                name: entry.name.clone(),
+                out_attrs: entry.out_attrs.clone(),
+                children: entry.children.clone(),
+                child_ids: entry.child_ids.clone(),
+                warnings: entry
+                    .warnings
+                    .iter()
+                    .map(|w| (w.code().to_string(), w.message.clone()))
+                    .collect(),
+                licenses: entry.licenses.clone(),
+                shared_defs: entry.shared_defs.clone(),
+                colors_remapped: entry.colors_remapped,
+                attributes_stripped: entry.attributes_stripped.clone(),
+                theme_vars: entry.theme_vars.clone(),
+            },
+        );
+        self.dirty = true;
+    }
+
+    /// Drop entries for inputs that no longer exist.
+    pub(crate) fn retain(&mut self, live: &std::collections::HashSet<String>) {
+        let before = self.disk.entries.len();
+        self.disk.entries.retain(|k, _| live.contains(k));
+        if self.disk.entries.len() != before {
+            self.dirty = true;
+        }
+    }
+
+    /// Write the cache back to disk if anything changed.
+    pub(crate) fn save(&self) -> Result<(), AppError> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let to_err = |e: std::io::Error| AppError::WriteFile {
+            path: self.path.display().to_string(),
+            source: e,
+        };
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(to_err)?;
+        }
+        let bytes = serde_json::to_vec(&self.disk).map_err(|e| to_err(e.into()))?;
+        std::fs::write(&self.path, bytes).map_err(to_err)
+    }
+}
+
+/// Fingerprint of the crate version and the options that shape parsed output.
+/// Options that only affect reporting or scheduling are reset before hashing.
+fn fingerprint(opts: &RunOpts) -> String {
+    let mut relevant = opts.clone();
+    relevant.quiet = false;
+    relevant.verbose = false;
+    relevant.dry_run = false;
+    relevant.diff = false;
+    relevant.backup = None;
+    relevant.archive_dir = None;
+    relevant.generator_comment = false;
+    relevant.hash_attrs = false;
+    relevant.fragment = false;
+    relevant.root_attrs.clear();
+    relevant.no_timestamp = false;
+    relevant.lock = false;
+    relevant.update_lock = false;
+    relevant.aliases.clear();
+    relevant.deprecated.clear();
+    relevant.tags.clear();
+    relevant.synonyms = None;
+    relevant.titles = None;
+    relevant.baseline = None;
+    relevant.overlays.clear();
+    relevant.files = None;
+    relevant.fail_on_warn = false;
+    relevant.allow.clear();
+    relevant.deny.clear();
+    relevant.warning_baseline = 0;
+    relevant.debounce_ms = 0;
+    relevant.jobs = None;
+    relevant.poll = false;
+    relevant.poll_interval_ms = 0;
+    relevant.rebuild_trigger = None;
+    relevant.cache_dir = None;
+    relevant.max_file_size = None;
+    relevant.max_icon_bytes = None;
+    relevant.max_icon_size = None;
+    sha256_hex(format!("{}:{relevant:?}", env!("CARGO_PKG_VERSION")).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> CacheEntry {
+        CacheEntry {
+            mtime: UNIX_EPOCH,
+            len: 0,
+            name: "a".into(),
+            out_attrs: vec![("width".into(), "1".into())],
+            children: "<g/>".into(),
+            child_ids: vec![],
+            path_str: "a.svg".into(),
+            warnings: vec![Warning::new(
+                WarningKind::MissingHeight,
+                "a.svg",
+                "Missing height on root <svg>",
+            )],
+            hash: "h1".into(),
+            licenses: vec![],
+            shared_defs: vec![],
+            colors_remapped: 0,
+            attributes_stripped: vec![],
+            theme_vars: vec![],
+            overrides: None,
+        }
+    }
+
+    #[test]
+    fn round_trips_and_checks_hash() {
+        let dir = std::env::temp_dir().join(format!("svg_sheet_cache_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let opts = RunOpts::default();
+        let mut cache = BuildCache::load(&dir, &opts);
+        cache.insert("a.svg", "h1".into(), &entry());
+        cache.save().expect("save");
+
+        let cache = BuildCache::load(&dir, &opts);
+        let hit = cache.get("a.svg", "h1").expect("hit");
+        assert_eq!(hit.children, "<g/>");
+        assert_eq!(hit.warnings, entry().warnings);
+        assert!(cache.get("a.svg", "h2").is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn fingerprint_ignores_reporting_options() {
+        let base = RunOpts::default();
+        let noisy = RunOpts {
+            verbose: true,
+            dry_run: true,
+            cache_dir: Some("x".into()),
+            ..Default::default()
+        };
+        assert_eq!(fingerprint(&base), fingerprint(&noisy));
+    }
+}
@@ -0,0 +1,229 @@
+//! Splitting the sprite into several sheets (`--chunk-by`), plus the combined
+//! manifest mapping each icon to the sheet that holds it.
+
+use super::CacheEntry;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// How icons are assigned to chunks.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChunkBy {
+    /// One chunk per top-level subdirectory of the input directory; icons
+    /// directly inside it form the `root` chunk
+    Dir,
+    /// Consecutive icons packed into chunks of at most this many bytes of
+    /// pattern markup (an icon larger than the limit gets a chunk of its own)
+    Size(u64),
+}
+
+/// Name of the chunk holding icons that sit directly in the input directory.
+pub const ROOT_CHUNK: &str = "root";
+
+/// Path of chunk `name` for the sprite `file`: `dist/sprite.svg` and `social`
+/// give `dist/sprite-social.svg`.
+///
+/// Example:
+/// ```
+/// use svg_sheet::svg::chunk::chunk_path;
+/// assert_eq!(chunk_path("dist/sprite.svg", "social"), "dist/sprite-social.svg");
+/// assert_eq!(chunk_path("icons", "2"), "icons-2");
+/// ```
+pub fn chunk_path(file: &str, name: &str) -> String {
+    let path = Path::new(file);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or(file);
+    let file_name = match path.extension().and_then(|s| s.to_str()) {
+        Some(ext) => format!("{stem}-{name}.{ext}"),
+        None => format!("{stem}-{name}"),
+    };
+    path.with_file_name(file_name).display().to_string()
+}
+
+/// Path of the combined manifest for the sprite `file`: `dist/sprite.svg`
+/// gives `dist/sprite.chunks.json`.
+///
+/// Example:
+/// ```
+/// assert_eq!(svg_sheet::svg::chunk::manifest_path("dist/sprite.svg"), "dist/sprite.chunks.json");
+/// ```
+pub fn manifest_path(file: &str) -> String {
+    Path::new(file)
+        .with_extension("chunks.json")
+        .display()
+        .to_string()
+}
+
+/// Assign `entries` to named chunks, keeping the input order within each.
+/// `root` is the input directory the entries' paths start with; `sizes` gives
+/// each entry's pattern size for `ChunkBy::Size`.
+pub(crate) fn split<'a>(
+    entries: &[&'a CacheEntry],
+    sizes: &[usize],
+    by: ChunkBy,
+    root: &str,
+) -> Vec<(String, Vec<&'a CacheEntry>)> {
+    let mut chunks: Vec<(String, Vec<&CacheEntry>)> = Vec::new();
+    match by {
+        ChunkBy::Dir => {
+            for entry in entries {
+                let name = dir_chunk(&entry.path_str, root);
+                match chunks.iter_mut().find(|(n, _)| *n == name) {
+                    Some((_, members)) => members.push(entry),
+                    None => chunks.push((name, vec![entry])),
+                }
+            }
+        }
+        ChunkBy::Size(limit) => {
+            let mut used = 0u64;
+            for (entry, &size) in entries.iter().zip(sizes) {
+                let size = size as u64;
+                match chunks.last_mut() {
+                    Some((_, members)) if used + size <= limit => members.push(entry),
+                    _ => {
+                        chunks.push(((chunks.len() + 1).to_string(), vec![entry]));
+                        used = 0;
+                    }
+                }
+                used += size;
+            }
+        }
+    }
+    chunks
+}
+
+/// Chunk name for an input: its top-level subdirectory below `root`, made
+/// safe for a file name, or `ROOT_CHUNK`.
+fn dir_chunk(path: &str, root: &str) -> String {
+    let relative = Path::new(path)
+        .strip_prefix(root)
+        .unwrap_or(Path::new(path));
+    let mut components = relative.components();
+    let first = components.next();
+    match (first, components.next()) {
+        (Some(dir), Some(_)) => {
+            let name = super::sanitize::sanitize_id(&dir.as_os_str().to_string_lossy());
+            if name.is_empty() {
+                ROOT_CHUNK.to_string()
+            } else {
+                name
+            }
+        }
+        _ => ROOT_CHUNK.to_string(),
+    }
+}
+
+/// Serialized form of the combined chunk manifest.
+#[derive(Serialize)]
+struct ChunkManifest<'a> {
+    /// Chunk name -> sprite path
+    chunks: BTreeMap<&'a str, &'a str>,
+    /// Icon id -> sprite path of the chunk holding it
+    icons: BTreeMap<&'a str, &'a str>,
+}
+
+/// Render the combined manifest for `chunks`, given as `(name, path, icons)`.
+/// Each `(alias, icon)` in `aliases` maps to the chunk holding its icon.
+pub(crate) fn manifest(
+    chunks: &[(String, String, Vec<&CacheEntry>)],
+    aliases: &[(String, String)],
+) -> String {
+    let mut manifest = ChunkManifest {
+        chunks: chunks
+            .iter()
+            .map(|(name, path, _)| (name.as_str(), path.as_str()))
+            .collect(),
+        icons: chunks
+            .iter()
+            .flat_map(|(_, path, members)| members.iter().map(|e| (e.name.as_str(), path.as_str())))
+            .collect(),
+    };
+    for (alias, target) in aliases {
+        if let Some(path) = manifest.icons.get(target.as_str()).copied() {
+            manifest.icons.insert(alias, path);
+        }
+    }
+    let mut out = serde_json::to_string_pretty(&manifest).expect("manifest serializes");
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn entry(name: &str, path: &str) -> CacheEntry {
+        CacheEntry {
+            mtime: UNIX_EPOCH,
+            len: 0,
+            name: name.into(),
+            out_attrs: vec![],
+            children: String::new(),
+            child_ids: vec![],
+            path_str: path.into(),
+            warnings: vec![],
+            hash: String::new(),
+            licenses: vec![],
+            shared_defs: vec![],
+            colors_remapped: 0,
+            attributes_stripped: vec![],
+            theme_vars: vec![],
+            overrides: None,
+        }
+    }
+
+    fn names(chunks: &[(String, Vec<&CacheEntry>)]) -> Vec<(String, Vec<String>)> {
+        chunks
+            .iter()
+            .map(|(n, m)| (n.clone(), m.iter().map(|e| e.name.clone()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn splits_by_top_level_directory() {
+        let a = entry("like", "svgs/social/like.svg");
+        let b = entry("bold", "svgs/editor/bold.svg");
+        let c = entry("share", "svgs/social/deep/share.svg");
+        let d = entry("logo", "svgs/logo.svg");
+        let chunks = split(&[&a, &b, &c, &d], &[], ChunkBy::Dir, "svgs");
+        assert_eq!(
+            names(&chunks),
+            vec![
+                ("social".into(), vec!["like".into(), "share".into()]),
+                ("editor".into(), vec!["bold".into()]),
+                ("root".into(), vec!["logo".into()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_by_size_in_order() {
+        let e: Vec<CacheEntry> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|n| entry(n, &format!("svgs/{n}.svg")))
+            .collect();
+        let refs: Vec<&CacheEntry> = e.iter().collect();
+        let chunks = split(&refs, &[40, 50, 200, 10], ChunkBy::Size(100), "svgs");
+        assert_eq!(
+            names(&chunks),
+            vec![
+                ("1".into(), vec!["a".into(), "b".into()]),
+                ("2".into(), vec!["c".into()]),
+                ("3".into(), vec!["d".into()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn manifest_maps_icons_to_chunks() {
+        let a = entry("like", "svgs/social/like.svg");
+        let out = manifest(
+            &[("social".into(), "sprite-social.svg".into(), vec![&a])],
+            &[("thumbs-up".into(), "like".into())],
+        );
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["chunks"]["social"], "sprite-social.svg");
+        assert_eq!(json["icons"]["like"], "sprite-social.svg");
+        assert_eq!(json["icons"]["thumbs-up"], "sprite-social.svg");
+    }
+}
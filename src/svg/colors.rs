@@ -0,0 +1,262 @@
+//! Color remapping from the config file's `[colors]` table, and the
+//! `--themable` rewrite of colors into CSS custom properties.
+
+use super::markup::{self, Token};
+use std::borrow::Cow;
+
+/// Properties whose values are remapped, as attributes or `style` declarations.
+const PROPERTIES: [&str; 3] = ["fill", "stroke", "stop-color"];
+
+/// Matching form of a color: trimmed, lowercase, `#abc` expanded to `#aabbcc`.
+fn normalize(color: &str) -> String {
+    let lower = color.trim().to_ascii_lowercase();
+    match lower.strip_prefix('#') {
+        Some(hex) if hex.len() == 3 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            hex.chars().fold(String::from("#"), |mut out, c| {
+                out.push(c);
+                out.push(c);
+                out
+            })
+        }
+        _ => lower,
+    }
+}
+
+/// Replacement for `value` under `map`, if any.
+fn lookup<'a>(map: &'a [(String, String)], value: &str) -> Option<&'a str> {
+    let value = normalize(value);
+    map.iter()
+        .find(|(from, _)| normalize(from) == value)
+        .map(|(_, to)| to.as_str())
+}
+
+/// Rewrite a `style` attribute's fill/stroke/stop-color declarations with `f`,
+/// counting replacements into `count`.
+fn rewrite_style(
+    style: &str,
+    f: &mut impl FnMut(&str, &str) -> Option<String>,
+    count: &mut usize,
+) -> String {
+    style
+        .split(';')
+        .map(|decl| {
+            let Some((prop, value)) = decl.split_once(':') else {
+                return decl.to_string();
+            };
+            let prop_name = prop.trim();
+            if !PROPERTIES.contains(&prop_name) {
+                return decl.to_string();
+            }
+            match f(prop_name, value.trim()) {
+                Some(to) => {
+                    *count += 1;
+                    format!("{prop}:{to}")
+                }
+                None => decl.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Rewrite fill/stroke/stop-color values in root `<svg>` attributes with `f`,
+/// called as `f(property, value)`. Returns the replacement count.
+fn rewrite_attrs(
+    attrs: &mut [(String, String)],
+    mut f: impl FnMut(&str, &str) -> Option<String>,
+) -> usize {
+    let mut count = 0;
+    for (k, v) in attrs.iter_mut() {
+        if k == "style" {
+            *v = rewrite_style(v, &mut f, &mut count);
+        } else if PROPERTIES.contains(&k.as_str())
+            && let Some(to) = f(k, v)
+        {
+            *v = to;
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Rewrite fill/stroke/stop-color values in `children` with `f`, called as
+/// `f(property, value)`. Returns the content and the number of replacements.
+/// Malformed markup is returned as is.
+fn rewrite(
+    children: &str,
+    mut f: impl FnMut(&str, &str) -> Option<String>,
+) -> (Cow<'_, str>, usize) {
+    let Some(tokens) = markup::tokenize(children) else {
+        return (Cow::Borrowed(children), 0);
+    };
+    let mut out = String::with_capacity(children.len());
+    let mut count = 0;
+    for token in &tokens {
+        let Token::Open { name, raw, empty } = *token else {
+            out.push_str(token.raw());
+            continue;
+        };
+        let Some(attrs) = markup::attributes(raw) else {
+            out.push_str(raw);
+            continue;
+        };
+        let before = count;
+        let values: Vec<String> = attrs
+            .iter()
+            .map(|&(k, v, _)| {
+                if k == "style" {
+                    rewrite_style(v, &mut f, &mut count)
+                } else if PROPERTIES.contains(&k)
+                    && let Some(to) = f(k, v)
+                {
+                    count += 1;
+                    to
+                } else {
+                    v.to_string()
+                }
+            })
+            .collect();
+        if count == before {
+            out.push_str(raw);
+            continue;
+        }
+        let attrs: Vec<(&str, &str, char)> = attrs
+            .iter()
+            .zip(&values)
+            .map(|(&(k, _, q), v)| {
+                // A replacement such as `url("#x")` must not end the attribute.
+                let q = if v.contains(q) {
+                    if q == '"' { '\'' } else { '"' }
+                } else {
+                    q
+                };
+                (k, v.as_str(), q)
+            })
+            .collect();
+        markup::push_tag(&mut out, name, &attrs, empty);
+    }
+    if count == 0 {
+        (Cow::Borrowed(children), 0)
+    } else {
+        (Cow::Owned(out), count)
+    }
+}
+
+/// Apply `map` to the root `<svg>` attributes. Returns the replacement count.
+pub(crate) fn remap_attrs(attrs: &mut [(String, String)], map: &[(String, String)]) -> usize {
+    if map.is_empty() {
+        return 0;
+    }
+    rewrite_attrs(attrs, |_, v| lookup(map, v).map(str::to_string))
+}
+
+/// Apply `map` to fills, strokes and stop colors in `children`. Returns the
+/// content and the number of replacements. Malformed markup is returned as is.
+pub(crate) fn remap<'a>(children: &'a str, map: &[(String, String)]) -> (Cow<'a, str>, usize) {
+    if map.is_empty() {
+        return (Cow::Borrowed(children), 0);
+    }
+    rewrite(children, |_, v| lookup(map, v).map(str::to_string))
+}
+
+/// Whether a paint value is a literal color (not `none`, `currentColor`, a
+/// paint server, or already a variable).
+fn is_literal_color(value: &str) -> bool {
+    let lower = value.trim().to_ascii_lowercase();
+    !(lower.is_empty()
+        || [
+            "none",
+            "currentcolor",
+            "inherit",
+            "transparent",
+            "context-fill",
+            "context-stroke",
+        ]
+        .contains(&lower.as_str())
+        || lower.starts_with("url(")
+        || lower.starts_with("var("))
+}
+
+/// CSS custom properties introduced by `--themable`, as `(name, default)`.
+pub(crate) type ThemeVars = Vec<(String, String)>;
+
+/// Wrap every literal fill, stroke and stop color (root attributes included)
+/// in `var(--icon-<role>, <original>)`. The role comes from `roles` (pairs of
+/// `(color, role)`), falling back to the property: `fill`, `stroke`, or
+/// `stop`. Returns the content and the variables used, in first-use order.
+pub(crate) fn themable<'a>(
+    children: &'a str,
+    attrs: &mut [(String, String)],
+    roles: &[(String, String)],
+) -> (Cow<'a, str>, ThemeVars) {
+    let mut vars: ThemeVars = Vec::new();
+    let mut wrap = |prop: &str, value: &str| {
+        if !is_literal_color(value) {
+            return None;
+        }
+        let role = lookup(roles, value)
+            .map(str::to_string)
+            .unwrap_or_else(|| prop.trim_end_matches("-color").to_string());
+        let var = format!("--icon-{role}");
+        let value = value.trim();
+        if !vars.iter().any(|(n, d)| *n == var && d == value) {
+            vars.push((var.clone(), value.to_string()));
+        }
+        Some(format!("var({var}, {value})"))
+    };
+    rewrite_attrs(attrs, &mut wrap);
+    let (children, _) = rewrite(children, &mut wrap);
+    (children, vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> Vec<(String, String)> {
+        vec![
+            ("#1A1A1A".to_string(), "currentColor".to_string()),
+            ("#f00".to_string(), "var(--danger)".to_string()),
+        ]
+    }
+
+    #[test]
+    fn remaps_attributes_and_style_declarations() {
+        let input = "<path fill=\"#1a1a1a\" d=\"M0 0\"/><g style=\"stroke: #FF0000; opacity:.5\"><stop stop-color='#111'/></g>";
+        let (out, count) = remap(input, &map());
+        assert_eq!(count, 2);
+        assert_eq!(
+            out,
+            "<path fill=\"currentColor\" d=\"M0 0\"/><g style=\"stroke:var(--danger); opacity:.5\"><stop stop-color='#111'/></g>"
+        );
+    }
+
+    #[test]
+    fn leaves_other_properties_and_root_attrs() {
+        let input = "<rect color=\"#1a1a1a\" fill=\"none\"/>";
+        assert_eq!(remap(input, &map()), (Cow::Borrowed(input), 0));
+
+        let mut attrs = vec![
+            ("fill".to_string(), "#1a1a1a".to_string()),
+            ("width".to_string(), "24".to_string()),
+        ];
+        assert_eq!(remap_attrs(&mut attrs, &map()), 1);
+        assert_eq!(attrs[0].1, "currentColor");
+    }
+
+    #[test]
+    fn themable_wraps_literal_colors_by_role() {
+        let roles = vec![("#1a1a1a".to_string(), "primary".to_string())];
+        let mut attrs = vec![("fill".to_string(), "#1A1A1A".to_string())];
+        let input = "<path fill=\"#f00\" stroke=\"currentColor\"/><g style=\"stroke:#1a1a1a\"><stop stop-color=\"#fff\"/></g><rect fill=\"url(#g)\"/>";
+        let (out, vars) = themable(input, &mut attrs, &roles);
+        assert_eq!(attrs[0].1, "var(--icon-primary, #1A1A1A)");
+        assert_eq!(
+            out,
+            "<path fill=\"var(--icon-fill, #f00)\" stroke=\"currentColor\"/><g style=\"stroke:var(--icon-primary, #1a1a1a)\"><stop stop-color=\"var(--icon-stop, #fff)\"/></g><rect fill=\"url(#g)\"/>"
+        );
+        let defaults: Vec<&str> = vars.iter().map(|(_, d)| d.as_str()).collect();
+        assert_eq!(defaults, ["#1A1A1A", "#f00", "#1a1a1a", "#fff"]);
+        assert_eq!(vars[0].0, "--icon-primary");
+    }
+}
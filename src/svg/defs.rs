@@ -0,0 +1,213 @@
+//! `--shared-defs`: hoist gradients and filters out of the icons into the
+//! sprite's root `<defs>`, merging structurally identical copies.
+//!
+//! Each hoisted definition is renamed after a hash of its canonical markup
+//! (attribute order and whitespace ignored), so identical definitions from
+//! different icons land on the same id and are emitted once.
+
+use super::markup::{self, Token};
+use std::borrow::Cow;
+
+/// Elements hoisted into the shared defs.
+const HOISTED: [&str; 3] = ["linearGradient", "radialGradient", "filter"];
+
+/// Move every referenced gradient and filter out of `children`, rewriting the
+/// `url(#...)`/`href="#..."` references to its shared id. Returns the new
+/// children and the hoisted `(id, markup)` pairs.
+///
+/// Definitions that reference other elements themselves (`href`, nested
+/// `url(...)`) stay in place, as do unreferenced ones.
+pub(crate) fn hoist(children: &str) -> (Cow<'_, str>, Vec<(String, String)>) {
+    let Some(tokens) = markup::tokenize(children) else {
+        return (Cow::Borrowed(children), Vec::new());
+    };
+    // (byte range in `children`, old id, shared id)
+    let mut moved: Vec<(std::ops::Range<usize>, &str, String)> = Vec::new();
+    let mut defs: Vec<(String, String)> = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let start = i;
+        let Token::Open { name, raw, empty } = tokens[i] else {
+            i += 1;
+            continue;
+        };
+        if !HOISTED.contains(&name) {
+            i += 1;
+            continue;
+        }
+        i = if empty {
+            i + 1
+        } else {
+            matching_close(&tokens, i)
+        };
+        let element: String = tokens[start..i].iter().map(Token::raw).collect();
+        let Some(attrs) = markup::attributes(raw) else {
+            continue;
+        };
+        let Some(&(_, id, _)) = attrs.iter().find(|(k, ..)| *k == "id") else {
+            continue;
+        };
+        if id.is_empty()
+            || element.contains("href")
+            || element.contains("url(")
+            || moved.iter().any(|(_, old, _)| *old == id)
+            || !super::ids::references_id(children, id)
+        {
+            continue;
+        }
+        let canonical = canonical(&tokens[start..i]);
+        let shared = format!(
+            "def-{}",
+            &super::source::sha256_hex(canonical.as_bytes())[..8]
+        );
+        let attrs: Vec<(&str, &str, char)> = attrs
+            .iter()
+            .map(|&(k, v, q)| {
+                if k == "id" {
+                    (k, shared.as_str(), '"')
+                } else {
+                    (k, v, q)
+                }
+            })
+            .collect();
+        let mut markup = String::new();
+        markup::push_tag(&mut markup, name, &attrs, empty);
+        // Ids inside the definition (e.g. on stops) are demoted like any other.
+        markup.push_str(&super::ids::rewrite_ids_to_data_ids(&element[raw.len()..]).0);
+        let from = offset(children, raw);
+        moved.push((from..from + element.len(), id, shared.clone()));
+        if !defs.iter().any(|(d, _)| *d == shared) {
+            defs.push((shared, markup));
+        }
+    }
+    if moved.is_empty() {
+        return (Cow::Borrowed(children), defs);
+    }
+
+    let mut out = String::with_capacity(children.len());
+    let mut from = 0;
+    for (range, ..) in &moved {
+        out.push_str(&children[from..range.start]);
+        from = range.end;
+    }
+    out.push_str(&children[from..]);
+    let renamed: Vec<(&str, &str)> = moved
+        .iter()
+        .map(|(_, old, shared)| (*old, shared.as_str()))
+        .collect();
+    let out = super::ids::rewrite_references(&out, &renamed);
+    (Cow::Owned(drop_empty_defs(&out)), defs)
+}
+
+/// The distinct shared definitions of `entries`, in first-use order, as markup
+/// for the sprite's root `<defs>`.
+#[cfg(feature = "fs")]
+pub(crate) fn collect(entries: &[&super::CacheEntry]) -> String {
+    let mut seen = std::collections::HashSet::new();
+    entries
+        .iter()
+        .flat_map(|e| &e.shared_defs)
+        .filter(|(id, _)| seen.insert(id.as_str()))
+        .map(|(_, markup)| markup.as_str())
+        .collect()
+}
+
+/// Index just past the end tag matching the start tag at `open`.
+pub(super) fn matching_close(tokens: &[Token], open: usize) -> usize {
+    let mut depth = 0usize;
+    for (n, token) in tokens.iter().enumerate().skip(open) {
+        match token {
+            Token::Open { empty: false, .. } => depth += 1,
+            Token::Close(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    return n + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+/// Canonical form of an element: attributes sorted (the root's `id` left
+/// out), whitespace collapsed.
+fn canonical(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for (n, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Open { name, raw, empty } => match markup::attributes(raw) {
+                Some(mut attrs) => {
+                    attrs.retain(|(k, ..)| n > 0 || *k != "id");
+                    attrs.sort_by_key(|&(k, v, _)| (k, v));
+                    let attrs: Vec<_> = attrs.into_iter().map(|(k, v, _)| (k, v, '"')).collect();
+                    markup::push_tag(&mut out, name, &attrs, *empty);
+                }
+                None => out.push_str(raw),
+            },
+            Token::Close(raw) => out.push_str(&raw.split_whitespace().collect::<String>()),
+            Token::Text(text) => {
+                out.push_str(&text.split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+        }
+    }
+    out
+}
+
+/// Remove `<defs>` elements left with nothing but whitespace.
+fn drop_empty_defs(s: &str) -> String {
+    let mut out = s.replace("<defs/>", "");
+    while let Some(open) = out.find("<defs>") {
+        let inner = &out[open + "<defs>".len()..];
+        let ws = inner.len() - inner.trim_start().len();
+        if !inner[ws..].starts_with("</defs>") {
+            // Not empty; leave this and any later ones alone.
+            break;
+        }
+        out.replace_range(open..open + "<defs>".len() + ws + "</defs>".len(), "");
+    }
+    out
+}
+
+/// Byte offset of `part`, a subslice of `whole`.
+fn offset(whole: &str, part: &str) -> usize {
+    part.as_ptr() as usize - whole.as_ptr() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRADIENT: &str = "<linearGradient id=\"paint0\" x2=\"1\"><stop offset=\"0\" stop-color=\"red\"/></linearGradient>";
+
+    #[test]
+    fn hoists_referenced_gradients_and_rewrites_references() {
+        let children = format!("<defs>{GRADIENT}</defs><path fill=\"url(#paint0)\" d=\"M0 0\"/>");
+        let (out, defs) = hoist(&children);
+        assert_eq!(defs.len(), 1);
+        let (id, markup) = &defs[0];
+        assert!(id.starts_with("def-"));
+        assert!(markup.starts_with(&format!("<linearGradient id=\"{id}\" x2=\"1\">")));
+        assert_eq!(out, format!("<path fill=\"url(#{id})\" d=\"M0 0\"/>"));
+    }
+
+    #[test]
+    fn identical_definitions_share_an_id() {
+        let reordered = "<linearGradient x2='1' id='g'>\n  <stop stop-color='red' offset='0'/>\n</linearGradient>";
+        let (_, a) = hoist(&format!("{GRADIENT}<path fill=\"url(#paint0)\"/>"));
+        let (_, b) = hoist(&format!("{reordered}<path fill='url(#g)'/>"));
+        assert_eq!(a[0].0, b[0].0);
+
+        let other = GRADIENT.replace("red", "blue");
+        let (_, c) = hoist(&format!("{other}<path fill=\"url(#paint0)\"/>"));
+        assert_ne!(a[0].0, c[0].0);
+    }
+
+    #[test]
+    fn leaves_linked_and_unreferenced_definitions() {
+        let linked = "<linearGradient id=\"b\" href=\"#a\"/><path fill=\"url(#b)\"/>";
+        assert!(hoist(linked).1.is_empty());
+        let unused = format!("<defs>{GRADIENT}</defs><path/>");
+        assert_eq!(hoist(&unused), (Cow::Borrowed(unused.as_str()), vec![]));
+    }
+}
@@ -0,0 +1,104 @@
+//! Deprecated icons, marked in the config's `[deprecated]` table, in a
+//! sidecar file, or with a `data-deprecated` attribute on the root `<svg>`. They still build, but
+//! raise the `deprecated-icon` warning and are flagged in the manifest and
+//! preview.
+
+use super::sidecar::{Sidecar, Sidecars};
+use super::{CacheEntry, RunOpts};
+use crate::error::{Warning, WarningKind};
+use std::collections::HashMap;
+
+/// Root attribute marking an icon deprecated; its value is the message.
+pub(crate) const ATTR: &str = "data-deprecated";
+
+/// The deprecation message of the icon `name` with root `attrs` and
+/// `sidecar` (empty when none was given), or `None` when it is not
+/// deprecated. The config's message wins over the sidecar's, and the
+/// sidecar's over the attribute's.
+pub(crate) fn message<'a, K: AsRef<str>, V: AsRef<str>>(
+    name: &str,
+    attrs: &'a [(K, V)],
+    sidecar: Option<&'a Sidecar>,
+    opts: &'a RunOpts,
+) -> Option<&'a str> {
+    opts.deprecated
+        .iter()
+        .find(|(icon, _)| icon == name)
+        .map(|(_, message)| message.as_str())
+        .or_else(|| sidecar?.deprecation())
+        .or_else(|| {
+            attrs
+                .iter()
+                .find(|(k, _)| k.as_ref() == ATTR)
+                .map(|(_, v)| v.as_ref())
+        })
+}
+
+/// The deprecated icons among `entries`, by name.
+pub(crate) fn collect<'a>(
+    entries: &[&'a CacheEntry],
+    sidecars: &'a Sidecars,
+    opts: &'a RunOpts,
+) -> HashMap<&'a str, &'a str> {
+    entries
+        .iter()
+        .filter_map(|e| {
+            let message = message(&e.name, &e.out_attrs, sidecars.get(&e.name), opts)?;
+            Some((e.name.as_str(), message))
+        })
+        .collect()
+}
+
+/// One-line description of a deprecated icon, as used in warnings.
+pub(crate) fn describe(name: &str, message: &str) -> String {
+    if message.is_empty() {
+        format!("Icon '{name}' is deprecated")
+    } else {
+        format!("Icon '{name}' is deprecated: {message}")
+    }
+}
+
+/// A `deprecated-icon` warning for each deprecated icon among `entries`.
+pub(crate) fn warnings(entries: &[&CacheEntry], deprecated: &HashMap<&str, &str>) -> Vec<Warning> {
+    entries
+        .iter()
+        .filter_map(|e| {
+            let message = deprecated.get(e.name.as_str())?;
+            Some(Warning::new(
+                WarningKind::DeprecatedIcon,
+                &e.path_str,
+                describe(&e.name, message),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_messages_win_over_the_sidecar_and_attribute() {
+        let opts = RunOpts {
+            deprecated: vec![("old".into(), "Use new".into())],
+            ..Default::default()
+        };
+        let attrs = [(ATTR, "Use newer")];
+        let sidecar = Sidecar {
+            deprecated: Some(super::super::sidecar::Deprecated::Flag(true)),
+            ..Default::default()
+        };
+        assert_eq!(
+            message("old", &attrs, Some(&sidecar), &opts),
+            Some("Use new")
+        );
+        assert_eq!(message("other", &attrs, Some(&sidecar), &opts), Some(""));
+        assert_eq!(message("other", &attrs, None, &opts), Some("Use newer"));
+        assert_eq!(message("other", &[("width", "1")], None, &opts), None);
+        assert_eq!(describe("old", ""), "Icon 'old' is deprecated");
+        assert_eq!(
+            describe("old", "Use new"),
+            "Icon 'old' is deprecated: Use new"
+        );
+    }
+}
@@ -0,0 +1,419 @@
+//! Comparison of two sprites: a pattern-level summary, used by `--check` to
+//! explain why a committed sprite is out of date, and a unified diff for
+//! `--diff` previews.
+
+/// Unchanged lines shown around each change in a unified diff.
+const CONTEXT: usize = 3;
+
+/// Edit distance past which the changed lines are shown as one replacement
+/// instead of searching for the shortest diff.
+const MAX_EDITS: usize = 2000;
+
+/// Split sprite markup into `(id, markup)` per `<pattern>`, in document order.
+/// Each segment runs to the next pattern, so trailing markup (comments,
+/// `</defs>`) is attributed to the pattern before it.
+fn patterns(sprite: &str) -> Vec<(&str, &str)> {
+    const OPEN: &str = "<pattern id=\"";
+    let starts: Vec<usize> = sprite.match_indices(OPEN).map(|(i, _)| i).collect();
+    starts
+        .iter()
+        .enumerate()
+        .filter_map(|(n, &start)| {
+            let end = starts.get(n + 1).copied().unwrap_or(sprite.len());
+            let segment = &sprite[start..end];
+            let id = segment[OPEN.len()..].split('"').next()?;
+            Some((id, segment.trim_end_matches(super::transform::SPRITE_CLOSE)))
+        })
+        .collect()
+}
+
+/// Markup of the pattern `id` in `list`, if present.
+fn lookup<'a>(list: &[(&str, &'a str)], id: &str) -> Option<&'a str> {
+    list.iter()
+        .find(|(i, _)| *i == id)
+        .map(|(_, markup)| *markup)
+}
+
+/// Ids of the patterns added to, removed from, and changed between two
+/// sprites.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Changes<'a> {
+    pub(crate) added: Vec<&'a str>,
+    pub(crate) removed: Vec<&'a str>,
+    pub(crate) changed: Vec<&'a str>,
+}
+
+/// Compare the patterns of `old` and `new` by id.
+pub(crate) fn changes<'a>(old: &'a str, new: &'a str) -> Changes<'a> {
+    let before = patterns(old);
+    let after = patterns(new);
+    Changes {
+        added: after
+            .iter()
+            .filter(|(id, _)| lookup(&before, id).is_none())
+            .map(|(id, _)| *id)
+            .collect(),
+        removed: before
+            .iter()
+            .filter(|(id, _)| lookup(&after, id).is_none())
+            .map(|(id, _)| *id)
+            .collect(),
+        changed: after
+            .iter()
+            .filter(|(id, markup)| lookup(&before, id).is_some_and(|m| m != *markup))
+            .map(|(id, _)| *id)
+            .collect(),
+    }
+}
+
+/// Describe how `new` differs from `old` as `added: a; removed: b; changed: c`.
+/// Falls back to a generic note when the difference is outside the patterns
+/// (header, ordering, custom template output).
+pub(crate) fn summarize(old: &str, new: &str) -> String {
+    let Changes {
+        added,
+        removed,
+        changed,
+    } = changes(old, new);
+    let mut parts = Vec::new();
+    for (label, ids) in [("added", added), ("removed", removed), ("changed", changed)] {
+        if !ids.is_empty() {
+            parts.push(format!("{label}: {}", ids.join(", ")));
+        }
+    }
+    if parts.is_empty() {
+        "content differs outside the icon patterns".to_string()
+    } else {
+        parts.join("; ")
+    }
+}
+
+/// Compare `sprite` with the `--baseline` sprite at `path`.
+pub(crate) fn baseline(
+    path: &std::path::Path,
+    sprite: &str,
+) -> Result<super::summary::BaselineChanges, crate::error::AppError> {
+    let old = std::fs::read_to_string(path).map_err(|e| crate::error::AppError::ReadFile {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+    let Changes {
+        added,
+        removed,
+        changed,
+    } = changes(&old, sprite);
+    let owned = |ids: Vec<&str>| ids.into_iter().map(str::to_string).collect();
+    Ok(super::summary::BaselineChanges {
+        path: path.display().to_string(),
+        added: owned(added),
+        removed: owned(removed),
+        changed: owned(changed),
+    })
+}
+
+/// Unified diff from `old` (`None` for a missing file) to `new`, labelled
+/// with `path`; empty when they are equal.
+///
+/// Sprites are a single line, so lines are also broken before every
+/// `<pattern` and `</defs>` to make each icon its own line.
+pub(crate) fn unified(path: &str, old: Option<&str>, new: &str) -> String {
+    let a = lines(old.unwrap_or_default());
+    let b = lines(new);
+    let ops = edits(&a, &b);
+    if ops.iter().all(|op| *op == Op::Equal) {
+        return String::new();
+    }
+    let mut out = match old {
+        Some(_) => format!("--- {path}\n+++ {path}\n"),
+        None => format!("--- /dev/null\n+++ {path}\n"),
+    };
+    // Line positions in `a` and `b` before each op.
+    let mut at = Vec::with_capacity(ops.len() + 1);
+    let (mut i, mut j) = (0, 0);
+    for op in &ops {
+        at.push((i, j));
+        match op {
+            Op::Equal => (i, j) = (i + 1, j + 1),
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+    at.push((i, j));
+
+    let changes: Vec<usize> = (0..ops.len()).filter(|&n| ops[n] != Op::Equal).collect();
+    let mut n = 0;
+    while n < changes.len() {
+        let mut last = n;
+        while last + 1 < changes.len() && changes[last + 1] - changes[last] <= 2 * CONTEXT {
+            last += 1;
+        }
+        let start = changes[n].saturating_sub(CONTEXT);
+        let end = (changes[last] + CONTEXT + 1).min(ops.len());
+        let ((a0, b0), (a1, b1)) = (at[start], at[end]);
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(a0, a1 - a0),
+            range(b0, b1 - b0)
+        ));
+        for (op, &(i, j)) in ops[start..end].iter().zip(&at[start..end]) {
+            match op {
+                Op::Equal => out.push_str(&format!(" {}\n", a[i])),
+                Op::Delete => out.push_str(&format!("-{}\n", a[i])),
+                Op::Insert => out.push_str(&format!("+{}\n", b[j])),
+            }
+        }
+        n = last + 1;
+    }
+    out
+}
+
+/// Hunk range `start,count` (1-based; the line before an empty range).
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{count}", start + 1),
+    }
+}
+
+/// Lines of `s`, also broken before each `<pattern` and `</defs>`.
+fn lines(s: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    for line in s.strip_suffix('\n').unwrap_or(s).split('\n') {
+        if s.is_empty() {
+            break;
+        }
+        let mut breaks: Vec<usize> = ["<pattern", "</defs>"]
+            .iter()
+            .flat_map(|m| line.match_indices(m).map(|(i, _)| i))
+            .filter(|&i| i > 0)
+            .collect();
+        breaks.sort_unstable();
+        let mut from = 0;
+        for at in breaks {
+            out.push(&line[from..at]);
+            from = at;
+        }
+        out.push(&line[from..]);
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Edit script turning `a` into `b`: the common prefix and suffix, and the
+/// shortest script between them (Myers' algorithm), or a plain replacement
+/// past `MAX_EDITS`.
+fn edits(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let middle = myers(a_mid, b_mid).unwrap_or_else(|| {
+        let mut ops = vec![Op::Delete; a_mid.len()];
+        ops.extend(vec![Op::Insert; b_mid.len()]);
+        ops
+    });
+    let mut ops = vec![Op::Equal; prefix];
+    ops.extend(middle);
+    ops.extend(vec![Op::Equal; suffix]);
+    ops
+}
+
+/// Shortest edit script between `a` and `b`, or `None` past `MAX_EDITS`.
+fn myers(a: &[&str], b: &[&str]) -> Option<Vec<Op>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    if max == 0 {
+        return Some(Vec::new());
+    }
+    let off = max as isize;
+    // Furthest x reached on each diagonal k = x - y, indexed by k + off.
+    let mut v = vec![0isize; 2 * max + 2];
+    // Snapshot of diagonals -d..=d after each step d, for backtracking.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    for d in 0..=max.min(MAX_EDITS) as isize {
+        for k in (-d..=d).step_by(2) {
+            let down = k == -d || (k != d && v[(k - 1 + off) as usize] < v[(k + 1 + off) as usize]);
+            let mut x = if down {
+                v[(k + 1 + off) as usize]
+            } else {
+                v[(k - 1 + off) as usize] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(k + off) as usize] = x;
+            if x >= n && y >= m {
+                trace.push(v[(off - d) as usize..=(off + d) as usize].to_vec());
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+        trace.push(v[(off - d) as usize..=(off + d) as usize].to_vec());
+    }
+    None
+}
+
+/// Walk the Myers `trace` back from `(n, m)` into an edit script.
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Op> {
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..trace.len() as isize).rev() {
+        let prev = &trace[d as usize - 1];
+        let get = |k: isize| prev[(k + d - 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = get(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            ops.push(Op::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        ops.push(if x == prev_x { Op::Insert } else { Op::Delete });
+        (x, y) = (prev_x, prev_y);
+    }
+    ops.extend(std::iter::repeat_n(Op::Equal, x as usize));
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sprite(patterns: &[&str]) -> String {
+        format!(
+            "{}{}{}",
+            crate::svg::transform::sprite_open(&[]),
+            patterns.concat(),
+            crate::svg::transform::SPRITE_CLOSE
+        )
+    }
+
+    #[test]
+    fn reports_added_removed_and_changed_icons() {
+        let old = sprite(&[
+            "<pattern id=\"a\"><g/></pattern>",
+            "<pattern id=\"b\"><g/></pattern>",
+        ]);
+        let new = sprite(&[
+            "<pattern id=\"a\"><path/></pattern>",
+            "<pattern id=\"c\"><g/></pattern>",
+        ]);
+        assert_eq!(summarize(&old, &new), "added: c; removed: b; changed: a");
+    }
+
+    #[test]
+    fn falls_back_when_patterns_match() {
+        let old = sprite(&[
+            "<pattern id=\"a\"><g/></pattern>",
+            "<pattern id=\"b\"><g/></pattern>",
+        ]);
+        let new = sprite(&[
+            "<pattern id=\"b\"><g/></pattern>",
+            "<pattern id=\"a\"><g/></pattern>",
+        ]);
+        assert_eq!(
+            summarize(&old, &new),
+            "content differs outside the icon patterns"
+        );
+    }
+
+    #[test]
+    fn unified_diff_puts_each_pattern_on_its_own_line() {
+        let old = sprite(&[
+            "<pattern id=\"a\"><g/></pattern>",
+            "<pattern id=\"b\"><g/></pattern>",
+        ]);
+        let new = sprite(&[
+            "<pattern id=\"a\"><path/></pattern>",
+            "<pattern id=\"b\"><g/></pattern>",
+            "<pattern id=\"c\"><g/></pattern>",
+        ]);
+        assert_eq!(
+            unified("sprite.svg", Some(&old), &new),
+            "--- sprite.svg\n+++ sprite.svg\n@@ -1,4 +1,5 @@\n \
+             <svg xmlns=\"http://www.w3.org/2000/svg\"><defs>\n\
+             -<pattern id=\"a\"><g/></pattern>\n\
+             +<pattern id=\"a\"><path/></pattern>\n \
+             <pattern id=\"b\"><g/></pattern>\n\
+             +<pattern id=\"c\"><g/></pattern>\n \
+             </defs></svg>\n"
+        );
+        assert_eq!(unified("sprite.svg", Some(&old), &old), "");
+    }
+
+    #[test]
+    fn unified_diff_splits_distant_changes_into_hunks() {
+        let old: String = (0..20).map(|n| format!("{n}\n")).collect();
+        let new: String = (0..20)
+            .filter(|&n| n != 17)
+            .map(|n| {
+                if n == 2 {
+                    "two\n".into()
+                } else {
+                    format!("{n}\n")
+                }
+            })
+            .collect();
+        let diff = unified("list.txt", Some(&old), &new);
+        let hunks: Vec<&str> = diff.lines().filter(|l| l.starts_with("@@")).collect();
+        assert_eq!(hunks, ["@@ -1,6 +1,6 @@", "@@ -15,6 +15,5 @@"]);
+
+        let created = unified("new.txt", None, "a\nb\n");
+        assert_eq!(
+            created,
+            "--- /dev/null\n+++ new.txt\n@@ -0,0 +1,2 @@\n+a\n+b\n"
+        );
+    }
+
+    #[test]
+    fn edit_scripts_are_shortest_and_rebuild_the_new_lines() {
+        let cases: [(&[&str], &[&str], usize); 4] = [
+            (
+                &["a", "b", "c", "a", "b", "b", "a"],
+                &["c", "b", "a", "b", "a", "c"],
+                5,
+            ),
+            (&[], &["x"], 1),
+            (&["x", "y"], &[], 2),
+            (&["a", "b"], &["b", "a"], 2),
+        ];
+        for (a, b, distance) in cases {
+            let ops = edits(a, b);
+            assert_eq!(ops.iter().filter(|op| **op != Op::Equal).count(), distance);
+            let (mut i, mut j, mut rebuilt) = (0, 0, Vec::new());
+            for op in ops {
+                match op {
+                    Op::Equal => {
+                        assert_eq!(a[i], b[j]);
+                        rebuilt.push(a[i]);
+                        (i, j) = (i + 1, j + 1);
+                    }
+                    Op::Delete => i += 1,
+                    Op::Insert => {
+                        rebuilt.push(b[j]);
+                        j += 1;
+                    }
+                }
+            }
+            assert_eq!(rebuilt, b);
+        }
+    }
+}
@@ -0,0 +1,1253 @@
+//! Additional build artifacts rendered from the same parse pass as the sprite.
+
+use super::CacheEntry;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+mod esm;
+mod react_native;
+mod search_index;
+
+pub(crate) use search_index::load_synonyms;
+
+/// Kind of artifact produced by `--emit kind=path`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EmitKind {
+    /// A copy of the sprite sheet
+    Sprite,
+    /// JSON manifest listing every icon with its dimensions and source
+    Manifest,
+    /// Standalone HTML page previewing every icon
+    Preview,
+    /// TypeScript declarations of the icon names
+    Ts,
+    /// Bundler manifest mapping icon names to a content-hashed sprite URL
+    AssetsManifest,
+    /// Tailwind plugin data: icon name to mask-image data URI and dimensions
+    Tailwind,
+    /// Storybook stories (TypeScript CSF, or MDX for `.mdx`) showing every icon
+    Storybook,
+    /// Iconify JSON collection for Iconify-based tooling
+    Iconify,
+    /// `react-native-svg` components, one per icon
+    ReactNative,
+    /// Search index documents (names, tags, synonyms) for icon pickers
+    SearchIndex,
+    /// Markdown table of every icon with a preview image and its size
+    Markdown,
+    /// ES module exporting each icon's standalone markup as a string
+    Esm,
+    /// JSON object mapping icon names to standalone SVG documents
+    IconsJson,
+    /// HTML fragment inlining the sprite in a hidden `<svg>`
+    InlineHtml,
+}
+
+impl EmitKind {
+    /// All kinds, in the order they are documented.
+    pub const ALL: [EmitKind; 14] = [
+        EmitKind::Sprite,
+        EmitKind::Manifest,
+        EmitKind::Preview,
+        EmitKind::Ts,
+        EmitKind::AssetsManifest,
+        EmitKind::Tailwind,
+        EmitKind::Storybook,
+        EmitKind::Iconify,
+        EmitKind::ReactNative,
+        EmitKind::SearchIndex,
+        EmitKind::Markdown,
+        EmitKind::Esm,
+        EmitKind::IconsJson,
+        EmitKind::InlineHtml,
+    ];
+
+    /// Name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            EmitKind::Sprite => "sprite",
+            EmitKind::Manifest => "manifest",
+            EmitKind::Preview => "preview",
+            EmitKind::Ts => "ts",
+            EmitKind::AssetsManifest => "assets-manifest",
+            EmitKind::Tailwind => "tailwind",
+            EmitKind::Storybook => "storybook",
+            EmitKind::Iconify => "iconify",
+            EmitKind::ReactNative => "react-native",
+            EmitKind::SearchIndex => "search-index",
+            EmitKind::Markdown => "markdown",
+            EmitKind::Esm => "esm",
+            EmitKind::IconsJson => "icons-json",
+            EmitKind::InlineHtml => "inline-html",
+        }
+    }
+}
+
+impl fmt::Display for EmitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One artifact to write: what to render and where.
+///
+/// Example:
+/// ```
+/// use svg_sheet::svg::emit::{EmitKind, EmitTarget};
+/// let t: EmitTarget = "manifest=dist/icons.json".parse().unwrap();
+/// assert_eq!(t.kind, EmitKind::Manifest);
+/// assert_eq!(t.path, "dist/icons.json");
+/// assert!("pdf=x.pdf".parse::<EmitTarget>().is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmitTarget {
+    pub kind: EmitKind,
+    /// Output path, or `-` for stdout
+    pub path: String,
+}
+
+impl FromStr for EmitTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, path) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected KIND=PATH, got '{s}'"))?;
+        let kind = EmitKind::ALL
+            .into_iter()
+            .find(|k| k.name() == kind.trim())
+            .ok_or_else(|| {
+                let names: Vec<_> = EmitKind::ALL.iter().map(|k| k.name()).collect();
+                format!(
+                    "unknown emit kind '{kind}' (expected one of: {})",
+                    names.join(", ")
+                )
+            })?;
+        let path = path.trim();
+        if path.is_empty() {
+            return Err(format!("missing path for '{kind}'"));
+        }
+        Ok(EmitTarget {
+            kind,
+            path: path.to_string(),
+        })
+    }
+}
+
+/// Everything a build produced, shared by all emitters.
+pub(crate) struct EmitContext<'a> {
+    /// Emitted icons, in output order
+    pub entries: &'a [&'a CacheEntry],
+    /// The rendered sprite sheet
+    pub sprite: &'a str,
+    /// Where the sprite was written
+    pub sprite_path: &'a str,
+    /// Deduplicated icon name -> name of the identical icon it aliases
+    pub aliases: std::collections::HashMap<String, String>,
+    /// Config `[aliases]` names, with the icon each points to
+    pub config_aliases: Vec<(&'a str, &'a CacheEntry)>,
+    /// Deprecated icon name -> deprecation message (possibly empty)
+    pub deprecated: std::collections::HashMap<&'a str, &'a str>,
+    /// Input directory the entries' paths start with, for categories; empty
+    /// when there is none
+    pub root: &'a str,
+    /// Config `[tags]`: icon name -> its tags
+    pub tags: &'a [(String, Vec<String>)],
+    /// Synonym groups from `--synonyms`
+    pub synonyms: &'a [Vec<String>],
+    /// Sidecar files of the icons, by name
+    pub sidecars: &'a super::sidecar::Sidecars,
+}
+
+/// Render the artifact `kind` from `ctx`. `target_path` selects between JSON
+/// and JavaScript module output (or TypeScript and JavaScript) where a kind
+/// supports both.
+pub(crate) fn render(kind: EmitKind, ctx: &EmitContext, target_path: &str) -> String {
+    let entries = ctx.entries;
+    match kind {
+        EmitKind::Sprite => ctx.sprite.to_string(),
+        EmitKind::Manifest => manifest(ctx),
+        EmitKind::Preview => preview(ctx),
+        EmitKind::Ts => typescript(ctx),
+        EmitKind::AssetsManifest => {
+            assets_manifest(entries, &hashed_name(ctx.sprite_path, ctx.sprite))
+        }
+        EmitKind::Tailwind => tailwind(entries, target_path),
+        EmitKind::Storybook => storybook(entries, ctx.sprite, target_path),
+        EmitKind::Iconify => iconify(ctx, target_path),
+        EmitKind::ReactNative => react_native::render(ctx, target_path),
+        EmitKind::SearchIndex => search_index::render(ctx),
+        EmitKind::Markdown => markdown(entries, target_path),
+        EmitKind::Esm => esm::render(ctx, target_path),
+        EmitKind::IconsJson => icons_json(ctx),
+        EmitKind::InlineHtml => inline_html(ctx.sprite),
+    }
+}
+
+/// File name of the content-hashed sprite copy referenced by the assets
+/// manifest, e.g. `sprite.3f2a9c1d.svg` for `dist/sprite.svg`.
+///
+/// Example:
+/// ```
+/// let name = svg_sheet::svg::emit::hashed_name("dist/sprite.svg", "<svg/>");
+/// assert!(name.starts_with("sprite.") && name.ends_with(".svg") && name.len() == 19);
+/// ```
+pub fn hashed_name(sprite_path: &str, sprite: &str) -> String {
+    let path = std::path::Path::new(sprite_path);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|s| *s != super::STDIO)
+        .unwrap_or("sprite");
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("svg");
+    let hash = super::source::sha256_hex(sprite.as_bytes());
+    format!("{stem}.{}.{ext}", &hash[..8])
+}
+
+/// Serialized form of the JSON manifest.
+#[derive(Serialize)]
+struct Manifest<'a> {
+    sprite: &'a str,
+    icons: Vec<ManifestIcon<'a>>,
+}
+
+/// One icon entry in the JSON manifest.
+#[derive(Serialize)]
+struct ManifestIcon<'a> {
+    id: &'a str,
+    source: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<&'a str>,
+    #[serde(rename = "viewBox", skip_serializing_if = "Option::is_none")]
+    view_box: Option<&'a str>,
+    #[serde(rename = "aliasOf", skip_serializing_if = "Option::is_none")]
+    alias_of: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    variables: Vec<ManifestVariable<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deprecated: Option<Deprecation<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    tags: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+}
+
+/// How the manifest flags a deprecated icon: its message, or `true` when
+/// there is none.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Deprecation<'a> {
+    Flag(bool),
+    Message(&'a str),
+}
+
+impl<'a> Deprecation<'a> {
+    fn of(ctx: &EmitContext<'a>, name: &str) -> Option<Self> {
+        ctx.deprecated.get(name).map(|m| match *m {
+            "" => Deprecation::Flag(true),
+            m => Deprecation::Message(m),
+        })
+    }
+}
+
+/// A CSS custom property an icon reads under `--themable`, with the color
+/// used when it is unset.
+#[derive(Serialize)]
+struct ManifestVariable<'a> {
+    name: &'a str,
+    default: &'a str,
+}
+
+/// An icon's category: the subdirectories below the input directory holding
+/// its file, joined with `/`. `None` for icons directly inside it and for
+/// icons from other sources (collections, packages).
+fn category(ctx: &EmitContext, entry: &CacheEntry) -> Option<String> {
+    if ctx.root.is_empty() {
+        return None;
+    }
+    let relative = std::path::Path::new(&entry.path_str)
+        .strip_prefix(ctx.root)
+        .ok()?;
+    let dirs: Vec<String> = relative
+        .parent()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    (!dirs.is_empty()).then(|| dirs.join("/"))
+}
+
+/// The tags of the icon `name`, from the config's `[tags]` and its sidecar.
+fn tags<'a>(ctx: &EmitContext<'a>, name: &str) -> &'a [String] {
+    ctx.tags
+        .iter()
+        .find(|(icon, _)| icon == name)
+        .map_or(&[], |(_, tags)| tags.as_slice())
+}
+
+/// The sidecar title of the icon `name`.
+fn title<'a>(ctx: &EmitContext<'a>, name: &str) -> Option<&'a str> {
+    ctx.sidecars.get(name)?.title.as_deref()
+}
+
+/// The sidecar description of the icon `name`.
+fn description<'a>(ctx: &EmitContext<'a>, name: &str) -> Option<&'a str> {
+    ctx.sidecars.get(name)?.description.as_deref()
+}
+
+/// Look up a root attribute of an entry.
+fn attr<'a>(entry: &'a CacheEntry, name: &str) -> Option<&'a str> {
+    entry
+        .out_attrs
+        .iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.as_str())
+}
+
+fn manifest(ctx: &EmitContext) -> String {
+    let mut manifest = Manifest {
+        sprite: ctx.sprite_path,
+        icons: ctx
+            .entries
+            .iter()
+            .map(|e| ManifestIcon {
+                id: &e.name,
+                source: &e.path_str,
+                width: attr(e, "width"),
+                height: attr(e, "height"),
+                view_box: attr(e, "viewBox"),
+                alias_of: ctx.aliases.get(&e.name).map(String::as_str),
+                variables: e
+                    .theme_vars
+                    .iter()
+                    .map(|(name, default)| ManifestVariable { name, default })
+                    .collect(),
+                deprecated: Deprecation::of(ctx, &e.name),
+                category: category(ctx, e),
+                tags: tags(ctx, &e.name),
+                title: title(ctx, &e.name),
+                description: description(ctx, &e.name),
+            })
+            .collect(),
+    };
+    manifest
+        .icons
+        .extend(ctx.config_aliases.iter().map(|(alias, e)| ManifestIcon {
+            id: alias,
+            source: &e.path_str,
+            width: attr(e, "width"),
+            height: attr(e, "height"),
+            view_box: attr(e, "viewBox"),
+            alias_of: Some(&e.name),
+            variables: Vec::new(),
+            deprecated: Deprecation::of(ctx, &e.name),
+            category: category(ctx, e),
+            tags: tags(ctx, &e.name),
+            title: title(ctx, &e.name),
+            description: description(ctx, &e.name),
+        }));
+    let mut out = serde_json::to_string_pretty(&manifest).expect("manifest serializes");
+    out.push('\n');
+    out
+}
+
+/// Flat `name -> url` map in the style of webpack-assets-manifest.
+fn assets_manifest(entries: &[&CacheEntry], hashed: &str) -> String {
+    let map: serde_json::Map<String, serde_json::Value> = entries
+        .iter()
+        .map(|e| (e.name.clone(), format!("{hashed}#{}", e.name).into()))
+        .collect();
+    let mut out = serde_json::to_string_pretty(&map).expect("manifest serializes");
+    out.push('\n');
+    out
+}
+
+/// Icon data for a Tailwind plugin, as JSON (`.json`) or an ES module.
+///
+/// Each icon maps to `{ maskImage, width, height, viewBox }` where
+/// `maskImage` is a ready-to-use `url("data:image/svg+xml,...")` value.
+fn tailwind(entries: &[&CacheEntry], target_path: &str) -> String {
+    let map: serde_json::Map<String, serde_json::Value> = entries
+        .iter()
+        .map(|e| {
+            let mut icon = serde_json::Map::new();
+            icon.insert(
+                "maskImage".into(),
+                format!("url(\"{}\")", data_uri(&standalone_svg(e))).into(),
+            );
+            for key in ["width", "height"] {
+                if let Some(n) = attr(e, key).and_then(|v| v.parse::<serde_json::Number>().ok()) {
+                    icon.insert(key.into(), n.into());
+                }
+            }
+            if let Some(vb) = attr(e, "viewBox") {
+                icon.insert("viewBox".into(), vb.into());
+            }
+            (e.name.clone(), icon.into())
+        })
+        .collect();
+    let json = serde_json::to_string_pretty(&map).expect("tailwind data serializes");
+    if target_path.ends_with(".json") {
+        format!("{json}\n")
+    } else if target_path.ends_with(".cjs") {
+        format!("// Generated by svg_sheet. Do not edit.\nmodule.exports = {json};\n")
+    } else {
+        format!("// Generated by svg_sheet. Do not edit.\nexport default {json};\n")
+    }
+}
+
+/// Reconstruct a standalone `<svg>` document for one icon.
+pub(crate) fn standalone_svg(entry: &CacheEntry) -> String {
+    let mut out = String::from("<svg xmlns=\"http://www.w3.org/2000/svg\"");
+    for (k, v) in &entry.out_attrs {
+        if k == "xmlns" || k == "data-id" {
+            continue;
+        }
+        out.push_str(&format!(" {k}=\"{v}\""));
+    }
+    out.push('>');
+    if !entry.shared_defs.is_empty() {
+        out.push_str("<defs>");
+        for (_, markup) in &entry.shared_defs {
+            out.push_str(markup);
+        }
+        out.push_str("</defs>");
+    }
+    out.push_str(&entry.children);
+    out.push_str("</svg>");
+    out
+}
+
+/// Percent-encode an SVG document into a `data:` URI usable inside CSS `url("")`.
+fn data_uri(svg: &str) -> String {
+    let mut out = String::from("data:image/svg+xml,");
+    for b in svg.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => out.push(b as char),
+            b'-' | b'_' | b'.' | b'~' | b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+'
+            | b',' | b';' | b'=' | b':' | b'@' | b'/' | b'?' | b' ' => out.push(b as char),
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+/// Serialized form of an Iconify JSON collection.
+#[derive(Serialize)]
+struct IconifyCollection<'a> {
+    prefix: String,
+    icons: BTreeMap<&'a str, IconifyIcon>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    aliases: BTreeMap<&'a str, IconifyAlias<'a>>,
+    /// Dimensions shared by every icon, omitted from the icons themselves
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<serde_json::Number>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<serde_json::Number>,
+}
+
+/// One icon of an Iconify collection; `left`/`top` are omitted when zero.
+#[derive(Serialize, Default)]
+struct IconifyIcon {
+    body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    left: Option<serde_json::Number>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top: Option<serde_json::Number>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<serde_json::Number>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<serde_json::Number>,
+}
+
+#[derive(Serialize)]
+struct IconifyAlias<'a> {
+    parent: &'a str,
+}
+
+/// Root attributes carried by the view box or meaningless inside a body;
+/// the others (fill, stroke, ...) wrap the body in a `<g>`.
+const ICONIFY_DROPPED_ATTRS: [&str; 9] = [
+    "width",
+    "height",
+    "viewBox",
+    "preserveAspectRatio",
+    "data-id",
+    "id",
+    "version",
+    "x",
+    "y",
+];
+
+/// Convert an entry into an Iconify icon: its shared definitions and content
+/// as the body, and its view box (or width/height) as the dimensions.
+fn iconify_icon(entry: &CacheEntry) -> IconifyIcon {
+    let number = |v: &str| v.parse::<serde_json::Number>().ok();
+    let mut icon = IconifyIcon::default();
+    let presentation: Vec<&(String, String)> = entry
+        .out_attrs
+        .iter()
+        .filter(|(k, _)| !ICONIFY_DROPPED_ATTRS.contains(&k.as_str()) && !k.starts_with("xmlns"))
+        .collect();
+    let wrapped = !presentation.is_empty();
+    if wrapped {
+        icon.body.push_str("<g");
+        for (k, v) in presentation {
+            icon.body.push_str(&format!(" {k}=\"{v}\""));
+        }
+        icon.body.push('>');
+    }
+    if !entry.shared_defs.is_empty() {
+        icon.body.push_str("<defs>");
+        for (_, markup) in &entry.shared_defs {
+            icon.body.push_str(markup);
+        }
+        icon.body.push_str("</defs>");
+    }
+    icon.body.push_str(&entry.children);
+    if wrapped {
+        icon.body.push_str("</g>");
+    }
+    let view_box: Vec<&str> = attr(entry, "viewBox")
+        .map(|vb| vb.split_whitespace().collect())
+        .unwrap_or_default();
+    if let [left, top, width, height] = view_box[..] {
+        icon.left = number(left).filter(|n| n.as_f64() != Some(0.0));
+        icon.top = number(top).filter(|n| n.as_f64() != Some(0.0));
+        icon.width = number(width);
+        icon.height = number(height);
+    } else {
+        icon.width = attr(entry, "width").and_then(number);
+        icon.height = attr(entry, "height").and_then(number);
+    }
+    icon
+}
+
+/// The icon set as an Iconify JSON collection whose prefix is the target's
+/// file stem. `--dedupe` copies become aliases of the icon they duplicate.
+fn iconify(ctx: &EmitContext, target_path: &str) -> String {
+    let stem = std::path::Path::new(target_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|s| *s != super::STDIO)
+        .unwrap_or("icons");
+    let prefix = super::naming::apply_case(stem, super::naming::IdCase::Kebab);
+    let mut collection = IconifyCollection {
+        prefix: if prefix.is_empty() {
+            "icons".to_string()
+        } else {
+            prefix
+        },
+        icons: BTreeMap::new(),
+        aliases: BTreeMap::new(),
+        width: None,
+        height: None,
+    };
+    for e in ctx.entries {
+        match ctx.aliases.get(&e.name) {
+            Some(parent) => {
+                collection.aliases.insert(&e.name, IconifyAlias { parent });
+            }
+            None => {
+                collection.icons.insert(&e.name, iconify_icon(e));
+            }
+        }
+    }
+    for (alias, e) in &ctx.config_aliases {
+        collection
+            .aliases
+            .insert(alias, IconifyAlias { parent: &e.name });
+    }
+    let shared = |get: fn(&IconifyIcon) -> &Option<serde_json::Number>| {
+        let mut values = collection.icons.values().map(get);
+        let first = values.next()?.clone()?;
+        values.all(|v| v.as_ref() == Some(&first)).then_some(first)
+    };
+    let (width, height) = (shared(|i| &i.width), shared(|i| &i.height));
+    for icon in collection.icons.values_mut() {
+        if width.is_some() {
+            icon.width = None;
+        }
+        if height.is_some() {
+            icon.height = None;
+        }
+    }
+    collection.width = width;
+    collection.height = height;
+    let mut out = serde_json::to_string_pretty(&collection).expect("collection serializes");
+    out.push('\n');
+    out
+}
+
+fn preview(ctx: &EmitContext) -> String {
+    let mut out = String::from(
+        "<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Icon preview</title>\n<style>\nbody{font-family:system-ui,sans-serif;margin:2rem}\ninput{margin-bottom:1rem;padding:.25rem .5rem}\n.grid{display:grid;grid-template-columns:repeat(auto-fill,minmax(8rem,1fr));gap:1rem}\nfigure{margin:0;padding:1rem;border:1px solid #ddd;border-radius:.5rem;text-align:center}\nfigcaption{margin-top:.5rem;font-size:.8rem;word-break:break-all}\nfigcaption small{display:block;color:#666}\n.deprecated{border-style:dashed;opacity:.6}\n.deprecated figcaption{text-decoration:line-through}\n</style>\n</head>\n<body>\n<div hidden>",
+    );
+    out.push_str(ctx.sprite);
+    out.push_str("</div>\n<input type=\"search\" placeholder=\"Filter by name, category or tag\" oninput=\"for(const f of document.querySelectorAll('figure'))f.hidden=!f.dataset.search.includes(this.value.toLowerCase())\">\n<div class=\"grid\">\n");
+    for e in ctx.entries {
+        let w = attr(e, "width").unwrap_or("24");
+        let h = attr(e, "height").unwrap_or("24");
+        let id = escape_html(&e.name);
+        let flag = match ctx.deprecated.get(e.name.as_str()) {
+            Some(message) => {
+                let title = escape_html(&super::deprecation::describe(&e.name, message));
+                format!(" class=\"deprecated\" title=\"{title}\"")
+            }
+            None => match description(ctx, &e.name) {
+                Some(description) => format!(" title=\"{}\"", escape_html(description)),
+                None => String::new(),
+            },
+        };
+        let details: Vec<String> = title(ctx, &e.name)
+            .map(str::to_string)
+            .into_iter()
+            .chain(category(ctx, e))
+            .chain(tags(ctx, &e.name).iter().map(|t| format!("#{t}")))
+            .collect();
+        let search = std::iter::once(e.name.as_str())
+            .chain(details.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        let search = escape_html(&search);
+        let details = match details.is_empty() {
+            true => String::new(),
+            false => format!("<small>{}</small>", escape_html(&details.join(" "))),
+        };
+        out.push_str(&format!(
+            "<figure{flag} data-search=\"{search}\"><svg width=\"{w}\" height=\"{h}\"><rect width=\"100%\" height=\"100%\" fill=\"url(#{id})\"/></svg><figcaption>{id}{details}</figcaption></figure>\n"
+        ));
+    }
+    out.push_str("</div>\n</body>\n</html>\n");
+    out
+}
+
+/// Storybook stories rendering every icon from the inlined sprite: an MDX
+/// page for `.mdx` paths, otherwise a TypeScript CSF module with a `Gallery`
+/// story and a `Playground` story whose `name` control lists the icons.
+fn storybook(entries: &[&CacheEntry], sprite: &str, target_path: &str) -> String {
+    let js = |s: &str| serde_json::to_string(s).expect("string serializes");
+    let icons: Vec<String> = entries
+        .iter()
+        .map(|e| {
+            format!(
+                "  {{ name: {}, width: {}, height: {} }},\n",
+                js(&e.name),
+                js(attr(e, "width").unwrap_or("24")),
+                js(attr(e, "height").unwrap_or("24"))
+            )
+        })
+        .collect();
+    let mut out = String::new();
+    if target_path.ends_with(".mdx") {
+        out.push_str("{/* Generated by svg_sheet. Do not edit. */}\n\n");
+        out.push_str("import { Meta } from \"@storybook/blocks\";\n\n");
+        out.push_str(&format!("export const SPRITE = {};\n\n", js(sprite)));
+        out.push_str("export const ICONS = [\n");
+        out.extend(icons);
+        out.push_str("];\n\n<Meta title=\"Icons\" />\n\n# Icons\n\n");
+        out.push_str(STORYBOOK_GALLERY_MDX);
+        return out;
+    }
+    out.push_str("// Generated by svg_sheet. Do not edit.\n");
+    out.push_str("import type { Meta, StoryObj } from \"@storybook/react\";\n\n");
+    out.push_str(&format!("const SPRITE = {};\n\n", js(sprite)));
+    out.push_str("const ICONS = [\n");
+    out.extend(icons);
+    out.push_str("] as const;\n");
+    out.push_str(STORYBOOK_CSF);
+    out
+}
+
+/// Component and stories following the sprite and icon list in CSF output.
+const STORYBOOK_CSF: &str = r#"
+function Icon({ name, width, height }: { name: string; width: string; height: string }) {
+  return (
+    <svg width={width} height={height} role="img" aria-label={name}>
+      <rect width="100%" height="100%" fill={`url(#${name})`} />
+    </svg>
+  );
+}
+
+const meta: Meta<typeof Icon> = {
+  title: "Icons",
+  component: Icon,
+  decorators: [
+    (Story) => (
+      <>
+        <div hidden dangerouslySetInnerHTML={{ __html: SPRITE }} />
+        <Story />
+      </>
+    ),
+  ],
+  argTypes: { name: { control: "select", options: ICONS.map((i) => i.name) } },
+};
+export default meta;
+
+type Story = StoryObj<typeof Icon>;
+
+export const Gallery: Story = {
+  render: () => (
+    <div style={{ display: "grid", gridTemplateColumns: "repeat(auto-fill, minmax(8rem, 1fr))", gap: "1rem" }}>
+      {ICONS.map((icon) => (
+        <figure key={icon.name} style={{ margin: 0, textAlign: "center" }}>
+          <Icon {...icon} />
+          <figcaption style={{ fontSize: ".8rem", wordBreak: "break-all" }}>{icon.name}</figcaption>
+        </figure>
+      ))}
+    </div>
+  ),
+};
+
+export const Playground: Story = { args: { ...ICONS[0] } };
+"#;
+
+/// Gallery markup of the MDX story page.
+const STORYBOOK_GALLERY_MDX: &str = r#"<div hidden dangerouslySetInnerHTML={{ __html: SPRITE }} />
+
+<div style={{ display: "grid", gridTemplateColumns: "repeat(auto-fill, minmax(8rem, 1fr))", gap: "1rem" }}>
+  {ICONS.map((icon) => (
+    <figure key={icon.name} style={{ margin: 0, textAlign: "center" }}>
+      <svg width={icon.width} height={icon.height} role="img" aria-label={icon.name}>
+        <rect width="100%" height="100%" fill={`url(#${icon.name})`} />
+      </svg>
+      <figcaption style={{ fontSize: ".8rem", wordBreak: "break-all" }}>{icon.name}</figcaption>
+    </figure>
+  ))}
+</div>
+"#;
+
+/// A JSON object mapping each icon name, config aliases included, to its
+/// standalone `<svg>` document.
+fn icons_json(ctx: &EmitContext) -> String {
+    let map: BTreeMap<&str, String> = ctx
+        .entries
+        .iter()
+        .map(|e| (e.name.as_str(), standalone_svg(e)))
+        .chain(
+            ctx.config_aliases
+                .iter()
+                .map(|(alias, e)| (*alias, standalone_svg(e))),
+        )
+        .collect();
+    let mut out = serde_json::to_string_pretty(&map).expect("icons serialize");
+    out.push('\n');
+    out
+}
+
+/// Attributes hiding an inline sprite from layout and assistive technology.
+const INLINE_ATTRS: [(&str, &str); 3] = [
+    ("aria-hidden", "true"),
+    ("focusable", "false"),
+    ("style", "display:none"),
+];
+
+/// The sprite as an HTML fragment for the top of `<body>`: its root `<svg>`
+/// hidden by `INLINE_ATTRS`, except those set by `--root-attr`. A templated
+/// sprite without the built-in root is wrapped in a hidden `<svg>` instead.
+fn inline_html(sprite: &str) -> String {
+    let (head, attrs, body) = match super::transform::split_root(sprite) {
+        Some((head, attrs, rest)) => (head, attrs, format!("<defs>{rest}")),
+        None => ("", "", format!("{sprite}</svg>")),
+    };
+    let mut out = format!("{head}<svg xmlns=\"http://www.w3.org/2000/svg\"{attrs}");
+    for (k, v) in INLINE_ATTRS {
+        if !attrs.contains(&format!(" {k}=")) {
+            out.push_str(&format!(" {k}=\"{v}\""));
+        }
+    }
+    out.push('>');
+    out.push_str(&body);
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+fn typescript(ctx: &EmitContext) -> String {
+    let names: Vec<String> = ctx
+        .entries
+        .iter()
+        .map(|e| e.name.as_str())
+        .chain(ctx.config_aliases.iter().map(|(alias, _)| *alias))
+        .map(|name| serde_json::to_string(name).expect("string serializes"))
+        .collect();
+    let mut out = String::from("// Generated by svg_sheet. Do not edit.\n\n");
+    out.push_str("export const ICON_NAMES = [\n");
+    for n in &names {
+        out.push_str(&format!("  {n},\n"));
+    }
+    out.push_str("] as const;\n\nexport type IconName = (typeof ICON_NAMES)[number];\n");
+    out
+}
+
+/// Escape text for inclusion in HTML content or attributes.
+/// A Markdown table of the icons: a preview `<img>` of each source file,
+/// linked relative to `target_path`, then the name and size. Icons without a
+/// file of their own (split sprites, collections) get no preview.
+fn markdown(entries: &[&CacheEntry], target_path: &str) -> String {
+    let base = std::path::Path::new(target_path)
+        .parent()
+        .unwrap_or(std::path::Path::new(""));
+    let mut out = format!(
+        "<!-- Generated by svg_sheet. Do not edit. -->\n\n# Icons\n\n{} icon(s).\n\n| Preview | Name | Size |\n| :-: | --- | --- |\n",
+        entries.len()
+    );
+    for e in entries {
+        let preview = match std::path::Path::new(&e.path_str) {
+            source if source.is_file() => format!(
+                "<img src=\"{}\" width=\"24\" height=\"24\" alt=\"{}\">",
+                escape_html(&relative_path(base, source)),
+                escape_html(&e.name)
+            ),
+            _ => String::new(),
+        };
+        let size = match (attr(e, "width"), attr(e, "height")) {
+            (Some(w), Some(h)) => format!("{w}×{h}"),
+            _ => attr(e, "viewBox")
+                .and_then(|v| {
+                    let parts: Vec<&str> = v
+                        .split(|c: char| c == ',' || c.is_ascii_whitespace())
+                        .filter(|p| !p.is_empty())
+                        .collect();
+                    Some(format!("{}×{}", parts.get(2)?, parts.get(3)?))
+                })
+                .unwrap_or_default(),
+        };
+        out.push_str(&format!(
+            "| {preview} | `{}` | {size} |\n",
+            e.name.replace('|', "\\|")
+        ));
+    }
+    out
+}
+
+/// `target` as a `/`-separated path relative to the directory `base`; both
+/// are resolved against the working directory first.
+fn relative_path(base: &std::path::Path, target: &std::path::Path) -> String {
+    use std::path::Component;
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let absolute = |p: &std::path::Path| -> Vec<String> {
+        cwd.join(p)
+            .components()
+            .filter(|c| !matches!(c, Component::CurDir))
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect()
+    };
+    let (base, target) = (absolute(base), absolute(target));
+    let common = base.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    let mut parts = vec![".."; base.len() - common];
+    parts.extend(target[common..].iter().map(String::as_str));
+    parts.join("/")
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn render_with(
+        kind: EmitKind,
+        entries: &[&CacheEntry],
+        sprite: &str,
+        sprite_path: &str,
+        target_path: &str,
+    ) -> String {
+        let ctx = EmitContext {
+            entries,
+            sprite,
+            sprite_path,
+            aliases: Default::default(),
+            config_aliases: Vec::new(),
+            deprecated: Default::default(),
+            root: "",
+            tags: &[],
+            synonyms: &[],
+            sidecars: &Default::default(),
+        };
+        render(kind, &ctx, target_path)
+    }
+
+    pub(super) fn entry(name: &str) -> CacheEntry {
+        CacheEntry {
+            mtime: UNIX_EPOCH,
+            len: 0,
+            name: name.into(),
+            out_attrs: vec![
+                ("width".into(), "24".into()),
+                ("viewBox".into(), "0 0 24 24".into()),
+            ],
+            children: "<g/>".into(),
+            child_ids: vec![],
+            path_str: format!("svgs/{name}.svg"),
+            warnings: vec![],
+            hash: String::new(),
+            licenses: vec![],
+            shared_defs: vec![],
+            colors_remapped: 0,
+            attributes_stripped: vec![],
+            theme_vars: vec![],
+            overrides: None,
+        }
+    }
+
+    #[test]
+    fn parses_targets() {
+        let t: EmitTarget = " ts = types/icons.d.ts".parse().unwrap();
+        assert_eq!(t.kind, EmitKind::Ts);
+        assert_eq!(t.path, "types/icons.d.ts");
+        assert!("manifest".parse::<EmitTarget>().is_err());
+        assert!("manifest=".parse::<EmitTarget>().is_err());
+    }
+
+    #[test]
+    fn manifest_lists_icons() {
+        let (a, b) = (entry("a"), entry("b"));
+        let json: serde_json::Value = serde_json::from_str(&render_with(
+            EmitKind::Manifest,
+            &[&a, &b],
+            "",
+            "sprite.svg",
+            "m.json",
+        ))
+        .unwrap();
+        assert_eq!(json["sprite"], "sprite.svg");
+        assert_eq!(json["icons"][1]["id"], "b");
+        assert_eq!(json["icons"][0]["viewBox"], "0 0 24 24");
+        assert!(json["icons"][0].get("height").is_none());
+
+        let ctx = EmitContext {
+            entries: &[&a],
+            sprite: "",
+            sprite_path: "sprite.svg",
+            aliases: Default::default(),
+            config_aliases: vec![("old-a", &a)],
+            deprecated: [("a", "Use b")].into(),
+            root: "",
+            tags: &[],
+            synonyms: &[],
+            sidecars: &Default::default(),
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&render(EmitKind::Manifest, &ctx, "m.json")).unwrap();
+        assert_eq!(json["icons"][1]["id"], "old-a");
+        assert_eq!(json["icons"][1]["aliasOf"], "a");
+        assert_eq!(json["icons"][1]["source"], "svgs/a.svg");
+        assert_eq!(json["icons"][0]["deprecated"], "Use b");
+        let ts = render(EmitKind::Ts, &ctx, "icons.ts");
+        assert!(ts.contains("  \"a\",\n  \"old-a\",\n"));
+    }
+
+    #[test]
+    fn manifest_and_preview_carry_categories_and_tags() {
+        let a = entry("a");
+        let b = CacheEntry {
+            path_str: "svgs/brand/social/b.svg".into(),
+            ..entry("b")
+        };
+        let tags = [("b".to_string(), vec!["share".to_string()])];
+        let ctx = EmitContext {
+            entries: &[&a, &b],
+            sprite: "",
+            sprite_path: "sprite.svg",
+            aliases: Default::default(),
+            config_aliases: vec![("old-b", &b)],
+            deprecated: Default::default(),
+            root: "svgs",
+            tags: &tags,
+            synonyms: &[],
+            sidecars: &Default::default(),
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&render(EmitKind::Manifest, &ctx, "m.json")).unwrap();
+        assert!(json["icons"][0].get("category").is_none());
+        assert!(json["icons"][0].get("tags").is_none());
+        assert_eq!(json["icons"][1]["category"], "brand/social");
+        assert_eq!(json["icons"][1]["tags"], serde_json::json!(["share"]));
+        assert_eq!(json["icons"][2]["category"], "brand/social");
+        let html = render(EmitKind::Preview, &ctx, "p.html");
+        assert!(html.contains("<figure data-search=\"a\">"));
+        assert!(html.contains("<figure data-search=\"b brand/social #share\">"));
+        assert!(html.contains("<figcaption>b<small>brand/social #share</small></figcaption>"));
+    }
+
+    #[test]
+    fn manifest_and_preview_carry_sidecar_metadata() {
+        let a = entry("a");
+        let sidecars = super::super::sidecar::Sidecars::from([(
+            "a".to_string(),
+            super::super::sidecar::Sidecar {
+                title: Some("Home".into()),
+                description: Some("Go to the \"start\" page".into()),
+                ..Default::default()
+            },
+        )]);
+        let ctx = EmitContext {
+            entries: &[&a],
+            sprite: "",
+            sprite_path: "sprite.svg",
+            aliases: Default::default(),
+            config_aliases: vec![],
+            deprecated: Default::default(),
+            root: "",
+            tags: &[],
+            synonyms: &[],
+            sidecars: &sidecars,
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&render(EmitKind::Manifest, &ctx, "m.json")).unwrap();
+        assert_eq!(json["icons"][0]["title"], "Home");
+        assert_eq!(json["icons"][0]["description"], "Go to the \"start\" page");
+        let html = render(EmitKind::Preview, &ctx, "p.html");
+        assert!(html.contains(
+            "<figure title=\"Go to the &quot;start&quot; page\" data-search=\"a home\">"
+        ));
+        assert!(html.contains("<figcaption>a<small>Home</small></figcaption>"));
+    }
+
+    #[test]
+    fn markdown_links_previews_relative_to_the_output() {
+        let dir = std::env::temp_dir().join("svg_emit_markdown");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("svgs")).unwrap();
+        std::fs::write(dir.join("svgs/a.svg"), "<svg/>").unwrap();
+        let a = CacheEntry {
+            path_str: dir.join("svgs/a.svg").display().to_string(),
+            out_attrs: vec![("width".into(), "16".into()), ("height".into(), "8".into())],
+            ..entry("a")
+        };
+        let b = CacheEntry {
+            path_str: "sheet.svg#b|c".into(),
+            ..entry("b|c")
+        };
+        let target = dir.join("docs/ICONS.md").display().to_string();
+        let md = render_with(EmitKind::Markdown, &[&a, &b], "", "sprite.svg", &target);
+        assert!(md.contains("2 icon(s)."));
+        assert!(md.contains(
+            "| <img src=\"../svgs/a.svg\" width=\"24\" height=\"24\" alt=\"a\"> | `a` | 16×8 |\n"
+        ));
+        assert!(md.contains("|  | `b\\|c` | 24×24 |\n"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn icons_json_maps_names_to_standalone_svgs() {
+        let mut a = entry("a");
+        a.shared_defs = vec![("g1".into(), "<linearGradient id=\"g1\"/>".into())];
+        let b = entry("b");
+        let json: serde_json::Value = serde_json::from_str(&render_with(
+            EmitKind::IconsJson,
+            &[&b, &a],
+            "",
+            "sprite.svg",
+            "icons.json",
+        ))
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "a": "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" viewBox=\"0 0 24 24\"><defs><linearGradient id=\"g1\"/></defs><g/></svg>",
+                "b": "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" viewBox=\"0 0 24 24\"><g/></svg>"
+            })
+        );
+    }
+
+    #[test]
+    fn inline_html_hides_the_sprite() {
+        let html = inline_html(
+            "<!-- x --><svg xmlns=\"http://www.w3.org/2000/svg\"><defs><pattern id=\"a\"></pattern></defs></svg>",
+        );
+        assert_eq!(
+            html,
+            "<!-- x --><svg xmlns=\"http://www.w3.org/2000/svg\" aria-hidden=\"true\" focusable=\"false\" style=\"display:none\"><defs><pattern id=\"a\"></pattern></defs></svg>\n"
+        );
+        assert_eq!(
+            inline_html("<symbol id=\"a\"/>\n"),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" aria-hidden=\"true\" focusable=\"false\" style=\"display:none\"><symbol id=\"a\"/>\n</svg>\n"
+        );
+        // Root attributes of the sprite win over the defaults.
+        assert_eq!(
+            inline_html(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"sprite\" style=\"position:absolute\"><defs></defs></svg>\n"
+            ),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"sprite\" style=\"position:absolute\" aria-hidden=\"true\" focusable=\"false\"><defs></defs></svg>\n"
+        );
+    }
+
+    #[test]
+    fn assets_manifest_maps_names_to_hashed_urls() {
+        let (a, b) = (entry("b-icon"), entry("a-icon"));
+        let out = render_with(
+            EmitKind::AssetsManifest,
+            &[&a, &b],
+            "<svg/>",
+            "out/sprite.svg",
+            "a.json",
+        );
+        let hashed = hashed_name("out/sprite.svg", "<svg/>");
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["a-icon"], format!("{hashed}#a-icon"));
+        assert_ne!(hashed, hashed_name("out/sprite.svg", "<svg></svg>"));
+        assert!(hashed_name("-", "<svg/>").starts_with("sprite."));
+    }
+
+    #[test]
+    fn tailwind_data_has_mask_and_dimensions() {
+        let a = entry("arrow");
+        let json = render_with(EmitKind::Tailwind, &[&a], "", "sprite.svg", "icons.json");
+        let v: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(v["arrow"]["width"], 24);
+        assert_eq!(v["arrow"]["viewBox"], "0 0 24 24");
+        let mask = v["arrow"]["maskImage"].as_str().unwrap();
+        assert!(
+            mask.starts_with(
+                "url(\"data:image/svg+xml,%3Csvg xmlns=%22http://www.w3.org/2000/svg%22"
+            )
+        );
+        assert!(!mask[5..mask.len() - 2].contains(['"', '<', '#']));
+
+        let js = render_with(EmitKind::Tailwind, &[&a], "", "sprite.svg", "icons.js");
+        assert!(js.contains("export default {"));
+        let cjs = render_with(EmitKind::Tailwind, &[&a], "", "sprite.svg", "icons.cjs");
+        assert!(cjs.contains("module.exports = {"));
+    }
+
+    #[test]
+    fn storybook_renders_csf_or_mdx() {
+        let a = entry("arrow");
+        let csf = render_with(
+            EmitKind::Storybook,
+            &[&a],
+            "<svg>\"S\"</svg>",
+            "sprite.svg",
+            "Icons.stories.tsx",
+        );
+        assert!(csf.contains("const SPRITE = \"<svg>\\\"S\\\"</svg>\";"));
+        assert!(csf.contains("  { name: \"arrow\", width: \"24\", height: \"24\" },\n] as const;"));
+        assert!(csf.contains("export const Gallery: Story"));
+
+        let mdx = render_with(
+            EmitKind::Storybook,
+            &[&a],
+            "<svg/>",
+            "sprite.svg",
+            "Icons.mdx",
+        );
+        assert!(mdx.starts_with("{/* Generated by svg_sheet. Do not edit. */}"));
+        assert!(mdx.contains("<Meta title=\"Icons\" />"));
+        assert!(!mdx.contains("as const"));
+    }
+
+    #[test]
+    fn react_native_exports_a_component_per_icon() {
+        let mut a = entry("arrow-left");
+        a.children = "<path d=\"M0 0\" stroke-linecap=\"round\"/>".into();
+        let tsx = render_with(EmitKind::ReactNative, &[&a], "", "sprite.svg", "Icons.tsx");
+        assert!(tsx.contains("import Svg, { Path, type SvgProps } from \"react-native-svg\";"));
+        assert!(tsx.contains(
+            "export function ArrowLeft(props: SvgProps) {\n  return (\n    <Svg width=\"24\" viewBox=\"0 0 24 24\" {...props}>\n      <Path d=\"M0 0\" strokeLinecap=\"round\" />\n    </Svg>\n  );\n}\n"
+        ));
+        assert!(tsx.contains("  \"arrow-left\": ArrowLeft,\n} as const;"));
+
+        let js = render_with(EmitKind::ReactNative, &[&a], "", "sprite.svg", "Icons.js");
+        assert!(js.contains("export function ArrowLeft(props) {"));
+        assert!(!js.contains("SvgProps") && !js.contains("as const"));
+    }
+
+    #[test]
+    fn typescript_declares_names() {
+        let a = entry("arrow-left");
+        let ts = render_with(EmitKind::Ts, &[&a], "", "sprite.svg", "icons.ts");
+        assert!(ts.contains("  \"arrow-left\",\n"));
+        assert!(ts.contains("export type IconName"));
+    }
+
+    #[test]
+    fn iconify_collection_hoists_shared_dimensions_and_aliases_copies() {
+        let a = entry("arrow");
+        let mut b = entry("wide");
+        b.out_attrs = vec![
+            ("viewBox".into(), "-2 0 32 24".into()),
+            ("fill".into(), "none".into()),
+        ];
+        b.shared_defs = vec![("g1".into(), "<linearGradient id=\"g1\"/>".into())];
+        let copy = entry("arrow-copy");
+        let ctx = EmitContext {
+            entries: &[&a, &b, &copy],
+            sprite: "",
+            sprite_path: "sprite.svg",
+            aliases: [("arrow-copy".to_string(), "arrow".to_string())].into(),
+            config_aliases: vec![("arrow-right", &a)],
+            deprecated: Default::default(),
+            root: "",
+            tags: &[],
+            synonyms: &[],
+            sidecars: &Default::default(),
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&render(EmitKind::Iconify, &ctx, "dist/My Icons.json")).unwrap();
+        assert_eq!(json["prefix"], "my-icons");
+        assert_eq!(json["height"], 24);
+        assert_eq!(
+            json["icons"]["arrow"],
+            serde_json::json!({ "body": "<g/>", "width": 24 })
+        );
+        assert_eq!(
+            json["icons"]["wide"],
+            serde_json::json!({
+                "body": "<g fill=\"none\"><defs><linearGradient id=\"g1\"/></defs><g/></g>",
+                "left": -2,
+                "width": 32
+            })
+        );
+        assert_eq!(json["aliases"]["arrow-copy"]["parent"], "arrow");
+        assert_eq!(json["aliases"]["arrow-right"]["parent"], "arrow");
+        assert!(json["icons"].get("arrow-copy").is_none());
+    }
+
+    #[test]
+    fn preview_inlines_sprite_and_escapes_names() {
+        let a = entry("a&b");
+        let html = render_with(
+            EmitKind::Preview,
+            &[&a],
+            "<svg>SPRITE</svg>",
+            "sprite.svg",
+            "p.html",
+        );
+        assert!(html.contains("<div hidden><svg>SPRITE</svg></div>"));
+        assert!(html.contains("<figcaption>a&amp;b</figcaption>"));
+
+        let ctx = EmitContext {
+            entries: &[&a],
+            sprite: "",
+            sprite_path: "sprite.svg",
+            aliases: Default::default(),
+            config_aliases: Vec::new(),
+            deprecated: [("a&b", "")].into(),
+            root: "",
+            tags: &[],
+            synonyms: &[],
+            sidecars: &Default::default(),
+        };
+        let html = render(EmitKind::Preview, &ctx, "p.html");
+        assert!(
+            html.contains("<figure class=\"deprecated\" title=\"Icon 'a&amp;b' is deprecated\" ")
+        );
+    }
+}
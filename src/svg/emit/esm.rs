@@ -0,0 +1,169 @@
+//! An ES module exporting each icon's standalone markup as a string, for
+//! consumers that inline icons at runtime instead of referencing a sheet.
+
+use super::super::naming::{IdCase, apply_case};
+use super::{EmitContext, standalone_svg};
+use std::collections::HashSet;
+
+/// Words JavaScript does not accept as `const` names.
+const RESERVED: [&str; 46] = [
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+];
+
+/// A module with an `export const` per icon holding its `<svg>` markup,
+/// and a default export mapping icon names to them. Config aliases are
+/// listed in the map under their own name. `.ts` paths also get an
+/// `IconName` union.
+pub(super) fn render(ctx: &EmitContext, target_path: &str) -> String {
+    let typed = target_path.ends_with(".ts") || target_path.ends_with(".mts");
+    let mut taken = HashSet::new();
+    let mut out = String::from("// Generated by svg_sheet. Do not edit.\n\n");
+    let mut constants = Vec::new();
+    for e in ctx.entries {
+        let name = const_name(&e.name, &mut taken);
+        out.push_str(&format!(
+            "export const {name} = {};\n",
+            js_string(&standalone_svg(e))
+        ));
+        constants.push((e.name.as_str(), name));
+    }
+    let mut map: Vec<(&str, &str)> = constants
+        .iter()
+        .map(|(id, name)| (*id, name.as_str()))
+        .collect();
+    for (alias, target) in &ctx.config_aliases {
+        if let Some((_, name)) = constants.iter().find(|(id, _)| *id == target.name) {
+            map.push((alias, name));
+        }
+    }
+    out.push_str("\nconst icons = {\n");
+    for (id, name) in map {
+        out.push_str(&format!("  {}: {name},\n", js_string(id)));
+    }
+    out.push_str(if typed { "} as const;\n" } else { "};\n" });
+    if typed {
+        out.push_str("\nexport type IconName = keyof typeof icons;\n");
+    }
+    out.push_str("\nexport default icons;\n");
+    out
+}
+
+fn js_string(s: &str) -> String {
+    serde_json::to_string(s).expect("string serializes")
+}
+
+/// A unique camelCase identifier for `id`: `arrow-left` gives `arrowLeft`,
+/// `1st` gives `icon1st`, and reserved words such as `delete` get an `Icon`
+/// suffix.
+fn const_name(id: &str, taken: &mut HashSet<String>) -> String {
+    let camel = apply_case(id, IdCase::Camel);
+    let mut base: String = camel
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '$')
+        .collect();
+    match base.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => {}
+        _ => base = format!("icon{base}"),
+    }
+    if RESERVED.contains(&base.as_str()) || base == "icons" {
+        base.push_str("Icon");
+    }
+    let mut name = base.clone();
+    let mut n = 2;
+    while !taken.insert(name.clone()) {
+        name = format!("{base}{n}");
+        n += 1;
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::entry;
+    use super::*;
+
+    #[test]
+    fn exports_markup_and_a_default_map() {
+        let (a, b) = (entry("arrow-left"), entry("delete"));
+        let ctx = EmitContext {
+            entries: &[&a, &b],
+            sprite: "",
+            sprite_path: "sprite.svg",
+            aliases: Default::default(),
+            config_aliases: vec![("back", &a)],
+            deprecated: Default::default(),
+            root: "",
+            tags: &[],
+            synonyms: &[],
+            sidecars: &Default::default(),
+        };
+        let js = render(&ctx, "icons.mjs");
+        assert!(js.contains(
+            "export const arrowLeft = \"<svg xmlns=\\\"http://www.w3.org/2000/svg\\\" width=\\\"24\\\" viewBox=\\\"0 0 24 24\\\"><g/></svg>\";\n"
+        ));
+        assert!(js.contains("export const deleteIcon = "));
+        assert!(js.contains(
+            "const icons = {\n  \"arrow-left\": arrowLeft,\n  \"delete\": deleteIcon,\n  \"back\": arrowLeft,\n};\n"
+        ));
+        assert!(js.ends_with("export default icons;\n"));
+        assert!(!js.contains("IconName"));
+
+        let ts = render(&ctx, "icons.ts");
+        assert!(ts.contains("} as const;\n\nexport type IconName = keyof typeof icons;\n"));
+    }
+
+    #[test]
+    fn const_names_are_unique_identifiers() {
+        let mut taken = HashSet::new();
+        assert_eq!(const_name("arrow-left", &mut taken), "arrowLeft");
+        assert_eq!(const_name("arrow_left", &mut taken), "arrowLeft2");
+        assert_eq!(const_name("1st", &mut taken), "icon1st");
+        assert_eq!(const_name("new", &mut taken), "newIcon");
+        assert_eq!(const_name("icons", &mut taken), "iconsIcon");
+    }
+}
@@ -0,0 +1,313 @@
+//! `react-native-svg` components, one per icon, converted from the parsed
+//! markup since React Native cannot reference a sprite with `<use href>`.
+
+use super::super::CacheEntry;
+use super::super::markup::{self, Token};
+use super::super::naming::{IdCase, apply_case};
+use super::EmitContext;
+use std::collections::{BTreeSet, HashSet};
+
+/// SVG elements and the `react-native-svg` components that render them.
+/// Anything else (`title`, `style`, filters, animation) is dropped along
+/// with its content.
+const COMPONENTS: [(&str, &str); 22] = [
+    ("circle", "Circle"),
+    ("clipPath", "ClipPath"),
+    ("defs", "Defs"),
+    ("ellipse", "Ellipse"),
+    ("foreignObject", "ForeignObject"),
+    ("g", "G"),
+    ("image", "Image"),
+    ("line", "Line"),
+    ("linearGradient", "LinearGradient"),
+    ("marker", "Marker"),
+    ("mask", "Mask"),
+    ("path", "Path"),
+    ("pattern", "Pattern"),
+    ("polygon", "Polygon"),
+    ("polyline", "Polyline"),
+    ("radialGradient", "RadialGradient"),
+    ("rect", "Rect"),
+    ("stop", "Stop"),
+    ("symbol", "Symbol"),
+    ("text", "Text"),
+    ("textPath", "TextPath"),
+    ("use", "Use"),
+];
+
+/// Root attributes carried by the component's own props rather than copied.
+const ROOT_SKIPPED_ATTRS: [&str; 4] = ["id", "data-id", "version", "class"];
+
+/// A module exporting a component per icon plus an `icons` map by name.
+/// `.tsx`/`.ts` paths get `SvgProps` typings; other paths plain JSX.
+/// Config aliases are listed in `icons` under their own name.
+pub(super) fn render(ctx: &EmitContext, target_path: &str) -> String {
+    let typed = target_path.ends_with(".tsx") || target_path.ends_with(".ts");
+    let mut used = BTreeSet::new();
+    let mut taken = HashSet::new();
+    let mut components = Vec::new();
+    for e in ctx.entries {
+        let name = component_name(&e.name, &mut taken);
+        let body = icon_body(e, &mut used).unwrap_or_else(|| {
+            tracing::warn!(icon = %e.name, "Could not convert icon markup for react-native");
+            String::new()
+        });
+        components.push((e, name, body));
+    }
+
+    let mut out = String::from("// Generated by svg_sheet. Do not edit.\n");
+    let mut names: Vec<&str> = used.into_iter().collect();
+    if typed {
+        names.push("type SvgProps");
+    }
+    if names.is_empty() {
+        out.push_str("import Svg from \"react-native-svg\";\n");
+    } else {
+        out.push_str(&format!(
+            "import Svg, {{ {} }} from \"react-native-svg\";\n",
+            names.join(", ")
+        ));
+    }
+    let props = if typed { "props: SvgProps" } else { "props" };
+    for (e, name, body) in &components {
+        out.push_str(&format!(
+            "\nexport function {name}({props}) {{\n  return (\n    <Svg"
+        ));
+        for (k, v) in &e.out_attrs {
+            if ROOT_SKIPPED_ATTRS.contains(&k.as_str()) {
+                continue;
+            }
+            if let Some(prop) = prop_name(k) {
+                push_prop(&mut out, &prop, v);
+            }
+        }
+        out.push_str(" {...props}>\n");
+        out.push_str(body);
+        out.push_str("    </Svg>\n  );\n}\n");
+    }
+    out.push_str("\nexport const icons = {\n");
+    for (e, name, _) in &components {
+        out.push_str(&format!("  {}: {name},\n", js_string(&e.name)));
+    }
+    for (alias, target) in &ctx.config_aliases {
+        if let Some((_, name, _)) = components.iter().find(|(e, ..)| e.name == target.name) {
+            out.push_str(&format!("  {}: {name},\n", js_string(alias)));
+        }
+    }
+    out.push_str(if typed { "} as const;\n" } else { "};\n" });
+    if typed {
+        out.push_str("\nexport type IconName = keyof typeof icons;\n");
+    }
+    out
+}
+
+/// JSX for an icon's shared definitions and children, indented inside
+/// `<Svg>`, recording the components it uses; `None` for malformed markup.
+fn icon_body(entry: &CacheEntry, used: &mut BTreeSet<&'static str>) -> Option<String> {
+    let mut source = String::new();
+    if !entry.shared_defs.is_empty() {
+        source.push_str("<defs>");
+        for (_, markup) in &entry.shared_defs {
+            source.push_str(markup);
+        }
+        source.push_str("</defs>");
+    }
+    source.push_str(&entry.children);
+
+    let mut out = String::new();
+    let mut depth = 3;
+    // Depth of an unsupported element whose content is being skipped.
+    let mut skipping: Option<usize> = None;
+    for token in markup::tokenize(&source)? {
+        match token {
+            Token::Open { name, raw, empty } => {
+                let component = COMPONENTS.iter().find(|(n, _)| *n == name).map(|(_, c)| *c);
+                if skipping.is_some() || component.is_none() {
+                    if !empty {
+                        depth += 1;
+                        skipping.get_or_insert(depth);
+                    }
+                    continue;
+                }
+                let component = component?;
+                used.insert(component);
+                indent(&mut out, depth);
+                out.push('<');
+                out.push_str(component);
+                for (k, v) in jsx_props(&markup::attributes(raw)?) {
+                    push_prop(&mut out, &k, &v);
+                }
+                if empty {
+                    out.push_str(" />\n");
+                } else {
+                    out.push_str(">\n");
+                    depth += 1;
+                }
+            }
+            Token::Close(raw) => {
+                if let Some(level) = skipping {
+                    if level == depth {
+                        skipping = None;
+                    }
+                    depth -= 1;
+                    continue;
+                }
+                depth = depth.checked_sub(1)?;
+                let name = raw.trim_start_matches("</").trim_end_matches('>').trim();
+                let (_, component) = COMPONENTS.iter().find(|(n, _)| *n == name)?;
+                indent(&mut out, depth);
+                out.push_str(&format!("</{component}>\n"));
+            }
+            Token::Text(text) => {
+                let text = text.trim();
+                if skipping.is_some() || text.is_empty() || text.starts_with("<!--") {
+                    continue;
+                }
+                let text = text
+                    .strip_prefix("<![CDATA[")
+                    .and_then(|t| t.strip_suffix("]]>"))
+                    .map_or_else(|| decode_entities(text), str::to_string);
+                indent(&mut out, depth);
+                out.push_str(&format!("{{{}}}\n", js_string(&text)));
+            }
+        }
+    }
+    Some(out)
+}
+
+/// JSX props for an element's attributes: names camelCased, `style`
+/// declarations spread into props, and XML-only attributes dropped.
+fn jsx_props(attrs: &[(&str, &str, char)]) -> Vec<(String, String)> {
+    let mut props: Vec<(String, String)> = Vec::new();
+    let mut set = |name: String, value: String| {
+        props.retain(|(k, _)| *k != name);
+        props.push((name, value));
+    };
+    for (k, v, _) in attrs {
+        if *k == "style" {
+            for decl in v.split(';') {
+                if let Some((name, value)) = decl.split_once(':')
+                    && let Some(name) = prop_name(name.trim())
+                {
+                    set(name, value.trim().to_string());
+                }
+            }
+        } else if let Some(name) = prop_name(k) {
+            set(name, v.to_string());
+        }
+    }
+    props
+}
+
+/// The prop for an SVG attribute, or `None` for attributes React Native
+/// has no use for.
+fn prop_name(attr: &str) -> Option<String> {
+    match attr {
+        "xlink:href" => return Some("href".to_string()),
+        "class" => return None,
+        _ if attr.contains(':') || attr.starts_with("xmlns") || attr.starts_with("data-") => {
+            return None;
+        }
+        _ => {}
+    }
+    let mut out = String::with_capacity(attr.len());
+    let mut upper = false;
+    for c in attr.chars() {
+        if c == '-' {
+            upper = true;
+        } else if upper {
+            out.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    (!out.is_empty()).then_some(out)
+}
+
+/// Append ` name="value"`, or ` name={"value"}` when the value holds a
+/// double quote, decoding the XML entities JSX strings do not handle.
+fn push_prop(out: &mut String, name: &str, value: &str) {
+    let value = decode_entities(value);
+    if value.contains('"') {
+        out.push_str(&format!(" {name}={{{}}}", js_string(&value)));
+    } else {
+        out.push_str(&format!(" {name}=\"{value}\""));
+    }
+}
+
+/// Resolve the predefined XML entities.
+fn decode_entities(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn js_string(s: &str) -> String {
+    serde_json::to_string(s).expect("string serializes")
+}
+
+fn indent(out: &mut String, depth: usize) {
+    out.push_str(&"  ".repeat(depth));
+}
+
+/// PascalCase component name for an icon id, prefixed with `Icon` when it
+/// would not start with a letter and made unique among `taken`.
+fn component_name(id: &str, taken: &mut HashSet<String>) -> String {
+    let camel = apply_case(id, IdCase::Camel);
+    let mut base: String = camel
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    match base.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => base[..1].make_ascii_uppercase(),
+        _ => base = format!("Icon{base}"),
+    }
+    let mut name = base.clone();
+    let mut n = 2;
+    while name == "Svg" || !taken.insert(name.clone()) {
+        name = format!("{base}{n}");
+        n += 1;
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::entry;
+    use super::*;
+
+    #[test]
+    fn converts_markup_to_components() {
+        let mut used = BTreeSet::new();
+        let icon = CacheEntry {
+            children: "<title>x</title><g fill-rule=\"evenodd\" style=\"stroke-width: 2; opacity:.5\"><path d=\"M0 0\"/><use xlink:href=\"#a\"/><filter id=\"f\"><feBlur/></filter></g><text x=\"1\">A &amp; B</text>".into(),
+            ..entry("a")
+        };
+        let body = icon_body(&icon, &mut used).unwrap();
+        assert_eq!(
+            body,
+            "      <G fillRule=\"evenodd\" strokeWidth=\"2\" opacity=\".5\">\n        <Path d=\"M0 0\" />\n        <Use href=\"#a\" />\n      </G>\n      <Text x=\"1\">\n        {\"A & B\"}\n      </Text>\n"
+        );
+        assert_eq!(
+            used.into_iter().collect::<Vec<_>>(),
+            ["G", "Path", "Text", "Use"]
+        );
+        let broken = CacheEntry {
+            children: "<g a='>".into(),
+            ..entry("b")
+        };
+        assert!(icon_body(&broken, &mut BTreeSet::new()).is_none());
+    }
+
+    #[test]
+    fn component_names_are_unique_identifiers() {
+        let mut taken = HashSet::new();
+        assert_eq!(component_name("arrow-left", &mut taken), "ArrowLeft");
+        assert_eq!(component_name("arrow_left", &mut taken), "ArrowLeft2");
+        assert_eq!(component_name("1st", &mut taken), "Icon1st");
+        assert_eq!(component_name("svg", &mut taken), "Svg2");
+    }
+}
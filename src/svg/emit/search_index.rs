@@ -0,0 +1,171 @@
+//! Search index for icon pickers: one flat document per icon, in a shape
+//! fuse.js and MiniSearch index directly.
+
+use super::super::CacheEntry;
+use super::{EmitContext, attr, category, description, tags, title};
+use crate::error::AppError;
+use serde::Serialize;
+use std::path::Path;
+
+/// One document of the index.
+#[derive(Serialize)]
+struct Document<'a> {
+    id: &'a str,
+    /// The id split into words, for fuzzy matching
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+    tags: &'a [String],
+    synonyms: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<&'a str>,
+    #[serde(rename = "viewBox", skip_serializing_if = "Option::is_none")]
+    view_box: Option<&'a str>,
+}
+
+/// Read the `--synonyms` file at `path`.
+pub(crate) fn load_synonyms(path: &Path) -> Result<Vec<Vec<String>>, AppError> {
+    let text = std::fs::read_to_string(path).map_err(|e| AppError::ReadFile {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+    Ok(parse_synonyms(&text))
+}
+
+/// Synonym groups, one per line as comma-separated terms (`trash, delete,
+/// bin`), lowercased. Blank lines and `#` comments are skipped.
+fn parse_synonyms(text: &str) -> Vec<Vec<String>> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .map(|line| {
+            line.split(',')
+                .map(|term| term.trim().to_lowercase())
+                .filter(|term| !term.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+/// A JSON array with a document per icon: `id`, the `name` words, its sidecar
+/// `title` and `description`, `category`, `tags`, `synonyms`, and dimensions. Config aliases are listed
+/// among the synonyms of their icon rather than as documents of their own.
+pub(super) fn render(ctx: &EmitContext) -> String {
+    let documents: Vec<Document> = ctx
+        .entries
+        .iter()
+        .map(|e| Document {
+            id: &e.name,
+            name: words(&e.name).join(" "),
+            title: title(ctx, &e.name),
+            description: description(ctx, &e.name),
+            category: category(ctx, e),
+            tags: tags(ctx, &e.name),
+            synonyms: synonyms(ctx, e),
+            width: attr(e, "width"),
+            height: attr(e, "height"),
+            view_box: attr(e, "viewBox"),
+        })
+        .collect();
+    let mut out = serde_json::to_string_pretty(&documents).expect("search index serializes");
+    out.push('\n');
+    out
+}
+
+/// The lowercased words of an id, split on `-`, `_`, and spaces.
+fn words(id: &str) -> Vec<String> {
+    id.split(['-', '_', ' '])
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Alias names of `entry`, then the other terms of every synonym group
+/// sharing a term with its id, its words, its tags, or its aliases.
+fn synonyms(ctx: &EmitContext, entry: &CacheEntry) -> Vec<String> {
+    let mut out: Vec<String> = ctx
+        .config_aliases
+        .iter()
+        .filter(|(_, target)| target.name == entry.name)
+        .map(|(alias, _)| alias.to_string())
+        .collect();
+    let mut terms = words(&entry.name);
+    terms.push(entry.name.to_lowercase());
+    terms.extend(tags(ctx, &entry.name).iter().map(|t| t.to_lowercase()));
+    terms.extend(out.iter().map(|a| a.to_lowercase()));
+    for group in ctx.synonyms {
+        if group.iter().any(|term| terms.contains(term)) {
+            for term in group {
+                if !terms.contains(term) && !out.contains(term) {
+                    out.push(term.clone());
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::entry;
+    use super::*;
+
+    #[test]
+    fn parses_synonym_groups() {
+        let groups = parse_synonyms("# icons\nTrash, delete , bin\n\nsearch\nhome, house # ok\n");
+        assert_eq!(
+            groups,
+            [vec!["trash", "delete", "bin"], vec!["home", "house"]]
+        );
+    }
+
+    #[test]
+    fn documents_carry_words_tags_and_synonyms() {
+        let (a, b) = (entry("trash-can"), entry("home"));
+        let tags = [("home".to_string(), vec!["house".to_string()])];
+        let synonyms = [
+            vec!["trash".to_string(), "delete".into(), "bin".into()],
+            vec!["house".to_string(), "building".into()],
+        ];
+        let ctx = EmitContext {
+            entries: &[&a, &b],
+            sprite: "",
+            sprite_path: "sprite.svg",
+            aliases: Default::default(),
+            config_aliases: vec![("remove", &a)],
+            deprecated: Default::default(),
+            root: "",
+            tags: &tags,
+            synonyms: &synonyms,
+            sidecars: &Default::default(),
+        };
+        let json: serde_json::Value = serde_json::from_str(&render(&ctx)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {
+                    "id": "trash-can",
+                    "name": "trash can",
+                    "tags": [],
+                    "synonyms": ["remove", "delete", "bin"],
+                    "width": "24",
+                    "viewBox": "0 0 24 24"
+                },
+                {
+                    "id": "home",
+                    "name": "home",
+                    "tags": ["house"],
+                    "synonyms": ["building"],
+                    "width": "24",
+                    "viewBox": "0 0 24 24"
+                }
+            ])
+        );
+    }
+}
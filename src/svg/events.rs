@@ -0,0 +1,152 @@
+//! Machine-readable watch events (`watch --events json`), one JSON object per line.
+
+use super::RunOpts;
+use super::summary::{BuildSummary, WatchTotals};
+use crate::error::AppError;
+use serde::Serialize;
+
+/// A watch-mode event, serialized with an `event` tag in snake_case.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum WatchEvent<'a> {
+    WatchStarted {
+        directory: &'a str,
+        file: &'a str,
+        mode: &'a str,
+    },
+    BuildStarted,
+    BuildSucceeded {
+        icons: usize,
+        warnings: usize,
+        bytes: u64,
+        duration_ms: u128,
+        unchanged: bool,
+    },
+    BuildSkipped {
+        reason: String,
+    },
+    BuildFailed {
+        /// Stable error code (`AppError::code`)
+        code: &'static str,
+        error: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        line: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        column: Option<usize>,
+    },
+    ConfigReloaded {
+        path: &'a str,
+        changed: Vec<&'static str>,
+    },
+    WatchStopped {
+        rebuilds: usize,
+        failures: usize,
+        duration_ms: u128,
+    },
+}
+
+impl<'a> WatchEvent<'a> {
+    /// Event for a failed build.
+    pub(crate) fn failed(error: &'a AppError) -> Self {
+        WatchEvent::BuildFailed {
+            code: error.code(),
+            error: error.to_string(),
+            path: error.path(),
+            line: error.location().map(|(line, _)| line),
+            column: error.location().map(|(_, column)| column),
+        }
+    }
+
+    /// Event for the end of a watch session.
+    pub(crate) fn stopped(totals: &WatchTotals) -> Self {
+        WatchEvent::WatchStopped {
+            rebuilds: totals.rebuilds,
+            failures: totals.failures,
+            duration_ms: totals.duration.as_millis(),
+        }
+    }
+
+    /// Event for a finished build.
+    pub(crate) fn succeeded(summary: &BuildSummary) -> Self {
+        WatchEvent::BuildSucceeded {
+            icons: summary.icons,
+            warnings: summary.warnings.len(),
+            bytes: summary.bytes,
+            duration_ms: summary.duration.as_millis(),
+            unchanged: summary.unchanged,
+        }
+    }
+}
+
+/// Print `event` as a JSON line on stdout when JSON events are enabled.
+pub(crate) fn emit(opts: &RunOpts, event: WatchEvent) {
+    if !opts.json_events {
+        return;
+    }
+    use std::io::Write;
+    let line = serde_json::to_string(&event).expect("event serializes");
+    let mut out = std::io::stdout().lock();
+    let _ = writeln!(out, "{line}");
+    let _ = out.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn events_serialize_with_tag() {
+        let started = serde_json::to_string(&WatchEvent::BuildStarted).unwrap();
+        assert_eq!(started, r#"{"event":"build_started"}"#);
+
+        let summary = BuildSummary {
+            icons: 3,
+            duration: Duration::from_millis(12),
+            ..Default::default()
+        };
+        let done = serde_json::to_string(&WatchEvent::succeeded(&summary)).unwrap();
+        assert_eq!(
+            done,
+            r#"{"event":"build_succeeded","icons":3,"warnings":0,"bytes":0,"duration_ms":12,"unchanged":false}"#
+        );
+
+        let error = AppError::NoSvgFiles {
+            path: "icons".into(),
+        };
+        let failed = serde_json::to_string(&WatchEvent::failed(&error)).unwrap();
+        assert_eq!(
+            failed,
+            r#"{"event":"build_failed","code":"E0005","error":"no SVG files found in directory: icons","path":"icons"}"#
+        );
+
+        let error = AppError::ParseSvg {
+            path: "a.svg".into(),
+            message: "unterminated attribute value".into(),
+            location: Some((3, 9)),
+        };
+        let failed = serde_json::to_string(&WatchEvent::failed(&error)).unwrap();
+        assert!(failed.ends_with(r#""path":"a.svg","line":3,"column":9}"#));
+
+        let reloaded = WatchEvent::ConfigReloaded {
+            path: "svg_sheet.toml",
+            changed: vec!["colors", "emit"],
+        };
+        assert_eq!(
+            serde_json::to_string(&reloaded).unwrap(),
+            r#"{"event":"config_reloaded","path":"svg_sheet.toml","changed":["colors","emit"]}"#
+        );
+        let totals = WatchTotals {
+            rebuilds: 4,
+            failures: 1,
+            duration: Duration::from_secs(2),
+        };
+        let stopped = serde_json::to_string(&WatchEvent::stopped(&totals)).unwrap();
+        assert_eq!(
+            stopped,
+            r#"{"event":"watch_stopped","rebuilds":4,"failures":1,"duration_ms":2000}"#
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! `--flatten-transforms`: fold `transform` attributes on paths and groups into
+//! the path data itself.
+//!
+//! A group's transform is pushed into its descendants only when every one of
+//! them can absorb it; otherwise the group keeps it. Scaling, rotation and skew
+//! are left alone on stroked content (stroke width would change). No
+//! transform, translations included, is folded into an element referencing
+//! paint servers, clips or masks (`url(...)`), or into a group with such a
+//! descendant: those resolve in the element's transformed user space.
+
+use super::markup::{self, Token};
+use super::path_data::{self, IDENTITY, Matrix};
+use std::borrow::Cow;
+
+/// Flatten transforms in `children`. `stroked` tells whether the root `<svg>`
+/// sets a stroke inherited by the content. Malformed markup is returned as is.
+pub(crate) fn flatten(children: &str, stroked: bool) -> Cow<'_, str> {
+    let Some(tokens) = markup::tokenize(children) else {
+        return Cow::Borrowed(children);
+    };
+    let mut out = String::with_capacity(children.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        walk(&tokens, &mut i, &IDENTITY, stroked, &mut out);
+        // Stray end tag at the top level: copy it and carry on.
+        if let Some(Token::Close(raw)) = tokens.get(i) {
+            out.push_str(raw);
+            i += 1;
+        }
+    }
+    if out == children {
+        Cow::Borrowed(children)
+    } else {
+        Cow::Owned(out)
+    }
+}
+
+/// Whether a root `<svg>` attribute list sets a stroke.
+pub(crate) fn sets_stroke(attrs: &[(String, String)]) -> bool {
+    attrs.iter().any(|(k, v)| is_stroke_attr(k, v))
+}
+
+/// Copy the sibling nodes starting at `*i` into `out`, applying `m` (inherited
+/// from flattened ancestors). Stops before the end tag closing the parent.
+/// Returns `false` when `m` is not the identity and some node cannot absorb it;
+/// `out` is then partially written and must be discarded by the caller.
+fn walk(tokens: &[Token], i: &mut usize, m: &Matrix, stroked: bool, out: &mut String) -> bool {
+    let inherited = *m != IDENTITY;
+    while let Some(token) = tokens.get(*i) {
+        let (name, raw, empty) = match token {
+            Token::Text(text) => {
+                out.push_str(text);
+                *i += 1;
+                continue;
+            }
+            Token::Close(_) => return true,
+            Token::Open { name, raw, empty } => (*name, *raw, *empty),
+        };
+        *i += 1;
+        let parsed = markup::attributes(raw);
+        let attrs = parsed.clone().unwrap_or_default();
+        let stroked = stroked || attrs.iter().any(|(k, v, _)| is_stroke_attr(k, v));
+        let own = attrs.iter().find(|(k, ..)| *k == "transform");
+        let combined = match own.map(|(_, v, _)| path_data::parse_transform(v)) {
+            None => Some(*m),
+            Some(Some(t)) => Some(path_data::multiply(m, &t)),
+            Some(None) => None,
+        };
+        let references = attrs.iter().any(|(_, v, _)| v.contains("url("));
+        let absorbable = combined.filter(|c| {
+            parsed.is_some() && !references && (path_data::is_translation(c) || !stroked)
+        });
+
+        let mark = out.len();
+        let mut flattened = false;
+        if let Some(c) = absorbable.filter(|_| own.is_some() || inherited) {
+            match name {
+                "path" => {
+                    let d = attrs.iter().find(|(k, ..)| *k == "d");
+                    let new_d = match d {
+                        Some((_, d, _)) => path_data::transform(d, &c),
+                        None => Some(String::new()),
+                    };
+                    if let Some(new_d) = new_d {
+                        push_tag(out, name, &attrs, Some(&new_d), empty);
+                        flattened = true;
+                    }
+                }
+                "g" => {
+                    let after = *i;
+                    push_tag(out, name, &attrs, None, empty);
+                    if empty || walk_children(tokens, i, &c, stroked, out) {
+                        flattened = true;
+                    } else {
+                        out.truncate(mark);
+                        *i = after;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if flattened {
+            // Path children (`<title>`, animations) are not geometry.
+            if name == "path" && !empty {
+                walk_children(tokens, i, &IDENTITY, stroked, out);
+            }
+            continue;
+        }
+        if inherited {
+            return false;
+        }
+        out.push_str(raw);
+        if !empty {
+            walk_children(tokens, i, &IDENTITY, stroked, out);
+        }
+    }
+    true
+}
+
+/// Walk the children of the element just opened, then copy its end tag.
+fn walk_children(
+    tokens: &[Token],
+    i: &mut usize,
+    m: &Matrix,
+    stroked: bool,
+    out: &mut String,
+) -> bool {
+    if !walk(tokens, i, m, stroked, out) {
+        return false;
+    }
+    if let Some(Token::Close(raw)) = tokens.get(*i) {
+        out.push_str(raw);
+        *i += 1;
+    }
+    true
+}
+
+/// Write a start tag without its `transform`, replacing `d` when given.
+fn push_tag(
+    out: &mut String,
+    name: &str,
+    attrs: &[(&str, &str, char)],
+    d: Option<&str>,
+    empty: bool,
+) {
+    let attrs: Vec<(&str, &str, char)> = attrs
+        .iter()
+        .filter(|(k, ..)| *k != "transform")
+        .map(|&(k, v, q)| match d {
+            Some(d) if k == "d" => (k, d, '"'),
+            _ => (k, v, q),
+        })
+        .collect();
+    markup::push_tag(out, name, &attrs, empty);
+}
+
+/// Whether an attribute sets a visible stroke (or a stroke width).
+fn is_stroke_attr(key: &str, value: &str) -> bool {
+    (key == "stroke" && value != "none")
+        || key == "stroke-width"
+        || (key == "style" && value.contains("stroke"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_path_and_group_transforms() {
+        let input = r#"<g transform="translate(2 2)"><path d="M0 0h4" fill="red"/><path transform="scale(2)" d="M1 1v1"/></g>"#;
+        assert_eq!(
+            flatten(input, false),
+            r#"<g><path d="M2 2L6 2" fill="red"/><path d="M4 4L4 6"/></g>"#
+        );
+    }
+
+    #[test]
+    fn keeps_transforms_that_cannot_be_absorbed() {
+        // A rect cannot absorb the group's transform, so the group keeps it,
+        // but its path child still flattens its own.
+        let input = r#"<g transform="scale(2)"><rect width="1"/><path transform="translate(1)" d="M0 0"/></g>"#;
+        assert_eq!(
+            flatten(input, false),
+            r#"<g transform="scale(2)"><rect width="1"/><path d="M1 0"/></g>"#
+        );
+
+        let stroked = r#"<path stroke="red" transform="scale(2)" d="M0 0"/>"#;
+        assert_eq!(flatten(stroked, false), stroked);
+        let inherited = r#"<path transform="scale(2)" d="M0 0"/>"#;
+        assert_eq!(flatten(inherited, true), inherited);
+        assert_eq!(
+            flatten(inherited, false),
+            r#"<path d="M0 0"/>"#,
+            "unstroked scales flatten"
+        );
+
+        let painted = r#"<path fill="url(#g)" transform="scale(2)" d="M0 0"/>"#;
+        assert_eq!(flatten(painted, false), painted);
+        // User-space clips stay put, so even a translation must not move the shape.
+        let clipped = r#"<clipPath id="c"><rect width="5" height="5"/></clipPath><path transform="translate(10 0)" clip-path="url(#c)" d="M0 0h5v5h-5z"/>"#;
+        assert_eq!(flatten(clipped, false), clipped);
+        let group =
+            r#"<g transform="translate(10)"><path d="M0 0"/><path mask="url(#m)" d="M1 1"/></g>"#;
+        assert_eq!(flatten(group, false), group);
+        let unknown = r#"<path transform="perspective(2)" d="M0 0"/>"#;
+        assert_eq!(flatten(unknown, false), unknown);
+    }
+
+    #[test]
+    fn passes_other_markup_through() {
+        let input = "<!-- <g transform='x'> --><title>a</title><defs><path d='M0 0'/></defs>";
+        assert_eq!(flatten(input, false), input);
+        assert_eq!(flatten("<path", false), "<path");
+    }
+}
@@ -0,0 +1,79 @@
+//! `--generator-comment`: a comment atop the sprite naming the generator, its
+//! version, and the build time. The time honors `SOURCE_DATE_EPOCH`, so
+//! release pipelines can reproduce the sprite byte for byte.
+
+use super::RunOpts;
+use crate::error::AppError;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable overriding the build time, in seconds since the Unix
+/// epoch (<https://reproducible-builds.org/specs/source-date-epoch/>).
+pub(crate) const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// The generator comment, ending in a newline: `<!-- Generated by svg_sheet
+/// 1.2.3 on 2024-01-31T23:59:59Z -->`, without the time under
+/// `--no-timestamp`.
+pub(crate) fn comment(opts: &RunOpts) -> Result<String, AppError> {
+    let mut text = format!("Generated by svg_sheet {}", env!("CARGO_PKG_VERSION"));
+    if !opts.no_timestamp {
+        let epoch = std::env::var(SOURCE_DATE_EPOCH).ok();
+        text.push_str(&format!(" on {}", rfc3339(build_time(epoch.as_deref())?)));
+    }
+    Ok(format!("<!-- {text} -->\n"))
+}
+
+/// Seconds since the epoch: `epoch` (the value of `SOURCE_DATE_EPOCH`) when
+/// set and non-empty, otherwise the current time.
+fn build_time(epoch: Option<&str>) -> Result<u64, AppError> {
+    match epoch.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(value) => value.parse().map_err(|_| AppError::Config {
+            path: SOURCE_DATE_EPOCH.into(),
+            message: format!("expected a Unix timestamp in seconds, got '{value}'"),
+        }),
+        None => Ok(SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())),
+    }
+}
+
+/// `secs` as an RFC 3339 UTC timestamp (`2024-01-31T23:59:59Z`).
+fn rfc3339(secs: u64) -> String {
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = super::archive::civil_from_days(days as i64);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_date_epoch_fixes_the_build_time() {
+        assert_eq!(build_time(Some("951827696")).unwrap(), 951_827_696);
+        assert!(build_time(Some("")).unwrap() > 951_827_696);
+        let err = build_time(Some("yesterday")).unwrap_err();
+        assert!(err.to_string().contains("got 'yesterday'"));
+        assert_eq!(rfc3339(951_827_696), "2000-02-29T12:34:56Z");
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn no_timestamp_leaves_the_time_out() {
+        let opts = RunOpts {
+            no_timestamp: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            comment(&opts).unwrap(),
+            format!(
+                "<!-- Generated by svg_sheet {} -->\n",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! `--plain-href`: SVG 2 `href` in place of the deprecated `xlink:href`.
+
+use super::markup::{self, Token};
+use super::namespaces;
+use std::borrow::Cow;
+
+/// Rewrite every `xlink:href` in `children` as `href`, or drop it where the
+/// element already has an `href`, which SVG 2 gives precedence. The root's
+/// `xmlns:xlink` goes too once no `xlink:` attribute is left. Markup that does
+/// not tokenize is left as is.
+///
+/// References stay in the `href="#id"` form `ids` detects and rewrites.
+pub(crate) fn plain<'a>(children: &'a str, out_attrs: &mut Vec<(String, String)>) -> Cow<'a, str> {
+    let children = rewrite(children);
+    if !namespaces::uses("xlink", out_attrs, &children) {
+        out_attrs.retain(|(k, _)| k != "xmlns:xlink");
+    }
+    children
+}
+
+fn rewrite(children: &str) -> Cow<'_, str> {
+    if !children.contains("xlink:href") {
+        return Cow::Borrowed(children);
+    }
+    let Some(tokens) = markup::tokenize(children) else {
+        return Cow::Borrowed(children);
+    };
+    let mut out = String::with_capacity(children.len());
+    let mut rewrote = false;
+    for token in tokens {
+        if let Token::Open { name, raw, empty } = token
+            && let Some(attrs) = markup::attributes(raw)
+            && attrs.iter().any(|(k, ..)| *k == "xlink:href")
+        {
+            let has_href = attrs.iter().any(|(k, ..)| *k == "href");
+            let attrs: Vec<_> = attrs
+                .into_iter()
+                .filter(|(k, ..)| !(has_href && *k == "xlink:href"))
+                .map(|(k, v, q)| (if k == "xlink:href" { "href" } else { k }, v, q))
+                .collect();
+            markup::push_tag(&mut out, name, &attrs, empty);
+            rewrote = true;
+        } else {
+            out.push_str(token.raw());
+        }
+    }
+    if rewrote {
+        Cow::Owned(out)
+    } else {
+        Cow::Borrowed(children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XLINK: (&str, &str) = ("xmlns:xlink", "http://www.w3.org/1999/xlink");
+
+    fn root() -> Vec<(String, String)> {
+        vec![
+            (XLINK.0.to_string(), XLINK.1.to_string()),
+            ("width".to_string(), "24".to_string()),
+        ]
+    }
+
+    #[test]
+    fn rewrites_xlink_href_and_drops_the_namespace() {
+        let mut attrs = root();
+        let out = plain(
+            "<use xlink:href=\"#a\"/><image href=\"b.png\" xlink:href='old.png'></image>",
+            &mut attrs,
+        );
+        assert_eq!(out, "<use href=\"#a\"/><image href=\"b.png\"></image>");
+        assert_eq!(attrs, [("width".to_string(), "24".to_string())]);
+        assert_eq!(crate::svg::ids::referenced_ids(&out), ["a"]);
+    }
+
+    #[test]
+    fn keeps_the_namespace_while_other_xlink_attributes_remain() {
+        let mut attrs = root();
+        let out = plain(
+            "<a xlink:href=\"#a\" xlink:title=\"A\"><g/></a>",
+            &mut attrs,
+        );
+        assert_eq!(out, "<a href=\"#a\" xlink:title=\"A\"><g/></a>");
+        assert_eq!(attrs, root());
+    }
+
+    #[test]
+    fn untouched_markup_is_borrowed() {
+        let mut attrs = root();
+        assert!(matches!(
+            plain("<use href=\"#a\"/>", &mut attrs),
+            Cow::Borrowed(_)
+        ));
+    }
+}
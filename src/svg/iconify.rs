@@ -0,0 +1,281 @@
+//! Iconify JSON collections (`--input-iconify`) read as inputs next to the
+//! icon directory.
+//!
+//! Each icon (and alias) becomes an SVG document built from its `body` and
+//! view box, then goes through the same parse pass as files on disk.
+
+use super::{CacheEntry, RunOpts, naming};
+use crate::error::AppError;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Size of an icon whose collection sets neither `width` nor `height`.
+const DEFAULT_SIZE: f64 = 16.0;
+
+/// Aliases may point at aliases; chains longer than this are treated as loops.
+const MAX_ALIAS_DEPTH: usize = 32;
+
+/// Serialized form of an Iconify collection; fields this tool does not use
+/// (`info`, `categories`, ...) are ignored.
+#[derive(Deserialize)]
+struct Collection {
+    icons: BTreeMap<String, Icon>,
+    #[serde(default)]
+    aliases: BTreeMap<String, Alias>,
+    /// Defaults for every icon
+    #[serde(flatten)]
+    props: Props,
+}
+
+#[derive(Deserialize)]
+struct Icon {
+    body: String,
+    #[serde(flatten)]
+    props: Props,
+}
+
+#[derive(Deserialize)]
+struct Alias {
+    parent: String,
+    #[serde(flatten)]
+    props: Props,
+}
+
+/// Optional view box and transformation fields shared by collections, icons,
+/// and aliases.
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+struct Props {
+    left: Option<f64>,
+    top: Option<f64>,
+    width: Option<f64>,
+    height: Option<f64>,
+    rotate: Option<i64>,
+    h_flip: Option<bool>,
+    v_flip: Option<bool>,
+}
+
+impl Props {
+    /// `self` overridden by `over`: view box fields are replaced, rotations
+    /// add up, and flips toggle.
+    fn merge(self, over: Props) -> Props {
+        let toggle = |a: Option<bool>, b: Option<bool>| match (a, b) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(false) ^ b.unwrap_or(false)),
+        };
+        Props {
+            left: over.left.or(self.left),
+            top: over.top.or(self.top),
+            width: over.width.or(self.width),
+            height: over.height.or(self.height),
+            rotate: match (self.rotate, over.rotate) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+            },
+            h_flip: toggle(self.h_flip, over.h_flip),
+            v_flip: toggle(self.v_flip, over.v_flip),
+        }
+    }
+}
+
+/// Render an icon `body` with resolved `props` as a standalone `<svg>`,
+/// applying flips and rotation the way Iconify's `iconToSVG` does.
+fn to_svg(body: &str, props: Props) -> String {
+    let mut left = props.left.unwrap_or(0.0);
+    let mut top = props.top.unwrap_or(0.0);
+    let mut width = props.width.or(props.height).unwrap_or(DEFAULT_SIZE);
+    let mut height = props.height.or(props.width).unwrap_or(DEFAULT_SIZE);
+    let mut rotate = props.rotate.unwrap_or(0);
+    let mut transforms: Vec<String> = Vec::new();
+    match (props.h_flip.unwrap_or(false), props.v_flip.unwrap_or(false)) {
+        (true, true) => rotate += 2,
+        (true, false) => {
+            transforms.push(format!(
+                "translate({} {}) scale(-1 1)",
+                width + left,
+                0.0 - top
+            ));
+            left = 0.0;
+            top = 0.0;
+        }
+        (false, true) => {
+            transforms.push(format!(
+                "translate({} {}) scale(1 -1)",
+                0.0 - left,
+                height + top
+            ));
+            left = 0.0;
+            top = 0.0;
+        }
+        (false, false) => {}
+    }
+    let rotate = rotate.rem_euclid(4);
+    match rotate {
+        1 => {
+            let c = height / 2.0 + top;
+            transforms.insert(0, format!("rotate(90 {c} {c})"));
+        }
+        2 => {
+            let (cx, cy) = (width / 2.0 + left, height / 2.0 + top);
+            transforms.insert(0, format!("rotate(180 {cx} {cy})"));
+        }
+        3 => {
+            let c = width / 2.0 + left;
+            transforms.insert(0, format!("rotate(-90 {c} {c})"));
+        }
+        _ => {}
+    }
+    if rotate % 2 == 1 {
+        std::mem::swap(&mut left, &mut top);
+        std::mem::swap(&mut width, &mut height);
+    }
+    let body = if transforms.is_empty() {
+        body.to_string()
+    } else {
+        format!("<g transform=\"{}\">{body}</g>", transforms.join(" "))
+    };
+    format!(
+        "<svg width=\"{width}\" height=\"{height}\" viewBox=\"{left} {top} {width} {height}\">{body}</svg>"
+    )
+}
+
+/// Every icon and alias of a collection as `(name, svg)`, sorted by name.
+fn documents(path: &str, text: &str) -> Result<Vec<(String, String)>, AppError> {
+    let invalid = |message: String| AppError::Iconify {
+        path: path.to_string(),
+        message,
+    };
+    let collection: Collection = serde_json::from_str(text).map_err(|e| invalid(e.to_string()))?;
+    let mut docs: BTreeMap<&str, String> = collection
+        .icons
+        .iter()
+        .map(|(name, icon)| {
+            let props = collection.props.merge(icon.props);
+            (name.as_str(), to_svg(&icon.body, props))
+        })
+        .collect();
+    for (name, alias) in &collection.aliases {
+        if collection.icons.contains_key(name) {
+            continue;
+        }
+        let mut props = alias.props;
+        let mut parent = alias.parent.as_str();
+        let mut depth = 0;
+        let icon = loop {
+            if let Some(icon) = collection.icons.get(parent) {
+                break icon;
+            }
+            let Some(next) = collection.aliases.get(parent) else {
+                return Err(invalid(format!(
+                    "alias `{name}` has unknown parent `{parent}`"
+                )));
+            };
+            depth += 1;
+            if depth > MAX_ALIAS_DEPTH {
+                return Err(invalid(format!("alias `{name}` is part of a loop")));
+            }
+            props = next.props.merge(props);
+            parent = &next.parent;
+        };
+        let props = collection.props.merge(icon.props).merge(props);
+        docs.insert(name, to_svg(&icon.body, props));
+    }
+    Ok(docs
+        .into_iter()
+        .map(|(name, doc)| (name.to_string(), doc))
+        .collect())
+}
+
+/// Parse the icons of every `--input-iconify` collection, in command-line
+/// order and by name within a collection, narrowed by `--only`. Each entry's
+/// source is `<collection>#<icon>`.
+pub(crate) fn load_all(opts: &RunOpts) -> Result<Vec<CacheEntry>, AppError> {
+    let only = super::inputs::expand_only(&opts.only)?;
+    let mut entries = Vec::new();
+    for path in &opts.iconify {
+        let _span = tracing::debug_span!("iconify", file = %path.display()).entered();
+        let origin = path.display().to_string();
+        let text = std::fs::read_to_string(path).map_err(|e| AppError::ReadFile {
+            path: origin.clone(),
+            source: e,
+        })?;
+        for (icon, doc) in documents(&origin, &text)? {
+            let name = naming::apply_case(&icon, opts.id_case);
+            if name.is_empty() || !(only.is_empty() || only.contains(&name)) {
+                continue;
+            }
+            entries.push(super::parse_icon(
+                &name,
+                &format!("{origin}#{icon}"),
+                &doc,
+                opts,
+            )?);
+        }
+    }
+    tracing::debug!(count = entries.len(), "Loaded Iconify icons");
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLLECTION: &str = r#"{
+        "prefix": "demo",
+        "width": 24,
+        "height": 24,
+        "icons": {
+            "arrow": { "body": "<path d=\"M0 0h24\"/>" },
+            "wide": { "body": "<path d=\"M0 0h32\"/>", "width": 32, "left": -4 }
+        },
+        "aliases": {
+            "arrow-back": { "parent": "arrow", "hFlip": true },
+            "arrow-down": { "parent": "arrow-up", "rotate": 2 },
+            "arrow-up": { "parent": "arrow", "rotate": 3 }
+        }
+    }"#;
+
+    fn docs() -> BTreeMap<String, String> {
+        documents("demo.json", COLLECTION)
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn builds_documents_from_bodies_and_view_boxes() {
+        let docs = docs();
+        assert_eq!(
+            docs["arrow"],
+            "<svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><path d=\"M0 0h24\"/></svg>"
+        );
+        assert!(docs["wide"].contains("width=\"32\" height=\"24\" viewBox=\"-4 0 32 24\""));
+    }
+
+    #[test]
+    fn resolves_alias_chains_with_transformations() {
+        let docs = docs();
+        assert!(
+            docs["arrow-back"]
+                .contains("<g transform=\"translate(24 0) scale(-1 1)\"><path d=\"M0 0h24\"/></g>")
+        );
+        assert!(docs["arrow-up"].contains("<g transform=\"rotate(-90 12 12)\">"));
+        // 3 + 2 quarter turns
+        assert!(docs["arrow-down"].contains("<g transform=\"rotate(90 12 12)\">"));
+    }
+
+    #[test]
+    fn rejects_dangling_aliases_and_bad_json() {
+        let text = r#"{"icons": {}, "aliases": {"up": {"parent": "arrow"}}}"#;
+        match documents("x.json", text) {
+            Err(AppError::Iconify { message, .. }) => {
+                assert_eq!(message, "alias `up` has unknown parent `arrow`")
+            }
+            other => panic!("unexpected: {other:?}"),
+        }
+        assert!(matches!(
+            documents("x.json", "{\"prefix\": \"x\"}"),
+            Err(AppError::Iconify { .. })
+        ));
+    }
+}
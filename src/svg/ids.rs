@@ -1,6 +1,21 @@
 //! ID extraction and reference detection utilities.
 
-/// Detect simple references to an id within content: `href="#id"`, `xlink:href="#id"`, or `url(#id)`.
+use std::borrow::Cow;
+
+/// What to do with the `id` attribute on an input's root `<svg>`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RootIdPolicy {
+    /// Keep it queryable as a real `id` on a `<g>` wrapping the icon's content
+    Keep,
+    /// Move it to a `data-id` attribute on the pattern
+    #[default]
+    DataId,
+    /// Remove it
+    Drop,
+}
+
+/// Detect simple references to an id within content: `href="#id"`,
+/// `xlink:href="#id"` (either quote), or `url(#id)` (quoted or not).
 ///
 /// Example:
 /// ```
@@ -14,6 +29,165 @@ pub fn references_id(content: &str, id: &str) -> bool {
         || content.contains(&format!("href='#{id}'"))
         || content.contains(&format!("xlink:href='#{id}'"))
         || content.contains(&format!("url(#{id})"))
+        || content.contains(&format!("url('#{id}')"))
+        || content.contains(&format!("url(\"#{id}\")"))
+}
+
+/// The distinct ids referenced within content as `href="#id"`,
+/// `xlink:href="#id"` (either quote), or `url(#id)` (quoted or not).
+///
+/// Example:
+/// ```
+/// let s = "<use href=\"#a\"/><rect fill=\"url(#b)\" stroke=\"url('#c')\"/>";
+/// assert_eq!(svg_sheet::svg::ids::referenced_ids(s), ["a", "b", "c"]);
+/// ```
+pub fn referenced_ids(content: &str) -> Vec<&str> {
+    let mut ids: Vec<&str> = Vec::new();
+    for (open, close) in [
+        ("href=\"#", '"'),
+        ("href='#", '\''),
+        ("url(#", ')'),
+        ("url('#", '\''),
+        ("url(\"#", '"'),
+    ] {
+        let mut rest = content;
+        while let Some(at) = rest.find(open) {
+            rest = &rest[at + open.len()..];
+            let Some(end) = rest.find(close) else { break };
+            let id = &rest[..end];
+            if !id.is_empty() && !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+/// Every `id` attribute in `markup`; `None` for malformed markup.
+pub(crate) fn defined_ids(markup: &str) -> Option<Vec<String>> {
+    let mut ids = Vec::new();
+    for token in super::markup::tokenize(markup)? {
+        if let super::markup::Token::Open { raw, .. } = token
+            && let Some(attrs) = super::markup::attributes(raw)
+            && let Some(&(_, id, _)) = attrs.iter().find(|(k, ..)| *k == "id")
+        {
+            ids.push(id.to_string());
+        }
+    }
+    Some(ids)
+}
+
+/// Elements whose ids `scope_def_ids` makes unique per icon.
+const DEF_LIKE: [&str; 5] = [
+    "linearGradient",
+    "radialGradient",
+    "clipPath",
+    "mask",
+    "filter",
+];
+
+/// Rename the ids of referenced definitions (gradients, clip paths, masks,
+/// filters) to `{prefix}-{id}` and rewrite every `url(#id)` and `href="#id"`
+/// reference to them, so exports that all use `clip0` or `paint0_linear` keep
+/// working side by side in one sprite. Returns the content and the new ids.
+///
+/// A scoped id can still match another icon's pattern or scoped id (icon `a`
+/// with `b-c` and icon `a-b` with `c` both give `a-b-c`); builds check every
+/// id of the sprite for that.
+///
+/// Example:
+/// ```
+/// let (out, ids) = svg_sheet::svg::ids::scope_def_ids(
+///     "<clipPath id=\"clip0\"><rect/></clipPath><g clip-path=\"url(#clip0)\"/>",
+///     "arrow",
+/// );
+/// assert_eq!(
+///     out,
+///     "<clipPath id=\"arrow-clip0\"><rect/></clipPath><g clip-path=\"url(#arrow-clip0)\"/>"
+/// );
+/// assert_eq!(ids, vec!["arrow-clip0".to_string()]);
+/// ```
+pub fn scope_def_ids<'a>(s: &'a str, prefix: &str) -> (Cow<'a, str>, Vec<String>) {
+    let Some(tokens) = super::markup::tokenize(s) else {
+        return (Cow::Borrowed(s), Vec::new());
+    };
+    let mut renamed: Vec<(&str, String)> = Vec::new();
+    let mut out = String::with_capacity(s.len());
+    for token in &tokens {
+        let super::markup::Token::Open { name, raw, empty } = *token else {
+            out.push_str(token.raw());
+            continue;
+        };
+        let attrs = super::markup::attributes(raw).filter(|_| DEF_LIKE.contains(&name));
+        let Some((attrs, id)) = attrs.and_then(|attrs| {
+            let &(_, id, _) = attrs.iter().find(|(k, ..)| *k == "id")?;
+            Some((attrs, id))
+        }) else {
+            out.push_str(raw);
+            continue;
+        };
+        if renamed.iter().any(|(old, _)| *old == id) || !references_id(s, id) {
+            out.push_str(raw);
+            continue;
+        }
+        let scoped = format!("{prefix}-{}", crate::svg::sanitize::sanitize_id(id));
+        let attrs: Vec<(&str, &str, char)> = attrs
+            .iter()
+            .map(|&(k, v, q)| {
+                if k == "id" {
+                    (k, scoped.as_str(), q)
+                } else {
+                    (k, v, q)
+                }
+            })
+            .collect();
+        super::markup::push_tag(&mut out, name, &attrs, empty);
+        renamed.push((id, scoped));
+    }
+    if renamed.is_empty() {
+        return (Cow::Borrowed(s), Vec::new());
+    }
+    let out = rewrite_references(&out, &renamed);
+    (
+        Cow::Owned(out),
+        renamed.into_iter().map(|(_, new)| new).collect(),
+    )
+}
+
+/// Point every `url(#old)` (quoted or not) and `href="#old"` reference at its
+/// `new` id from `renamed` `(old, new)` pairs. One pass over `s`, so a new id
+/// that is also an old one is not renamed again.
+pub(crate) fn rewrite_references<O, N>(s: &str, renamed: &[(O, N)]) -> String
+where
+    O: AsRef<str>,
+    N: AsRef<str>,
+{
+    const REFERENCES: [(&str, &str); 5] = [
+        ("url(#", ")"),
+        ("url('#", "')"),
+        ("url(\"#", "\")"),
+        ("href=\"#", "\""),
+        ("href='#", "'"),
+    ];
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some((at, open, close)) = REFERENCES
+        .iter()
+        .filter_map(|&(open, close)| rest.find(open).map(|at| (at, open, close)))
+        .min_by_key(|&(at, ..)| at)
+    {
+        let start = at + open.len();
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(end) = rest.find(close)
+            && let Some((_, new)) = renamed.iter().find(|(old, _)| old.as_ref() == &rest[..end])
+        {
+            out.push_str(new.as_ref());
+            rest = &rest[end..];
+        }
+    }
+    out.push_str(rest);
+    out
 }
 
 /// Return whether a character is valid in an attribute name context.
@@ -33,7 +207,29 @@ fn is_name_char(ch: char) -> bool {
 /// assert!(out.contains("data-id=\"a-2\""));
 /// assert_eq!(ids, vec!["a".to_string(), "a-2".to_string()]);
 /// ```
-pub fn rewrite_ids_to_data_ids(s: &str) -> (String, Vec<String>) {
+pub fn rewrite_ids_to_data_ids(s: &str) -> (Cow<'_, str>, Vec<String>) {
+    rewrite_ids_to_data_ids_except(s, &[])
+}
+
+/// Like `rewrite_ids_to_data_ids`, but ids listed in `keep` stay real `id`
+/// attributes (and are not returned).
+///
+/// Example:
+/// ```
+/// let (out, ids) = svg_sheet::svg::ids::rewrite_ids_to_data_ids_except(
+///     "<clipPath id='a-clip0'/><g id='b'/>",
+///     &["a-clip0".to_string()],
+/// );
+/// assert_eq!(out, "<clipPath id='a-clip0'/><g data-id='b'/>");
+/// assert_eq!(ids, vec!["b".to_string()]);
+/// ```
+pub fn rewrite_ids_to_data_ids_except<'a>(
+    s: &'a str,
+    keep: &[String],
+) -> (Cow<'a, str>, Vec<String>) {
+    if !s.contains("id=") {
+        return (Cow::Borrowed(s), Vec::new());
+    }
     let bytes = s.as_bytes();
     let mut out = String::with_capacity(s.len());
     let mut data_ids = Vec::new();
@@ -62,6 +258,13 @@ pub fn rewrite_ids_to_data_ids(s: &str) -> (String, Vec<String>) {
                             // Extract value
                             if let Ok(val) =
                                 std::str::from_utf8(&bytes[start..j]).map(|v| v.to_string())
+                                && keep.contains(&val)
+                            {
+                                out.push_str(&s[i..=j]);
+                                i = j + 1;
+                                break;
+                            } else if let Ok(val) =
+                                std::str::from_utf8(&bytes[start..j]).map(|v| v.to_string())
                             {
                                 // Sanitize and disambiguate
                                 let mut sanitized = crate::svg::sanitize::sanitize_id(&val);
@@ -106,7 +309,10 @@ pub fn rewrite_ids_to_data_ids(s: &str) -> (String, Vec<String>) {
         out.push(bytes[i] as char);
         i += 1;
     }
-    (out, data_ids)
+    if data_ids.is_empty() {
+        return (Cow::Borrowed(s), data_ids);
+    }
+    (Cow::Owned(out), data_ids)
 }
 
 #[cfg(test)]
@@ -153,6 +359,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn untouched_content_is_borrowed() {
+        let input = "<clipPath id=\"c\"/><g data-id=\"x\"/>";
+        assert!(matches!(scope_def_ids(input, "a").0, Cow::Borrowed(_)));
+        let keep = ["c".to_string()];
+        assert!(matches!(
+            rewrite_ids_to_data_ids_except(input, &keep),
+            (Cow::Borrowed(_), ids) if ids.is_empty()
+        ));
+    }
+
+    #[test]
+    fn scopes_defs_referenced_with_quoted_url_and_single_quoted_href() {
+        let input = "<defs><linearGradient id=\"g\"/><filter id='p'/></defs>\
+                     <rect fill=\"url('#g')\"/><filter href='#p'/>";
+        let (out, ids) = scope_def_ids(input, "icon");
+        assert_eq!(ids, ["icon-g", "icon-p"]);
+        assert!(out.contains("url('#icon-g')"), "{out}");
+        assert!(out.contains("href='#icon-p'"), "{out}");
+        assert!(references_id("fill=\"url(\"#g\")\"", "g"));
+    }
+
+    #[test]
+    fn scoped_ids_are_not_renamed_twice() {
+        // `c` becomes `a-c`, which is also an id of the icon: each reference
+        // must follow only its own definition's rename.
+        let input = "<clipPath id=\"c\"/><clipPath id=\"a-c\"/>\
+                     <g clip-path=\"url(#c)\"/><g clip-path=\"url(#a-c)\"/>";
+        let (out, ids) = scope_def_ids(input, "a");
+        assert_eq!(ids, ["a-c", "a-a-c"]);
+        assert_eq!(
+            out,
+            "<clipPath id=\"a-c\"/><clipPath id=\"a-a-c\"/>\
+             <g clip-path=\"url(#a-c)\"/><g clip-path=\"url(#a-a-c)\"/>"
+        );
+    }
+
     #[test]
     fn rewrite_ids_simple() {
         let input = "<g id=\"a\"/><g id='a'/><g id=\"b\"/>";
@@ -0,0 +1,578 @@
+//! Input discovery shared by one-shot builds and both watch backends.
+
+use super::{RunOpts, naming, sanitize};
+use crate::error::{AppError, Warning, WarningKind};
+use std::path::{Path, PathBuf};
+
+/// Name of the ignore file read from the input directory.
+pub(crate) const IGNORE_FILE: &str = ".spriteignore";
+
+/// List the `.svg` files (extension matched case-insensitively, see `is_svg`)
+/// in `directory`, sorted by path. Subdirectories are descended into only with
+/// `opts.recursive`. Builds and both watch backends share this listing.
+///
+/// Entries matched by a `.spriteignore` file (gitignore syntax, as implemented by
+/// the `ignore` crate) are excluded. With `opts.use_gitignore`, `.gitignore`
+/// files are honored as well, even outside a git repository.
+///
+/// Symlinks are skipped unless `opts.follow_symlinks` is set, in which case they
+/// are resolved to their targets; broken links and loops are reported as
+/// warnings and skipped.
+pub(crate) fn list_svg_files(directory: &str, opts: &RunOpts) -> Result<Vec<PathBuf>, AppError> {
+    walk_svg_files(directory, opts, true)
+}
+
+/// `list_svg_files`, logging skipped symlinks and walk errors only when
+/// `report` is set.
+fn walk_svg_files(directory: &str, opts: &RunOpts, report: bool) -> Result<Vec<PathBuf>, AppError> {
+    std::fs::read_dir(directory).map_err(|e| AppError::ReadDir {
+        path: directory.to_string(),
+        source: e,
+    })?;
+
+    let mut builder = ignore::WalkBuilder::new(directory);
+    builder
+        .standard_filters(false)
+        .max_depth(if opts.recursive { None } else { Some(1) })
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .git_ignore(opts.use_gitignore)
+        .parents(opts.use_gitignore)
+        .require_git(false)
+        .follow_links(opts.follow_symlinks);
+
+    let mut paths: Vec<PathBuf> = builder
+        .build()
+        .filter_map(|res| match res {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                if report {
+                    report_walk_error(directory, &e);
+                }
+                None
+            }
+        })
+        .filter(|entry| entry.depth() > 0)
+        .filter(|entry| {
+            if entry.path_is_symlink() && !opts.follow_symlinks {
+                if report {
+                    tracing::warn!(path = %entry.path().display(), "Skipping symlink (use --follow-symlinks to include it)");
+                }
+                return false;
+            }
+            entry.file_type().is_some_and(|t| !t.is_dir())
+        })
+        .map(|entry| entry.into_path())
+        .filter(|p| is_svg(p))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// List the inputs of a build of `directory` into `file`: `list_svg_files`
+/// minus the output itself, any `--emit` artifact, and under `--chunk-by`
+/// any chunk sheet, matched by resolved path so that an unrelated input
+/// sharing the output's filename is kept.
+pub(crate) fn list_inputs(
+    directory: &str,
+    file: &str,
+    opts: &RunOpts,
+) -> Result<Vec<PathBuf>, AppError> {
+    let outputs = output_paths(file, opts);
+    let mut paths = list_svg_files(directory, opts)?;
+    paths.retain(|p| !super::shared_defs::is_defs_file(directory, p));
+    if !outputs.is_empty() {
+        paths.retain(|p| !outputs.contains(&resolve(p)));
+    }
+    if opts.chunk_by.is_some() && file != super::STDIO {
+        paths.retain(|p| !is_chunk_of(&resolve(p), &resolve(Path::new(file))));
+    }
+    Ok(paths)
+}
+
+/// The inputs of a build into `file` given by `--files-from`: the listed SVG
+/// files that exist, sorted and without repeats, minus outputs and the
+/// `_defs.svg` of `directory`. Listed files that no longer exist (deleted in
+/// the diff the list came from) are skipped.
+pub(crate) fn list_files(
+    directory: &str,
+    file: &str,
+    files: &[PathBuf],
+    opts: &RunOpts,
+) -> Vec<PathBuf> {
+    let outputs = output_paths(file, opts);
+    let mut paths: Vec<PathBuf> = files
+        .iter()
+        .filter(|p| is_svg(p) && !super::shared_defs::is_defs_file(directory, p))
+        .filter(|p| {
+            let exists = p.is_file();
+            if !exists {
+                tracing::debug!(path = %p.display(), "Skipping listed file that does not exist");
+            }
+            exists
+        })
+        .filter(|p| outputs.is_empty() || !outputs.contains(&resolve(p)))
+        .cloned()
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Warn when the output (or an emitted artifact) would be picked up as an
+/// input; `list_inputs` excludes it, but it usually signals a misconfiguration.
+/// Skipped entries are left for the build's own listing to log, so each is
+/// reported once.
+pub(crate) fn warn_if_output_in_inputs(directory: &str, file: &str, opts: &RunOpts) {
+    let Ok(paths) = walk_svg_files(directory, opts, false) else {
+        return;
+    };
+    let outputs = output_paths(file, opts);
+    for path in paths.iter().filter(|p| outputs.contains(&resolve(p))) {
+        tracing::warn!(
+            directory,
+            path = %path.display(),
+            "Output is inside the input directory; excluding it from inputs"
+        );
+    }
+}
+
+/// Resolved paths of everything a build writes, excluding stdout.
+fn output_paths(file: &str, opts: &RunOpts) -> Vec<PathBuf> {
+    std::iter::once(file)
+        .chain(opts.emit.iter().map(|t| t.path.as_str()))
+        .filter(|p| *p != super::STDIO)
+        .map(|p| resolve(Path::new(p)))
+        .collect()
+}
+
+/// Whether the resolved `path` is named like a chunk sheet of the resolved
+/// sprite `file` (`sprite-<chunk>.svg` beside `sprite.svg`).
+fn is_chunk_of(path: &Path, file: &Path) -> bool {
+    let (Some(stem), Some(name)) = (
+        file.file_stem().and_then(|s| s.to_str()),
+        path.file_name().and_then(|s| s.to_str()),
+    ) else {
+        return false;
+    };
+    path.parent() == file.parent()
+        && path.extension() == file.extension()
+        && name.starts_with(&format!("{stem}-"))
+}
+
+/// Absolute, symlink-free form of `path`. The file itself need not exist yet,
+/// only its parent directory.
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(p) = path.canonicalize() {
+        return p;
+    }
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Whether `path` names an SVG input: its extension is `svg` in any case
+/// (`icon.svg`, `Icon.SVG`, `logo.Svg`).
+pub(crate) fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+}
+
+/// `name` without a trailing `.svg` extension, matched case-insensitively.
+fn strip_svg_extension(name: &str) -> &str {
+    match name
+        .len()
+        .checked_sub(4)
+        .and_then(|at| name.split_at_checked(at))
+    {
+        Some((stem, ext)) if ext.eq_ignore_ascii_case(".svg") => stem,
+        _ => name,
+    }
+}
+
+/// Derive the icon name (pattern id) from an input path: the filename without
+/// `.svg`, prefixed by its subdirectories below `root` (joined with
+/// `opts.id_separator`), each part cased according to `opts.id_case`.
+///
+/// Parts that are not valid UTF-8 are converted lossily and sanitized, so such
+/// files still build (see `filename_warning`).
+pub(crate) fn icon_name(root: &Path, path: &Path, opts: &RunOpts) -> Option<String> {
+    let file_name = path.file_name()?;
+    let mut parts = Vec::new();
+    if let Some(parent) = path.parent().and_then(|p| p.strip_prefix(root).ok()) {
+        for dir in parent.components() {
+            parts.push(naming::apply_case(
+                &lossy_part(dir.as_os_str()),
+                opts.id_case,
+            ));
+        }
+    }
+    let stem = match file_name.to_str() {
+        Some(name) => strip_svg_extension(name).to_string(),
+        None => lossy_part(path.file_stem()?),
+    };
+    parts.push(naming::apply_case(&stem, opts.id_case));
+    let name = parts.join(&opts.id_separator);
+    (!name.is_empty()).then_some(name)
+}
+
+/// A path component as UTF-8; invalid sequences become U+FFFD, which
+/// sanitization then turns into `-`.
+fn lossy_part(part: &std::ffi::OsStr) -> String {
+    match part.to_str() {
+        Some(s) => s.to_string(),
+        None => sanitize::sanitize_id(&part.to_string_lossy()),
+    }
+}
+
+/// Warning for an input whose path below `root` is not valid UTF-8 and so has
+/// a lossily derived id.
+pub(crate) fn filename_warning(root: &Path, path: &Path) -> Option<Warning> {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.to_str().is_none().then(|| {
+        Warning::new(
+            WarningKind::LossyFilename,
+            path.display().to_string(),
+            format!(
+                "Filename '{}' is not valid UTF-8; id derived lossily",
+                relative.display()
+            ),
+        )
+    })
+}
+
+/// Keep only the inputs named by `opts.only`, in listing order.
+///
+/// Each `--only` entry is either an icon name or the path of an existing file
+/// listing one name per line (blank lines and `#` comments are ignored).
+/// Requested names without a matching input, either a file or one of the
+/// `collected` collection and package icons, fail with `UnknownIcons`.
+pub(crate) fn select_only(
+    root: &Path,
+    paths: Vec<PathBuf>,
+    collected: &[super::CacheEntry],
+    opts: &RunOpts,
+) -> Result<Vec<PathBuf>, AppError> {
+    if opts.only.is_empty() {
+        return Ok(paths);
+    }
+    let wanted = expand_only(&opts.only)?;
+    let selected: Vec<PathBuf> = paths
+        .into_iter()
+        .filter(|p| icon_name(root, p, opts).is_some_and(|n| wanted.contains(&n)))
+        .collect();
+    let found: std::collections::BTreeSet<String> = selected
+        .iter()
+        .filter_map(|p| icon_name(root, p, opts))
+        .chain(collected.iter().map(|e| e.name.clone()))
+        .collect();
+    let missing: Vec<String> = wanted.difference(&found).cloned().collect();
+    if !missing.is_empty() {
+        return Err(AppError::UnknownIcons { names: missing });
+    }
+    Ok(selected)
+}
+
+/// Expand `--only` entries into a set of icon names, reading list files.
+pub(crate) fn expand_only(
+    entries: &[String],
+) -> Result<std::collections::BTreeSet<String>, AppError> {
+    let mut names = std::collections::BTreeSet::new();
+    for entry in entries {
+        let path = std::path::Path::new(entry);
+        if !path.is_file() {
+            names.insert(entry.trim().to_string());
+            continue;
+        }
+        let content = std::fs::read_to_string(path).map_err(|e| AppError::ReadFile {
+            path: entry.clone(),
+            source: e,
+        })?;
+        names.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(str::to_string),
+        );
+    }
+    Ok(names)
+}
+
+/// Log a walk error, naming broken symlinks and symlink loops explicitly.
+fn report_walk_error(directory: &str, e: &ignore::Error) {
+    if is_loop(e) {
+        tracing::warn!(directory, error = %e, "Skipping symlink loop");
+    } else if e
+        .io_error()
+        .is_some_and(|io| io.kind() == std::io::ErrorKind::NotFound)
+    {
+        tracing::warn!(directory, error = %e, "Skipping broken symlink");
+    } else {
+        tracing::warn!(directory, error = %e, "Skipping unreadable entry");
+    }
+}
+
+/// Return whether a walk error is (or wraps) a directory loop.
+fn is_loop(e: &ignore::Error) -> bool {
+    match e {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => is_loop(err),
+        _ => false,
+    }
+}
+
+/// Read metadata for a listed input, following symlinks only when enabled so
+/// change detection matches what the build actually reads.
+pub(crate) fn metadata(
+    path: &std::path::Path,
+    opts: &RunOpts,
+) -> std::io::Result<std::fs::Metadata> {
+    if opts.follow_symlinks {
+        std::fs::metadata(path)
+    } else {
+        std::fs::symlink_metadata(path)
+    }
+}
+
+/// Paths of the ignore files that influence `list_svg_files` for `directory`,
+/// including those in the subdirectories of the listed `paths`.
+pub(crate) fn ignore_files(directory: &str, paths: &[PathBuf], opts: &RunOpts) -> Vec<PathBuf> {
+    let mut dirs: Vec<&Path> = vec![Path::new(directory)];
+    if opts.recursive {
+        dirs.extend(paths.iter().filter_map(|p| p.parent()));
+        dirs.sort();
+        dirs.dedup();
+    }
+    let mut files = Vec::new();
+    for dir in dirs {
+        files.push(dir.join(IGNORE_FILE));
+        if opts.use_gitignore {
+            files.push(dir.join(".gitignore"));
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn tmp(prefix: &str) -> PathBuf {
+        let p = std::env::temp_dir().join(format!("{prefix}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&p);
+        fs::create_dir_all(&p).unwrap();
+        p
+    }
+
+    #[test]
+    fn spriteignore_excludes_matching_files() {
+        let dir = tmp("svg_inputs_ignore");
+        fs::write(dir.join("a.svg"), "<svg></svg>").unwrap();
+        fs::write(dir.join("a.svg~"), "backup").unwrap();
+        fs::write(dir.join("draft-b.svg"), "<svg></svg>").unwrap();
+        fs::write(dir.join(IGNORE_FILE), "draft-*\n").unwrap();
+        let paths = list_svg_files(dir.to_str().unwrap(), &RunOpts::default()).unwrap();
+        assert_eq!(paths, vec![dir.join("a.svg")]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn chunk_sheets_are_not_inputs() {
+        let dir = tmp("svg_inputs_chunks");
+        for name in ["a.svg", "sprite-social.svg", "sprite.svg", "other-1.svg"] {
+            fs::write(dir.join(name), "<svg></svg>").unwrap();
+        }
+        let opts = RunOpts {
+            chunk_by: Some(super::super::chunk::ChunkBy::Dir),
+            ..Default::default()
+        };
+        let file = dir.join("sprite.svg");
+        let paths = list_inputs(dir.to_str().unwrap(), file.to_str().unwrap(), &opts).unwrap();
+        assert_eq!(paths, vec![dir.join("a.svg"), dir.join("other-1.svg")]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn listed_files_skip_missing_outputs_and_other_files() {
+        let dir = tmp("svg_inputs_listed");
+        for name in ["b.svg", "a.svg", "sprite.svg", "_defs.svg", "notes.txt"] {
+            fs::write(dir.join(name), "<svg></svg>").unwrap();
+        }
+        let listed: Vec<PathBuf> = [
+            "b.svg",
+            "gone.svg",
+            "a.svg",
+            "b.svg",
+            "sprite.svg",
+            "_defs.svg",
+            "notes.txt",
+        ]
+        .iter()
+        .map(|name| dir.join(name))
+        .collect();
+        let d = dir.to_str().unwrap();
+        let file = dir.join("sprite.svg");
+        let paths = list_files(d, file.to_str().unwrap(), &listed, &RunOpts::default());
+        assert_eq!(paths, vec![dir.join("a.svg"), dir.join("b.svg")]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_filenames_get_sanitized_ids_and_a_warning() {
+        use std::os::unix::ffi::OsStrExt;
+        let root = Path::new("icons");
+        let path = root.join(std::ffi::OsStr::from_bytes(b"bad\xffname.svg"));
+        let opts = RunOpts::default();
+        assert_eq!(icon_name(root, &path, &opts).as_deref(), Some("bad-name"));
+        let warning = filename_warning(root, &path).unwrap();
+        assert_eq!(warning.kind, WarningKind::LossyFilename);
+        assert!(warning.message.contains("not valid UTF-8"));
+        assert_eq!(filename_warning(root, &root.join("ok.svg")), None);
+    }
+
+    #[test]
+    fn list_inputs_excludes_only_the_resolved_output() {
+        let dir = tmp("svg_inputs_output");
+        fs::create_dir_all(dir.join("dist")).unwrap();
+        fs::write(dir.join("a.svg"), "<svg></svg>").unwrap();
+        fs::write(dir.join("sprite.svg"), "<svg></svg>").unwrap();
+        let d = dir.to_str().unwrap();
+        let opts = RunOpts::default();
+
+        let inside = dir.join("sprite.svg");
+        let paths = list_inputs(d, inside.to_str().unwrap(), &opts).unwrap();
+        assert_eq!(paths, vec![dir.join("a.svg")]);
+
+        // Same filename elsewhere: the input named `sprite` is kept.
+        let elsewhere = dir.join("dist/sprite.svg");
+        let paths = list_inputs(d, elsewhere.to_str().unwrap(), &opts).unwrap();
+        assert_eq!(paths, vec![dir.join("a.svg"), dir.join("sprite.svg")]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn svg_extension_matches_case_insensitively() {
+        let dir = tmp("svg_inputs_ext_case");
+        for name in ["a.svg", "Icon.SVG", "logo.Svg", "notes.txt", "svg"] {
+            fs::write(dir.join(name), "<svg></svg>").unwrap();
+        }
+        let paths = list_svg_files(dir.to_str().unwrap(), &RunOpts::default()).unwrap();
+        let names: Vec<String> = paths
+            .iter()
+            .filter_map(|p| icon_name(&dir, p, &RunOpts::default()))
+            .collect();
+        assert_eq!(names, vec!["Icon", "a", "logo"]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn recursive_listing_prefixes_ids_with_subdirectories() {
+        let dir = tmp("svg_inputs_recursive");
+        fs::create_dir_all(dir.join("social/Brand")).unwrap();
+        fs::write(dir.join("home.svg"), "<svg></svg>").unwrap();
+        fs::write(dir.join("social/Brand/twitter.svg"), "<svg></svg>").unwrap();
+        let d = dir.to_str().unwrap();
+        assert_eq!(list_svg_files(d, &RunOpts::default()).unwrap().len(), 1);
+
+        let opts = RunOpts {
+            recursive: true,
+            id_case: naming::IdCase::Kebab,
+            ..Default::default()
+        };
+        let paths = list_svg_files(d, &opts).unwrap();
+        let names: Vec<String> = paths
+            .iter()
+            .map(|p| icon_name(&dir, p, &opts).unwrap())
+            .collect();
+        assert_eq!(names, vec!["home", "social-brand-twitter"]);
+
+        let opts = RunOpts {
+            id_separator: ".".into(),
+            ..opts
+        };
+        assert_eq!(
+            icon_name(&dir, &paths[1], &opts).unwrap(),
+            "social.brand.twitter"
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_followed_only_when_enabled() {
+        use std::os::unix::fs::symlink;
+        let dir = tmp("svg_inputs_symlinks");
+        let target = dir.join("target");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("real.svg"), "<svg></svg>").unwrap();
+        symlink(target.join("real.svg"), dir.join("linked.svg")).unwrap();
+        symlink(dir.join("missing.svg"), dir.join("broken.svg")).unwrap();
+        symlink(dir.join("loop-b.svg"), dir.join("loop-a.svg")).unwrap();
+        symlink(dir.join("loop-a.svg"), dir.join("loop-b.svg")).unwrap();
+        let d = dir.to_str().unwrap();
+
+        assert!(list_svg_files(d, &RunOpts::default()).unwrap().is_empty());
+
+        let opts = RunOpts {
+            follow_symlinks: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            list_svg_files(d, &opts).unwrap(),
+            vec![dir.join("linked.svg")]
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn select_only_reads_names_and_list_files() {
+        let dir = tmp("svg_inputs_only");
+        for n in ["a", "b", "c"] {
+            fs::write(dir.join(format!("{n}.svg")), "<svg></svg>").unwrap();
+        }
+        let list = dir.join("icons.txt");
+        fs::write(&list, "# used icons\nc\n\n").unwrap();
+        let paths = list_svg_files(dir.to_str().unwrap(), &RunOpts::default()).unwrap();
+        let opts = RunOpts {
+            only: vec!["a".into(), list.display().to_string()],
+            ..Default::default()
+        };
+        let selected = select_only(&dir, paths.clone(), &[], &opts).unwrap();
+        assert_eq!(selected, vec![dir.join("a.svg"), dir.join("c.svg")]);
+
+        let opts = RunOpts {
+            only: vec!["a".into(), "zzz".into()],
+            ..Default::default()
+        };
+        match select_only(&dir, paths, &[], &opts) {
+            Err(AppError::UnknownIcons { names }) => assert_eq!(names, vec!["zzz".to_string()]),
+            other => panic!("unexpected: {other:?}"),
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn gitignore_only_applies_when_enabled() {
+        let dir = tmp("svg_inputs_gitignore");
+        fs::write(dir.join("a.svg"), "<svg></svg>").unwrap();
+        fs::write(dir.join("b.svg"), "<svg></svg>").unwrap();
+        fs::write(dir.join(".gitignore"), "b.svg\n").unwrap();
+        let d = dir.to_str().unwrap();
+        assert_eq!(list_svg_files(d, &RunOpts::default()).unwrap().len(), 2);
+        let opts = RunOpts {
+            use_gitignore: true,
+            ..Default::default()
+        };
+        assert_eq!(list_svg_files(d, &opts).unwrap(), vec![dir.join("a.svg")]);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,179 @@
+//! `--lock`: a lockfile beside the sprite recording each icon's content hash,
+//! so edits to a shared sprite's icons have to be accepted explicitly with
+//! `--update-lock` instead of slipping through review.
+
+use super::{CacheEntry, RunOpts, source};
+use crate::error::AppError;
+use std::path::{Path, PathBuf};
+
+/// Header line of the lockfile.
+const HEADER: &str = "# Generated by svg_sheet. Accept icon changes with --update-lock.";
+
+/// The lockfile of the sprite `file`: `<stem>.lock` beside it
+/// (`dist/sprite.svg` gives `dist/sprite.lock`), or `sprite.lock` for stdout.
+pub(crate) fn lock_path(file: &str) -> PathBuf {
+    let path = Path::new(file);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|s| *s != super::STDIO)
+        .unwrap_or("sprite");
+    path.with_file_name(format!("{stem}.lock"))
+}
+
+/// Hash of an icon's emitted content: root attributes, shared definitions,
+/// and children, so edits to the source that change nothing in the sprite
+/// (formatting, comments) do not count.
+pub(crate) fn content_hash(entry: &CacheEntry) -> String {
+    let mut content = String::new();
+    for (k, v) in &entry.out_attrs {
+        content.push_str(&format!(" {k}=\"{v}\""));
+    }
+    content.push('>');
+    for (_, markup) in &entry.shared_defs {
+        content.push_str(markup);
+    }
+    content.push_str(&entry.children);
+    source::sha256_hex(content.as_bytes())
+}
+
+/// The `(name, hash)` lines of a lockfile, skipping comments.
+fn parse(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let (hash, name) = line.split_once("  ")?;
+            Some((name.to_string(), hash.to_string()))
+        })
+        .collect()
+}
+
+/// Lockfile contents for `icons`, sorted by name.
+fn render(icons: &mut [(String, String)]) -> String {
+    icons.sort();
+    let mut out = format!("{HEADER}\n");
+    for (name, hash) in icons.iter() {
+        out.push_str(&format!("{hash}  {name}\n"));
+    }
+    out
+}
+
+/// Compare `entries` with the lockfile of `file`. Fails with the icons whose
+/// content differs from the recorded hash, unless `--update-lock` accepts
+/// them; otherwise (re)writes the lockfile with the current icons. Icons
+/// left out by `--only` keep their recorded hashes. Nothing is written for
+/// `--dry-run` or `--check`.
+pub(crate) fn verify(file: &str, entries: &[&CacheEntry], opts: &RunOpts) -> Result<(), AppError> {
+    let path = lock_path(file);
+    let origin = path.display().to_string();
+    let existing = match std::fs::read_to_string(&path) {
+        Ok(text) => Some(text),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(AppError::ReadFile {
+                path: origin,
+                source: e,
+            });
+        }
+    };
+    let recorded = existing.as_deref().map(parse).unwrap_or_default();
+    let mut icons: Vec<(String, String)> = entries
+        .iter()
+        .map(|e| (e.name.clone(), content_hash(e)))
+        .collect();
+    let changed: Vec<String> = icons
+        .iter()
+        .filter(|(name, hash)| recorded.iter().any(|(n, h)| n == name && h != hash))
+        .map(|(name, _)| name.clone())
+        .collect();
+    if !changed.is_empty() && !opts.update_lock {
+        return Err(AppError::LockMismatch {
+            path: origin,
+            names: changed,
+        });
+    }
+    for name in &changed {
+        tracing::info!(icon = %name, "Accepted icon change into the lockfile");
+    }
+    if !opts.only.is_empty() {
+        icons.extend(
+            recorded
+                .into_iter()
+                .filter(|(name, _)| !entries.iter().any(|e| &e.name == name)),
+        );
+    }
+    let rendered = render(&mut icons);
+    if existing.as_deref() == Some(rendered.as_str()) || opts.dry_run || opts.check {
+        return Ok(());
+    }
+    std::fs::write(&path, rendered).map_err(|e| AppError::WriteFile {
+        path: origin,
+        source: e,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn icon(name: &str, children: &str) -> CacheEntry {
+        CacheEntry {
+            mtime: std::time::UNIX_EPOCH,
+            len: 0,
+            name: name.into(),
+            out_attrs: vec![("viewBox".into(), "0 0 24 24".into())],
+            children: children.into(),
+            child_ids: vec![],
+            path_str: format!("{name}.svg"),
+            warnings: vec![],
+            hash: String::new(),
+            licenses: vec![],
+            shared_defs: vec![],
+            colors_remapped: 0,
+            attributes_stripped: vec![],
+            theme_vars: vec![],
+            overrides: None,
+        }
+    }
+
+    #[test]
+    fn lock_path_sits_beside_the_sprite() {
+        assert_eq!(lock_path("dist/icons.svg"), Path::new("dist/icons.lock"));
+        assert_eq!(lock_path("-"), Path::new("sprite.lock"));
+    }
+
+    #[test]
+    fn changed_icons_need_update_lock() {
+        let dir = std::env::temp_dir().join("svg_lock_verify");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("sprite.svg").display().to_string();
+        let opts = RunOpts::default();
+        let (a, b) = (icon("a", "<g/>"), icon("b", "<path/>"));
+        verify(&file, &[&b, &a], &opts).unwrap();
+        let lock = fs::read_to_string(lock_path(&file)).unwrap();
+        assert_eq!(
+            parse(&lock)
+                .iter()
+                .map(|(n, _)| n.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+
+        // New icons are recorded without complaint; edits are not.
+        let edited = icon("a", "<g fill=\"red\"/>");
+        let c = icon("c", "<circle/>");
+        let err = verify(&file, &[&edited, &b, &c], &opts).unwrap_err();
+        assert_eq!(err.names(), ["a".to_string()]);
+        assert_eq!(fs::read_to_string(lock_path(&file)).unwrap(), lock);
+
+        let update = RunOpts {
+            update_lock: true,
+            ..Default::default()
+        };
+        verify(&file, &[&edited, &b, &c], &update).unwrap();
+        verify(&file, &[&edited, &b, &c], &opts).unwrap();
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,219 @@
+//! Minimal tokenizer for icon markup, for passes that rewrite individual tags
+//! (`--flatten-transforms`, `--compact-paths`) without a full XML parser.
+
+use std::borrow::Cow;
+
+/// One lexical piece of the markup.
+pub(crate) enum Token<'a> {
+    /// Text, comments, CDATA, processing instructions
+    Text(&'a str),
+    /// Start tag (possibly self-closing)
+    Open {
+        name: &'a str,
+        raw: &'a str,
+        empty: bool,
+    },
+    /// End tag
+    Close(&'a str),
+}
+
+impl<'a> Token<'a> {
+    /// The token's source text.
+    pub(crate) fn raw(&self) -> &'a str {
+        match self {
+            Token::Text(raw) | Token::Close(raw) | Token::Open { raw, .. } => raw,
+        }
+    }
+}
+
+/// Write a start tag from its name and `(name, value, quote)` attributes.
+pub(crate) fn push_tag(out: &mut String, name: &str, attrs: &[(&str, &str, char)], empty: bool) {
+    out.push('<');
+    out.push_str(name);
+    for (k, v, q) in attrs {
+        out.push_str(&format!(" {k}={q}{v}{q}"));
+    }
+    out.push_str(if empty { "/>" } else { ">" });
+}
+
+/// Attributes of a start tag as `(name, value, quote)`; `None` when they do
+/// not all parse, so the tag is never rewritten lossily.
+pub(crate) fn attributes(tag: &str) -> Option<Vec<(&str, &str, char)>> {
+    let body = tag.trim_start_matches('<').trim_end_matches('>');
+    let body = body.strip_suffix('/').unwrap_or(body);
+    let mut rest = body.trim_start_matches(|c: char| !c.is_ascii_whitespace());
+    let mut attrs = Vec::new();
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim();
+        let after = rest[eq + 1..].trim_start();
+        let q = after.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let len = after[1..].find(q)?;
+        attrs.push((key, &after[1..1 + len], q));
+        rest = &after[len + 2..];
+    }
+    rest.trim().is_empty().then_some(attrs)
+}
+
+/// Split markup into tokens; `None` if a tag is unterminated.
+pub(crate) fn tokenize(s: &str) -> Option<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut from = 0;
+    while from < s.len() {
+        let token = next_token(&s[from..])?;
+        from += token.raw().len();
+        tokens.push(token);
+    }
+    Some(tokens)
+}
+
+/// Length of the element `s` starts with, through the end tag that closes
+/// it, skipping comments, CDATA and nested elements of the same name;
+/// `None` when it is not closed or a tag is unterminated.
+pub(crate) fn element_len(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut from = 0;
+    while from < s.len() {
+        let token = next_token(&s[from..])?;
+        from += token.raw().len();
+        match token {
+            Token::Open { empty: false, .. } => depth += 1,
+            Token::Open { empty: true, .. } if depth == 0 => return Some(from),
+            Token::Close(_) => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(from);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The token `s` starts with; `None` if it is an unterminated tag.
+fn next_token(s: &str) -> Option<Token<'_>> {
+    let lt = s.find('<').unwrap_or(s.len());
+    if lt > 0 {
+        return Some(Token::Text(&s[..lt]));
+    }
+    if let Some((_, close)) = [("<!--", "-->"), ("<![CDATA[", "]]>"), ("<?", "?>")]
+        .into_iter()
+        .find(|(open, _)| s.starts_with(open))
+    {
+        let len = s.find(close)? + close.len();
+        return Some(Token::Text(&s[..len]));
+    }
+    let raw = &s[..super::raster::tag_end(s)? + 1];
+    Some(if raw.starts_with("</") {
+        Token::Close(raw)
+    } else if raw.starts_with("<!") {
+        Token::Text(raw)
+    } else {
+        let name_end = raw[1..]
+            .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+            .map_or(raw.len(), |n| n + 1);
+        Token::Open {
+            name: &raw[1..name_end],
+            raw,
+            empty: raw.ends_with("/>"),
+        }
+    })
+}
+
+/// Rewrite the `d` attribute of every `<path>` with `f`, leaving paths where
+/// `f` returns `None` untouched. Malformed markup is returned as is.
+pub(crate) fn rewrite_path_data(
+    children: &str,
+    f: impl Fn(&str) -> Option<String>,
+) -> Cow<'_, str> {
+    let Some(tokens) = tokenize(children) else {
+        return Cow::Borrowed(children);
+    };
+    let mut out = String::with_capacity(children.len());
+    let mut changed = false;
+    for token in &tokens {
+        let raw = match token {
+            Token::Text(raw) | Token::Close(raw) => raw,
+            Token::Open { name, raw, empty } => {
+                let rewritten = (*name == "path")
+                    .then(|| attributes(raw))
+                    .flatten()
+                    .and_then(|attrs| {
+                        let (_, d, _) = attrs.iter().find(|(k, ..)| *k == "d")?;
+                        let new_d = f(d).filter(|new_d| new_d != d)?;
+                        let attrs: Vec<_> = attrs
+                            .iter()
+                            .map(|&(k, v, q)| {
+                                if k == "d" {
+                                    (k, new_d.as_str(), '"')
+                                } else {
+                                    (k, v, q)
+                                }
+                            })
+                            .collect();
+                        let mut tag = String::new();
+                        push_tag(&mut tag, name, &attrs, *empty);
+                        Some(tag)
+                    });
+                if let Some(tag) = rewritten {
+                    out.push_str(&tag);
+                    changed = true;
+                    continue;
+                }
+                raw
+            }
+        };
+        out.push_str(raw);
+    }
+    if changed {
+        Cow::Owned(out)
+    } else {
+        Cow::Borrowed(children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_tags_comments_and_text() {
+        let tokens = tokenize("<g a='1'><!-- <x> -->t<path/></g>").unwrap();
+        let kinds: Vec<&str> = tokens
+            .iter()
+            .map(|t| match t {
+                Token::Text(_) => "text",
+                Token::Open { empty: true, .. } => "empty",
+                Token::Open { .. } => "open",
+                Token::Close(_) => "close",
+            })
+            .collect();
+        assert_eq!(kinds, ["open", "text", "text", "empty", "close"]);
+        assert!(tokenize("<g a='>").is_none());
+        assert_eq!(
+            attributes("<g a='1' b=\"x y\"/>"),
+            Some(vec![("a", "1", '\''), ("b", "x y", '"')])
+        );
+        assert_eq!(attributes("<g a=1>"), None);
+    }
+
+    #[test]
+    fn element_len_skips_comments_and_nested_elements() {
+        let input = "<svg><!-- </svg> --><svg><g/></svg></svg><svg>";
+        assert_eq!(element_len(input), Some(input.len() - 5));
+        assert_eq!(element_len("<path/>tail"), Some(7));
+        assert_eq!(element_len("<svg><g></svg>"), None);
+        assert_eq!(element_len("<svg><g a='></svg>"), None);
+    }
+
+    #[test]
+    fn rewrites_only_path_data() {
+        let input = "<path d='a' fill='red'/><g d='a'/><path d='b'/>";
+        let out = rewrite_path_data(input, |d| (d == "a").then(|| "z".to_string()));
+        assert_eq!(out, "<path d=\"z\" fill='red'/><g d='a'/><path d='b'/>");
+        assert!(matches!(
+            rewrite_path_data(input, |_| None),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+//! Namespace declarations of icon roots, declared once on the sprite's root
+//! instead of on every `<pattern>`.
+
+use super::markup::{self, Token};
+use std::borrow::Cow;
+
+/// The SVG namespace every generated sprite declares on its root.
+pub(crate) const SVG_NS: &str = "http://www.w3.org/2000/svg";
+
+/// Whether `prefix` qualifies an attribute of the icon's root or an element
+/// or attribute of its markup. Markup that does not tokenize counts as using
+/// it, so its declaration is kept.
+pub(crate) fn uses(prefix: &str, attrs: &[(String, String)], children: &str) -> bool {
+    let qualified = format!("{prefix}:");
+    if attrs.iter().any(|(k, _)| k.starts_with(&qualified)) {
+        return true;
+    }
+    if !children.contains(&qualified) {
+        return false;
+    }
+    let Some(tokens) = markup::tokenize(children) else {
+        return true;
+    };
+    tokens.iter().any(|token| match token {
+        Token::Open { name, raw, .. } => {
+            name.starts_with(&qualified)
+                || markup::attributes(raw)
+                    .is_none_or(|attrs| attrs.iter().any(|(k, ..)| k.starts_with(&qualified)))
+        }
+        _ => false,
+    })
+}
+
+/// The prefixed declarations (`xmlns:xlink`, ...) the icons use, each once
+/// as first declared, to put on the sprite's root. Unused ones are left out.
+pub(crate) fn hoist<'a, I>(icons: I) -> Vec<(String, String)>
+where
+    I: IntoIterator<Item = (&'a [(String, String)], &'a str)>,
+{
+    let mut hoisted: Vec<(String, String)> = Vec::new();
+    for (attrs, children) in icons {
+        for (k, v) in attrs {
+            if let Some(prefix) = k.strip_prefix("xmlns:")
+                && !hoisted.iter().any(|(h, _)| h == k)
+                && uses(prefix, attrs, children)
+            {
+                hoisted.push((k.clone(), v.clone()));
+            }
+        }
+    }
+    hoisted
+}
+
+/// An icon's root attributes for its `<pattern>`, without the namespace
+/// declarations the sprite's `root` attributes already make or the icon does
+/// not use. A prefix the root binds to another namespace stays declared.
+pub(crate) fn pattern_attrs<'a>(
+    attrs: &'a [(String, String)],
+    children: &str,
+    root: &[(String, String)],
+) -> Cow<'a, [(String, String)]> {
+    let redundant = |(k, v): &(String, String)| {
+        if k == "xmlns" {
+            return v == SVG_NS;
+        }
+        let Some(prefix) = k.strip_prefix("xmlns:") else {
+            return false;
+        };
+        root.iter().any(|(rk, rv)| rk == k && rv == v) || !uses(prefix, attrs, children)
+    };
+    if attrs.iter().any(redundant) {
+        Cow::Owned(attrs.iter().filter(|a| !redundant(a)).cloned().collect())
+    } else {
+        Cow::Borrowed(attrs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XLINK: &str = "http://www.w3.org/1999/xlink";
+
+    fn attrs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn hoists_used_declarations_once() {
+        let a = attrs(&[("xmlns", SVG_NS), ("xmlns:xlink", XLINK)]);
+        let b = attrs(&[("xmlns:xlink", XLINK), ("xmlns:sketch", "s")]);
+        let hoisted = hoist([
+            (a.as_slice(), "<use xlink:href=\"#p\"/>"),
+            (b.as_slice(), "<image\n xlink:href=\"x.png\"/>"),
+        ]);
+        assert_eq!(hoisted, attrs(&[("xmlns:xlink", XLINK)]));
+        assert!(hoist([(a.as_slice(), "<text>see xlink:href</text>")]).is_empty());
+    }
+
+    #[test]
+    fn drops_declarations_the_root_makes() {
+        let icon = attrs(&[
+            ("xmlns", SVG_NS),
+            ("xmlns:xlink", XLINK),
+            ("xmlns:sketch", "s"),
+            ("width", "24"),
+        ]);
+        let root = attrs(&[("xmlns:xlink", XLINK)]);
+        let kept = pattern_attrs(&icon, "<use xlink:href=\"#p\"/>", &root);
+        assert_eq!(kept.as_ref(), attrs(&[("width", "24")]));
+
+        let other = attrs(&[("xmlns:xlink", "urn:other")]);
+        let kept = pattern_attrs(&icon, "<use xlink:href=\"#p\"/>", &other);
+        assert_eq!(
+            kept.as_ref(),
+            attrs(&[("xmlns:xlink", XLINK), ("width", "24")])
+        );
+
+        let plain = attrs(&[("width", "24")]);
+        assert!(matches!(
+            pattern_attrs(&plain, "<g/>", &root),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+//! Pattern id naming schemes derived from input filenames.
+
+/// Casing applied to filename-derived pattern ids.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum IdCase {
+    /// Use the filename stem unchanged
+    #[default]
+    Keep,
+    /// `arrow-left`
+    Kebab,
+    /// `arrowLeft`
+    Camel,
+    /// `arrow_left`
+    Snake,
+}
+
+/// Apply `case` to a filename stem.
+///
+/// Words are split on `-`, `_`, `.`, whitespace, and lower-to-upper case
+/// boundaries (`HTMLParser` splits as `html`, `parser`).
+///
+/// Example:
+/// ```
+/// use svg_sheet::svg::naming::{IdCase, apply_case};
+/// assert_eq!(apply_case("ArrowLeft", IdCase::Kebab), "arrow-left");
+/// assert_eq!(apply_case("arrow_left", IdCase::Camel), "arrowLeft");
+/// assert_eq!(apply_case("arrow-left", IdCase::Snake), "arrow_left");
+/// assert_eq!(apply_case("Arrow Left", IdCase::Keep), "Arrow Left");
+/// ```
+pub fn apply_case(stem: &str, case: IdCase) -> String {
+    let words = split_words(stem);
+    match case {
+        IdCase::Keep => stem.to_string(),
+        IdCase::Kebab => words.join("-"),
+        IdCase::Snake => words.join("_"),
+        IdCase::Camel => {
+            let mut out = String::with_capacity(stem.len());
+            for (i, word) in words.iter().enumerate() {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) if i > 0 => {
+                        out.extend(first.to_uppercase());
+                        out.push_str(chars.as_str());
+                    }
+                    _ => out.push_str(word),
+                }
+            }
+            out
+        }
+    }
+}
+
+/// Split a stem into lowercase words.
+fn split_words(stem: &str) -> Vec<String> {
+    let chars: Vec<char> = stem.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if c.is_uppercase() && !current.is_empty() {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_lower) {
+                words.push(std::mem::take(&mut current));
+            }
+        }
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_mixed_conventions() {
+        assert_eq!(split_words("ArrowLeft"), vec!["arrow", "left"]);
+        assert_eq!(split_words("HTMLParser"), vec!["html", "parser"]);
+        assert_eq!(split_words("icon_2x-Large"), vec!["icon", "2x", "large"]);
+        assert_eq!(split_words("--a__b  "), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn applies_each_case() {
+        assert_eq!(apply_case("ArrowLeft", IdCase::Kebab), "arrow-left");
+        assert_eq!(apply_case("arrow-left", IdCase::Camel), "arrowLeft");
+        assert_eq!(apply_case("arrowLeft", IdCase::Snake), "arrow_left");
+        assert_eq!(apply_case("ArrowLeft", IdCase::Keep), "ArrowLeft");
+    }
+}
@@ -1,28 +1,119 @@
-//! Normalization utilities for lengths and viewBox values.
+//! Normalization utilities for lengths, viewBox, and preserveAspectRatio values.
+
+/// Pixels per unit of the absolute units, at the CSS and SVG 96 dpi.
+const UNITS: [(&str, f64); 6] = [
+    ("px", 1.0),
+    ("pt", 96.0 / 72.0),
+    ("pc", 16.0),
+    ("mm", 96.0 / 25.4),
+    ("cm", 96.0 / 2.54),
+    ("in", 96.0),
+];
+
+/// Pixels per `em`/`rem` unless `--em-size` says otherwise.
+pub const DEFAULT_EM_PX: f64 = 16.0;
+
+/// Decimal places kept in normalized dimensions and `viewBox` numbers unless
+/// `--precision` says otherwise.
+pub const DEFAULT_PRECISION: usize = 4;
 
 /// Parse and normalize positive length values for width/height.
-/// Accepts unitless or 'px' suffix. Returns normalized string (e.g., "24").
+/// Accepts unitless or 'px' values, and converts `pt`, `pc`, `mm`, `cm`,
+/// `in`, `em` and `rem` to pixels (with a 16px em). Returns normalized
+/// string (e.g., "24"), rounded to `DEFAULT_PRECISION` decimals.
 ///
 /// Example:
 /// ```
 /// assert_eq!(svg_sheet::svg::normalize::normalize_length("24px"), Some("24".into()));
+/// assert_eq!(svg_sheet::svg::normalize::normalize_length("18pt"), Some("24".into()));
 /// ```
 pub fn normalize_length(v: &str) -> Option<String> {
+    normalize_length_with(v, DEFAULT_EM_PX, DEFAULT_PRECISION)
+}
+
+/// `normalize_length`, converting `em` and `rem` as `em_px` pixels each and
+/// rounding to `digits` decimals.
+///
+/// Example:
+/// ```
+/// use svg_sheet::svg::normalize::normalize_length_with;
+/// assert_eq!(normalize_length_with("1.5em", 16.0, 4), Some("24".into()));
+/// assert_eq!(normalize_length_with("1rem", 20.0, 4), Some("20".into()));
+/// assert_eq!(normalize_length_with("1mm", 16.0, 2), Some("3.78".into()));
+/// assert_eq!(normalize_length_with("100%", 16.0, 4), None);
+/// ```
+pub fn normalize_length_with(v: &str, em_px: f64, digits: usize) -> Option<String> {
     let t = v.trim();
-    let num = if let Some(stripped) = t.strip_suffix("px") {
-        stripped.trim()
-    } else {
-        t
+    let (num, scale) = match t.strip_suffix("rem").or_else(|| t.strip_suffix("em")) {
+        Some(num) => (num, em_px),
+        None => UNITS
+            .iter()
+            .find_map(|(unit, scale)| Some((t.strip_suffix(unit)?, *scale)))
+            .unwrap_or((t, 1.0)),
     };
-    // Reject percentages or other units
-    if num.ends_with('%') || num.ends_with("em") || num.ends_with("rem") {
+    let val: f64 = num.trim().parse().ok()?;
+    let px = val * scale;
+    if !(px.is_finite() && px > 0.0) {
         return None;
     }
+    Some(normalize_number(round_number(px, digits)))
+}
+
+/// Round `n` to `digits` decimals, so float noise such as
+/// `23.999999999999996` becomes `24`. A non-zero value too small to survive
+/// the rounding is kept as is rather than collapsing to zero.
+///
+/// Example:
+/// ```
+/// use svg_sheet::svg::normalize::round_number;
+/// assert_eq!(round_number(23.999999999999996, 4), 24.0);
+/// assert_eq!(round_number(-0.00001, 4), 0.0);
+/// assert_eq!(round_number(0.00001, 4), 0.00001);
+/// ```
+pub fn round_number(n: f64, digits: usize) -> f64 {
+    let scale = 10f64.powi(digits.min(i32::MAX as usize) as i32);
+    let scaled = n * scale;
+    // Near 2^52 a double has no fractional digits left to round, and
+    // rounding again could step to the neighbouring value.
+    if scaled.is_nan() || scaled.abs() >= 1e15 {
+        return n;
+    }
+    // `+ 0.0` turns `-0` into `0`.
+    let rounded = scaled.round() / scale + 0.0;
+    if rounded == 0.0 && n > 0.0 {
+        n
+    } else {
+        rounded
+    }
+}
+
+/// What to do with a percentage `width`/`height` on an icon's root.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PercentDims {
+    /// Drop the attribute and rely on the `viewBox`
+    Strip,
+    /// Keep the normalized percentage
+    Keep,
+    /// Fail the build
+    #[default]
+    Error,
+}
+
+/// Parse and normalize a positive percentage such as `100%` or ` 50.0 %`.
+///
+/// Example:
+/// ```
+/// use svg_sheet::svg::normalize::normalize_percent;
+/// assert_eq!(normalize_percent(" 100.0 %"), Some("100%".into()));
+/// assert_eq!(normalize_percent("0%"), None);
+/// ```
+pub fn normalize_percent(v: &str) -> Option<String> {
+    let num = v.trim().strip_suffix('%')?.trim();
     let val: f64 = num.parse().ok()?;
     if !(val.is_finite() && val > 0.0) {
         return None;
     }
-    Some(normalize_number(val))
+    Some(format!("{}%", normalize_number(val)))
 }
 
 /// Canonicalize a floating number into an integer string when possible.
@@ -34,7 +125,8 @@ pub fn normalize_number(n: f64) -> String {
     }
 }
 
-/// Normalize `viewBox` into four numbers separated by single spaces.
+/// Normalize `viewBox` into four numbers separated by single spaces, rounded
+/// to `DEFAULT_PRECISION` decimals.
 /// Accepts commas and/or whitespace as separators. Requires width/height > 0.
 ///
 /// Example:
@@ -45,6 +137,19 @@ pub fn normalize_number(n: f64) -> String {
 /// );
 /// ```
 pub fn normalize_viewbox(v: &str) -> Option<String> {
+    normalize_viewbox_with(v, DEFAULT_PRECISION)
+}
+
+/// `normalize_viewbox`, rounding to `digits` decimals.
+///
+/// Example:
+/// ```
+/// assert_eq!(
+///     svg_sheet::svg::normalize::normalize_viewbox_with("0 0 23.999999999999996 24.25", 1),
+///     Some("0 0 24 24.3".into())
+/// );
+/// ```
+pub fn normalize_viewbox_with(v: &str, digits: usize) -> Option<String> {
     let replaced = v.replace(',', " ");
     let parts: Vec<&str> = replaced.split_whitespace().collect();
     if parts.len() != 4 {
@@ -57,20 +162,81 @@ pub fn normalize_viewbox(v: &str) -> Option<String> {
     if !(width.is_finite() && width > 0.0 && height.is_finite() && height > 0.0) {
         return None;
     }
+    let n = |v: f64| normalize_number(round_number(v, digits));
     Some(format!(
         "{} {} {} {}",
-        normalize_number(min_x),
-        normalize_number(min_y),
-        normalize_number(width),
-        normalize_number(height)
+        n(min_x),
+        n(min_y),
+        n(width),
+        n(height)
     ))
 }
 
+/// Alignment keywords accepted by `preserveAspectRatio`.
+const ALIGNS: [&str; 10] = [
+    "none", "xMinYMin", "xMidYMin", "xMaxYMin", "xMinYMid", "xMidYMid", "xMaxYMid", "xMinYMax",
+    "xMidYMax", "xMaxYMax",
+];
+
+/// Validate `preserveAspectRatio` against the SVG grammar
+/// (`[defer] <align> [meet | slice]`) and normalize whitespace to single
+/// spaces. The obsolete `defer` keyword is dropped.
+///
+/// Example:
+/// ```
+/// use svg_sheet::svg::normalize::normalize_preserve_aspect_ratio as par;
+/// assert_eq!(par("  xMidYMid   slice "), Some("xMidYMid slice".into()));
+/// assert_eq!(par("xmidymid"), None);
+/// ```
+pub fn normalize_preserve_aspect_ratio(v: &str) -> Option<String> {
+    let mut parts = v.split_whitespace().peekable();
+    if parts.peek() == Some(&"defer") {
+        parts.next();
+    }
+    let align = parts.next().filter(|a| ALIGNS.contains(a))?;
+    match (parts.next(), parts.next()) {
+        (None, _) => Some(align.to_string()),
+        (Some(mos @ ("meet" | "slice")), None) => Some(format!("{align} {mos}")),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use proptest::prelude::*;
 
+    #[test]
+    fn preserve_aspect_ratio_grammar() {
+        assert_eq!(normalize_preserve_aspect_ratio("none"), Some("none".into()));
+        assert_eq!(
+            normalize_preserve_aspect_ratio("defer xMinYMax meet"),
+            Some("xMinYMax meet".into())
+        );
+        assert_eq!(normalize_preserve_aspect_ratio(""), None);
+        assert_eq!(normalize_preserve_aspect_ratio("meet"), None);
+        assert_eq!(normalize_preserve_aspect_ratio("xMidYMid stretch"), None);
+        assert_eq!(normalize_preserve_aspect_ratio("xMidYMid meet slice"), None);
+    }
+
+    #[test]
+    fn converts_absolute_and_font_units() {
+        assert_eq!(normalize_length("0.25in"), Some("24".into()));
+        assert_eq!(normalize_length(" 1.5 pc "), Some("24".into()));
+        assert_eq!(normalize_length("18pt"), Some("24".into()));
+        assert_eq!(normalize_length("2.54cm"), Some("96".into()));
+        assert_eq!(normalize_length("6.35mm"), Some("24".into()));
+        assert_eq!(normalize_length("1.5rem"), Some("24".into()));
+        assert_eq!(normalize_length_with("2em", 12.0, 4), Some("24".into()));
+        assert_eq!(normalize_length("24.123456px"), Some("24.1235".into()));
+        assert_eq!(
+            normalize_length_with("23.999999999999996", 16.0, 4),
+            Some("24".into())
+        );
+        assert_eq!(normalize_length("-1mm"), None);
+        assert_eq!(normalize_length("2ex"), None);
+    }
+
     // Property: normalize_length accepts positive numbers (with optional px and whitespace),
     // returns a canonical representation that is idempotent and parsable > 0.
     proptest! {
@@ -156,8 +322,9 @@ mod tests {
             let ry: f64 = parts[1].parse().unwrap();
             let rw: f64 = parts[2].parse().unwrap();
             let rh: f64 = parts[3].parse().unwrap();
-            prop_assert!((rx - min_x).abs() <= 1e-9 || (min_x.is_sign_negative() == rx.is_sign_negative()));
-            prop_assert!((ry - min_y).abs() <= 1e-9 || (min_y.is_sign_negative() == ry.is_sign_negative()));
+            // Rounded to DEFAULT_PRECISION decimals
+            prop_assert!((rx - min_x).abs() < 1e-4);
+            prop_assert!((ry - min_y).abs() < 1e-4);
             prop_assert!(rw > 0.0 && rh > 0.0);
             prop_assert_eq!(normalize_viewbox(&out), Some(out.clone()));
         }
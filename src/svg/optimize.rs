@@ -0,0 +1,97 @@
+//! `--optimize`: drop markup that renders nothing, such as comments,
+//! `<metadata>` blocks, empty groups, and indentation between tags.
+
+use super::markup::{self, Token};
+use std::borrow::Cow;
+
+/// Elements whose whitespace-only text is rendered.
+const TEXT_ELEMENTS: [&str; 3] = ["text", "tspan", "textPath"];
+
+/// Elements with no rendering whose whole subtree is dropped.
+const DROPPED: [&str; 1] = ["metadata"];
+
+/// Drop comments (except `<!--! ... -->` license comments), `<metadata>`
+/// elements, `<g>` and `<defs>` without attributes or content, and
+/// whitespace-only text outside text elements. Markup that does not tokenize
+/// is left as is.
+pub(crate) fn optimize(children: &str) -> Cow<'_, str> {
+    let Some(tokens) = markup::tokenize(children) else {
+        return Cow::Borrowed(children);
+    };
+    let mut out = String::with_capacity(children.len());
+    // Names of the open elements, and whether each was dropped.
+    let mut open: Vec<(&str, bool)> = Vec::new();
+    let mut dropping = 0;
+    let mut text_depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match *token {
+            Token::Open { name, raw, empty } => {
+                let drop = dropping > 0
+                    || DROPPED.contains(&name)
+                    || is_empty_group(raw, name, empty, tokens.get(i + 1));
+                if !empty {
+                    open.push((name, drop));
+                    dropping += usize::from(drop);
+                    text_depth += usize::from(TEXT_ELEMENTS.contains(&name));
+                }
+                if !drop {
+                    out.push_str(raw);
+                }
+            }
+            Token::Close(raw) => {
+                let Some((name, drop)) = open.pop() else {
+                    return Cow::Borrowed(children);
+                };
+                dropping -= usize::from(drop);
+                text_depth -= usize::from(TEXT_ELEMENTS.contains(&name));
+                if !drop {
+                    out.push_str(raw);
+                }
+            }
+            Token::Text(raw) => {
+                let comment = raw.starts_with("<!--") && !raw.starts_with("<!--!");
+                let blank = raw.trim().is_empty() && text_depth == 0;
+                if dropping == 0 && !comment && !blank {
+                    out.push_str(raw);
+                }
+            }
+        }
+    }
+    if out.len() == children.len() {
+        Cow::Borrowed(children)
+    } else {
+        Cow::Owned(out)
+    }
+}
+
+/// Whether the start tag `raw` opens a `<g>` or `<defs>` without attributes
+/// whose content, up to `next`, is empty.
+fn is_empty_group(raw: &str, name: &str, empty: bool, next: Option<&Token>) -> bool {
+    if !matches!(name, "g" | "defs") || markup::attributes(raw).is_none_or(|a| !a.is_empty()) {
+        return false;
+    }
+    empty || matches!(next, Some(Token::Close(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_comments_metadata_blank_text_and_empty_groups() {
+        let input = "\n  <!-- Generator: Sketch -->\n  <metadata><rdf:RDF/></metadata>\n  <g>\n    <path d=\"M0 0\"/>\n  </g>\n  <g></g><defs/><g class=\"x\"/>\n";
+        assert_eq!(optimize(input), "<g><path d=\"M0 0\"/></g><g class=\"x\"/>");
+    }
+
+    #[test]
+    fn keeps_license_comments_and_text_whitespace() {
+        let input = "<!--! (c) Acme --><text>a<tspan>b</tspan> <tspan>c</tspan></text>";
+        assert!(matches!(optimize(input), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn untouched_and_malformed_markup_is_borrowed() {
+        assert!(matches!(optimize("<path d=\"M0 0\"/>"), Cow::Borrowed(_)));
+        assert!(matches!(optimize("<g><!-- x"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,123 @@
+//! Overlay directories (`--overlay`): icons that replace same-named icons of
+//! the input directory and of earlier inputs, for layouts such as vendor icons
+//! plus local overrides.
+
+use super::{CacheEntry, RunOpts, inputs};
+use crate::error::AppError;
+use std::path::{Path, PathBuf};
+
+/// Parse the icons of every `--overlay` directory, in command-line order and
+/// by path within a directory, narrowed by `--only`. Directories are listed
+/// like the input directory (ignore files, `--recursive`).
+pub(crate) fn load_all(opts: &RunOpts) -> Result<Vec<CacheEntry>, AppError> {
+    let only = inputs::expand_only(&opts.only)?;
+    let mut entries = Vec::new();
+    for dir in &opts.overlays {
+        let _span = tracing::debug_span!("overlay", dir = %dir.display()).entered();
+        let paths = inputs::list_svg_files(&dir.display().to_string(), opts)?;
+        for path in &paths {
+            let Some(name) = inputs::icon_name(dir, path, opts) else {
+                continue;
+            };
+            if !(only.is_empty() || only.contains(&name)) {
+                continue;
+            }
+            let (_, doc) = super::read_source(dir, path, opts)?;
+            entries.push(super::parse_document(
+                &name,
+                &path.display().to_string(),
+                &doc,
+                opts,
+            )?);
+        }
+    }
+    tracing::debug!(count = entries.len(), "Loaded overlay icons");
+    Ok(entries)
+}
+
+/// Add `overlay` icons to the inputs, each replacing the input file under
+/// `root` or the `collected` icon (including earlier overlay icons) with the
+/// same id, and recording what it replaced in `overrides`.
+pub(crate) fn apply(
+    root: &Path,
+    paths: &mut Vec<PathBuf>,
+    collected: &mut Vec<CacheEntry>,
+    overlay: Vec<CacheEntry>,
+    opts: &RunOpts,
+) {
+    for mut entry in overlay {
+        if let Some(n) = paths
+            .iter()
+            .position(|p| inputs::icon_name(root, p, opts).as_ref() == Some(&entry.name))
+        {
+            entry.overrides = Some(paths.remove(n).display().to_string());
+        } else if let Some(n) = collected.iter().position(|e| e.name == entry.name) {
+            entry.overrides = Some(collected.remove(n).path_str);
+        }
+        if let Some(replaced) = &entry.overrides {
+            tracing::info!(icon = %entry.name, path = %entry.path_str, replaced = %replaced, "Icon overridden");
+        }
+        collected.push(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn later_directories_replace_earlier_icons() {
+        let dir = std::env::temp_dir().join("svg_overlays_apply");
+        let _ = fs::remove_dir_all(&dir);
+        for sub in ["base", "vendor", "local"] {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        let svg = "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>";
+        for file in [
+            "base/a.svg",
+            "base/b.svg",
+            "vendor/b.svg",
+            "vendor/c.svg",
+            "local/c.svg",
+        ] {
+            fs::write(dir.join(file), svg).unwrap();
+        }
+        let opts = RunOpts {
+            overlays: vec![dir.join("vendor"), dir.join("local")],
+            ..Default::default()
+        };
+        let root = dir.join("base");
+        let mut paths = inputs::list_svg_files(root.to_str().unwrap(), &opts).unwrap();
+        let mut collected = Vec::new();
+        apply(
+            &root,
+            &mut paths,
+            &mut collected,
+            load_all(&opts).unwrap(),
+            &opts,
+        );
+        assert_eq!(paths, [root.join("a.svg")]);
+        let icons: Vec<(&str, &str, Option<&str>)> = collected
+            .iter()
+            .map(|e| (e.name.as_str(), e.path_str.as_str(), e.overrides.as_deref()))
+            .collect();
+        let path = |f: &str| dir.join(f).display().to_string();
+        assert_eq!(
+            icons,
+            [
+                (
+                    "b",
+                    path("vendor/b.svg").as_str(),
+                    Some(path("base/b.svg").as_str())
+                ),
+                (
+                    "c",
+                    path("local/c.svg").as_str(),
+                    Some(path("vendor/c.svg").as_str())
+                ),
+            ]
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+}
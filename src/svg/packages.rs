@@ -0,0 +1,186 @@
+//! Icons of installed npm packages (`--from-package`), found by walking up
+//! through `node_modules` directories the way Node resolves packages.
+
+use super::{CacheEntry, RunOpts, inputs};
+use crate::error::AppError;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// One `--from-package` input: a directory of SVGs inside an installed
+/// package, and the prefix of the ids built from it.
+///
+/// Example:
+/// ```
+/// use svg_sheet::svg::packages::PackageSource;
+/// let p: PackageSource = "@tabler/icons/icons/outline=tb".parse().unwrap();
+/// assert_eq!((p.package.as_str(), p.subdir.as_str(), p.prefix.as_str()), ("@tabler/icons", "icons/outline", "tb"));
+/// let p: PackageSource = "heroicons/24/outline".parse().unwrap();
+/// assert_eq!(p.prefix, "heroicons");
+/// assert!("@tabler".parse::<PackageSource>().is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackageSource {
+    /// Package name, scoped (`@tabler/icons`) or not
+    pub package: String,
+    /// Directory inside the package holding the SVGs; empty for its root
+    pub subdir: String,
+    /// Joined to each icon id with `--id-separator`; empty for none. Defaults
+    /// to the package name (`tabler-icons` for `@tabler/icons`)
+    pub prefix: String,
+}
+
+impl FromStr for PackageSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, prefix) = match s.split_once('=') {
+            Some((path, prefix)) => (path, Some(prefix.trim())),
+            None => (s, None),
+        };
+        let mut parts = path.trim().trim_matches('/').split('/');
+        let mut package = parts.next().unwrap_or_default().to_string();
+        if package.starts_with('@') {
+            match parts.next() {
+                Some(name) if !name.is_empty() => package = format!("{package}/{name}"),
+                _ => return Err(format!("expected @scope/name, got '{package}'")),
+            }
+        }
+        if package.is_empty() || package == "@" {
+            return Err(format!("missing package name in '{s}'"));
+        }
+        let subdir: Vec<&str> = parts.collect();
+        if subdir.iter().any(|p| p.is_empty() || *p == "..") {
+            return Err(format!("invalid path inside package in '{s}'"));
+        }
+        let prefix = match prefix {
+            Some(prefix) => prefix.to_string(),
+            None => package.trim_start_matches('@').replace('/', "-"),
+        };
+        Ok(PackageSource {
+            package,
+            subdir: subdir.join("/"),
+            prefix,
+        })
+    }
+}
+
+impl fmt::Display for PackageSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.package)?;
+        if !self.subdir.is_empty() {
+            write!(f, "/{}", self.subdir)?;
+        }
+        Ok(())
+    }
+}
+
+impl PackageSource {
+    /// The package directory in the nearest `node_modules` at or above
+    /// `from` that has it, if any.
+    pub fn resolve(&self, from: &Path) -> Option<PathBuf> {
+        from.ancestors()
+            .map(|dir| {
+                dir.join("node_modules")
+                    .join(&self.package)
+                    .join(&self.subdir)
+            })
+            .find(|dir| dir.is_dir())
+    }
+
+    /// Pattern id of an icon named `name` inside this package.
+    fn icon_id(&self, name: String, opts: &RunOpts) -> String {
+        if self.prefix.is_empty() {
+            name
+        } else {
+            format!("{}{}{name}", self.prefix, opts.id_separator)
+        }
+    }
+}
+
+/// Parse the icons of every `--from-package` input, resolved from the
+/// working directory, in command-line order and by path within a package,
+/// narrowed by `--only`. Package directories are listed like the input
+/// directory (ignore files, `--recursive`).
+pub(crate) fn load_all(opts: &RunOpts) -> Result<Vec<CacheEntry>, AppError> {
+    if opts.packages.is_empty() {
+        return Ok(Vec::new());
+    }
+    let cwd = std::env::current_dir().map_err(|e| AppError::ReadDir {
+        path: ".".into(),
+        source: e,
+    })?;
+    let only = inputs::expand_only(&opts.only)?;
+    let mut entries = Vec::new();
+    for source in &opts.packages {
+        let _span = tracing::debug_span!("package", package = %source).entered();
+        let dir = source
+            .resolve(&cwd)
+            .ok_or_else(|| AppError::PackageNotFound {
+                package: source.to_string(),
+                from: cwd.display().to_string(),
+            })?;
+        let paths = inputs::list_svg_files(&dir.display().to_string(), opts)?;
+        if paths.is_empty() {
+            tracing::warn!(package = %source, dir = %dir.display(), "No SVG files found in package");
+        }
+        for path in &paths {
+            let Some(name) = inputs::icon_name(&dir, path, opts) else {
+                continue;
+            };
+            let name = source.icon_id(name, opts);
+            if !(only.is_empty() || only.contains(&name)) {
+                continue;
+            }
+            let (_, doc) = super::read_source(&dir, path, opts)?;
+            entries.push(super::parse_document(
+                &name,
+                &path.display().to_string(),
+                &doc,
+                opts,
+            )?);
+        }
+    }
+    tracing::debug!(count = entries.len(), "Loaded package icons");
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn resolves_through_ancestor_node_modules() {
+        let root = std::env::temp_dir().join("svg_packages_resolve");
+        let _ = fs::remove_dir_all(&root);
+        let icons = root.join("node_modules/heroicons/24/outline");
+        fs::create_dir_all(&icons).unwrap();
+        fs::create_dir_all(root.join("apps/web")).unwrap();
+
+        let source: PackageSource = "heroicons/24/outline".parse().unwrap();
+        assert_eq!(source.resolve(&root.join("apps/web")), Some(icons));
+        let missing: PackageSource = "lucide-static/icons".parse().unwrap();
+        assert_eq!(missing.resolve(&root.join("apps/web")), None);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn parses_specs_and_prefixes_ids() {
+        let p: PackageSource = "heroicons/24/solid=".parse().unwrap();
+        assert_eq!(p.prefix, "");
+        assert_eq!(p.to_string(), "heroicons/24/solid");
+        assert_eq!(p.icon_id("bell".into(), &RunOpts::default()), "bell");
+
+        let p: PackageSource = "@tabler/icons".parse().unwrap();
+        assert_eq!((p.subdir.as_str(), p.prefix.as_str()), ("", "tabler-icons"));
+        let opts = RunOpts {
+            id_separator: "_".into(),
+            ..Default::default()
+        };
+        assert_eq!(p.icon_id("bell".into(), &opts), "tabler-icons_bell");
+
+        assert!("heroicons/../x".parse::<PackageSource>().is_err());
+        assert!("=x".parse::<PackageSource>().is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::AppError;
 use winnow::{
     PResult, Parser,
     ascii::{multispace0, multispace1},
@@ -8,13 +9,80 @@ use winnow::{
 /// Parse an `<svg>` element, returning a vector of `(key, value)` attributes and the inner children string.
 /// Public within the crate.
 pub(crate) fn parse_svg<'s>(input: &mut &'s str) -> PResult<(Vec<(&'s str, &'s str)>, &'s str)> {
+    let start = *input;
     entry_tag.parse_next(input)?;
     let attrs = attributes.parse_next(input)?;
     preceded(multispace0, '>').parse_next(input)?;
+    // End at the `</svg>` closing the root, past any in comments, CDATA or
+    // nested `<svg>`; markup that does not balance falls back to the first.
+    let open = start.len() - input.len();
+    if let Some(len) = super::markup::element_len(start)
+        && start[..len].ends_with("</svg>")
+    {
+        let children = &start[open..len - "</svg>".len()];
+        *input = &start[len..];
+        return Ok((attrs, children));
+    }
     let children = terminated(take_until(0.., "</svg>"), "</svg>").parse_next(input)?;
     Ok((attrs, children))
 }
 
+/// Why `parse_svg` rejects `input`: the byte offset of the problem and what
+/// was expected there.
+pub(crate) fn syntax_error(input: &str) -> (usize, &'static str) {
+    let mut s = input;
+    if entry_tag.parse_next(&mut s).is_err() {
+        return (0, "expected '<svg' followed by whitespace");
+    }
+    let _ = attributes.parse_next(&mut s);
+    let _: PResult<&str> = multispace0.parse_next(&mut s);
+    let offset = input.len() - s.len();
+    if s.is_empty() {
+        return (
+            offset,
+            "unexpected end of file; expected '>' after attributes",
+        );
+    }
+    if !s.starts_with('>') {
+        let mut value = s;
+        if kebab_alpha1.parse_next(&mut value).is_ok() && parse_eq_ws.parse_next(&mut value).is_ok()
+        {
+            let at = input.len() - value.len();
+            return match value.chars().next() {
+                Some('"' | '\'') => (at, "unterminated attribute value"),
+                _ => (at, "expected a quoted attribute value"),
+            };
+        }
+        return (offset, "expected '>' after attributes");
+    }
+    (0, "'<svg>' is never closed; expected '</svg>'")
+}
+
+/// A located `ParseSvg` error for `body`, which `parse_svg` rejected, read
+/// from `origin` where `body` starts at the 1-based line/column `start`.
+pub(crate) fn located_error(origin: &str, body: &str, start: (usize, usize)) -> AppError {
+    let (offset, message) = syntax_error(body);
+    let (line, column) = line_column(body, offset);
+    let column = if line == 1 {
+        column + start.1 - 1
+    } else {
+        column
+    };
+    AppError::ParseSvg {
+        path: origin.to_string(),
+        message: message.to_string(),
+        location: Some((line + start.0 - 1, column)),
+    }
+}
+
+/// 1-based line and column (in characters) of the byte `offset` in `text`.
+pub(crate) fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |n| n + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
 /// Parse zero or more attributes separated by whitespace from the `<svg>` start tag.
 fn attributes<'s>(input: &mut &'s str) -> PResult<Vec<(&'s str, &'s str)>> {
     multispace0.parse_next(input)?;
@@ -147,6 +215,32 @@ mod tests {
         assert_eq!(result, ("focusable", "focusable"));
     }
 
+    #[test]
+    fn syntax_errors_point_at_the_problem() {
+        let input = "<svg width=\"24\"\n  height=\"24\" fill=#000>";
+        let (offset, message) = syntax_error(input);
+        assert_eq!(line_column(input, offset), (2, 20));
+        assert_eq!(message, "expected a quoted attribute value");
+
+        let input = "<svg width=\"24\"\n  <path/></svg>";
+        let (offset, message) = syntax_error(input);
+        assert_eq!(line_column(input, offset), (2, 3));
+        assert_eq!(message, "expected '>' after attributes");
+
+        assert_eq!(
+            syntax_error("<svg a=\"1\" b='2"),
+            (13, "unterminated attribute value")
+        );
+        assert_eq!(
+            syntax_error("<svg width=\"1\"><g/>"),
+            (0, "'<svg>' is never closed; expected '</svg>'")
+        );
+        assert_eq!(
+            syntax_error("<path/>"),
+            (0, "expected '<svg' followed by whitespace")
+        );
+    }
+
     #[test]
     fn parse_svg_simple() {
         let input = r##"<svg id="test" fill="#000000">Something</svg>"##;
@@ -156,6 +250,18 @@ mod tests {
         };
     }
 
+    #[test]
+    fn parse_svg_ends_at_the_root_end_tag() {
+        let mut s = "<svg width='1'><!-- </svg> --><svg x='0'><g/></svg><path/></svg>tail";
+        let (_, children) = parse_svg.parse_next(&mut s).expect("parse svg");
+        assert_eq!(children, "<!-- </svg> --><svg x='0'><g/></svg><path/>");
+        assert_eq!(s, "tail");
+
+        let mut s = "<svg width='1'><path></svg>tail";
+        let (_, children) = parse_svg.parse_next(&mut s).expect("unbalanced");
+        assert_eq!(children, "<path>");
+    }
+
     #[test]
     fn parse_svg_multiline_opening_tag() {
         let input = r#"<svg
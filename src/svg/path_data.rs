@@ -0,0 +1,558 @@
+//! Path data (`d` attribute) parsing, affine transformation, and formatting.
+
+/// One path command with its arguments, e.g. `C` with six numbers.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Segment {
+    pub(crate) cmd: char,
+    pub(crate) args: Vec<f64>,
+}
+
+/// An affine matrix `[a, b, c, d, e, f]` as in SVG's `matrix(a b c d e f)`.
+pub(crate) type Matrix = [f64; 6];
+
+/// The identity transform.
+pub(crate) const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Number of arguments taken by one repetition of `cmd`.
+fn arity(cmd: char) -> usize {
+    match cmd.to_ascii_uppercase() {
+        'M' | 'L' | 'T' => 2,
+        'H' | 'V' => 1,
+        'S' | 'Q' => 4,
+        'C' => 6,
+        'A' => 7,
+        _ => 0,
+    }
+}
+
+/// Parse path data into segments, one per command repetition (implicit
+/// repeats are made explicit; extra `M` pairs become `L`). Returns `None` on
+/// malformed data.
+pub(crate) fn parse(d: &str) -> Option<Vec<Segment>> {
+    let bytes = d.as_bytes();
+    let mut i = 0;
+    let mut out = Vec::new();
+    let skip = |i: &mut usize| {
+        while *i < bytes.len() && (bytes[*i].is_ascii_whitespace() || bytes[*i] == b',') {
+            *i += 1;
+        }
+    };
+    loop {
+        skip(&mut i);
+        if i >= bytes.len() {
+            return Some(out);
+        }
+        let mut cmd = bytes[i] as char;
+        if !"MmLlHhVvCcSsQqTtAaZz".contains(cmd) {
+            return None;
+        }
+        i += 1;
+        let n = arity(cmd);
+        if n == 0 {
+            out.push(Segment { cmd, args: vec![] });
+            continue;
+        }
+        let mut first = true;
+        loop {
+            let mut args = Vec::with_capacity(n);
+            for k in 0..n {
+                skip(&mut i);
+                // Arc flags are single digits and may be written without separators.
+                let value = if cmd.eq_ignore_ascii_case(&'a') && (k == 3 || k == 4) {
+                    match bytes.get(i) {
+                        Some(b'0') => Some(0.0),
+                        Some(b'1') => Some(1.0),
+                        _ => None,
+                    }
+                    .inspect(|_| i += 1)
+                } else {
+                    number(bytes, &mut i)
+                };
+                match value {
+                    Some(v) => args.push(v),
+                    None if k == 0 && !first => break,
+                    None => return None,
+                }
+            }
+            if args.is_empty() {
+                break;
+            }
+            out.push(Segment { cmd, args });
+            if cmd == 'M' {
+                cmd = 'L';
+            } else if cmd == 'm' {
+                cmd = 'l';
+            }
+            first = false;
+        }
+    }
+}
+
+/// Read one number at `*i`, advancing past it.
+fn number(bytes: &[u8], i: &mut usize) -> Option<f64> {
+    let start = *i;
+    let mut j = *i;
+    if matches!(bytes.get(j), Some(b'+' | b'-')) {
+        j += 1;
+    }
+    let digits = |j: &mut usize| {
+        let from = *j;
+        while bytes.get(*j).is_some_and(u8::is_ascii_digit) {
+            *j += 1;
+        }
+        *j > from
+    };
+    let mut any = digits(&mut j);
+    if bytes.get(j) == Some(&b'.') {
+        j += 1;
+        any |= digits(&mut j);
+    }
+    if !any {
+        return None;
+    }
+    if matches!(bytes.get(j), Some(b'e' | b'E')) {
+        let mut k = j + 1;
+        if matches!(bytes.get(k), Some(b'+' | b'-')) {
+            k += 1;
+        }
+        if digits(&mut k) {
+            j = k;
+        }
+    }
+    let value = std::str::from_utf8(&bytes[start..j]).ok()?.parse().ok()?;
+    *i = j;
+    Some(value)
+}
+
+/// Convert every segment to its absolute (uppercase) form.
+pub(crate) fn to_absolute(segments: &[Segment]) -> Vec<Segment> {
+    let (mut cx, mut cy, mut sx, mut sy) = (0.0, 0.0, 0.0, 0.0);
+    let mut out = Vec::with_capacity(segments.len());
+    for seg in segments {
+        let rel = seg.cmd.is_ascii_lowercase();
+        let cmd = seg.cmd.to_ascii_uppercase();
+        let mut args = seg.args.clone();
+        if rel {
+            match cmd {
+                'H' => args[0] += cx,
+                'V' => args[0] += cy,
+                'A' => {
+                    args[5] += cx;
+                    args[6] += cy;
+                }
+                _ => {
+                    for pair in args.chunks_mut(2) {
+                        pair[0] += cx;
+                        pair[1] += cy;
+                    }
+                }
+            }
+        }
+        match cmd {
+            'Z' => (cx, cy) = (sx, sy),
+            'H' => cx = args[0],
+            'V' => cy = args[0],
+            _ => {
+                let n = args.len();
+                (cx, cy) = (args[n - 2], args[n - 1]);
+                if cmd == 'M' {
+                    (sx, sy) = (cx, cy);
+                }
+            }
+        }
+        out.push(Segment { cmd, args });
+    }
+    out
+}
+
+/// Compose `outer` after `inner`: the result maps `p` to `outer(inner(p))`.
+pub(crate) fn multiply(outer: &Matrix, inner: &Matrix) -> Matrix {
+    let [a, b, c, d, e, f] = *outer;
+    let [a2, b2, c2, d2, e2, f2] = *inner;
+    [
+        a * a2 + c * b2,
+        b * a2 + d * b2,
+        a * c2 + c * d2,
+        b * c2 + d * d2,
+        a * e2 + c * f2 + e,
+        b * e2 + d * f2 + f,
+    ]
+}
+
+/// Parse a `transform` attribute (`translate`, `scale`, `matrix`, `rotate`,
+/// `skewX`, `skewY`, in any sequence) into one matrix.
+pub(crate) fn parse_transform(value: &str) -> Option<Matrix> {
+    let mut m = IDENTITY;
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let open = rest.find('(')?;
+        let close = rest.find(')')?;
+        let name = rest[..open].trim();
+        let args: Vec<f64> = rest[open + 1..close]
+            .split(|c: char| c == ',' || c.is_ascii_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .ok()?;
+        let step = match (name, args.as_slice()) {
+            ("matrix", [a, b, c, d, e, f]) => [*a, *b, *c, *d, *e, *f],
+            ("translate", [x]) => [1.0, 0.0, 0.0, 1.0, *x, 0.0],
+            ("translate", [x, y]) => [1.0, 0.0, 0.0, 1.0, *x, *y],
+            ("scale", [s]) => [*s, 0.0, 0.0, *s, 0.0, 0.0],
+            ("scale", [x, y]) => [*x, 0.0, 0.0, *y, 0.0, 0.0],
+            ("rotate", [deg]) => rotation(*deg),
+            ("rotate", [deg, x, y]) => {
+                let to = [1.0, 0.0, 0.0, 1.0, *x, *y];
+                let back = [1.0, 0.0, 0.0, 1.0, -x, -y];
+                multiply(&multiply(&to, &rotation(*deg)), &back)
+            }
+            ("skewX", [deg]) => [1.0, 0.0, deg.to_radians().tan(), 1.0, 0.0, 0.0],
+            ("skewY", [deg]) => [1.0, deg.to_radians().tan(), 0.0, 1.0, 0.0, 0.0],
+            _ => return None,
+        };
+        m = multiply(&m, &step);
+        rest = rest[close + 1..].trim_start_matches(|c: char| c == ',' || c.is_ascii_whitespace());
+    }
+    Some(m)
+}
+
+/// Rotation by `deg` degrees about the origin.
+fn rotation(deg: f64) -> Matrix {
+    let (sin, cos) = deg.to_radians().sin_cos();
+    [cos, sin, -sin, cos, 0.0, 0.0]
+}
+
+/// Whether `m` only translates.
+pub(crate) fn is_translation(m: &Matrix) -> bool {
+    m[0] == 1.0 && m[1] == 0.0 && m[2] == 0.0 && m[3] == 1.0
+}
+
+/// Apply `m` to path data, returning absolute path data. Returns `None` when
+/// the data is malformed or an arc cannot be expressed after the transform
+/// (e.g. a rotated ellipse under non-uniform scaling).
+pub(crate) fn transform(d: &str, m: &Matrix) -> Option<String> {
+    let point = |x: f64, y: f64| (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5]);
+    let (mut cx, mut cy, mut sx, mut sy) = (0.0, 0.0, 0.0, 0.0);
+    let mut out = Vec::new();
+    for seg in to_absolute(&parse(d)?) {
+        let mut args = seg.args.clone();
+        let cmd = match seg.cmd {
+            'Z' => {
+                (cx, cy) = (sx, sy);
+                out.push(seg);
+                continue;
+            }
+            // Axis-aligned lines stop being axis-aligned under rotation/skew.
+            'H' => {
+                args = vec![args[0], cy];
+                'L'
+            }
+            'V' => {
+                args = vec![cx, args[0]];
+                'L'
+            }
+            'A' => {
+                let [rx, ry, rot] = arc_radii(args[0], args[1], args[2], m)?;
+                let mirrored = m[0] * m[3] - m[1] * m[2] < 0.0;
+                args[0] = rx;
+                args[1] = ry;
+                args[2] = rot;
+                if mirrored {
+                    args[4] = 1.0 - args[4];
+                }
+                let (x, y) = (args[5], args[6]);
+                (cx, cy) = (x, y);
+                (args[5], args[6]) = point(x, y);
+                out.push(Segment { cmd: 'A', args });
+                continue;
+            }
+            c => c,
+        };
+        let n = args.len();
+        (cx, cy) = (args[n - 2], args[n - 1]);
+        if cmd == 'M' {
+            (sx, sy) = (cx, cy);
+        }
+        for pair in args.chunks_mut(2) {
+            (pair[0], pair[1]) = point(pair[0], pair[1]);
+        }
+        out.push(Segment { cmd, args });
+    }
+    Some(format(&out))
+}
+
+/// Radii and x-axis rotation of an arc's ellipse after applying `m`, for the
+/// cases where the result is exact: similarity transforms (uniform scale,
+/// rotation, reflection) and axis-aligned scaling of unrotated ellipses.
+fn arc_radii(rx: f64, ry: f64, rot: f64, m: &Matrix) -> Option<[f64; 3]> {
+    let [a, b, c, d, ..] = *m;
+    let det = a * d - b * c;
+    let close = |x: f64, y: f64| (x - y).abs() < 1e-9;
+    if close(a, d) && close(b, -c) || close(a, -d) && close(b, c) {
+        let scale = (a * a + b * b).sqrt();
+        let angle = b.atan2(a).to_degrees();
+        let rot = if det < 0.0 { angle - rot } else { rot + angle };
+        return Some([rx * scale, ry * scale, rot.rem_euclid(180.0)]);
+    }
+    if close(b, 0.0) && close(c, 0.0) && close(rot.rem_euclid(90.0), 0.0) {
+        let (sx, sy) = (a.abs(), d.abs());
+        return Some(if close(rot.rem_euclid(180.0), 0.0) {
+            [rx * sx, ry * sy, rot]
+        } else {
+            [rx * sy, ry * sx, rot]
+        });
+    }
+    None
+}
+
+/// Format segments as compact path data, numbers rounded to 3 decimals.
+pub(crate) fn format(segments: &[Segment]) -> String {
+    let mut out = String::new();
+    for seg in segments {
+        out.push(seg.cmd);
+        for (i, v) in seg.args.iter().enumerate() {
+            let n = format_number(*v, 3);
+            if i > 0 && !n.starts_with('-') {
+                out.push(' ');
+            }
+            out.push_str(&n);
+        }
+    }
+    out
+}
+
+/// Round to `digits` decimals and drop trailing zeros (`1.500` -> `1.5`,
+/// `-0` -> `0`).
+fn format_number(v: f64, digits: usize) -> String {
+    let s = format!("{v:.digits$}");
+    let s = if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &s
+    };
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Rewrite path data in its shortest equivalent form: each command absolute or
+/// relative (whichever is shorter), axis-aligned lines as `H`/`V`, repeated
+/// commands implicit, minimal separators, and movetos that start empty
+/// subpaths dropped. Numbers keep the input's precision, so the geometry is
+/// unchanged. Returns `None` when `d` is malformed.
+pub(crate) fn compact(d: &str) -> Option<String> {
+    let digits = precision(d);
+    let scale = 10f64.powi(digits as i32);
+    let round = |v: f64| (v * scale).round() / scale;
+    let segments = to_absolute(&parse(d)?);
+    let mut out = String::with_capacity(d.len());
+    let mut prev: Option<char> = None;
+    let (mut cx, mut cy, mut sx, mut sy) = (0.0, 0.0, 0.0, 0.0);
+    for (n, seg) in segments.iter().enumerate() {
+        // A moveto followed by another moveto (or nothing) draws nothing.
+        if seg.cmd == 'M' && segments.get(n + 1).is_none_or(|next| next.cmd == 'M') {
+            continue;
+        }
+        if seg.cmd == 'Z' {
+            out.push('z');
+            prev = Some('z');
+            (cx, cy) = (sx, sy);
+            continue;
+        }
+        let abs: Vec<f64> = seg.args.iter().map(|v| round(*v)).collect();
+        let (cmd, abs) = match seg.cmd {
+            'L' if abs[1] == cy => ('H', vec![abs[0]]),
+            'L' if abs[0] == cx => ('V', vec![abs[1]]),
+            c => (c, abs),
+        };
+        let rel: Vec<f64> = match cmd {
+            'H' => vec![abs[0] - cx],
+            'V' => vec![abs[0] - cy],
+            'A' => {
+                let mut rel = abs.clone();
+                rel[5] -= cx;
+                rel[6] -= cy;
+                rel
+            }
+            _ => abs.chunks(2).flat_map(|p| [p[0] - cx, p[1] - cy]).collect(),
+        };
+        let abs_nums: Vec<String> = abs.iter().map(|v| short_number(*v, digits)).collect();
+        let rel_nums: Vec<String> = rel.iter().map(|v| short_number(*v, digits)).collect();
+        let (letter, nums) = if joined_len(&rel_nums) < joined_len(&abs_nums) {
+            (cmd.to_ascii_lowercase(), rel_nums)
+        } else {
+            (cmd, abs_nums)
+        };
+        // `M x y x y` continues with implicit linetos; other commands repeat.
+        let implicit = match (prev, letter) {
+            (_, 'M' | 'm') => false,
+            (Some('M'), 'L') | (Some('m'), 'l') => true,
+            (Some(p), l) => p == l,
+            (None, _) => false,
+        };
+        if !implicit {
+            out.push(letter);
+        }
+        for num in &nums {
+            push_number(&mut out, num);
+        }
+        prev = Some(letter);
+        match cmd {
+            'H' => cx = abs[0],
+            'V' => cy = abs[0],
+            _ => (cx, cy) = (abs[abs.len() - 2], abs[abs.len() - 1]),
+        }
+        if cmd == 'M' {
+            (sx, sy) = (cx, cy);
+        }
+    }
+    Some(out)
+}
+
+/// Decimal places needed to keep every number in `d` exact (at most 6).
+fn precision(d: &str) -> usize {
+    let mut max = 0;
+    let mut fraction = None;
+    for c in d.chars() {
+        match c {
+            '.' => fraction = Some(0),
+            '0'..='9' => {
+                if let Some(n) = fraction.as_mut() {
+                    *n += 1;
+                    max = max.max(*n);
+                }
+            }
+            'e' | 'E' => return 6,
+            _ => fraction = None,
+        }
+    }
+    max.min(6)
+}
+
+/// `format_number` without the leading zero of fractions (`0.5` -> `.5`).
+fn short_number(v: f64, digits: usize) -> String {
+    let s = format_number(v, digits);
+    if let Some(rest) = s.strip_prefix("0.") {
+        format!(".{rest}")
+    } else if let Some(rest) = s.strip_prefix("-0.") {
+        format!("-.{rest}")
+    } else {
+        s
+    }
+}
+
+/// Append `num`, separated from a preceding number only where needed: not
+/// before a sign, nor before `.` when the previous number has a fraction.
+fn push_number(out: &mut String, num: &str) {
+    let tail = &out[out
+        .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.')
+        .len()..];
+    let joins = num.starts_with('-') || (num.starts_with('.') && tail.contains('.'));
+    if !tail.is_empty() && !joins {
+        out.push(' ');
+    }
+    out.push_str(num);
+}
+
+/// Length of `nums` written with minimal separators.
+fn joined_len(nums: &[String]) -> usize {
+    let mut out = String::new();
+    for num in nums {
+        push_number(&mut out, num);
+    }
+    out.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_implicit_repeats_and_compact_numbers() {
+        let segs = parse("M1.5.5 2 2l-1-1zA1 1 0 01 3 3").unwrap();
+        let cmds: String = segs.iter().map(|s| s.cmd).collect();
+        assert_eq!(cmds, "MLlzA");
+        assert_eq!(segs[0].args, vec![1.5, 0.5]);
+        assert_eq!(segs[2].args, vec![-1.0, -1.0]);
+        assert_eq!(segs[4].args, vec![1.0, 1.0, 0.0, 0.0, 1.0, 3.0, 3.0]);
+        assert!(parse("M0 0 L").is_none());
+        assert!(parse("X1").is_none());
+    }
+
+    #[test]
+    fn transforms_translate_scale_and_axis_lines() {
+        let m = parse_transform("translate(10 5) scale(2)").unwrap();
+        assert_eq!(
+            transform("M1 1h2v2H1z", &m).as_deref(),
+            Some("M12 7L16 7L16 11L12 11Z")
+        );
+        let t = parse_transform("translate(-1,-1)").unwrap();
+        assert!(is_translation(&t));
+        assert_eq!(transform("m2 2l1 1", &t).as_deref(), Some("M1 1L2 2"));
+    }
+
+    #[test]
+    fn arcs_transform_only_when_exact() {
+        let mirror = parse_transform("scale(-1 1)").unwrap();
+        assert_eq!(
+            transform("M0 0A2 2 0 0 1 4 0", &mirror).as_deref(),
+            Some("M0 0A2 2 0 0 0-4 0")
+        );
+        let stretch = parse_transform("scale(2 1)").unwrap();
+        assert_eq!(
+            transform("M0 0A2 1 0 0 1 4 0", &stretch).as_deref(),
+            Some("M0 0A4 1 0 0 1 8 0")
+        );
+        assert!(transform("M0 0A2 1 30 0 1 4 0", &stretch).is_none());
+    }
+
+    #[test]
+    fn compacts_to_shortest_form() {
+        assert_eq!(
+            compact("M 10 10 L 20 10 L 20 20 L 10 20 Z").as_deref(),
+            Some("M10 10H20V20H10z")
+        );
+        assert_eq!(
+            compact("M100 100 L101 101 L102 102").as_deref(),
+            Some("M100 100l1 1 1 1")
+        );
+        assert_eq!(
+            compact("M0.5 0.5 L 1.25 -0.75").as_deref(),
+            Some("M.5.5 1.25-.75")
+        );
+        assert_eq!(compact("M1 1 M2 2 L3 3 M9 9").as_deref(), Some("M2 2 3 3"));
+        assert_eq!(
+            compact("M10 10 a 2 2 0 0 1 4 0 z m 5 5 l 1 0").as_deref(),
+            Some("M10 10a2 2 0 0 1 4 0zm5 5h1")
+        );
+        assert!(compact("M0 0 L").is_none());
+    }
+
+    #[test]
+    fn compaction_keeps_input_precision() {
+        // Relative offsets are taken from the rounded absolute positions, so
+        // errors do not accumulate along the path.
+        let d = "M0.333 0.333L100.667 0.333L100.667 100.001";
+        let compacted = compact(d).unwrap();
+        let a = to_absolute(&parse(d).unwrap());
+        let b = to_absolute(&parse(&compacted).unwrap());
+        let end = |s: &[Segment]| match s.last().unwrap() {
+            Segment { cmd: 'V', args } => args[0],
+            Segment { args, .. } => args[args.len() - 1],
+        };
+        assert!((end(&a) - end(&b)).abs() < 1e-9, "{compacted}");
+        assert_eq!(precision("M1 1.25 2.5e3"), 6);
+        assert_eq!(precision("M1.125 2"), 3);
+    }
+
+    #[test]
+    fn composes_matrices() {
+        let m = parse_transform("rotate(90)").unwrap();
+        assert_eq!(transform("M1 0", &m).as_deref(), Some("M0 1"));
+        let around = parse_transform("rotate(180 1 1)").unwrap();
+        assert_eq!(transform("M0 0", &around).as_deref(), Some("M2 2"));
+        assert!(parse_transform("perspective(1)").is_none());
+    }
+}
@@ -0,0 +1,142 @@
+//! The named passes run on each icon after its root `<svg id>` is handled, so
+//! a config can reorder them or leave some out.
+//!
+//! Sprite-wide id collision checks run on the result whatever the order: an
+//! icon built without `scope-ids` keeps its own ids, and fails the build if
+//! they clash.
+
+use std::fmt;
+
+/// One markup pass of the per-icon pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Root `width`/`height`, `viewBox` and `preserveAspectRatio` in canonical
+    /// form
+    NormalizeDims,
+    /// The config's `[attributes]` table: drop attributes by name or pattern
+    StripAttributes,
+    /// `--plain-href`: rewrite `xlink:href` as `href`
+    PlainHref,
+    /// `--raster-images`: warn about, keep, or strip embedded raster images
+    RasterImages,
+    /// The config's `[colors]` replacements
+    Colors,
+    /// `--themable`: wrap colors in CSS variables
+    Themable,
+    /// `--flatten-transforms`: apply transforms to path coordinates
+    FlattenTransforms,
+    /// `--compact-paths`: shorten path data
+    CompactPaths,
+    /// `--optimize`: drop comments, metadata, empty groups and indentation
+    Optimize,
+    /// `--shared-defs`: hoist gradients and filters into the sprite's root
+    SharedDefs,
+    /// `--plugin` WebAssembly transforms (`plugins` feature)
+    Plugins,
+    /// Scope referenced definition ids to the icon and move other ids to
+    /// `data-id`
+    ScopeIds,
+    /// `<title>`/`<desc>` from sidecars and `--titles`, added when the sprite
+    /// is assembled wherever the stage is listed
+    A11y,
+}
+
+impl Stage {
+    /// Every stage, in the default order.
+    pub const DEFAULT: [Stage; 13] = [
+        Stage::NormalizeDims,
+        Stage::StripAttributes,
+        Stage::PlainHref,
+        Stage::RasterImages,
+        Stage::Colors,
+        Stage::Themable,
+        Stage::FlattenTransforms,
+        Stage::CompactPaths,
+        Stage::Optimize,
+        Stage::SharedDefs,
+        Stage::Plugins,
+        Stage::ScopeIds,
+        Stage::A11y,
+    ];
+
+    /// Name of the stage in the config's `pipeline` list, matching its flag.
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::NormalizeDims => "normalize-dims",
+            Stage::StripAttributes => "strip-attributes",
+            Stage::PlainHref => "plain-href",
+            Stage::RasterImages => "raster-images",
+            Stage::Colors => "colors",
+            Stage::Themable => "themable",
+            Stage::FlattenTransforms => "flatten-transforms",
+            Stage::CompactPaths => "compact-paths",
+            Stage::Optimize => "optimize",
+            Stage::SharedDefs => "shared-defs",
+            Stage::Plugins => "plugins",
+            Stage::ScopeIds => "scope-ids",
+            Stage::A11y => "a11y",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for Stage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Stage::DEFAULT
+            .into_iter()
+            .find(|stage| stage.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Stage::DEFAULT.iter().map(|s| s.name()).collect();
+                format!(
+                    "unknown pipeline stage '{s}'; expected one of {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Parse a config `pipeline` list: the stages to run, in order. Stages left
+/// out are skipped; a stage may appear once.
+///
+/// Example:
+/// ```
+/// use svg_sheet::svg::pipeline::{Stage, parse};
+/// let stages = parse(&["shared-defs", "colors"]).unwrap();
+/// assert_eq!(stages, [Stage::SharedDefs, Stage::Colors]);
+/// assert!(parse(&["colors", "colors"]).is_err());
+/// ```
+pub fn parse<S: AsRef<str>>(names: &[S]) -> Result<Vec<Stage>, String> {
+    let mut stages = Vec::with_capacity(names.len());
+    for name in names {
+        let stage: Stage = name.as_ref().parse()?;
+        if stages.contains(&stage) {
+            return Err(format!("pipeline stage '{stage}' is listed twice"));
+        }
+        stages.push(stage);
+    }
+    Ok(stages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for stage in Stage::DEFAULT {
+            assert_eq!(stage.name().parse::<Stage>(), Ok(stage));
+        }
+        let err = "minify".parse::<Stage>().unwrap_err();
+        assert!(err.starts_with(
+            "unknown pipeline stage 'minify'; expected one of normalize-dims, strip-attributes"
+        ));
+    }
+}
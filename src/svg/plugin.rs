@@ -0,0 +1,344 @@
+//! `--plugin`: user transforms compiled to WebAssembly and run on every icon
+//! after the built-in passes, for rules that do not belong in the tool
+//! (watermarks, legal metadata).
+//!
+//! A plugin is a core WebAssembly module without imports that exports:
+//!
+//! - `memory`, its linear memory;
+//! - `alloc(len: i32) -> i32`, where the host may write `len` bytes;
+//! - `transform(ptr: i32, len: i32) -> i64`, taking the icon as UTF-8 JSON at
+//!   `ptr` and returning where its result is, packed as `ptr << 32 | len`.
+//!
+//! The icon is `{ "name", "attributes": [[name, value], ...], "children" }`,
+//! the result `{ "attributes", "children" }` (`attributes` optional) or
+//! `{ "error" }` to fail the build. Every icon runs in a fresh instance with
+//! bounded fuel and memory.
+
+use super::markup::{self, Token};
+use super::source;
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Fuel (roughly, instructions) one icon may use before the plugin is stopped.
+const FUEL: u64 = 1_000_000_000;
+
+/// Linear memory one instance may grow to.
+const MEMORY_BYTES: usize = 256 << 20;
+
+/// The icon handed to a plugin.
+#[derive(Serialize)]
+struct Icon<'a> {
+    name: &'a str,
+    attributes: &'a [(String, String)],
+    children: &'a str,
+}
+
+/// What a plugin hands back.
+#[derive(Deserialize)]
+struct Output {
+    attributes: Option<Vec<(String, String)>>,
+    children: Option<String>,
+    error: Option<String>,
+}
+
+/// A compiled `--plugin` module, shared by every icon of a build.
+#[derive(Clone)]
+pub struct Plugin {
+    path: PathBuf,
+    hash: String,
+    engine: wasmi::Engine,
+    module: wasmi::Module,
+    fuel: u64,
+}
+
+/// The path and content hash, so cached icons are rebuilt when a plugin
+/// changes.
+impl std::fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Plugin")
+            .field("path", &self.path)
+            .field("hash", &self.hash)
+            .finish()
+    }
+}
+
+impl Plugin {
+    /// Read and compile the module at `path`, checking its imports and exports.
+    pub fn load(path: &Path) -> Result<Plugin, AppError> {
+        let wasm = std::fs::read(path).map_err(|e| AppError::ReadFile {
+            path: path.display().to_string(),
+            source: e,
+        })?;
+        let fail = |message: String| AppError::Plugin {
+            path: path.display().to_string(),
+            message,
+        };
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+        let engine = wasmi::Engine::new(&config);
+        let module = wasmi::Module::new(&engine, &wasm).map_err(|e| fail(e.to_string()))?;
+        if let Some(import) = module.imports().next() {
+            return Err(fail(format!(
+                "imports `{}::{}`, but plugins get no imports",
+                import.module(),
+                import.name()
+            )));
+        }
+        for (export, func) in [("memory", false), ("alloc", true), ("transform", true)] {
+            match module.get_export(export) {
+                Some(wasmi::ExternType::Func(_)) if func => {}
+                Some(wasmi::ExternType::Memory(_)) if !func => {}
+                _ => return Err(fail(format!("does not export `{export}`"))),
+            }
+        }
+        Ok(Plugin {
+            path: path.to_path_buf(),
+            hash: source::sha256_hex(&wasm),
+            engine,
+            module,
+            fuel: FUEL,
+        })
+    }
+
+    /// Run the plugin on the icon `name` read from `origin`, replacing its
+    /// root `attributes` when the result has some and returning its children.
+    pub(crate) fn run(
+        &self,
+        name: &str,
+        origin: &str,
+        attributes: &mut Vec<(String, String)>,
+        children: &str,
+    ) -> Result<String, AppError> {
+        let fail = |message: String| AppError::Plugin {
+            path: self.path.display().to_string(),
+            message: format!("{origin}: {message}"),
+        };
+        let icon = Icon {
+            name,
+            attributes,
+            children,
+        };
+        let input = serde_json::to_vec(&icon).expect("icon serializes");
+        let output = self.call(&input).map_err(fail)?;
+        let output: Output =
+            serde_json::from_slice(&output).map_err(|e| fail(format!("invalid result: {e}")))?;
+        if let Some(message) = output.error {
+            return Err(fail(message));
+        }
+        let Some(children) = output.children else {
+            return Err(fail("result has no `children`".to_string()));
+        };
+        if !well_formed(&children) {
+            return Err(fail(
+                "result `children` is not well-formed markup".to_string(),
+            ));
+        }
+        if let Some(out) = output.attributes {
+            *attributes = out;
+        }
+        Ok(children)
+    }
+
+    /// Instantiate the module, pass it `input` and read back its result.
+    fn call(&self, input: &[u8]) -> Result<Vec<u8>, String> {
+        let limits = wasmi::StoreLimitsBuilder::new()
+            .memory_size(MEMORY_BYTES)
+            .build();
+        let mut store = wasmi::Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.fuel).map_err(|e| e.to_string())?;
+        let instance = wasmi::Linker::new(&self.engine)
+            .instantiate_and_start(&mut store, &self.module)
+            .map_err(|e| e.to_string())?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or("does not export `memory`")?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "alloc")
+            .map_err(|e| format!("`alloc`: {e}"))?;
+        let transform = instance
+            .get_typed_func::<(i32, i32), i64>(&store, "transform")
+            .map_err(|e| format!("`transform`: {e}"))?;
+        let len = i32::try_from(input.len()).map_err(|_| "icon is too large".to_string())?;
+        let ptr = alloc
+            .call(&mut store, len)
+            .map_err(|e| format!("`alloc`: {e}"))?;
+        memory
+            .write(&mut store, ptr as u32 as usize, input)
+            .map_err(|e| format!("`alloc` returned {ptr}: {e}"))?;
+        let packed = transform
+            .call(&mut store, (ptr, len))
+            .map_err(|e| format!("`transform`: {e}"))? as u64;
+        let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        memory
+            .data(&store)
+            .get(ptr..ptr + len)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| format!("result at {ptr} ({len} bytes) is outside its memory"))
+    }
+}
+
+/// Whether `markup` tokenizes with every start tag closed, in order.
+fn well_formed(markup: &str) -> bool {
+    let Some(tokens) = markup::tokenize(markup) else {
+        return false;
+    };
+    let mut open = Vec::new();
+    for token in tokens {
+        match token {
+            Token::Open {
+                name, empty: false, ..
+            } => open.push(name),
+            Token::Close(raw) => {
+                let name = raw[2..raw.len() - 1].trim_end();
+                if open.pop() != Some(name) {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+    open.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `wat` compiled to a module under a fresh directory and load it.
+    fn plugin(name: &str, wat: &str) -> Result<Plugin, AppError> {
+        let dir = std::env::temp_dir().join(format!("svg_sheet_plugin_{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{name}.wasm"));
+        std::fs::write(&path, wat::parse_str(wat).unwrap()).unwrap();
+        Plugin::load(&path)
+    }
+
+    /// A plugin returning `result` whatever the icon.
+    fn constant(name: &str, result: &str) -> Plugin {
+        let data = result.replace('\\', "\\\\").replace('"', "\\\"");
+        plugin(
+            name,
+            &format!(
+                r#"(module
+                  (memory (export "memory") 1)
+                  (data (i32.const 0) "{data}")
+                  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+                  (func (export "transform") (param i32 i32) (result i64)
+                    (i64.const {})))"#,
+                result.len()
+            ),
+        )
+        .unwrap()
+    }
+
+    fn attrs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn identity_plugin_sees_the_icon_as_json() {
+        // Hands back the input, which has a `children` key, untouched.
+        let echo = plugin(
+            "echo",
+            r#"(module
+              (memory (export "memory") 1)
+              (func (export "alloc") (param i32) (result i32) (i32.const 16))
+              (func (export "transform") (param $ptr i32) (param $len i32) (result i64)
+                (i64.or
+                  (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                  (i64.extend_i32_u (local.get $len)))))"#,
+        )
+        .unwrap();
+        let mut root = attrs(&[("viewBox", "0 0 24 24")]);
+        let out = echo.run("home", "home.svg", &mut root, "<path d=\"M0 0\"/>");
+        assert_eq!(out.unwrap(), "<path d=\"M0 0\"/>");
+        assert_eq!(root, attrs(&[("viewBox", "0 0 24 24")]));
+    }
+
+    #[test]
+    fn result_replaces_children_and_attributes() {
+        let stamp = constant(
+            "stamp",
+            r#"{"attributes":[["data-owner","acme"]],"children":"<g/><desc>(c) Acme</desc>"}"#,
+        );
+        let mut root = attrs(&[("viewBox", "0 0 24 24")]);
+        let out = stamp.run("home", "home.svg", &mut root, "<g/>").unwrap();
+        assert_eq!(out, "<g/><desc>(c) Acme</desc>");
+        assert_eq!(root, attrs(&[("data-owner", "acme")]));
+    }
+
+    #[test]
+    fn errors_name_the_plugin_and_the_icon() {
+        let err = constant("refuse", r#"{"error":"no license"}"#)
+            .run("home", "svgs/home.svg", &mut Vec::new(), "<g/>")
+            .expect_err("plugin error");
+        assert_eq!(err.code(), "E0027");
+        assert!(err.path().unwrap().ends_with("refuse.wasm"));
+        assert!(
+            err.to_string().ends_with("svgs/home.svg: no license"),
+            "{err}"
+        );
+
+        for (name, result, message) in [
+            ("garbage", "nope", "invalid result"),
+            ("empty", "{}", "result has no `children`"),
+            ("unclosed", r#"{"children":"<g>"}"#, "not well-formed"),
+            (
+                "escapes",
+                r#"{"children":"</pattern><g/>"}"#,
+                "not well-formed",
+            ),
+        ] {
+            let err = constant(name, result)
+                .run("home", "home.svg", &mut Vec::new(), "<g/>")
+                .expect_err(name);
+            assert!(err.to_string().contains(message), "{err}");
+        }
+    }
+
+    #[test]
+    fn runaway_plugins_are_stopped() {
+        let mut spin = plugin(
+            "spin",
+            r#"(module
+              (memory (export "memory") 1)
+              (func (export "alloc") (param i32) (result i32) (i32.const 0))
+              (func (export "transform") (param i32 i32) (result i64)
+                (loop $l (br $l))
+                (i64.const 0)))"#,
+        )
+        .unwrap();
+        spin.fuel = 10_000;
+        let err = spin
+            .run("home", "home.svg", &mut Vec::new(), "<g/>")
+            .expect_err("out of fuel");
+        assert!(err.to_string().contains("`transform`"), "{err}");
+    }
+
+    #[test]
+    fn load_checks_imports_and_exports() {
+        let err = plugin("no_alloc", r#"(module (memory (export "memory") 1))"#)
+            .expect_err("missing exports");
+        assert!(err.to_string().contains("does not export `alloc`"), "{err}");
+
+        let err = plugin(
+            "imports",
+            r#"(module (import "wasi_snapshot_preview1" "fd_write" (func)))"#,
+        )
+        .expect_err("imports");
+        assert!(
+            err.to_string()
+                .contains("imports `wasi_snapshot_preview1::fd_write`"),
+            "{err}"
+        );
+
+        let err = Plugin::load(Path::new("/nonexistent/plugin.wasm")).expect_err("missing file");
+        assert_eq!(err.code(), "E0002");
+    }
+}
@@ -0,0 +1,152 @@
+//! Policy for `<image>` elements embedding raster data (`data:image/png;base64,...`).
+
+use std::borrow::Cow;
+
+/// What to do with embedded raster images at or above the size threshold.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RasterPolicy {
+    /// Keep them silently
+    Allow,
+    /// Keep them and emit a warning naming the type and size
+    #[default]
+    Warn,
+    /// Remove the `<image>` element
+    Strip,
+}
+
+/// Apply `policy` to the embedded raster images in `children` whose data URI
+/// is at least `threshold` bytes. Returns the content (rewritten only when
+/// something was stripped) and one warning per image kept under
+/// `RasterPolicy::Warn`.
+///
+/// Images referencing external files and `data:image/svg+xml` are vector or
+/// not embedded, and are left alone.
+pub(crate) fn apply(
+    children: &str,
+    policy: RasterPolicy,
+    threshold: u64,
+) -> (Cow<'_, str>, Vec<String>) {
+    let mut warnings = Vec::new();
+    if policy == RasterPolicy::Allow {
+        return (Cow::Borrowed(children), warnings);
+    }
+    let mut out = String::new();
+    let mut from = 0;
+    while let Some((start, end, mime, size)) = next_raster(&children[from..], threshold) {
+        let (start, end) = (from + start, from + end);
+        if policy == RasterPolicy::Strip {
+            tracing::info!(mime, size, "Stripped embedded raster image");
+            out.push_str(&children[from..start]);
+        } else {
+            warnings.push(format!(
+                "Embedded raster image ({mime}, {}); use --raster-images strip to remove it",
+                super::summary::format_bytes(size)
+            ));
+        }
+        from = end;
+    }
+    if policy == RasterPolicy::Warn || from == 0 {
+        return (Cow::Borrowed(children), warnings);
+    }
+    out.push_str(&children[from..]);
+    (Cow::Owned(out), warnings)
+}
+
+/// Locate the next `<image>` element with a raster data URI of at least
+/// `threshold` bytes: `(start, end, mime, uri_len)`.
+fn next_raster(s: &str, threshold: u64) -> Option<(usize, usize, &str, u64)> {
+    let mut from = 0;
+    while let Some(at) = s[from..].find("<image") {
+        let start = from + at;
+        let after = &s[start + "<image".len()..];
+        from = start + "<image".len();
+        if !after.starts_with(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>') {
+            continue;
+        }
+        let tag_len = tag_end(after)?;
+        let tag = &after[..tag_len];
+        let mut end = from + tag_len + 1;
+        if !tag.ends_with('/')
+            && let Some(close) = s[end..].find("</image>")
+        {
+            end += close + "</image>".len();
+        }
+        let Some(uri) = href(tag) else {
+            continue;
+        };
+        let Some(mime) = uri
+            .strip_prefix("data:")
+            .and_then(|d| d.split([';', ',']).next())
+        else {
+            continue;
+        };
+        let raster = mime.starts_with("image/") && mime != "image/svg+xml";
+        if raster && uri.len() as u64 >= threshold {
+            return Some((start, end, mime, uri.len() as u64));
+        }
+        from = end;
+    }
+    None
+}
+
+/// Offset of the `>` closing a start tag, skipping quoted attribute values.
+pub(crate) fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Value of the `href` (or `xlink:href`) attribute in a start tag.
+fn href(tag: &str) -> Option<&str> {
+    let at = tag.find("href=")?;
+    let value = &tag[at + "href=".len()..];
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &value[1..];
+    Some(&value[..value.find(quote)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &str = "<image width='1' href='data:image/png;base64,iVBORw0KGgo='/>";
+
+    #[test]
+    fn warns_strips_or_allows_embedded_rasters() {
+        let content = format!("<g/>{PNG}<image href=\"a.png\"/><path/>");
+
+        let (kept, warnings) = apply(&content, RasterPolicy::Warn, 0);
+        assert_eq!(kept, content);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("image/png"));
+
+        let (stripped, warnings) = apply(&content, RasterPolicy::Strip, 0);
+        assert_eq!(stripped, "<g/><image href=\"a.png\"/><path/>");
+        assert!(warnings.is_empty());
+
+        let (allowed, warnings) = apply(&content, RasterPolicy::Allow, 0);
+        assert_eq!(allowed, content);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn threshold_and_element_forms() {
+        let (_, warnings) = apply(PNG, RasterPolicy::Warn, 1024);
+        assert!(warnings.is_empty(), "below threshold");
+
+        let paired =
+            "<image xlink:href=\"data:image/jpeg;base64,/9j/\"><title>x</title></image><g/>";
+        assert_eq!(apply(paired, RasterPolicy::Strip, 0).0, "<g/>");
+
+        let vector = "<image href='data:image/svg+xml;utf8,<svg/>'/>";
+        assert_eq!(apply(vector, RasterPolicy::Strip, 0).0, vector);
+        assert_eq!(apply("<imageX/>", RasterPolicy::Strip, 0).0, "<imageX/>");
+    }
+}
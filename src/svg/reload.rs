@@ -0,0 +1,202 @@
+//! Config file reloading and `--rebuild-trigger` files for watch mode.
+//!
+//! The library does not read `svg_sheet.toml` itself; the caller supplies a
+//! loader that turns the current file into `RunOpts`, and the watch loop calls
+//! it whenever the file's size or modification time changes.
+
+use super::RunOpts;
+use crate::error::AppError;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Loader producing fresh `RunOpts` from the config file.
+pub(crate) type Loader<'a> = Box<dyn FnMut() -> Result<RunOpts, AppError> + 'a>;
+
+/// A config file watched for changes, with the loader applied on each one.
+pub(crate) struct ConfigWatch<'a> {
+    path: PathBuf,
+    stamp: Option<(u64, SystemTime)>,
+    load: Loader<'a>,
+}
+
+impl<'a> ConfigWatch<'a> {
+    pub(crate) fn new(path: &Path, load: Loader<'a>) -> Self {
+        ConfigWatch {
+            path: path.to_path_buf(),
+            stamp: stamp(path),
+            load,
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reload when the file was created, edited, or removed since the last
+    /// call; `None` when it is unchanged.
+    pub(crate) fn poll(&mut self) -> Option<Result<RunOpts, AppError>> {
+        let now = stamp(&self.path);
+        if now == self.stamp {
+            return None;
+        }
+        self.stamp = now;
+        Some((self.load)())
+    }
+}
+
+/// A `--rebuild-trigger` file: creating or touching it asks a running watcher
+/// for a full rebuild.
+pub(crate) struct Trigger {
+    path: PathBuf,
+    stamp: Option<(u64, SystemTime)>,
+}
+
+impl Trigger {
+    pub(crate) fn new(path: &Path) -> Self {
+        Trigger {
+            path: path.to_path_buf(),
+            stamp: stamp(path),
+        }
+    }
+
+    /// Whether the file was created or touched since the last call. Removing
+    /// it does not count.
+    pub(crate) fn fired(&mut self) -> bool {
+        let now = stamp(&self.path);
+        let fired = now.is_some() && now != self.stamp;
+        self.stamp = now;
+        fired
+    }
+}
+
+/// Size and modification time of `path`, or `None` when it does not exist.
+fn stamp(path: &Path) -> Option<(u64, SystemTime)> {
+    let md = std::fs::metadata(path).ok()?;
+    Some((md.len(), md.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+}
+
+/// Names of the config-controlled settings that differ between `old` and `new`.
+pub(crate) fn changed_settings(old: &RunOpts, new: &RunOpts) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+    if old.emit != new.emit {
+        changed.push("emit");
+    }
+    if old.strip_attributes != new.strip_attributes || old.keep_attributes != new.keep_attributes {
+        changed.push("attributes");
+    }
+    if old.colors != new.colors {
+        changed.push("colors");
+    }
+    if old.roles != new.roles {
+        changed.push("roles");
+    }
+    if old.aliases != new.aliases {
+        changed.push("aliases");
+    }
+    if old.deprecated != new.deprecated {
+        changed.push("deprecated");
+    }
+    if old.tags != new.tags {
+        changed.push("tags");
+    }
+    if old.root_attrs != new.root_attrs {
+        changed.push("root");
+    }
+    if old.pipeline != new.pipeline {
+        changed.push("pipeline");
+    }
+    if old.allow != new.allow || old.deny != new.deny {
+        changed.push("warning rules");
+    }
+    if old.warning_baseline != new.warning_baseline {
+        changed.push("warning baseline");
+    }
+    let switches = |o: &RunOpts| {
+        [
+            o.source_comments,
+            o.hash_attrs,
+            o.keep_license_comments,
+            o.dedupe,
+            o.detect_duplicates,
+            o.compact_paths,
+            o.shared_defs,
+            o.plain_href,
+            o.optimize,
+            o.flatten_transforms,
+            o.fail_on_warn,
+            o.strict,
+            o.lock,
+        ]
+    };
+    if switches(old) != switches(new) {
+        changed.push("profile");
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::WarningKind;
+
+    #[test]
+    fn reloads_only_when_the_file_changes() {
+        let dir = std::env::temp_dir().join("svg_reload_poll");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("svg_sheet.toml");
+        let mut loads = 0;
+        let mut watch = ConfigWatch::new(
+            &path,
+            Box::new(|| {
+                loads += 1;
+                Ok(RunOpts::default())
+            }),
+        );
+        assert!(watch.poll().is_none(), "missing file is unchanged");
+        std::fs::write(&path, "emit = []\n").unwrap();
+        assert!(watch.poll().is_some_and(|r| r.is_ok()));
+        assert!(watch.poll().is_none());
+        std::fs::write(&path, "emit = [\"ts=icons.ts\"]\n").unwrap();
+        assert!(watch.poll().is_some());
+        std::fs::remove_file(&path).unwrap();
+        assert!(watch.poll().is_some(), "removal reloads the defaults");
+        drop(watch);
+        assert_eq!(loads, 3);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn triggers_fire_on_create_and_touch() {
+        let dir = std::env::temp_dir().join("svg_reload_trigger");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rebuild");
+        let mut trigger = Trigger::new(&path);
+        assert!(!trigger.fired());
+        std::fs::write(&path, "").unwrap();
+        assert!(trigger.fired());
+        assert!(!trigger.fired());
+        std::fs::write(&path, "x").unwrap();
+        assert!(trigger.fired());
+        std::fs::remove_file(&path).unwrap();
+        assert!(!trigger.fired());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn lists_changed_settings() {
+        let old = RunOpts::default();
+        assert!(changed_settings(&old, &RunOpts::default()).is_empty());
+        let new = RunOpts {
+            colors: vec![("#000".into(), "currentColor".into())],
+            deny: vec![WarningKind::MissingViewBox],
+            warning_baseline: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            changed_settings(&old, &new),
+            ["colors", "warning rules", "warning baseline"]
+        );
+    }
+}
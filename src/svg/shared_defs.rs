@@ -0,0 +1,181 @@
+//! `_defs.svg`: definitions (brand gradients, filters, clip paths) shared by
+//! every icon, placed once in the sprite's root `<defs>`.
+
+use super::ids::defined_ids;
+use super::{CacheEntry, parsing, source};
+use crate::error::{AppError, Warning, WarningKind};
+use std::path::Path;
+use winnow::Parser;
+
+/// File name of the shared definitions, read from the input directory.
+pub(crate) const DEFS_FILE: &str = "_defs.svg";
+
+/// The parsed contents of `_defs.svg`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SharedDefs {
+    /// Path the definitions were read from
+    pub path: String,
+    /// Markup for the sprite's root `<defs>`
+    pub markup: String,
+    /// Every id defined in `markup`
+    pub ids: Vec<String>,
+}
+
+/// Whether `path` is the shared definitions file of `directory`.
+pub(crate) fn is_defs_file(directory: &str, path: &Path) -> bool {
+    path.parent() == Some(Path::new(directory))
+        && path
+            .file_name()
+            .is_some_and(|n| n.eq_ignore_ascii_case(DEFS_FILE))
+}
+
+/// Read `_defs.svg` from `directory`; `None` when there is none or no
+/// directory was read (an empty `directory`). The root
+/// `<svg>` (and a `<defs>` wrapping all of its content) is dropped, and the
+/// ids inside are kept as written.
+pub(crate) fn load(directory: &str) -> Result<Option<SharedDefs>, AppError> {
+    let path = Path::new(directory).join(DEFS_FILE);
+    if directory.is_empty() || !path.is_file() {
+        return Ok(None);
+    }
+    let origin = path.display().to_string();
+    let file = std::fs::File::open(&path).map_err(|e| AppError::ReadFile {
+        path: origin.clone(),
+        source: e,
+    })?;
+    let doc = source::read_document(file).map_err(|e| AppError::ReadFile {
+        path: origin.clone(),
+        source: e,
+    })?;
+    let mut body: &str = &doc.body;
+    let (_, children) = parsing::parse_svg
+        .parse_next(&mut body)
+        .map_err(|_| parsing::located_error(&origin, &doc.body, doc.start))?;
+    let markup = unwrap_defs(children.trim()).to_string();
+    let ids = defined_ids(&markup).ok_or_else(|| AppError::ParseSvg {
+        path: origin.clone(),
+        message: "unterminated tag".into(),
+        location: None,
+    })?;
+    tracing::debug!(path = %origin, ids = ids.len(), "Loaded shared definitions");
+    Ok(Some(SharedDefs {
+        path: origin,
+        markup,
+        ids,
+    }))
+}
+
+/// The content of a single `<defs>` element wrapping all of `children`, or
+/// `children` itself.
+fn unwrap_defs(children: &str) -> &str {
+    children
+        .strip_prefix("<defs>")
+        .and_then(|rest| rest.strip_suffix("</defs>"))
+        .filter(|inner| !inner.contains("<defs"))
+        .map_or(children, str::trim)
+}
+
+/// Fail when a shared definition reuses an icon's id.
+pub(crate) fn check_collisions(defs: &SharedDefs, entries: &[&CacheEntry]) -> Result<(), AppError> {
+    for id in &defs.ids {
+        if let Some(entry) = entries.iter().find(|e| &e.name == id) {
+            return Err(AppError::IdCollision {
+                id: id.clone(),
+                first_path: entry.path_str.clone(),
+                second_path: defs.path.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// A warning for each icon referencing an id that is not defined in the
+/// icon, in `defs`, or as an icon of the sprite.
+pub(crate) fn unresolved(defs: &SharedDefs, entries: &[&CacheEntry]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for entry in entries {
+        let local = defined_ids(&entry.children).unwrap_or_default();
+        for id in super::ids::referenced_ids(&entry.children) {
+            let resolved = local.iter().any(|l| l == id)
+                || defs.ids.iter().any(|d| d == id)
+                || entry.shared_defs.iter().any(|(d, _)| d == id)
+                || entries.iter().any(|e| e.name == id);
+            if !resolved {
+                warnings.push(Warning::new(
+                    WarningKind::UnresolvedReference,
+                    &entry.path_str,
+                    format!(
+                        "Reference to '#{id}' matches no definition in the icon or {DEFS_FILE}"
+                    ),
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn loads_definitions_without_the_wrappers() {
+        let dir = std::env::temp_dir().join("svg_shared_defs_load");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let d = dir.to_str().unwrap();
+        assert!(load(d).unwrap().is_none());
+        fs::write(
+            dir.join(DEFS_FILE),
+            "<?xml version=\"1.0\"?><svg xmlns=\"http://www.w3.org/2000/svg\"><defs><linearGradient id=\"brand\"><stop id=\"s\" offset=\"0\"/></linearGradient></defs></svg>",
+        )
+        .unwrap();
+        let defs = load(d).unwrap().unwrap();
+        assert_eq!(
+            defs.markup,
+            "<linearGradient id=\"brand\"><stop id=\"s\" offset=\"0\"/></linearGradient>"
+        );
+        assert_eq!(defs.ids, ["brand", "s"]);
+        assert!(is_defs_file(d, &dir.join(DEFS_FILE)));
+        assert!(!is_defs_file(d, &dir.join("sub").join(DEFS_FILE)));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn reports_references_that_resolve_nowhere() {
+        let defs = SharedDefs {
+            ids: vec!["brand".into()],
+            ..Default::default()
+        };
+        let icon = |name: &str, children: &str| CacheEntry {
+            mtime: std::time::UNIX_EPOCH,
+            len: 0,
+            name: name.into(),
+            out_attrs: vec![],
+            children: children.into(),
+            child_ids: vec![],
+            path_str: format!("{name}.svg"),
+            warnings: vec![],
+            hash: String::new(),
+            licenses: vec![],
+            shared_defs: vec![],
+            colors_remapped: 0,
+            attributes_stripped: vec![],
+            theme_vars: vec![],
+            overrides: None,
+        };
+        let a = icon("a", "<path fill=\"url(#brand)\"/><use href=\"#b\"/>");
+        let b = icon(
+            "b",
+            "<clipPath id=\"b-c\"/><g clip-path=\"url(#b-c)\" fill=\"url(#brnd)\"/>",
+        );
+        let warnings = unresolved(&defs, &[&a, &b]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "b.svg");
+        assert_eq!(
+            warnings[0].message,
+            "Reference to '#brnd' matches no definition in the icon or _defs.svg"
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! Sidecar metadata: an optional `<icon>.svg.toml` file beside an icon with
+//! its title, description, tags, deprecation, and root attribute overrides,
+//! so metadata lives next to the artwork where designers edit it.
+
+use super::CacheEntry;
+use crate::error::AppError;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Sidecars of the icons of a build, by icon name.
+pub(crate) type Sidecars = HashMap<String, Sidecar>;
+
+/// The contents of a sidecar file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Sidecar {
+    /// Added to the symbol as `<title>` when the icon has none
+    pub title: Option<String>,
+    /// Added to the symbol as `<desc>` when the icon has none
+    pub description: Option<String>,
+    /// Merged with the config's `[tags]` for the icon
+    pub tags: Vec<String>,
+    /// `true`, or a message such as `"Use house instead"`
+    pub deprecated: Option<Deprecated>,
+    /// Root attributes set on the symbol, replacing the icon's own
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// A sidecar's `deprecated` value.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Deprecated {
+    Flag(bool),
+    Message(String),
+}
+
+impl Sidecar {
+    /// The deprecation message (empty for `deprecated = true`), or `None`
+    /// when the sidecar does not deprecate the icon.
+    pub(crate) fn deprecation(&self) -> Option<&str> {
+        match self.deprecated.as_ref()? {
+            Deprecated::Flag(true) => Some(""),
+            Deprecated::Flag(false) => None,
+            Deprecated::Message(message) => Some(message),
+        }
+    }
+
+    /// Whether applying the sidecar changes the icon's markup.
+    /// Whether applying the sidecar changes the icon's markup, counting its
+    /// title and description only with `labels`.
+    fn changes_markup(&self, labels: bool) -> bool {
+        (labels && (self.title.is_some() || self.description.is_some()))
+            || !self.attributes.is_empty()
+    }
+}
+
+/// The sidecar file of the icon at `source`: `home.svg` gives `home.svg.toml`.
+pub(crate) fn path_of(source: &Path) -> PathBuf {
+    let mut path = source.as_os_str().to_owned();
+    path.push(".toml");
+    PathBuf::from(path)
+}
+
+/// Read the sidecar file of the icon at `source`, if there is one.
+pub(crate) fn load(source: &Path) -> Result<Option<Sidecar>, AppError> {
+    let path = path_of(source);
+    let origin = path.display().to_string();
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(AppError::ReadFile {
+                path: origin,
+                source: e,
+            });
+        }
+    };
+    let sidecar: Sidecar = toml::from_str(&text).map_err(|e| AppError::Sidecar {
+        path: origin.clone(),
+        message: e.message().to_string(),
+    })?;
+    if sidecar.attributes.contains_key("id") {
+        return Err(AppError::Sidecar {
+            path: origin,
+            message: "`id` cannot be overridden; rename the icon file instead".into(),
+        });
+    }
+    Ok(Some(sidecar))
+}
+
+/// The sidecars of `entries`. Icons without a file of their own (collection
+/// and package icons) have none.
+pub(crate) fn load_all(entries: &[&CacheEntry]) -> Result<Sidecars, AppError> {
+    let mut sidecars = Sidecars::new();
+    for entry in entries {
+        if let Some(sidecar) = load(Path::new(&entry.path_str))? {
+            tracing::debug!(icon = %entry.name, "Loaded sidecar metadata");
+            sidecars.insert(entry.name.clone(), sidecar);
+        }
+    }
+    Ok(sidecars)
+}
+
+/// Copies of the `entries` whose sidecar changes their markup, with the
+/// sidecar applied: attributes overridden, and with `labels` (the `a11y`
+/// stage), `<title>`/`<desc>` added.
+pub(crate) fn patch(entries: &[&CacheEntry], sidecars: &Sidecars, labels: bool) -> Vec<CacheEntry> {
+    entries
+        .iter()
+        .filter_map(|e| {
+            let sidecar = sidecars.get(&e.name).filter(|s| s.changes_markup(labels))?;
+            Some(apply(e, sidecar, labels))
+        })
+        .collect()
+}
+
+fn apply(entry: &CacheEntry, sidecar: &Sidecar, labels: bool) -> CacheEntry {
+    let mut entry = entry.clone();
+    for (k, v) in &sidecar.attributes {
+        match entry.out_attrs.iter_mut().find(|(name, _)| name == k) {
+            Some((_, value)) => *value = v.clone(),
+            None => entry.out_attrs.push((k.clone(), v.clone())),
+        }
+    }
+    if !labels {
+        return entry;
+    }
+    let mut text = String::new();
+    if let Some(title) = &sidecar.title
+        && !entry.children.contains("<title")
+    {
+        text.push_str(&format!("<title>{}</title>", escape_text(title)));
+    }
+    if let Some(description) = &sidecar.description
+        && !entry.children.contains("<desc")
+    {
+        text.push_str(&format!("<desc>{}</desc>", escape_text(description)));
+    }
+    entry.children.insert_str(0, &text);
+    entry
+}
+
+/// Config `[tags]` with each sidecar's tags added to its icon's.
+pub(crate) fn merge_tags(
+    tags: &[(String, Vec<String>)],
+    sidecars: &Sidecars,
+) -> Vec<(String, Vec<String>)> {
+    let mut merged = tags.to_vec();
+    let mut names: Vec<&String> = sidecars.keys().collect();
+    names.sort();
+    for name in names {
+        let extra = &sidecars[name].tags;
+        if extra.is_empty() {
+            continue;
+        }
+        let n = match merged.iter().position(|(icon, _)| icon == name) {
+            Some(n) => n,
+            None => {
+                merged.push((name.clone(), Vec::new()));
+                merged.len() - 1
+            }
+        };
+        for tag in extra {
+            if !merged[n].1.contains(tag) {
+                merged[n].1.push(tag.clone());
+            }
+        }
+    }
+    merged
+}
+
+fn escape_text(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn icon(name: &str) -> CacheEntry {
+        CacheEntry {
+            mtime: std::time::UNIX_EPOCH,
+            len: 0,
+            name: name.into(),
+            out_attrs: vec![
+                ("viewBox".into(), "0 0 24 24".into()),
+                ("fill".into(), "none".into()),
+            ],
+            children: "<path d=\"M0 0\"/>".into(),
+            child_ids: vec![],
+            path_str: format!("{name}.svg"),
+            warnings: vec![],
+            hash: String::new(),
+            licenses: vec![],
+            shared_defs: vec![],
+            colors_remapped: 0,
+            attributes_stripped: vec![],
+            theme_vars: vec![],
+            overrides: None,
+        }
+    }
+
+    #[test]
+    fn loads_sidecars_beside_icons() {
+        let dir = std::env::temp_dir().join("svg_sidecar_load");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let home = dir.join("home.svg");
+        assert_eq!(path_of(&home), dir.join("home.svg.toml"));
+        assert!(load(&home).unwrap().is_none());
+
+        fs::write(
+            path_of(&home),
+            "title = \"Home\"\ntags = [\"nav\"]\ndeprecated = true\n[attributes]\nfill = \"currentColor\"\n",
+        )
+        .unwrap();
+        let sidecar = load(&home).unwrap().unwrap();
+        assert_eq!(sidecar.title.as_deref(), Some("Home"));
+        assert_eq!(sidecar.deprecation(), Some(""));
+
+        fs::write(path_of(&home), "titel = \"Home\"\n").unwrap();
+        let err = load(&home).unwrap_err();
+        assert_eq!(err.code(), "E0026");
+        assert!(err.to_string().contains("unknown field `titel`"));
+
+        fs::write(path_of(&home), "[attributes]\nid = \"x\"\n").unwrap();
+        assert!(load(&home).unwrap_err().to_string().contains("`id`"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn patches_attributes_and_adds_title() {
+        let (a, b) = (icon("a"), icon("b"));
+        let sidecars = Sidecars::from([
+            (
+                "a".to_string(),
+                Sidecar {
+                    title: Some("A & B".into()),
+                    description: Some("First".into()),
+                    attributes: BTreeMap::from([
+                        ("fill".into(), "currentColor".into()),
+                        ("role".into(), "img".into()),
+                    ]),
+                    ..Default::default()
+                },
+            ),
+            (
+                "b".to_string(),
+                Sidecar {
+                    tags: vec!["x".into()],
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let patched = patch(&[&a, &b], &sidecars, true);
+        assert_eq!(patched.len(), 1);
+        assert_eq!(
+            patched[0].out_attrs,
+            [
+                ("viewBox".to_string(), "0 0 24 24".to_string()),
+                ("fill".into(), "currentColor".into()),
+                ("role".into(), "img".into()),
+            ]
+        );
+        assert_eq!(
+            patched[0].children,
+            "<title>A &amp; B</title><desc>First</desc><path d=\"M0 0\"/>"
+        );
+
+        // Without the `a11y` stage only the attributes apply.
+        let unlabeled = patch(&[&a, &b], &sidecars, false);
+        assert_eq!(unlabeled[0].out_attrs, patched[0].out_attrs);
+        assert_eq!(unlabeled[0].children, "<path d=\"M0 0\"/>");
+    }
+
+    #[test]
+    fn merges_sidecar_tags_into_config_tags() {
+        let tags = [("a".to_string(), vec!["nav".to_string()])];
+        let sidecars = Sidecars::from([
+            (
+                "a".to_string(),
+                Sidecar {
+                    tags: vec!["nav".into(), "home".into()],
+                    ..Default::default()
+                },
+            ),
+            (
+                "b".to_string(),
+                Sidecar {
+                    tags: vec!["x".into()],
+                    ..Default::default()
+                },
+            ),
+        ]);
+        assert_eq!(
+            merge_tags(&tags, &sidecars),
+            [
+                ("a".to_string(), vec!["nav".to_string(), "home".into()]),
+                ("b".to_string(), vec!["x".to_string()]),
+            ]
+        );
+    }
+}
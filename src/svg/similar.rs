@@ -0,0 +1,192 @@
+//! Near-duplicate icon detection (`--detect-duplicates`).
+//!
+//! Icons are compared by a canonical form that ignores whitespace, attribute
+//! order, quote style, and numeric precision beyond one decimal place, so
+//! drifted copies of the same glyph compare equal.
+
+use super::CacheEntry;
+use std::collections::HashMap;
+
+/// Return pairs `(first, other)` of icon names whose canonical forms match,
+/// in output order. Each icon is paired with the first icon of its group.
+pub(crate) fn near_duplicates(entries: &[&CacheEntry]) -> Vec<(String, String)> {
+    let mut groups: HashMap<String, &str> = HashMap::new();
+    let mut pairs = Vec::new();
+    for entry in entries {
+        match groups.entry(canonical(entry)) {
+            std::collections::hash_map::Entry::Occupied(o) => {
+                pairs.push((o.get().to_string(), entry.name.clone()));
+            }
+            std::collections::hash_map::Entry::Vacant(v) => {
+                v.insert(&entry.name);
+            }
+        }
+    }
+    pairs
+}
+
+/// Canonical form of an icon: sorted root attributes (without `data-id`)
+/// followed by the canonicalized children markup.
+fn canonical(entry: &CacheEntry) -> String {
+    let mut attrs: Vec<String> = entry
+        .out_attrs
+        .iter()
+        .filter(|(k, _)| k != "data-id")
+        .map(|(k, v)| format!("{k}={}", canonical_value(v)))
+        .collect();
+    attrs.sort();
+    format!("{}|{}", attrs.join(" "), canonical_markup(&entry.children))
+}
+
+/// Canonicalize markup tag by tag: attributes sorted and numbers rounded,
+/// whitespace between and inside tags collapsed.
+fn canonical_markup(markup: &str) -> String {
+    let mut out = String::new();
+    let mut rest = markup;
+    while let Some(start) = rest.find('<') {
+        let text = rest[..start]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        out.push_str(&text);
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        out.push_str(&canonical_tag(&rest[start + 1..start + len]));
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(&rest.split_whitespace().collect::<Vec<_>>().join(" "));
+    out
+}
+
+/// Canonicalize the inside of one tag (between `<` and `>`).
+fn canonical_tag(tag: &str) -> String {
+    let tag = tag.trim();
+    if tag.starts_with('/') || tag.starts_with('!') || tag.starts_with('?') {
+        return format!("<{}>", tag.split_whitespace().collect::<Vec<_>>().join(""));
+    }
+    let self_closing = tag.ends_with('/');
+    let tag = tag.trim_end_matches('/');
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let (name, mut attrs_src) = tag.split_at(name_end);
+    let mut attrs = Vec::new();
+    loop {
+        attrs_src = attrs_src.trim_start();
+        let Some(eq) = attrs_src.find('=') else {
+            break;
+        };
+        let key = attrs_src[..eq].trim();
+        let after = attrs_src[eq + 1..].trim_start();
+        let Some(quote) = after.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            break;
+        };
+        let Some(end) = after[1..].find(quote) else {
+            break;
+        };
+        let value = &after[1..1 + end];
+        attrs.push(format!("{key}=\"{}\"", canonical_value(value)));
+        attrs_src = &after[end + 2..];
+    }
+    attrs.sort();
+    let mut out = format!("<{name}");
+    for a in attrs {
+        out.push(' ');
+        out.push_str(&a);
+    }
+    out.push_str(if self_closing { "/>" } else { ">" });
+    out
+}
+
+/// Canonicalize an attribute value: numbers rounded, and whitespace kept only
+/// where it separates two numbers (`M0 0 L1 1` and `M0 0L1 1` compare equal).
+fn canonical_value(v: &str) -> String {
+    let rounded = round_numbers(v);
+    let words: Vec<&str> = rounded.split_whitespace().collect();
+    let mut out = String::with_capacity(rounded.len());
+    for (i, word) in words.iter().enumerate() {
+        let numeric_gap = i > 0
+            && words[i - 1].ends_with(|c: char| c.is_ascii_digit() || c == '.')
+            && word.starts_with(|c: char| c.is_ascii_digit() || c == '.');
+        if numeric_gap {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    out
+}
+
+/// Round every decimal number in `s` to one decimal place. Integers, including
+/// digits inside ids such as `clip0`, come through unchanged.
+fn round_numbers(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let starts_number = bytes[i].is_ascii_digit()
+            || (bytes[i] == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit));
+        if !starts_number {
+            let ch = s[i..].chars().next().expect("in bounds");
+            out.push(ch);
+            i += ch.len_utf8();
+            continue;
+        }
+        let start = i;
+        let mut seen_dot = false;
+        while i < bytes.len() && (bytes[i].is_ascii_digit() || (bytes[i] == b'.' && !seen_dot)) {
+            seen_dot |= bytes[i] == b'.';
+            i += 1;
+        }
+        match s[start..i].parse::<f64>() {
+            Ok(n) => {
+                let rounded = (n * 10.0).round() / 10.0;
+                out.push_str(&super::normalize::normalize_number(rounded));
+            }
+            Err(_) => out.push_str(&s[start..i]),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn entry(name: &str, children: &str) -> CacheEntry {
+        CacheEntry {
+            mtime: UNIX_EPOCH,
+            len: 0,
+            name: name.into(),
+            out_attrs: vec![("viewBox".into(), "0 0 24 24".into())],
+            children: children.into(),
+            child_ids: vec![],
+            path_str: format!("{name}.svg"),
+            warnings: vec![],
+            hash: String::new(),
+            licenses: vec![],
+            shared_defs: vec![],
+            colors_remapped: 0,
+            attributes_stripped: vec![],
+            theme_vars: vec![],
+            overrides: None,
+        }
+    }
+
+    #[test]
+    fn rounds_numbers_but_not_identifiers() {
+        assert_eq!(round_numbers("M0.04 12.96L3 4"), "M0 13L3 4");
+        assert_eq!(round_numbers("url(#clip0)"), "url(#clip0)");
+        assert_eq!(canonical_value(" M0 0 L 1.96\n1 "), "M0 0L2 1");
+    }
+
+    #[test]
+    fn flags_drifted_copies_only() {
+        let a = entry("a", "<path fill='red' d='M0 0L10 10'/>");
+        let b = entry("b", "<path   d=\"M0.001 0 L10 9.999\"\n fill=\"red\" />");
+        let c = entry("c", "<path fill='red' d='M0 0L12 12'/>");
+        assert_eq!(
+            near_duplicates(&[&a, &b, &c]),
+            vec![("a".to_string(), "b".to_string())]
+        );
+    }
+}
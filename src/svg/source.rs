@@ -0,0 +1,291 @@
+//! Reading SVG sources in one pass.
+//!
+//! The prolog before the root `<svg>` (BOM, XML declarations, comments) is
+//! consumed tag by tag through a buffered reader and hashed on the fly; it is
+//! never buffered whole. The body from the root element on is read into a
+//! single buffer, which the parser borrows without further copies, so memory
+//! is proportional to the body rather than to a multiple of the file. It is
+//! not bounded by the largest element: the parser needs the whole body, and
+//! `--max-file-size` is checked against the file's metadata before reading.
+//! Documents already in memory are not copied at all.
+
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::io::BufRead;
+#[cfg(feature = "fs")]
+use std::io::{BufReader, Read};
+
+/// One input document, read and fingerprinted in a single pass. Documents
+/// read from memory borrow their body from the input.
+pub(crate) struct SourceDoc<'a> {
+    /// The root `<svg>` element and anything after it
+    pub(crate) body: Cow<'a, str>,
+    /// SHA-256 (hex) of the raw input bytes
+    pub(crate) hash: String,
+    /// License-like comments found in the prolog
+    pub(crate) licenses: Vec<String>,
+    /// 1-based line and column where `body` starts in the input
+    pub(crate) start: (usize, usize),
+}
+
+/// Feeds every byte read from `inner` into a SHA-256 hasher.
+#[cfg(feature = "fs")]
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+#[cfg(feature = "fs")]
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// What `skip_prolog` leaves behind.
+struct Prolog {
+    /// Bytes read past the prolog: the start of the body
+    pending: Vec<u8>,
+    licenses: Vec<String>,
+    start: (usize, usize),
+    /// Whether `reader` is exhausted
+    #[cfg_attr(not(feature = "fs"), allow(dead_code))]
+    eof: bool,
+}
+
+/// Read an SVG document from `reader`, skipping its prolog. The body is read
+/// to the end into one buffer.
+///
+/// Fails with `InvalidData` when the body is not UTF-8, like
+/// `std::fs::read_to_string`.
+#[cfg(feature = "fs")]
+pub(crate) fn read_document<R: Read>(reader: R) -> std::io::Result<SourceDoc<'static>> {
+    let mut reader = BufReader::new(HashingReader {
+        inner: reader,
+        hasher: Sha256::new(),
+    });
+    let Prolog {
+        mut pending,
+        licenses,
+        start,
+        eof,
+    } = skip_prolog(&mut reader)?;
+    if !eof {
+        reader.read_to_end(&mut pending)?;
+    }
+    let hasher = reader.into_inner().hasher;
+    let body = String::from_utf8(pending)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    Ok(SourceDoc {
+        body: Cow::Owned(body),
+        hash: hex(&hasher.finalize()),
+        licenses,
+        start,
+    })
+}
+
+/// Like `read_document`, for a document already in memory: the body is
+/// borrowed from `content` rather than copied.
+pub(crate) fn document(content: &str) -> SourceDoc<'_> {
+    let mut rest = content.as_bytes();
+    let prolog = skip_prolog(&mut rest).expect("reading from memory cannot fail");
+    let offset = content.len() - rest.len() - prolog.pending.len();
+    SourceDoc {
+        body: Cow::Borrowed(&content[offset..]),
+        hash: sha256_hex(content.as_bytes()),
+        licenses: prolog.licenses,
+        start: prolog.start,
+    }
+}
+
+/// Consume the BOM, XML declarations, and comments before the root element
+/// tag by tag, collecting license comments along the way.
+fn skip_prolog<R: BufRead>(reader: &mut R) -> std::io::Result<Prolog> {
+    let mut pending: Vec<u8> = Vec::new();
+    let mut licenses = Vec::new();
+    let mut eof = false;
+    let mut first = true;
+    let mut position = (1, 1);
+    loop {
+        let start = pending
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(pending.len());
+        advance(&mut position, &pending[..start]);
+        pending.drain(..start);
+        if pending.is_empty() && !eof {
+            eof = reader.read_until(b'>', &mut pending)? == 0;
+            if first {
+                pending = pending
+                    .strip_prefix("\u{feff}".as_bytes())
+                    .map(<[u8]>::to_vec)
+                    .unwrap_or(pending);
+                first = false;
+            }
+            continue;
+        }
+        let (open, close): (&[u8], &[u8]) = if pending.starts_with(b"<?") {
+            (b"<?", b"?>")
+        } else if pending.starts_with(b"<!--") {
+            (b"<!--", b"-->")
+        } else {
+            break;
+        };
+        // Tags may contain `>` (`<!-- a > b -->`); keep reading to the real end.
+        let end = loop {
+            if let Some(at) = find(&pending[open.len()..], close) {
+                break Some(open.len() + at);
+            }
+            if eof || reader.read_until(b'>', &mut pending)? == 0 {
+                break None;
+            }
+        };
+        let Some(end) = end else {
+            // Unterminated: hand it to the parser, which reports the error.
+            eof = true;
+            break;
+        };
+        if open == b"<!--" {
+            let text = String::from_utf8_lossy(&pending[open.len()..end]);
+            let text = text.trim();
+            if is_license_comment(text) {
+                licenses.push(text.to_string());
+            }
+        }
+        advance(&mut position, &pending[..end + close.len()]);
+        pending.drain(..end + close.len());
+    }
+    Ok(Prolog {
+        pending,
+        licenses,
+        start: position,
+        eof,
+    })
+}
+
+/// Move the line/column `position` past the skipped `bytes`.
+fn advance(position: &mut (usize, usize), bytes: &[u8]) {
+    for &b in bytes {
+        if b == b'\n' {
+            *position = (position.0 + 1, 1);
+        } else if b & 0xc0 != 0x80 {
+            position.1 += 1;
+        }
+    }
+}
+
+/// Hex-encoded SHA-256 digest of `bytes`.
+///
+/// Stable across runs and toolchains, unlike `DefaultHasher`.
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Whether a prolog comment carries attribution worth keeping: it starts with
+/// `!` (the Font Awesome convention) or mentions a license or copyright.
+fn is_license_comment(text: &str) -> bool {
+    const MARKERS: [&str; 5] = ["license", "licence", "copyright", "(c)", "spdx"];
+    let lower = text.to_lowercase();
+    text.starts_with('!') || text.contains('©') || MARKERS.iter().any(|m| lower.contains(m))
+}
+
+/// Position of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_hex_is_stable() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    /// Yields one byte per `read`, exercising every buffer boundary.
+    #[cfg(feature = "fs")]
+    struct Trickle<'a>(&'a [u8]);
+
+    #[cfg(feature = "fs")]
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let Some((first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buf[0] = *first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn skips_prolog_and_keeps_license_comments() {
+        let input = "\u{feff}<?xml version='1.0'?>\n<!--! Font Awesome Free, License: CC BY 4.0 --><!-- Generator: a > b --><!-- Copyright 2024 Acme -->\n<svg width='1'><!-- license inside --></svg>";
+        for doc in [
+            read_document(input.as_bytes()).unwrap(),
+            read_document(Trickle(input.as_bytes())).unwrap(),
+        ] {
+            assert_eq!(doc.body, "<svg width='1'><!-- license inside --></svg>");
+            assert_eq!(
+                doc.licenses,
+                vec![
+                    "! Font Awesome Free, License: CC BY 4.0".to_string(),
+                    "Copyright 2024 Acme".to_string()
+                ]
+            );
+            assert_eq!(doc.hash, sha256_hex(input.as_bytes()));
+            assert_eq!(doc.start, (3, 1));
+        }
+    }
+
+    #[test]
+    fn in_memory_documents_borrow_their_body() {
+        let input = "\u{feff}<?xml version='1.0'?>\n<!-- Copyright Acme --> <svg>\u{e9}</svg>";
+        let doc = document(input);
+        assert!(matches!(doc.body, Cow::Borrowed("<svg>\u{e9}</svg>")));
+        assert_eq!(doc.licenses, ["Copyright Acme"]);
+        assert_eq!(doc.hash, sha256_hex(input.as_bytes()));
+        assert_eq!(doc.start, (2, 25));
+        assert!(matches!(
+            document("<!-- open").body,
+            Cow::Borrowed("<!-- open")
+        ));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn in_memory_and_streamed_documents_agree() {
+        let input = "\u{feff}<?xml version='1.0'?>\n<!-- Copyright Acme --> <svg>\u{e9}</svg>";
+        let doc = document(input);
+        let read = read_document(input.as_bytes()).unwrap();
+        assert_eq!(doc.body, read.body);
+        assert_eq!(
+            (doc.hash, doc.licenses, doc.start),
+            (read.hash, read.licenses, read.start)
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn passes_unterminated_and_invalid_input_through() {
+        let doc = read_document("<!-- open <svg/>".as_bytes()).unwrap();
+        assert_eq!(doc.body, "<!-- open <svg/>");
+        assert!(read_document("".as_bytes()).unwrap().body.is_empty());
+
+        let err = read_document(&b"<svg>\xff</svg>"[..])
+            .err()
+            .expect("invalid utf-8");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
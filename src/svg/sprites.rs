@@ -0,0 +1,262 @@
+//! Sprite sheets dropped into the icon directory (the output of this tool or
+//! of another generator), split back into their icons.
+//!
+//! A file is a sprite when its root `<svg>` holds nothing but `<symbol>` or
+//! `<pattern>` elements with ids, optionally inside `<defs>` next to shared
+//! definitions. Each becomes a standalone document that goes through the
+//! same parse pass as files on disk.
+
+use super::defs::matching_close;
+use super::markup::{self, Token};
+use super::{CacheEntry, RunOpts, inputs, parsing};
+use crate::error::AppError;
+use std::path::{Path, PathBuf};
+use winnow::Parser;
+
+/// Elements that hold one icon of a sprite.
+const ICON_ELEMENTS: [&str; 2] = ["symbol", "pattern"];
+
+/// Top-level elements carrying no rendering, ignored when detecting sprites.
+const IGNORED: [&str; 3] = ["title", "desc", "metadata"];
+
+/// Pattern attributes that only make sense on a `<pattern>` and are dropped
+/// from the icon's root `<svg>`.
+const PATTERN_ONLY: [&str; 7] = [
+    "x",
+    "y",
+    "patternUnits",
+    "patternContentUnits",
+    "patternTransform",
+    "href",
+    "xlink:href",
+];
+
+/// One icon element of a sprite, before it becomes a document.
+struct Icon<'a> {
+    id: &'a str,
+    attrs: Vec<(&'a str, &'a str, char)>,
+    inner: String,
+}
+
+/// Split the inputs under `root` that are sprites into their icons, narrowed
+/// by `--only`. Returns the remaining paths and the split icons, in path
+/// order and by position within a sprite. Each icon's id is named like a
+/// file `<id>.svg` beside the sprite, and its source is `<sprite>#<id>`.
+pub(crate) fn split_inputs(
+    root: &Path,
+    paths: Vec<PathBuf>,
+    opts: &RunOpts,
+) -> Result<(Vec<PathBuf>, Vec<CacheEntry>), AppError> {
+    let only = inputs::expand_only(&opts.only)?;
+    let mut files = Vec::with_capacity(paths.len());
+    let mut entries = Vec::new();
+    for path in paths {
+        let (_, doc) = super::read_source(root, &path, opts)?;
+        let mut body: &str = &doc.body;
+        let Some(icons) = parsing::parse_svg
+            .parse_next(&mut body)
+            .ok()
+            .and_then(|(_, children)| split(children))
+        else {
+            files.push(path);
+            continue;
+        };
+        let origin = path.display().to_string();
+        tracing::debug!(path = %origin, icons = icons.len(), "Splitting sprite input");
+        for (id, document) in icons {
+            let Some(name) =
+                inputs::icon_name(root, &path.with_file_name(format!("{id}.svg")), opts)
+            else {
+                continue;
+            };
+            if !(only.is_empty() || only.contains(&name)) {
+                continue;
+            }
+            entries.push(super::parse_icon(
+                &name,
+                &format!("{origin}#{id}"),
+                &document,
+                opts,
+            )?);
+        }
+    }
+    Ok((files, entries))
+}
+
+/// The icons of a sprite's root `children` as `(id, document)` pairs, or
+/// `None` when the markup is not a sprite.
+///
+/// Shared definitions in the root `<defs>` are copied into each icon that
+/// references them, and an empty element that only points at another icon
+/// through `href` (an alias, as written by `--dedupe`) becomes a copy of it.
+fn split(children: &str) -> Option<Vec<(String, String)>> {
+    let tokens = markup::tokenize(children)?;
+    let mut icons = Vec::new();
+    let mut shared: Vec<(&str, String)> = Vec::new();
+    collect(&tokens, false, &mut icons, &mut shared)?;
+    if icons.is_empty() {
+        return None;
+    }
+    let documents = icons
+        .iter()
+        .map(|icon| {
+            let target = alias_target(icon)
+                .and_then(|id| icons.iter().find(|i| i.id == id))
+                .unwrap_or(icon);
+            (icon.id.to_string(), document(target, &shared))
+        })
+        .collect();
+    Some(documents)
+}
+
+/// Walk sibling `tokens`, collecting icon elements and (inside `<defs>`)
+/// shared definitions; `None` on anything else that renders.
+fn collect<'a>(
+    tokens: &[Token<'a>],
+    in_defs: bool,
+    icons: &mut Vec<Icon<'a>>,
+    shared: &mut Vec<(&'a str, String)>,
+) -> Option<()> {
+    let mut i = 0;
+    while i < tokens.len() {
+        let (name, raw, empty) = match tokens[i] {
+            Token::Text(text) => {
+                if !(text.trim().is_empty() || text.starts_with("<!--")) {
+                    return None;
+                }
+                i += 1;
+                continue;
+            }
+            Token::Close(_) => return None,
+            Token::Open { name, raw, empty } => (name, raw, empty),
+        };
+        let end = if empty {
+            i + 1
+        } else {
+            matching_close(tokens, i)
+        };
+        let inner = if empty {
+            &tokens[i..i]
+        } else {
+            &tokens[i + 1..end.checked_sub(1)?]
+        };
+        if ICON_ELEMENTS.contains(&name) {
+            let attrs = markup::attributes(raw)?;
+            let id = attrs.iter().find(|(k, ..)| *k == "id")?.1;
+            icons.push(Icon {
+                id,
+                attrs,
+                inner: inner.iter().map(Token::raw).collect(),
+            });
+        } else if name == "defs" && !in_defs {
+            collect(inner, true, icons, shared)?;
+        } else if in_defs {
+            let id = markup::attributes(raw)?
+                .into_iter()
+                .find(|(k, ..)| *k == "id")
+                .map_or("", |(_, v, _)| v);
+            shared.push((id, tokens[i..end].iter().map(Token::raw).collect()));
+        } else if !IGNORED.contains(&name) {
+            return None;
+        }
+        i = end;
+    }
+    Some(())
+}
+
+/// The icon an empty element points at through `href`, if any.
+fn alias_target<'a>(icon: &Icon<'a>) -> Option<&'a str> {
+    if !icon.inner.trim().is_empty() {
+        return None;
+    }
+    icon.attrs
+        .iter()
+        .find(|(k, ..)| *k == "href" || *k == "xlink:href")
+        .and_then(|(_, v, _)| v.strip_prefix('#'))
+}
+
+/// A standalone `<svg>` for `icon`, with the shared definitions it uses
+/// (directly or through other definitions). An icon without `width` and
+/// `height` gets them from its view box.
+fn document(icon: &Icon, shared: &[(&str, String)]) -> String {
+    let mut used: Vec<usize> = Vec::new();
+    let mut pending = super::ids::referenced_ids(&icon.inner);
+    while let Some(id) = pending.pop() {
+        if let Some(n) = shared.iter().position(|(d, _)| *d == id)
+            && !used.contains(&n)
+        {
+            used.push(n);
+            pending.extend(super::ids::referenced_ids(&shared[n].1));
+        }
+    }
+    used.sort_unstable();
+
+    let mut attrs: Vec<(&str, &str, char)> = icon
+        .attrs
+        .iter()
+        .filter(|(k, ..)| *k != "id" && !PATTERN_ONLY.contains(k))
+        .copied()
+        .collect();
+    // Symbols rarely carry a size; take it from the view box.
+    let view_box = attrs
+        .iter()
+        .find(|(k, ..)| *k == "viewBox")
+        .map(|(_, v, _)| v.split(|c: char| c == ',' || c.is_ascii_whitespace()))
+        .map(|parts| parts.filter(|p| !p.is_empty()).collect::<Vec<_>>());
+    if let Some(&[_, _, width, height]) = view_box.as_deref()
+        && !attrs.iter().any(|(k, ..)| *k == "width" || *k == "height")
+    {
+        attrs.push(("width", width, '"'));
+        attrs.push(("height", height, '"'));
+    }
+    let mut out = String::new();
+    markup::push_tag(&mut out, "svg", &attrs, false);
+    if !used.is_empty() {
+        out.push_str("<defs>");
+        for n in used {
+            out.push_str(&shared[n].1);
+        }
+        out.push_str("</defs>");
+    }
+    out.push_str(&icon.inner);
+    out.push_str("</svg>");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_symbols_and_patterns_with_shared_defs() {
+        let sheet = "<defs><linearGradient id=\"g\"><stop offset=\"0\"/></linearGradient>\
+            <pattern id=\"a\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><path fill=\"url(#g)\" d=\"M0 0\"/></pattern>\
+            <pattern id=\"b\" href=\"#a\"></pattern></defs>\
+            <!-- more -->\n<symbol id=\"c\" viewBox=\"0 0 8 8\"><g/></symbol>";
+        let icons = split(sheet).unwrap();
+        let docs: Vec<(&str, &str)> = icons
+            .iter()
+            .map(|(id, doc)| (id.as_str(), doc.as_str()))
+            .collect();
+        let a = "<svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><defs><linearGradient id=\"g\"><stop offset=\"0\"/></linearGradient></defs><path fill=\"url(#g)\" d=\"M0 0\"/></svg>";
+        assert_eq!(
+            docs,
+            [
+                ("a", a),
+                ("b", a),
+                (
+                    "c",
+                    "<svg viewBox=\"0 0 8 8\" width=\"8\" height=\"8\"><g/></svg>"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn ordinary_icons_are_not_sprites() {
+        assert!(split("<path d=\"M0 0\"/>").is_none());
+        assert!(split("<defs><symbol id=\"s\"><g/></symbol></defs><use href=\"#s\"/>").is_none());
+        assert!(split("<title>x</title><defs><linearGradient id=\"g\"/></defs>").is_none());
+        assert!(split("<symbol><g/></symbol>").is_none());
+    }
+}
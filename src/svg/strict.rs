@@ -0,0 +1,148 @@
+//! `--strict`: checks that reject inputs the normal pipeline would quietly
+//! accept or rewrite.
+
+use super::markup::{self, Token};
+use super::sanitize;
+use crate::error::AppError;
+
+/// Attributes a root `<svg>` may carry besides `aria-*`, `data-*`, and
+/// `xmlns`/`xmlns:*` declarations: core and styling attributes, plus the
+/// presentation attributes inherited by the icon's content.
+const KNOWN_ROOT_ATTRS: [&str; 61] = [
+    "id",
+    "class",
+    "style",
+    "lang",
+    "xml:lang",
+    "xml:space",
+    "role",
+    "focusable",
+    "tabindex",
+    "version",
+    "baseProfile",
+    "x",
+    "y",
+    "width",
+    "height",
+    "viewBox",
+    "preserveAspectRatio",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "stroke",
+    "stroke-width",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-opacity",
+    "opacity",
+    "color",
+    "clip-path",
+    "clip-rule",
+    "mask",
+    "filter",
+    "transform",
+    "display",
+    "visibility",
+    "overflow",
+    "shape-rendering",
+    "text-rendering",
+    "image-rendering",
+    "color-rendering",
+    "color-interpolation",
+    "color-interpolation-filters",
+    "vector-effect",
+    "paint-order",
+    "pointer-events",
+    "cursor",
+    "mix-blend-mode",
+    "isolation",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-weight",
+    "text-anchor",
+    "dominant-baseline",
+    "letter-spacing",
+    "word-spacing",
+    "marker-start",
+    "marker-mid",
+    "marker-end",
+];
+
+/// Fail on a root attribute outside the known set (editor metadata such as
+/// `sodipodi:docname` included) and on any id, root or inner, that
+/// sanitization would rewrite.
+pub(crate) fn check(
+    origin: &str,
+    attributes: &[(&str, &str)],
+    children: &str,
+) -> Result<(), AppError> {
+    let violation = |message: String| AppError::StrictViolation {
+        path: origin.to_string(),
+        message,
+    };
+    for (k, v) in attributes {
+        let known = KNOWN_ROOT_ATTRS.contains(k)
+            || k.starts_with("aria-")
+            || k.starts_with("data-")
+            || *k == "xmlns"
+            || k.starts_with("xmlns:");
+        if !known {
+            return Err(violation(format!("unknown attribute '{k}' on root <svg>")));
+        }
+        if *k == "id" {
+            check_id(v).map_err(violation)?;
+        }
+    }
+    for token in markup::tokenize(children).unwrap_or_default() {
+        if let Token::Open { raw, .. } = token
+            && let Some(attrs) = markup::attributes(raw)
+            && let Some(&(_, id, _)) = attrs.iter().find(|(k, ..)| *k == "id")
+        {
+            check_id(id).map_err(violation)?;
+        }
+    }
+    Ok(())
+}
+
+/// Describe how sanitization would change `id`, if it would.
+fn check_id(id: &str) -> Result<(), String> {
+    let sanitized = sanitize::sanitize_id(id);
+    if sanitized == id {
+        Ok(())
+    } else {
+        Err(format!("id '{id}' would be sanitized to '{sanitized}'"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unknown_root_attributes_and_unsanitized_ids() {
+        let ok = [
+            ("width", "24"),
+            ("aria-hidden", "true"),
+            ("xmlns:xlink", "x"),
+            ("id", "icon"),
+        ];
+        assert!(check("a.svg", &ok, "<path id=\"p_1\"/>").is_ok());
+
+        let err = check("a.svg", &[("sodipodi:docname", "a.svg")], "").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "a.svg: unknown attribute 'sodipodi:docname' on root <svg> (rejected by --strict)"
+        );
+        let err = check("a.svg", &[("id", "1st icon")], "").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("id '1st icon' would be sanitized to 'st-icon'")
+        );
+        let err = check("a.svg", &ok, "<g><path id=\"a b\"/></g>").unwrap_err();
+        assert!(err.to_string().contains("id 'a b' would be sanitized"));
+    }
+}
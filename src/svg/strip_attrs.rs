@@ -0,0 +1,191 @@
+//! The config's `[attributes]` table: attributes dropped from each icon's root
+//! and children by name or `*` pattern, such as editor metadata that must not
+//! ship. Unlike optimization this touches nothing else, and every removal is
+//! counted for the build summary.
+
+use super::markup::{self, Token};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+/// Attributes never stripped: references and sizing rely on them.
+const PROTECTED: [&str; 2] = ["id", "viewBox"];
+
+/// Which attributes to drop: those matching `strip`, plus, when `keep` is not
+/// empty, those matching none of `keep`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Rules<'a> {
+    pub(crate) strip: &'a [String],
+    pub(crate) keep: &'a [String],
+}
+
+impl Rules<'_> {
+    /// Whether the rules drop nothing.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.strip.is_empty() && self.keep.is_empty()
+    }
+
+    fn strips(&self, name: &str) -> bool {
+        if PROTECTED.contains(&name) {
+            return false;
+        }
+        self.strip.iter().any(|p| matches(p, name))
+            || (!self.keep.is_empty() && !self.keep.iter().any(|p| matches(p, name)))
+    }
+}
+
+/// Whether attribute `name` matches `pattern`, where `*` stands for any run
+/// of characters.
+fn matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Drop the matching root attributes and child attributes, returning the
+/// rewritten children and `(attribute, count)` of what was removed, by name.
+/// Markup that does not tokenize is left as is.
+pub(crate) fn apply<'a>(
+    out_attrs: &mut Vec<(String, String)>,
+    children: &'a str,
+    rules: Rules,
+) -> (Cow<'a, str>, Vec<(String, usize)>) {
+    let mut removed: BTreeMap<String, usize> = BTreeMap::new();
+    out_attrs.retain(|(k, _)| {
+        let strip = rules.strips(k);
+        if strip {
+            *removed.entry(k.clone()).or_default() += 1;
+        }
+        !strip
+    });
+    let children = strip_children(children, rules, &mut removed);
+    (children, removed.into_iter().collect())
+}
+
+fn strip_children<'a>(
+    children: &'a str,
+    rules: Rules,
+    removed: &mut BTreeMap<String, usize>,
+) -> Cow<'a, str> {
+    let Some(tokens) = markup::tokenize(children) else {
+        return Cow::Borrowed(children);
+    };
+    let mut out = String::with_capacity(children.len());
+    let mut rewrote = false;
+    for token in tokens {
+        if let Token::Open { name, raw, empty } = token
+            && let Some(attrs) = markup::attributes(raw)
+            && attrs.iter().any(|(k, ..)| rules.strips(k))
+        {
+            let kept: Vec<_> = attrs
+                .into_iter()
+                .filter(|(k, ..)| {
+                    let strip = rules.strips(k);
+                    if strip {
+                        *removed.entry((*k).to_string()).or_default() += 1;
+                    }
+                    !strip
+                })
+                .collect();
+            markup::push_tag(&mut out, name, &kept, empty);
+            rewrote = true;
+        } else {
+            out.push_str(token.raw());
+        }
+    }
+    if rewrote {
+        Cow::Owned(out)
+    } else {
+        Cow::Borrowed(children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn patterns_match_with_wildcards() {
+        assert!(matches("xml:space", "xml:space"));
+        assert!(!matches("xml:space", "xml:spaces"));
+        assert!(matches("data-figma-*", "data-figma-id"));
+        assert!(!matches("data-figma-*", "data-id"));
+        assert!(matches("*-rule", "fill-rule"));
+        assert!(matches("sodipodi:*:x*", "sodipodi:a:xy"));
+        assert!(matches("*", "anything"));
+    }
+
+    #[test]
+    fn strips_root_and_children_and_counts_removals() {
+        let strip = strings(&["data-figma-*", "enable-background", "id"]);
+        let rules = Rules {
+            strip: &strip,
+            keep: &[],
+        };
+        let mut root = vec![
+            ("viewBox".to_string(), "0 0 24 24".to_string()),
+            ("enable-background".to_string(), "new".to_string()),
+        ];
+        let children = "<g data-figma-id=\"1\" id=\"a\"><path data-figma-name='p' d=\"M0 0\"/></g>";
+        let (out, removed) = apply(&mut root, children, rules);
+        assert_eq!(root, [("viewBox".to_string(), "0 0 24 24".to_string())]);
+        assert_eq!(out, "<g id=\"a\"><path d=\"M0 0\"/></g>");
+        assert_eq!(
+            removed,
+            [
+                ("data-figma-id".to_string(), 1),
+                ("data-figma-name".to_string(), 1),
+                ("enable-background".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn keep_list_drops_everything_else() {
+        let keep = strings(&["d", "fill*"]);
+        let rules = Rules {
+            strip: &[],
+            keep: &keep,
+        };
+        let mut root = vec![("width".to_string(), "24".to_string())];
+        let (out, removed) = apply(
+            &mut root,
+            "<path d=\"M0 0\" fill-rule=\"evenodd\" class=\"x\"/>",
+            rules,
+        );
+        assert!(root.is_empty());
+        assert_eq!(out, "<path d=\"M0 0\" fill-rule=\"evenodd\"/>");
+        assert_eq!(
+            removed,
+            [("class".to_string(), 1), ("width".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn untouched_markup_is_borrowed() {
+        let strip = strings(&["xml:space"]);
+        let rules = Rules {
+            strip: &strip,
+            keep: &[],
+        };
+        let (out, removed) = apply(&mut vec![], "<path d=\"M0 0\"/>", rules);
+        assert!(matches!(out, Cow::Borrowed(_)));
+        assert!(removed.is_empty());
+    }
+}
@@ -0,0 +1,464 @@
+//! Human-readable build summaries printed after a successful run.
+
+use super::RunOpts;
+use crate::error::Warning;
+use std::time::Duration;
+
+/// Per-file details collected while building a sprite.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileSummary {
+    /// Pattern id derived from the filename
+    pub name: String,
+    /// Source path of the input file
+    pub path: String,
+    /// Size in bytes of the emitted pattern markup
+    pub bytes: usize,
+    /// Number of warnings emitted for this file
+    pub warnings: usize,
+    /// Icon this one was deduplicated into, if any
+    pub alias_of: Option<String>,
+    /// Number of colors replaced through the config's `[colors]` table
+    pub colors_remapped: usize,
+    /// Attributes removed through the config's `[attributes]` table, as
+    /// `(attribute, count)`
+    pub attributes_stripped: Vec<(String, usize)>,
+    /// Source of the same-named icon this `--overlay` icon replaced
+    pub overrides: Option<String>,
+}
+
+/// Icons added, removed, or changed versus a `--baseline` sprite, by id.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BaselineChanges {
+    /// The baseline sprite compared against
+    pub path: String,
+    /// Icons missing from the baseline
+    pub added: Vec<String>,
+    /// Baseline icons the build no longer has
+    pub removed: Vec<String>,
+    /// Icons whose markup differs from the baseline's
+    pub changed: Vec<String>,
+}
+
+/// Aggregate statistics for a single build.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildSummary {
+    /// Output file the sprite was written to
+    pub output: String,
+    /// Number of icons emitted into the sprite
+    pub icons: usize,
+    /// Every warning of the build, per input in output order, then those
+    /// about the sprite as a whole
+    pub warnings: Vec<Warning>,
+    /// Size of the generated sprite in bytes
+    pub bytes: u64,
+    /// Wall-clock time spent building
+    pub duration: Duration,
+    /// Whether the output was skipped because of `--dry-run`
+    pub dry_run: bool,
+    /// Whether the write was skipped because the output was already up to date
+    pub unchanged: bool,
+    /// Number of icons emitted as aliases of identical icons
+    pub deduplicated: usize,
+    /// Total number of colors replaced through the config's `[colors]` table
+    pub colors_remapped: usize,
+    /// Total number of attributes removed through the config's `[attributes]` table
+    pub attributes_stripped: usize,
+    /// Number of sheets written under `--chunk-by`; 0 for a single sprite
+    pub chunks: usize,
+    /// Per-file details, in output order
+    pub files: Vec<FileSummary>,
+    /// Differences from the `--baseline` sprite, when one was given
+    pub baseline: Option<BaselineChanges>,
+}
+
+/// Render the summary for the verbosity selected in `opts`.
+/// Returns `None` when `--quiet` is set.
+///
+/// Example:
+/// ```
+/// use svg_sheet::svg::{RunOpts, summary::{BuildSummary, render}};
+/// let s = BuildSummary { output: "sprite.svg".into(), icons: 2, bytes: 2048, ..Default::default() };
+/// let text = render(&s, &RunOpts::default()).unwrap();
+/// assert!(text.starts_with("Built sprite.svg: 2 icons, 0 warnings, 2.0 KiB"));
+/// assert!(render(&s, &RunOpts { quiet: true, ..Default::default() }).is_none());
+/// ```
+pub fn render(summary: &BuildSummary, opts: &RunOpts) -> Option<String> {
+    if opts.quiet {
+        return None;
+    }
+    let verb = if summary.dry_run {
+        "Checked (dry run)"
+    } else if summary.unchanged {
+        "Unchanged"
+    } else {
+        "Built"
+    };
+    let output = if summary.output == super::STDIO {
+        "<stdout>"
+    } else {
+        summary.output.as_str()
+    };
+    let mut out = format!(
+        "{verb} {output}: {} {}, {} {}, {} in {} ms",
+        summary.icons,
+        plural(summary.icons, "icon", "icons"),
+        summary.warnings.len(),
+        plural(summary.warnings.len(), "warning", "warnings"),
+        format_bytes(summary.bytes),
+        summary.duration.as_millis()
+    );
+    if summary.deduplicated > 0 {
+        out.push_str(&format!(" ({} deduplicated)", summary.deduplicated));
+    }
+    if summary.colors_remapped > 0 {
+        out.push_str(&format!(
+            " ({} {} remapped)",
+            summary.colors_remapped,
+            plural(summary.colors_remapped, "color", "colors")
+        ));
+    }
+    if summary.attributes_stripped > 0 {
+        out.push_str(&format!(
+            " ({} {} stripped)",
+            summary.attributes_stripped,
+            plural(summary.attributes_stripped, "attribute", "attributes")
+        ));
+    }
+    if summary.chunks > 0 {
+        out.push_str(&format!(
+            " ({} {})",
+            summary.chunks,
+            plural(summary.chunks, "chunk", "chunks")
+        ));
+    }
+    let overridden: Vec<(&FileSummary, &String)> = summary
+        .files
+        .iter()
+        .filter_map(|f| Some((f, f.overrides.as_ref()?)))
+        .collect();
+    if !overridden.is_empty() {
+        out.push_str(&format!(" ({} overridden)", overridden.len()));
+    }
+    if !opts.verbose {
+        // Verbose output names the replaced sources in its per-file lines.
+        for (f, replaced) in overridden {
+            out.push_str(&format!("\n  {}: {} overrides {replaced}", f.name, f.path));
+        }
+    } else {
+        for f in &summary.files {
+            out.push_str(&format!(
+                "\n  {} ({}): {}, {} {}",
+                f.name,
+                f.path,
+                format_bytes(f.bytes as u64),
+                f.warnings,
+                plural(f.warnings, "warning", "warnings")
+            ));
+            if let Some(target) = &f.alias_of {
+                out.push_str(&format!(", alias of {target}"));
+            }
+            if f.colors_remapped > 0 {
+                out.push_str(&format!(
+                    ", {} {} remapped",
+                    f.colors_remapped,
+                    plural(f.colors_remapped, "color", "colors")
+                ));
+            }
+            if !f.attributes_stripped.is_empty() {
+                let listed: Vec<String> = f
+                    .attributes_stripped
+                    .iter()
+                    .map(|(name, n)| format!("{name} ({n})"))
+                    .collect();
+                out.push_str(&format!(", stripped {}", listed.join(", ")));
+            }
+            if let Some(replaced) = &f.overrides {
+                out.push_str(&format!(", overrides {replaced}"));
+            }
+        }
+    }
+    if let Some(baseline) = &summary.baseline {
+        out.push_str(&format!("\n  vs {}: ", baseline.path));
+        let parts: Vec<String> = [
+            ("added", &baseline.added),
+            ("removed", &baseline.removed),
+            ("changed", &baseline.changed),
+        ]
+        .iter()
+        .filter(|(_, ids)| !ids.is_empty())
+        .map(|(label, ids)| format!("{} {label} ({})", ids.len(), ids.join(", ")))
+        .collect();
+        if parts.is_empty() {
+            out.push_str("no icon changes");
+        } else {
+            out.push_str(&parts.join(", "));
+        }
+    }
+    Some(out)
+}
+
+/// Print the summary unless `--quiet` is set. It goes to stdout, or to stderr
+/// when the sprite itself is being written to stdout.
+pub fn print(summary: &BuildSummary, opts: &RunOpts) {
+    if let Some(text) = render(summary, opts) {
+        if summary.output == super::STDIO {
+            eprintln!("{text}");
+        } else {
+            println!("{text}");
+        }
+    }
+}
+
+/// Totals of a watch session, reported when it stops.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WatchTotals {
+    /// Rebuilds attempted, failed ones included
+    pub rebuilds: usize,
+    /// Rebuilds that ended in an error
+    pub failures: usize,
+    /// Time from the start of watching to the shutdown
+    pub duration: Duration,
+}
+
+/// Render the end-of-session line of watch mode; `None` under `--quiet`.
+///
+/// Example:
+/// ```
+/// use std::time::Duration;
+/// use svg_sheet::svg::{RunOpts, summary::{WatchTotals, render_session}};
+/// let totals = WatchTotals { rebuilds: 3, failures: 1, duration: Duration::from_secs(75) };
+/// assert_eq!(
+///     render_session(&totals, &RunOpts::default()).unwrap(),
+///     "Stopped watching after 75 s: 3 rebuilds, 1 failed"
+/// );
+/// ```
+pub fn render_session(totals: &WatchTotals, opts: &RunOpts) -> Option<String> {
+    if opts.quiet {
+        return None;
+    }
+    Some(format!(
+        "Stopped watching after {} s: {} {}, {} failed",
+        totals.duration.as_secs(),
+        totals.rebuilds,
+        plural(totals.rebuilds, "rebuild", "rebuilds"),
+        totals.failures
+    ))
+}
+
+/// Format a byte count using binary units (B, KiB, MiB).
+///
+/// Example:
+/// ```
+/// assert_eq!(svg_sheet::svg::summary::format_bytes(512), "512 B");
+/// assert_eq!(svg_sheet::svg::summary::format_bytes(1536), "1.5 KiB");
+/// ```
+pub fn format_bytes(n: u64) -> String {
+    const KIB: f64 = 1024.0;
+    const MIB: f64 = 1024.0 * 1024.0;
+    let v = n as f64;
+    if v >= MIB {
+        format!("{:.1} MiB", v / MIB)
+    } else if v >= KIB {
+        format!("{:.1} KiB", v / KIB)
+    } else {
+        format!("{n} B")
+    }
+}
+
+/// Warning for a sprite of `bytes` over the `--max-output-size` budget
+/// `limit`, naming the largest icons; `None` within budget.
+///
+/// Example:
+/// ```
+/// use svg_sheet::svg::summary::{FileSummary, over_budget};
+/// let files = vec![FileSummary { name: "logo".into(), bytes: 3072, ..Default::default() }];
+/// let message = over_budget(4096, 2048, &files).unwrap();
+/// assert!(message.starts_with("Sprite is 4.0 KiB, over the 2.0 KiB budget"));
+/// assert!(message.ends_with("largest icons: logo (3.0 KiB)"));
+/// assert!(over_budget(2048, 2048, &files).is_none());
+/// ```
+pub fn over_budget(bytes: u64, limit: u64, files: &[FileSummary]) -> Option<String> {
+    const LISTED: usize = 5;
+    if bytes <= limit {
+        return None;
+    }
+    let mut largest: Vec<&FileSummary> = files.iter().collect();
+    largest.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    let listed: Vec<String> = largest
+        .iter()
+        .take(LISTED)
+        .map(|f| format!("{} ({})", f.name, format_bytes(f.bytes as u64)))
+        .collect();
+    Some(format!(
+        "Sprite is {}, over the {} budget by {}; largest icons: {}",
+        format_bytes(bytes),
+        format_bytes(limit),
+        format_bytes(bytes - limit),
+        listed.join(", ")
+    ))
+}
+
+/// Choose the singular or plural form of a noun for `n`.
+fn plural(n: usize, one: &'static str, many: &'static str) -> &'static str {
+    if n == 1 { one } else { many }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> BuildSummary {
+        BuildSummary {
+            output: "out.svg".into(),
+            icons: 1,
+            warnings: vec![Warning::new(
+                crate::error::WarningKind::MissingWidth,
+                "svgs/a.svg",
+                "Missing width on root <svg>",
+            )],
+            bytes: 100,
+            duration: Duration::from_millis(7),
+            dry_run: false,
+            unchanged: false,
+            deduplicated: 0,
+            colors_remapped: 0,
+            attributes_stripped: 0,
+            chunks: 0,
+            files: vec![FileSummary {
+                name: "a".into(),
+                path: "svgs/a.svg".into(),
+                bytes: 100,
+                warnings: 1,
+                alias_of: None,
+                colors_remapped: 0,
+                attributes_stripped: vec![],
+                overrides: None,
+            }],
+            baseline: None,
+        }
+    }
+
+    #[test]
+    fn render_normal_is_single_line() {
+        let text = render(&sample(), &RunOpts::default()).unwrap();
+        assert_eq!(text, "Built out.svg: 1 icon, 1 warning, 100 B in 7 ms");
+    }
+
+    #[test]
+    fn render_verbose_lists_files() {
+        let opts = RunOpts {
+            verbose: true,
+            ..Default::default()
+        };
+        let text = render(&sample(), &opts).unwrap();
+        assert!(text.contains("\n  a (svgs/a.svg): 100 B, 1 warning"));
+    }
+
+    #[test]
+    fn render_reports_deduplicated_icons() {
+        let mut s = sample();
+        s.deduplicated = 1;
+        s.files[0].alias_of = Some("b".into());
+        let opts = RunOpts {
+            verbose: true,
+            ..Default::default()
+        };
+        let text = render(&s, &opts).unwrap();
+        assert!(text.contains("in 7 ms (1 deduplicated)"));
+        assert!(text.ends_with("1 warning, alias of b"));
+    }
+
+    #[test]
+    fn render_reports_remapped_colors() {
+        let mut s = sample();
+        s.colors_remapped = 2;
+        s.files[0].colors_remapped = 2;
+        let opts = RunOpts {
+            verbose: true,
+            ..Default::default()
+        };
+        let text = render(&s, &opts).unwrap();
+        assert!(text.contains("in 7 ms (2 colors remapped)"));
+        assert!(text.ends_with("1 warning, 2 colors remapped"));
+    }
+
+    #[test]
+    fn render_reports_stripped_attributes() {
+        let mut s = sample();
+        s.attributes_stripped = 3;
+        s.files[0].attributes_stripped = vec![("data-figma-id".into(), 2), ("xml:space".into(), 1)];
+        let opts = RunOpts {
+            verbose: true,
+            ..Default::default()
+        };
+        let text = render(&s, &opts).unwrap();
+        assert!(text.contains("in 7 ms (3 attributes stripped)"));
+        assert!(text.ends_with("1 warning, stripped data-figma-id (2), xml:space (1)"));
+    }
+
+    #[test]
+    fn render_lists_overridden_icons() {
+        let mut s = sample();
+        s.files[0].path = "local/a.svg".into();
+        s.files[0].overrides = Some("svgs/a.svg".into());
+        let text = render(&s, &RunOpts::default()).unwrap();
+        assert!(text.ends_with("in 7 ms (1 overridden)\n  a: local/a.svg overrides svgs/a.svg"));
+        let opts = RunOpts {
+            verbose: true,
+            ..Default::default()
+        };
+        let text = render(&s, &opts).unwrap();
+        assert!(text.ends_with("1 warning, overrides svgs/a.svg"));
+    }
+
+    #[test]
+    fn render_reports_chunks() {
+        let s = BuildSummary {
+            chunks: 3,
+            ..sample()
+        };
+        let text = render(&s, &RunOpts::default()).unwrap();
+        assert!(text.ends_with("in 7 ms (3 chunks)"));
+    }
+
+    #[test]
+    fn render_compares_with_the_baseline() {
+        let mut s = BuildSummary {
+            baseline: Some(BaselineChanges {
+                path: "old.svg".into(),
+                added: vec!["c".into(), "d".into()],
+                removed: vec![],
+                changed: vec!["a".into()],
+            }),
+            ..sample()
+        };
+        let text = render(&s, &RunOpts::default()).unwrap();
+        assert!(text.ends_with("in 7 ms\n  vs old.svg: 2 added (c, d), 1 changed (a)"));
+        s.baseline = Some(BaselineChanges {
+            path: "old.svg".into(),
+            ..Default::default()
+        });
+        let text = render(&s, &RunOpts::default()).unwrap();
+        assert!(text.ends_with("\n  vs old.svg: no icon changes"));
+    }
+
+    #[test]
+    fn render_unchanged_says_so() {
+        let s = BuildSummary {
+            unchanged: true,
+            ..sample()
+        };
+        let text = render(&s, &RunOpts::default()).unwrap();
+        assert!(text.starts_with("Unchanged out.svg: 1 icon"));
+    }
+
+    #[test]
+    fn render_dry_run_says_so() {
+        let s = BuildSummary {
+            dry_run: true,
+            ..sample()
+        };
+        let text = render(&s, &RunOpts::default()).unwrap();
+        assert!(text.starts_with("Checked (dry run) out.svg"));
+    }
+}
@@ -0,0 +1,165 @@
+//! User-supplied Tera templates replacing the built-in sprite markup.
+
+use super::CacheEntry;
+use crate::error::AppError;
+use serde::Serialize;
+
+/// One icon as exposed to templates.
+#[derive(Serialize)]
+struct TemplateIcon<'a> {
+    id: &'a str,
+    source: &'a str,
+    attributes: Vec<TemplateAttr<'a>>,
+    children: &'a str,
+}
+
+/// A root attribute, in source order.
+#[derive(Serialize)]
+struct TemplateAttr<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+/// A `--template`, parsed once for the whole sprite and the per-icon sizes.
+pub(crate) struct Template {
+    path: String,
+    tera: tera::Tera,
+}
+
+/// Name of the one template a `Template` holds.
+const NAME: &str = "sprite";
+
+impl Template {
+    /// Read and parse the Tera template at `path`.
+    pub(crate) fn load(path: &str) -> Result<Template, AppError> {
+        let source = std::fs::read_to_string(path).map_err(|e| AppError::ReadFile {
+            path: path.to_string(),
+            source: e,
+        })?;
+        let mut tera = tera::Tera::default();
+        tera.autoescape_on(vec![]);
+        tera.add_raw_template(NAME, &source)
+            .map_err(|e| self::error(path, &e))?;
+        Ok(Template {
+            path: path.to_string(),
+            tera,
+        })
+    }
+
+    /// Render `entries`.
+    ///
+    /// The template sees `icons`, a list of `{ id, source, attributes, children }`
+    /// where `attributes` is a list of `{ name, value }`, and `defs`, the markup
+    /// of the definitions hoisted by `--shared-defs` followed by `extra_defs`
+    /// (the contents of `_defs.svg`). Autoescaping is off since the output is
+    /// markup.
+    pub(crate) fn render(
+        &self,
+        entries: &[&CacheEntry],
+        extra_defs: &str,
+    ) -> Result<String, AppError> {
+        let icons: Vec<TemplateIcon> = entries.iter().map(|e| icon(e)).collect();
+        let mut context = tera::Context::new();
+        context.insert("icons", &icons);
+        context.insert("defs", &(super::defs::collect(entries) + extra_defs));
+        self.tera
+            .render(NAME, &context)
+            .map_err(|e| self::error(&self.path, &e))
+    }
+
+    /// Bytes the template emits for `entry`: its output for that icon alone
+    /// beyond `empty`, the length of its output for no icons.
+    pub(crate) fn icon_len(&self, entry: &CacheEntry, empty: usize) -> Result<usize, AppError> {
+        Ok(self.render(&[entry], "")?.len().saturating_sub(empty))
+    }
+}
+
+fn icon(e: &CacheEntry) -> TemplateIcon<'_> {
+    TemplateIcon {
+        id: &e.name,
+        source: &e.path_str,
+        attributes: e
+            .out_attrs
+            .iter()
+            .map(|(name, value)| TemplateAttr { name, value })
+            .collect(),
+        children: &e.children,
+    }
+}
+
+fn error(path: &str, e: &tera::Error) -> AppError {
+    AppError::Template {
+        path: path.to_string(),
+        message: error_chain(e),
+    }
+}
+
+/// Join a Tera error with its causes; the top-level message alone is vague.
+fn error_chain(e: &tera::Error) -> String {
+    let mut message = e.to_string();
+    let mut source = std::error::Error::source(e);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn entry(name: &str) -> CacheEntry {
+        CacheEntry {
+            mtime: UNIX_EPOCH,
+            len: 0,
+            name: name.into(),
+            out_attrs: vec![("viewBox".into(), "0 0 24 24".into())],
+            children: "<path d=\"M0 0\"/>".into(),
+            child_ids: vec![],
+            path_str: format!("{name}.svg"),
+            warnings: vec![],
+            hash: String::new(),
+            licenses: vec![],
+            shared_defs: vec![],
+            colors_remapped: 0,
+            attributes_stripped: vec![],
+            theme_vars: vec![],
+            overrides: None,
+        }
+    }
+
+    #[test]
+    fn renders_icons_into_template() {
+        let dir = std::env::temp_dir().join(format!("svg_template_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let tpl = dir.join("t.tera");
+        std::fs::write(
+            &tpl,
+            "<svg>{% for icon in icons %}<symbol id=\"{{ icon.id }}\"{% for a in icon.attributes %} {{ a.name }}=\"{{ a.value }}\"{% endfor %}>{{ icon.children }}</symbol>{% endfor %}</svg>",
+        )
+        .unwrap();
+        let a = entry("a");
+        let template = Template::load(tpl.to_str().unwrap()).expect("load");
+        let out = template.render(&[&a], "").expect("render");
+        assert_eq!(
+            out,
+            "<svg><symbol id=\"a\" viewBox=\"0 0 24 24\"><path d=\"M0 0\"/></symbol></svg>"
+        );
+        let empty = template.render(&[], "").unwrap().len();
+        assert_eq!(empty, "<svg></svg>".len());
+        assert_eq!(
+            template.icon_len(&a, empty).unwrap(),
+            out.len() - "<svg></svg>".len()
+        );
+
+        std::fs::write(&tpl, "{% for icon in icons %}").unwrap();
+        match Template::load(tpl.to_str().unwrap()).err() {
+            Some(AppError::Template { message, .. }) => assert!(!message.is_empty()),
+            other => panic!("unexpected: {other:?}"),
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,87 @@
+//! `--titles`: a JSON file mapping icon ids to human-readable titles and
+//! descriptions, so accessible labels do not have to follow file names.
+
+use super::sidecar::Sidecars;
+use crate::error::AppError;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// A title, or a title with a description.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Label {
+    Title(String),
+    Full {
+        title: Option<String>,
+        description: Option<String>,
+    },
+}
+
+/// Read the `--titles` file at `path`: an object keyed by icon id whose
+/// values are a title or `{ "title": …, "description": … }`.
+pub(crate) fn load(path: &Path) -> Result<BTreeMap<String, Label>, AppError> {
+    let origin = path.display().to_string();
+    let text = std::fs::read_to_string(path).map_err(|e| AppError::ReadFile {
+        path: origin.clone(),
+        source: e,
+    })?;
+    serde_json::from_str(&text).map_err(|e| AppError::Config {
+        path: origin,
+        message: e.to_string(),
+    })
+}
+
+/// Set the titles and descriptions of `labels` on the icons' sidecars,
+/// replacing the sidecars' own. Labels of icons outside `names` are logged
+/// and skipped.
+pub(crate) fn apply(sidecars: &mut Sidecars, labels: BTreeMap<String, Label>, names: &[&str]) {
+    for (name, label) in labels {
+        if !names.contains(&name.as_str()) {
+            tracing::warn!(icon = %name, "Titles file names an icon the build does not contain");
+            continue;
+        }
+        let sidecar = sidecars.entry(name).or_default();
+        match label {
+            Label::Title(title) => sidecar.title = Some(title),
+            Label::Full { title, description } => {
+                if title.is_some() {
+                    sidecar.title = title;
+                }
+                if description.is_some() {
+                    sidecar.description = description;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::sidecar::Sidecar;
+    use super::*;
+
+    #[test]
+    fn labels_replace_sidecar_titles() {
+        let labels: BTreeMap<String, Label> = serde_json::from_str(
+            r#"{"home": "Home", "trash": {"description": "Delete the item"}, "gone": "Gone"}"#,
+        )
+        .unwrap();
+        let mut sidecars = Sidecars::from([(
+            "trash".to_string(),
+            Sidecar {
+                title: Some("Trash".into()),
+                description: Some("Bin".into()),
+                ..Default::default()
+            },
+        )]);
+        apply(&mut sidecars, labels, &["home", "trash"]);
+        assert_eq!(sidecars["home"].title.as_deref(), Some("Home"));
+        assert_eq!(sidecars["trash"].title.as_deref(), Some("Trash"));
+        assert_eq!(
+            sidecars["trash"].description.as_deref(),
+            Some("Delete the item")
+        );
+        assert!(!sidecars.contains_key("gone"));
+    }
+}
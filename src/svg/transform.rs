@@ -1,24 +1,114 @@
 use super::SvgSprite;
 
+use super::namespaces;
+use std::borrow::Cow;
+
+/// Closing markup of every generated sprite.
+pub(crate) const SPRITE_CLOSE: &str = "</defs></svg>";
+
+/// Start of the root tag of every generated sprite.
+const ROOT_START: &str = r#"<svg xmlns="http://www.w3.org/2000/svg""#;
+
+/// Opening markup of a sprite whose root carries `attrs` (`--root-attr`)
+/// after its namespace.
+pub(crate) fn sprite_open(attrs: &[(String, String)]) -> String {
+    let mut out = String::from(ROOT_START);
+    for (k, v) in attrs {
+        out.push_str(&format!(" {k}=\"{}\"", escape_attr(v)));
+    }
+    out.push_str("><defs>");
+    out
+}
+
+/// Split a generated `sprite` at its root tag: the markup before it (leading
+/// comments), the attributes following `xmlns` (with their leading space),
+/// and everything after the opening `<defs>`.
+#[cfg(feature = "fs")]
+pub(crate) fn split_root(sprite: &str) -> Option<(&str, &str, &str)> {
+    let (head, rest) = sprite.split_once(ROOT_START)?;
+    let (attrs, body) = rest.split_once("><defs>")?;
+    Some((head, attrs, body))
+}
+
+/// Escape a raw attribute value; `>` too, so the root tag ends at the first `>`.
+fn escape_attr(v: &str) -> String {
+    v.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `sprite` without its `<svg>` wrapper (`--fragment`): any leading comments,
+/// then the `<defs>` element, ready to splice into an inline `<svg>`.
+#[cfg(feature = "fs")]
+pub(crate) fn fragment(sprite: &str) -> String {
+    match split_root(sprite) {
+        Some((head, _, rest)) => {
+            format!(
+                "{head}<defs>{}",
+                rest.strip_suffix("</svg>").unwrap_or(rest)
+            )
+        }
+        None => sprite.to_string(),
+    }
+}
+
 // Render the final sprite XML from a list of parsed SvgSprite entries
-#[cfg_attr(not(test), allow(dead_code))]
-pub(crate) fn transform(svgs: Vec<SvgSprite>) -> String {
-    let mut result = svgs.iter().fold(
-        String::from(r#"<svg xmlns="http://www.w3.org/2000/svg"><defs>"#),
-        |mut acc, svg| {
-            let name = &svg.name;
-            let children = &svg.children;
-            let attributes = &svg
-                .attributes
-                .iter()
-                .map(|(key, value)| format!(r#" {key}="{value}""#))
-                .collect::<String>();
-            acc.push_str(&format!(
-                r#"<pattern id="{name}"{attributes}>{children}</pattern>"#
-            ));
-            acc
-        },
+pub(crate) fn transform(svgs: &[SvgSprite]) -> String {
+    let root = namespaces::hoist(
+        svgs.iter()
+            .map(|svg| (svg.attributes.as_ref(), svg.children.as_ref())),
     );
-    result.push_str("</defs></svg>");
+    let open = sprite_open(&root);
+    let capacity = open.len()
+        + SPRITE_CLOSE.len()
+        + svgs
+            .iter()
+            .map(|svg| pattern_len(&svg.name, &svg.attributes, &svg.children))
+            .sum::<usize>();
+    let mut result = String::with_capacity(capacity);
+    result.push_str(&open);
+    for svg in svgs {
+        let attrs: Cow<[(String, String)]> =
+            namespaces::pattern_attrs(&svg.attributes, &svg.children, &root);
+        write_pattern(&mut result, &svg.name, &attrs, &svg.children);
+    }
+    result.push_str(SPRITE_CLOSE);
     result
 }
+
+/// Bytes `<pattern id="">` and `</pattern>` add around an icon.
+const PATTERN_WRAPPER: usize = r#"<pattern id="">"#.len() + "</pattern>".len();
+
+/// Length of the `<pattern>` markup `write_pattern` produces, so outputs can
+/// be allocated once up front.
+pub(crate) fn pattern_len(name: &str, attributes: &[(String, String)], children: &str) -> usize {
+    let attributes: usize = attributes
+        .iter()
+        .map(|(key, value)| r#" ="""#.len() + key.len() + value.len())
+        .sum();
+    PATTERN_WRAPPER + name.len() + attributes + children.len()
+}
+
+/// Append the `<pattern>` wrapper for one icon to `out`.
+#[tracing::instrument(level = "trace", skip_all, fields(icon = name))]
+pub(crate) fn write_pattern(
+    out: &mut String,
+    name: &str,
+    attributes: &[(String, String)],
+    children: &str,
+) {
+    out.push_str(r#"<pattern id=""#);
+    out.push_str(name);
+    out.push('"');
+    for (key, value) in attributes {
+        out.push(' ');
+        out.push_str(key);
+        out.push_str(r#"=""#);
+        out.push_str(value);
+        out.push('"');
+    }
+    out.push('>');
+    out.push_str(children);
+    out.push_str("</pattern>");
+}
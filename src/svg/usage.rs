@@ -0,0 +1,215 @@
+//! Icon reference scans over application source (`unused`, `missing`).
+
+use super::RunOpts;
+use crate::error::AppError;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Return the subset of `names` mentioned by a file under any of `srcs`.
+///
+/// Files are walked with the usual ignore rules (hidden files, `.gitignore`);
+/// files that are not UTF-8 are skipped.
+pub(crate) fn referenced_names(
+    srcs: &[PathBuf],
+    names: &BTreeSet<String>,
+    skip: &[PathBuf],
+    opts: &RunOpts,
+) -> Result<BTreeSet<String>, AppError> {
+    let mut found = BTreeSet::new();
+    for_each_source(srcs, skip, |_, text| {
+        for token in tokens(text, &opts.id_separator) {
+            for candidate in suffixes(token) {
+                if names.contains(candidate) {
+                    found.insert(candidate.to_string());
+                }
+            }
+        }
+    })?;
+    Ok(found)
+}
+
+/// A fragment reference into the sprite found in application source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconReference {
+    /// File containing the reference
+    pub path: PathBuf,
+    /// 1-based line of the reference
+    pub line: usize,
+    /// Referenced pattern id
+    pub name: String,
+}
+
+/// Collect every `<sprite>#<id>` reference under `srcs`, where `<sprite>` is
+/// the sprite's file name (e.g. `sprite.svg#arrow`, `url(/sprite.svg#arrow)`).
+pub(crate) fn sprite_references(
+    srcs: &[PathBuf],
+    sprite_name: &str,
+    skip: &[PathBuf],
+    opts: &RunOpts,
+) -> Result<Vec<IconReference>, AppError> {
+    let needle = format!("{sprite_name}#");
+    let mut refs = Vec::new();
+    for_each_source(srcs, skip, |path, text| {
+        for (at, _) in text.match_indices(&needle) {
+            // `my-sprite.svg#x` is not a reference to `sprite.svg`.
+            if text[..at]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+            {
+                continue;
+            }
+            let rest = &text[at + needle.len()..];
+            let Some(name) = tokens(rest, &opts.id_separator).next() else {
+                continue;
+            };
+            if !rest.starts_with(name) {
+                continue;
+            }
+            refs.push(IconReference {
+                path: path.to_path_buf(),
+                line: text[..at].matches('\n').count() + 1,
+                name: name.to_string(),
+            });
+        }
+    })?;
+    Ok(refs)
+}
+
+/// Call `f` with the path and content of each UTF-8 file under `srcs`, skipping
+/// `skip` (the sprite, the icon directory, emitted artifacts) since those list
+/// every icon.
+fn for_each_source(
+    srcs: &[PathBuf],
+    skip: &[PathBuf],
+    mut f: impl FnMut(&Path, &str),
+) -> Result<(), AppError> {
+    let skip: Vec<PathBuf> = skip.iter().filter_map(|p| p.canonicalize().ok()).collect();
+    for src in srcs {
+        for path in source_files(src, &skip)? {
+            let bytes = std::fs::read(&path).map_err(|e| AppError::ReadFile {
+                path: path.display().to_string(),
+                source: e,
+            })?;
+            let Ok(text) = std::str::from_utf8(&bytes) else {
+                tracing::debug!(path = %path.display(), "Skipping non-UTF-8 file");
+                continue;
+            };
+            f(&path, text);
+        }
+    }
+    Ok(())
+}
+
+/// Files below `src`, honoring ignore files and excluding `skip` (canonical
+/// paths, matched as files or as directories containing the file).
+fn source_files(src: &Path, skip: &[PathBuf]) -> Result<Vec<PathBuf>, AppError> {
+    std::fs::metadata(src).map_err(|e| AppError::ReadDir {
+        path: src.display().to_string(),
+        source: e,
+    })?;
+    let mut builder = ignore::WalkBuilder::new(src);
+    builder.require_git(false);
+    let mut files = Vec::new();
+    for entry in builder.build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!(path = %src.display(), error = %e, "Skipping unreadable entry");
+                continue;
+            }
+        };
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let path = entry.into_path();
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if skip.iter().any(|s| canonical.starts_with(s)) {
+            continue;
+        }
+        files.push(path);
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Identifier-like runs of `text`: ASCII alphanumerics, `_`, `-`, and the
+/// characters of the id separator.
+fn tokens<'a>(text: &'a str, separator: &'a str) -> impl Iterator<Item = &'a str> {
+    text.split(move |c: char| {
+        !(c.is_ascii_alphanumeric() || c == '_' || c == '-' || separator.contains(c))
+    })
+    .filter(|t| !t.is_empty())
+}
+
+/// A token and each of its tails after a `-` or `_`, so that prefixed uses
+/// such as `icon-arrow` count as references to `arrow`.
+fn suffixes(token: &str) -> impl Iterator<Item = &str> {
+    std::iter::once(token).chain(
+        token
+            .match_indices(['-', '_'])
+            .map(move |(i, _)| &token[i + 1..])
+            .filter(|t| !t.is_empty()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_whole_and_prefixed_references() {
+        let dir = std::env::temp_dir().join(format!("svg_usage_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("icons")).unwrap();
+        std::fs::write(
+            dir.join("app.html"),
+            "<use href=\"sprite.svg#arrow-left\"/><i class=\"icon-close\"></i>",
+        )
+        .unwrap();
+        // Every name appears in the skipped sprite; only app.html counts.
+        std::fs::write(
+            dir.join("icons/sprite.svg"),
+            "arrow-left close search arrow",
+        )
+        .unwrap();
+
+        let names: BTreeSet<String> = ["arrow", "arrow-left", "close", "search"]
+            .map(String::from)
+            .into();
+        let found = referenced_names(
+            std::slice::from_ref(&dir),
+            &names,
+            &[dir.join("icons")],
+            &RunOpts::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            found.into_iter().collect::<Vec<_>>(),
+            vec!["arrow-left", "close"]
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn collects_sprite_fragment_references() {
+        let dir = std::env::temp_dir().join(format!("svg_usage_refs_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("app.css"),
+            ".a { fill: url(/sprite.svg#arrow); }\n.b { fill: url(my-sprite.svg#other) }\n.c { fill: url(sprite.svg#clsoe) }",
+        )
+        .unwrap();
+        let refs = sprite_references(
+            std::slice::from_ref(&dir),
+            "sprite.svg",
+            &[],
+            &RunOpts::default(),
+        )
+        .unwrap();
+        let found: Vec<(usize, &str)> = refs.iter().map(|r| (r.line, r.name.as_str())).collect();
+        assert_eq!(found, vec![(1, "arrow"), (3, "clsoe")]);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
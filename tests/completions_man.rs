@@ -33,3 +33,22 @@ fn generates_man_page_into_out_dir() {
 
     temp.close().unwrap();
 }
+
+#[test]
+fn generates_nushell_and_fig_completions() {
+    let temp = assert_fs::TempDir::new().expect("tempdir");
+    let out_dir = temp.path();
+
+    for shell in ["nushell", "fig"] {
+        let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+        cmd.args(["completions", shell, "-o", out_dir.to_str().unwrap()]);
+        cmd.assert().success();
+    }
+
+    let nu = fs::read_to_string(out_dir.join("svg_sheet.nu")).expect("read nushell file");
+    assert!(nu.contains("export extern svg_sheet"), "{nu}");
+    let fig = fs::read_to_string(out_dir.join("svg_sheet.ts")).expect("read fig spec");
+    assert!(fig.contains("const completion: Fig.Spec"), "{fig}");
+
+    temp.close().unwrap();
+}
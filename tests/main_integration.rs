@@ -58,6 +58,149 @@ fn dry_run_does_not_write_output() {
     temp.close().unwrap();
 }
 
+#[test]
+fn diff_previews_changes_without_writing() {
+    let temp = assert_fs::TempDir::new().expect("tempdir");
+    let svgs = temp.child("svgs");
+    svgs.create_dir_all().unwrap();
+    let icon = "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>";
+    svgs.child("a.svg").write_str(icon).unwrap();
+    svgs.child("b.svg").write_str(icon).unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert().success();
+    let before = std::fs::read_to_string(temp.path().join("sprite.svg")).unwrap();
+
+    svgs.child("b.svg")
+        .write_str(&icon.replace("<g/>", "<path d=\"M0 0\"/>"))
+        .unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.arg("--diff");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "--- sprite.svg\n+++ sprite.svg\n@@",
+        ))
+        .stdout(predicate::str::contains("\n-<pattern id=\"b\""))
+        .stdout(predicate::str::contains("\n+<pattern id=\"b\""))
+        .stdout(predicate::str::contains("\n <pattern id=\"a\""));
+    assert_eq!(
+        std::fs::read_to_string(temp.path().join("sprite.svg")).unwrap(),
+        before
+    );
+    temp.close().unwrap();
+}
+
+#[test]
+fn baseline_lists_icon_changes_in_the_summary() {
+    let temp = assert_fs::TempDir::new().expect("tempdir");
+    let svgs = temp.child("svgs");
+    svgs.create_dir_all().unwrap();
+    let icon = "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>";
+    svgs.child("a.svg").write_str(icon).unwrap();
+    svgs.child("b.svg").write_str(icon).unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.args(["-f", "old.svg"]);
+    cmd.assert().success();
+
+    fs::remove_file(svgs.child("a.svg").path()).unwrap();
+    svgs.child("b.svg")
+        .write_str(&icon.replace("<g/>", "<path d=\"M0 0\"/>"))
+        .unwrap();
+    svgs.child("c.svg").write_str(icon).unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.args(["--baseline", "old.svg"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "\n  vs old.svg: 1 added (c), 1 removed (a), 1 changed (b)\n",
+    ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.args(["--baseline", "missing.svg"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("missing.svg"));
+    temp.close().unwrap();
+}
+
+#[test]
+fn backup_keeps_the_previous_sprite() {
+    let temp = assert_fs::TempDir::new().expect("tempdir");
+    let svgs = temp.child("svgs");
+    svgs.create_dir_all().unwrap();
+    let icon = "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>";
+    svgs.child("a.svg").write_str(icon).unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert().success();
+    let before = std::fs::read_to_string(temp.path().join("sprite.svg")).unwrap();
+
+    svgs.child("b.svg").write_str(icon).unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.args(["--backup", "build"]);
+    cmd.assert().success();
+    temp.child("sprite.svg.bak").assert(before.as_str());
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    svgs.child("c.svg").write_str(icon).unwrap();
+    cmd.arg("--backup=prev-{file}");
+    cmd.assert().success();
+    temp.child("prev-sprite.svg")
+        .assert(predicate::str::contains("<pattern id=\"b\""));
+    temp.close().unwrap();
+}
+
+#[test]
+fn archive_dir_keeps_a_copy_of_each_new_sprite() {
+    let temp = assert_fs::TempDir::new().expect("tempdir");
+    let svgs = temp.child("svgs");
+    svgs.create_dir_all().unwrap();
+    let icon = "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>";
+    svgs.child("a.svg").write_str(icon).unwrap();
+    let archived = || {
+        let mut names: Vec<String> = std::fs::read_dir(temp.path().join("builds"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    };
+    let run = || {
+        let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+        cmd.current_dir(temp.path());
+        cmd.args(["--archive-dir", "builds"]);
+        cmd.assert().success();
+    };
+
+    run();
+    let first = archived();
+    assert_eq!(first.len(), 1);
+    assert!(first[0].starts_with("sprite-") && first[0].ends_with(".svg"));
+    let sprite = std::fs::read_to_string(temp.path().join("sprite.svg")).unwrap();
+    temp.child("builds")
+        .child(&first[0])
+        .assert(sprite.as_str());
+
+    run();
+    assert_eq!(
+        archived(),
+        first,
+        "an unchanged sprite is not archived again"
+    );
+    svgs.child("b.svg").write_str(icon).unwrap();
+    run();
+    assert_eq!(archived().len(), 2);
+    temp.close().unwrap();
+}
+
 #[test]
 fn nonexistent_input_dir_error() {
     let temp = assert_fs::TempDir::new().expect("tempdir");
@@ -87,3 +230,1762 @@ fn fail_on_warn_exits_nonzero() {
 
     temp.close().unwrap();
 }
+
+#[test]
+fn warning_rules_from_flags_and_config() {
+    let temp = assert_fs::TempDir::new().expect("tempdir");
+    let svgs = temp.child("svgs");
+    svgs.create_dir_all().unwrap();
+    svgs.child("w.svg")
+        .write_str("<svg width=\"1\" height=\"1\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.args(["--deny", "missing-viewbox"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Error[E0013]"));
+
+    temp.child("svg_sheet.toml")
+        .write_str("[warnings]\nW0004 = \"allow\"\n")
+        .unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.arg("--fail-on-warn");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("0 warnings"));
+
+    // A flag overrides the config's level; the baseline tolerates one warning.
+    temp.child("svg_sheet.toml")
+        .write_str("[warnings]\nbaseline = 1\nmissing-viewbox = \"error\"\n")
+        .unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert().success();
+    svgs.child("x.svg")
+        .write_str("<svg width=\"1\" height=\"1\"><g/></svg>")
+        .unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("aborting due to 2 warning(s)"));
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.args(["--allow", "missing-viewbox"]);
+    cmd.assert().success();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.args(["--allow", "missing-title"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "unknown warning rule 'missing-title'",
+    ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn build_prints_summary_unless_quiet() {
+    let temp = assert_fs::TempDir::new().expect("tempdir");
+    let svgs = temp.child("svgs");
+    svgs.create_dir_all().unwrap();
+    svgs.child("a.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Built sprite.svg: 1 icon, 0 warnings",
+    ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.arg("--verbose");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("  a ("));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.arg("--quiet");
+    cmd.assert().success().stdout(predicate::str::is_empty());
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn stdin_to_stdout_streams_sprite() {
+    let input = "<svg id=\"dot\" width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>\n<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><path/></svg>";
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.args(["-d", "-", "-f", "-", "build"]).write_stdin(input);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("<svg"))
+        .stdout(predicate::str::contains("<pattern id=\"dot\""))
+        .stdout(predicate::str::contains("<pattern id=\"icon-2\""))
+        .stdout(predicate::str::contains("Built").not())
+        .stderr(predicate::str::contains("Built <stdout>: 2 icons"));
+}
+
+#[test]
+fn unused_lists_unreferenced_icons_and_fails_on_request() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    for name in ["arrow", "close"] {
+        temp.child(format!("svgs/{name}.svg"))
+            .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+            .unwrap();
+    }
+    temp.child("src/app.html")
+        .write_str("<svg><use href=\"/sprite.svg#arrow\"/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["unused", "--src", "src"]);
+    cmd.assert()
+        .success()
+        .stdout("close\n")
+        .stderr(predicate::str::contains("1 unused icon(s)"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["unused", "--src", "src", "--fail"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("1 unused icon(s): close"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn missing_reports_references_to_absent_icons() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/arrow.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+    temp.child("src/app.css")
+        .write_str(".ok { fill: url(sprite.svg#arrow) }\n.typo { fill: url(sprite.svg#arow) }\n")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["missing", "--src", "src"]);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("app.css:2: arow"))
+        .stdout(predicate::str::contains("arrow").not())
+        .stderr(predicate::str::contains(
+            "1 reference(s) to missing icon(s): arow",
+        ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn check_fails_when_sprite_is_stale() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let svg = "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>";
+    temp.child("svgs/a.svg").write_str(svg).unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--check");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("file does not exist"));
+    temp.child("sprite.svg").assert(predicate::path::missing());
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert().success();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--check");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Unchanged sprite.svg"));
+
+    temp.child("svgs/b.svg").write_str(svg).unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--check");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "sprite.svg is out of date (added: b)",
+    ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn size_limits_reject_oversized_inputs() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let path = format!("<path d=\"{}\"/>", "M0 0L1 1".repeat(200));
+    temp.child("svgs/big.svg")
+        .write_str(&format!(
+            "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\">{path}</svg>"
+        ))
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--max-file-size", "4K", "--max-icon-bytes", "4K"]);
+    cmd.assert().success();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--max-file-size", "1000"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "over the --max-file-size limit of 1000 bytes",
+    ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--max-icon-bytes", "1000"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("big.svg is "))
+        .stderr(predicate::str::contains(
+            "--max-icon-bytes limit of 1000 bytes",
+        ));
+
+    // With a template the limit applies to what the template emits per icon.
+    temp.child("ids.tera")
+        .write_str("<ul>{% for icon in icons %}<li>{{ icon.id }}</li>{% endfor %}</ul>")
+        .unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--template", "ids.tera", "--max-icon-bytes", "12"]);
+    cmd.assert().success();
+    assert_eq!(
+        fs::read_to_string(temp.child("sprite.svg").path()).unwrap(),
+        "<ul><li>big</li></ul>"
+    );
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--template", "ids.tera", "--max-icon-bytes", "11"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--max-icon-bytes limit of 11 bytes",
+    ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--max-file-size", "lots"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid size 'lots'"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn flatten_transforms_folds_translations_into_paths() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str(
+            "<svg width=\"4\" height=\"4\" viewBox=\"0 0 4 4\"><g transform=\"translate(1 1)\"><path d=\"M0 0h2v2z\"/></g></svg>",
+        )
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--flatten-transforms");
+    cmd.assert().success();
+
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.contains("<g><path d=\"M1 1L3 1L3 3Z\"/></g>"));
+    assert!(!sprite.contains("transform="));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn compact_paths_shortens_path_data() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str(
+            "<svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><path d=\"M 10 10 L 20 10 L 20 20 L 10 20 Z\"/></svg>",
+        )
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--compact-paths");
+    cmd.assert().success();
+
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.contains("<path d=\"M10 10H20V20H10z\"/>"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn shared_defs_emits_identical_gradients_once() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    for (name, id) in [("a", "paint0_linear"), ("b", "grad")] {
+        temp.child(format!("svgs/{name}.svg"))
+            .write_str(&format!(
+                "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><defs><linearGradient id=\"{id}\"><stop offset=\"1\" stop-color=\"red\"/></linearGradient></defs><path fill=\"url(#{id})\" d=\"M0 0\"/></svg>"
+            ))
+            .unwrap();
+    }
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--shared-defs");
+    cmd.assert().success();
+
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert_eq!(sprite.matches("<linearGradient").count(), 1);
+    let id = sprite
+        .split("<linearGradient id=\"")
+        .nth(1)
+        .and_then(|s| s.split('"').next())
+        .expect("shared gradient id");
+    assert_eq!(sprite.matches(&format!("url(#{id})")).count(), 2);
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn config_file_remaps_colors_and_adds_emit_targets() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str(
+            "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><path fill=\"#1A1A1A\" d=\"M0 0\"/></svg>",
+        )
+        .unwrap();
+    temp.child("svg_sheet.toml")
+        .write_str("emit = [\"ts=icons.ts\"]\n\n[colors]\n\"#1a1a1a\" = \"currentColor\"\n")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--verbose");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("(1 color remapped)"));
+
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.contains("<path fill=\"currentColor\" d=\"M0 0\"/>"));
+    temp.child("icons.ts").assert(predicate::path::exists());
+
+    temp.child("bad.toml").write_str("colours = {}\n").unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).args(["--config", "bad.toml"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid config (bad.toml)"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn config_aliases_add_patterns_pointing_at_icons() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/arrow-right.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+    temp.child("svg_sheet.toml")
+        .write_str("[aliases]\nchevron-right = \"arrow-right\"\n")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--emit", "manifest=icons.json"]);
+    cmd.assert().success();
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.contains("<pattern id=\"chevron-right\" href=\"#arrow-right\"></pattern>"));
+    let manifest = fs::read_to_string(temp.child("icons.json").path()).unwrap();
+    assert!(manifest.contains("\"aliasOf\": \"arrow-right\""));
+
+    temp.child("svg_sheet.toml")
+        .write_str("[aliases]\nchevron-right = \"arow-right\"\n")
+        .unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "alias 'chevron-right' points to unknown icon 'arow-right'",
+    ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn defs_file_is_injected_and_references_are_checked() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/_defs.svg")
+        .write_str("<svg xmlns=\"http://www.w3.org/2000/svg\"><defs><linearGradient id=\"brand\"><stop offset=\"0\"/></linearGradient></defs></svg>")
+        .unwrap();
+    temp.child("svgs/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><path fill=\"url(#brand)\" d=\"M0 0\"/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--deny", "unresolved-reference"]);
+    cmd.assert().success();
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.contains(
+        "<defs><linearGradient id=\"brand\"><stop offset=\"0\"/></linearGradient><pattern id=\"a\""
+    ));
+    assert!(!sprite.contains("id=\"_defs\""));
+
+    temp.child("svgs/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><path fill=\"url(#brnd)\" d=\"M0 0\"/></svg>")
+        .unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--deny", "unresolved-reference"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Reference to '#brnd' matches no definition",
+    ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn sprite_inputs_are_split_into_icons() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+    temp.child("svgs/legacy.svg")
+        .write_str("<svg xmlns=\"http://www.w3.org/2000/svg\"><symbol id=\"home\" viewBox=\"0 0 24 24\"><path d=\"M0 0\"/></symbol><symbol id=\"bell\" viewBox=\"0 0 24 24\"><circle r=\"1\"/></symbol></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--only", "a", "--only", "bell"]);
+    cmd.assert().success();
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.contains("<pattern id=\"a\""));
+    assert!(
+        sprite.contains("<pattern id=\"bell\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><circle r=\"1\"/></pattern>")
+    );
+    assert!(!sprite.contains("id=\"home\""));
+    assert!(!sprite.contains("id=\"legacy\""));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn overlay_icons_replace_same_named_icons() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("vendor/bell.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g id=\"vendor\"/></svg>")
+        .unwrap();
+    temp.child("vendor/home.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+    temp.child("local/bell.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><circle r=\"1\"/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["-d", "vendor", "--overlay", "local"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "(1 overridden)\n  bell: local/bell.svg overrides vendor/bell.svg",
+    ));
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.contains("<pattern id=\"bell\" width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><circle r=\"1\"/></pattern>"));
+    assert!(sprite.contains("<pattern id=\"home\""));
+    assert!(!sprite.contains("vendor"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn deprecated_icons_warn_and_are_reported_by_missing() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/arrow-old.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+    temp.child("svgs/bell-old.svg")
+        .write_str("<svg data-deprecated=\"Use bell\" width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+    temp.child("svg_sheet.toml")
+        .write_str("[deprecated]\narrow-old = \"Use arrow-right\"\n")
+        .unwrap();
+    temp.child("src/app.css")
+        .write_str("a { fill: url(sprite.svg#bell-old) }\n")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--emit", "manifest=icons.json"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2 warnings"));
+    let manifest = fs::read_to_string(temp.child("icons.json").path()).unwrap();
+    assert!(manifest.contains("\"deprecated\": \"Use arrow-right\""));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--deny", "deprecated-icon"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Icon 'bell-old' is deprecated: Use bell",
+    ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["missing", "--src", "src"]);
+    cmd.assert().success().stderr(predicate::str::contains(
+        "app.css:1: warning: Icon 'bell-old' is deprecated: Use bell",
+    ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn manifest_lists_categories_and_tags() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let svg = "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>";
+    temp.child("svgs/home.svg").write_str(svg).unwrap();
+    temp.child("svgs/brand/github.svg").write_str(svg).unwrap();
+    temp.child("svg_sheet.toml")
+        .write_str("[tags]\nbrand-github = [\"social\", \"code\"]\n")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).args([
+        "--recursive",
+        "--emit",
+        "manifest=icons.json",
+        "--emit",
+        "preview=icons.html",
+    ]);
+    cmd.assert().success();
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp.child("icons.json").path()).unwrap())
+            .unwrap();
+    let icons = manifest["icons"].as_array().unwrap();
+    let github = icons.iter().find(|i| i["id"] == "brand-github").unwrap();
+    assert_eq!(github["category"], "brand");
+    assert_eq!(github["tags"], serde_json::json!(["social", "code"]));
+    let home = icons.iter().find(|i| i["id"] == "home").unwrap();
+    assert!(home.get("category").is_none());
+    let preview = fs::read_to_string(temp.child("icons.html").path()).unwrap();
+    assert!(preview.contains("data-search=\"brand-github brand #social #code\""));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn search_index_lists_synonyms() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/trash-can.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+    temp.child("synonyms.txt")
+        .write_str("trash, delete, bin\n")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).args([
+        "--emit",
+        "search-index=search.json",
+        "--synonyms",
+        "synonyms.txt",
+    ]);
+    cmd.assert().success();
+    let index: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp.child("search.json").path()).unwrap())
+            .unwrap();
+    assert_eq!(index[0]["id"], "trash-can");
+    assert_eq!(index[0]["name"], "trash can");
+    assert_eq!(index[0]["synonyms"], serde_json::json!(["delete", "bin"]));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--synonyms", "missing.txt"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("missing.txt"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn markdown_out_writes_a_gallery() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/home.svg")
+        .write_str("<svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--markdown-out", "docs/ICONS.md"]);
+    cmd.assert().success();
+    temp.child("docs/ICONS.md").assert(predicate::str::contains(
+        "| <img src=\"../svgs/home.svg\" width=\"24\" height=\"24\" alt=\"home\"> | `home` | 24×24 |",
+    ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn json_icons_writes_standalone_svgs() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/home.svg")
+        .write_str("<svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--json-icons", "dist/icons.json"]);
+    cmd.assert().success();
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp.child("dist/icons.json").path()).unwrap())
+            .unwrap();
+    assert_eq!(
+        json["home"],
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><g/></svg>"
+    );
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn emit_inline_html_hides_the_sprite() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/home.svg")
+        .write_str("<svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--emit", "inline-html=sprite.inline.html"]);
+    cmd.assert().success();
+    let html = fs::read_to_string(temp.child("sprite.inline.html").path()).unwrap();
+    assert!(html.starts_with(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" aria-hidden=\"true\" focusable=\"false\" style=\"display:none\"><defs><pattern id=\"home\""
+    ));
+    assert!(html.ends_with("</defs></svg>\n"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn lock_rejects_changed_icons_until_updated() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let icon = temp.child("svgs/bell.svg");
+    icon.write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--lock");
+    cmd.assert().success();
+    temp.child("sprite.lock")
+        .assert(predicate::str::contains("  bell\n"));
+
+    icon.write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><path d=\"M0 0\"/></svg>")
+        .unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--lock");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("1 icon(s) changed since"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--update-lock");
+    cmd.assert().success();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--lock");
+    cmd.assert().success();
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn parse_errors_report_line_and_column() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/bad.svg")
+        .write_str("<?xml version=\"1.0\"?>\n<svg width=\"1\"\n  height=\"1\" <path/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "bad.svg:3:14: failed to parse svg: expected '>' after attributes",
+    ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn strict_rejects_questionable_inputs() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let icon = temp.child("svgs/a.svg");
+    icon.write_str(
+        "<svg width=\"1\" height=\"1\" sodipodi:docname=\"a.svg\" viewBox=\"0 0 1 1\"><g/></svg>",
+    )
+    .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--dry-run");
+    cmd.assert().success();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).args(["--dry-run", "--strict"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("E0025"))
+        .stderr(predicate::str::contains(
+            "unknown attribute 'sodipodi:docname' on root <svg>",
+        ));
+
+    icon.write_str("<svg width=\"1\" height=\"1\"><g/></svg>")
+        .unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--dry-run", "--strict", "--allow", "missing-viewbox"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("E0013"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn profiles_apply_their_switches() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+    temp.child("svg_sheet.toml")
+        .write_str("[profile.dev]\nsource-comments = true\nemit = [\"ts=icons.ts\"]\n")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).args(["--profile", "dev"]);
+    cmd.assert().success();
+    temp.child("sprite.svg")
+        .assert(predicate::str::contains("<!-- source: svgs/a.svg"));
+    temp.child("icons.ts").assert(predicate::path::exists());
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).args(["--profile", "prod"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "unknown profile 'prod' (defined: dev)",
+    ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn sidecar_files_add_metadata() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/home.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\" fill=\"none\"><g/></svg>")
+        .unwrap();
+    temp.child("svgs/home.svg.toml")
+        .write_str(
+            "title = \"Home\"\ntags = [\"navigation\"]\ndeprecated = \"Use house\"\n[attributes]\nfill = \"currentColor\"\n",
+        )
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--emit", "manifest=icons.json"]);
+    cmd.assert().success().stderr(predicate::str::contains(
+        "Icon 'home' is deprecated: Use house",
+    ));
+    temp.child("sprite.svg").assert(
+        predicate::str::contains("fill=\"currentColor\"")
+            .and(predicate::str::contains("<title>Home</title><g/>")),
+    );
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp.child("icons.json").path()).unwrap())
+            .unwrap();
+    let home = &manifest["icons"][0];
+    assert_eq!(home["title"], "Home");
+    assert_eq!(home["tags"], serde_json::json!(["navigation"]));
+    assert_eq!(home["deprecated"], "Use house");
+
+    temp.child("svgs/home.svg.toml")
+        .write_str("titel = \"Home\"\n")
+        .unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid sidecar metadata"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn titles_file_labels_icons() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/trash-can.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+    temp.child("titles.json")
+        .write_str(r#"{"trash-can": {"title": "Delete", "description": "Move to the trash"}}"#)
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--titles", "titles.json", "--emit", "manifest=icons.json"]);
+    cmd.assert().success();
+    temp.child("sprite.svg").assert(predicate::str::contains(
+        "<title>Delete</title><desc>Move to the trash</desc><g/>",
+    ));
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp.child("icons.json").path()).unwrap())
+            .unwrap();
+    assert_eq!(manifest["icons"][0]["title"], "Delete");
+    assert_eq!(manifest["icons"][0]["description"], "Move to the trash");
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn generator_comment_honors_source_date_epoch() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .env("SOURCE_DATE_EPOCH", "951827696")
+        .arg("--generator-comment");
+    cmd.assert().success();
+    temp.child("sprite.svg")
+        .assert(predicate::str::starts_with(format!(
+            "<!-- Generated by svg_sheet {} on 2000-02-29T12:34:56Z -->\n",
+            env!("CARGO_PKG_VERSION")
+        )));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .env_remove("SOURCE_DATE_EPOCH")
+        .args(["--generator-comment", "--no-timestamp"]);
+    cmd.assert().success();
+    temp.child("sprite.svg")
+        .assert(predicate::str::starts_with(format!(
+            "<!-- Generated by svg_sheet {} -->\n",
+            env!("CARGO_PKG_VERSION")
+        )));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn files_from_builds_only_listed_files() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let svg = "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>";
+    for name in ["svgs/a.svg", "svgs/b.svg", "svgs/brand/c.svg"] {
+        temp.child(name).write_str(svg).unwrap();
+    }
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--files-from", "-"])
+        .write_stdin("svgs/brand/c.svg\nsvgs/deleted.svg\nREADME.md\nsvgs/a.svg\n");
+    cmd.assert().success();
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.contains("id=\"a\"") && sprite.contains("id=\"brand-c\""));
+    assert!(!sprite.contains("id=\"b\""));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--files-from", "-", "-f", "other.svg"])
+        .write_stdin("svgs/deleted.svg\n");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("nothing to build"));
+    temp.child("other.svg").assert(predicate::path::missing());
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn root_attrs_from_config_and_flags() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/home.svg")
+        .write_str("<svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><g/></svg>")
+        .unwrap();
+    temp.child("svg_sheet.toml")
+        .write_str("[root]\nclass = \"sprite\"\naria-hidden = \"true\"\n")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).args([
+        "--root-attr",
+        "class=svg-sprite",
+        "--root-attr",
+        "data-x=a\"b",
+    ]);
+    cmd.assert().success();
+    temp.child("sprite.svg").assert(predicate::str::starts_with(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" aria-hidden=\"true\" class=\"svg-sprite\" data-x=\"a&quot;b\"><defs><pattern id=\"home\"",
+    ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--root-attr", "xmlns=x"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("'xmlns' is always set"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn fragment_leaves_out_the_svg_wrapper() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--fragment", "--emit", "preview=preview.html"]);
+    cmd.assert().success();
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.starts_with("<defs><pattern id=\"a\""), "{sprite}");
+    assert!(sprite.ends_with("</pattern></defs>"), "{sprite}");
+    temp.child("preview.html").assert(predicate::str::contains(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\"><defs><pattern",
+    ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--fragment", "--chunk-by", "dir"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn percent_dims_strip_accepts_responsive_exports() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/hero.svg")
+        .write_str("<svg width=\"100%\" height=\"100%\" viewBox=\"0 0 24 24\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--percent-dims"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--percent-dims", "strip"]);
+    cmd.assert().success();
+    temp.child("sprite.svg").assert(predicate::str::contains(
+        "<pattern id=\"hero\" viewBox=\"0 0 24 24\">",
+    ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn converts_physical_and_font_units_to_pixels() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/print.svg")
+        .write_str("<svg width=\"18pt\" height=\"1.2em\" viewBox=\"0 0 24 24\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).args(["--em-size", "20"]);
+    cmd.assert().success();
+    temp.child("sprite.svg").assert(predicate::str::contains(
+        "<pattern id=\"print\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\">",
+    ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn precision_rounds_viewbox_and_dimensions() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/noisy.svg")
+        .write_str(
+            "<svg width=\"23.999999999999996\" height=\"24.25\" viewBox=\"0 -0.00001 23.999999999999996 24.25\"><g/></svg>",
+        )
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert().success();
+    temp.child("sprite.svg").assert(predicate::str::contains(
+        "<pattern id=\"noisy\" width=\"24\" height=\"24.25\" viewBox=\"0 0 24 24.25\">",
+    ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).args(["--precision", "0"]);
+    cmd.assert().success();
+    temp.child("sprite.svg").assert(predicate::str::contains(
+        "<pattern id=\"noisy\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\">",
+    ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn max_icon_size_warns_with_a_breakdown() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/small.svg")
+        .write_str("<svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><g/></svg>")
+        .unwrap();
+    temp.child("svgs/map.svg")
+        .write_str(&format!(
+            "<svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><path d=\"{}\"/></svg>",
+            "M0 0L1 1".repeat(300)
+        ))
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--max-icon-size", "1K", "--deny", "icon-budget"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("E0013"))
+        .stderr(predicate::str::contains(
+            "Icon 'map' is 2.4 KiB, over the 1.0 KiB budget; path data 2.3 KiB, other markup 83 B",
+        ))
+        .stderr(predicate::str::contains("'small'").not());
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn themable_wraps_colors_in_role_variables() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str(
+            "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><path fill=\"#1a1a1a\" stroke=\"#f00\" d=\"M0 0\"/></svg>",
+        )
+        .unwrap();
+    temp.child("svg_sheet.toml")
+        .write_str("[roles]\nprimary = [\"#1a1a1a\"]\n")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--themable", "--emit", "manifest=icons.json"]);
+    cmd.assert().success();
+
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.contains(
+        "<path fill=\"var(--icon-primary, #1a1a1a)\" stroke=\"var(--icon-stroke, #f00)\" d=\"M0 0\"/>"
+    ));
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp.child("icons.json").path()).unwrap())
+            .unwrap();
+    assert_eq!(
+        manifest["icons"][0]["variables"],
+        serde_json::json!([
+            { "name": "--icon-primary", "default": "#1a1a1a" },
+            { "name": "--icon-stroke", "default": "#f00" }
+        ])
+    );
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn config_attributes_strip_and_report_removals() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str(
+            "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\" xml:space=\"preserve\"><g data-figma-id=\"1:2\"><path data-figma-name=\"p\" d=\"M0 0\"/></g></svg>",
+        )
+        .unwrap();
+    temp.child("svg_sheet.toml")
+        .write_str("[attributes]\nstrip = [\"data-figma-*\", \"xml:space\"]\n")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--verbose");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("(3 attributes stripped)"))
+        .stdout(predicate::str::contains(
+            "stripped data-figma-id (1), data-figma-name (1), xml:space (1)",
+        ));
+
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(!sprite.contains("data-figma") && !sprite.contains("xml:space"));
+    assert!(sprite.contains("<g><path d=\"M0 0\"/></g>"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn namespace_declarations_are_hoisted_to_the_sprite_root() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let svg = |body: &str| {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" xmlns:sketch=\"http://www.bohemiancoding.com/sketch/ns\" width=\"1\" height=\"1\" viewBox=\"0 0 1 1\">{body}</svg>"
+        )
+    };
+    temp.child("svgs/a.svg")
+        .write_str(&svg("<path id=\"p\" d=\"M0 0\"/><use xlink:href=\"#p\"/>"))
+        .unwrap();
+    temp.child("svgs/b.svg")
+        .write_str(&svg("<use xlink:href=\"#q\"/>"))
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert().success();
+
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.starts_with(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\"><defs>"
+    ));
+    assert_eq!(sprite.matches("xmlns").count(), 2);
+    assert!(!sprite.contains("sketch"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn plain_href_rewrites_xlink_references() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><clipPath id=\"c\"><rect/></clipPath><use xlink:href=\"#c\"/></svg>",
+        )
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).arg("--plain-href");
+    cmd.assert().success();
+
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(!sprite.contains("xlink"));
+    assert!(sprite.contains("<use href=\"#a-c\"/>"));
+
+    temp.close().unwrap();
+}
+
+#[cfg(feature = "plugins")]
+#[test]
+fn plugins_transform_icons_and_fail_the_build_with_their_error() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><path d=\"M0 0\"/></svg>")
+        .unwrap();
+    // Plugins answering every icon with `result`, stored at offset 0.
+    let plugin = |name: &str, result: &str| {
+        let wat = format!(
+            r#"(module
+              (memory (export "memory") 1)
+              (data (i32.const 0) "{}")
+              (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+              (func (export "transform") (param i32 i32) (result i64) (i64.const {})))"#,
+            result.replace('\\', "\\\\").replace('"', "\\\""),
+            result.len()
+        );
+        temp.child(name)
+            .write_binary(&wat::parse_str(wat).unwrap())
+            .unwrap();
+    };
+    plugin(
+        "stamp.wasm",
+        r#"{"children":"<path d=\"M0 0\"/><desc>(c) Acme</desc>","attributes":[["viewBox","0 0 1 1"]]}"#,
+    );
+    plugin("refuse.wasm", r#"{"error":"no license"}"#);
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--plugin", "stamp.wasm"]);
+    cmd.assert().success();
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.contains("<desc>(c) Acme</desc>"), "{sprite}");
+    assert!(!sprite.contains("width=\"1\""), "{sprite}");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--plugin", "stamp.wasm", "--plugin", "refuse.wasm"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("E0027"))
+        .stderr(predicate::str::contains("no license"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn config_pipeline_orders_and_skips_stages() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str(
+            "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><path fill=\"#1a1a1a\" d=\"M 0 0 L 1 1\"/></svg>",
+        )
+        .unwrap();
+    let build = |config: &str| {
+        temp.child("svg_sheet.toml").write_str(config).unwrap();
+        let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+        cmd.current_dir(temp.path())
+            .args(["--themable", "--compact-paths"]);
+        cmd.assert().success();
+        fs::read_to_string(temp.child("sprite.svg").path()).unwrap()
+    };
+    let colors = "[colors]\n\"#1a1a1a\" = \"currentColor\"\n";
+
+    let sprite = build(colors);
+    assert!(sprite.contains("<path fill=\"currentColor\" d=\"M0 0 1 1\"/>"));
+    let sprite = build(&format!("pipeline = [\"themable\", \"colors\"]\n{colors}"));
+    assert!(sprite.contains("<path fill=\"var(--icon-fill, #1a1a1a)\" d=\"M 0 0 L 1 1\"/>"));
+
+    temp.child("svg_sheet.toml")
+        .write_str("pipeline = [\"colors\", \"minify\"]\n")
+        .unwrap();
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown pipeline stage 'minify'"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn config_pipeline_stages_turn_on_their_pass() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str(
+            "<svg width=\"1.0px\" height=\"1\" viewBox=\"0 0 1 1\">\n  <!-- layer -->\n  <path id=\"b\" d=\"M 0 0 L 1 1\"/>\n</svg>",
+        )
+        .unwrap();
+    let build = |pipeline: &str| {
+        temp.child("svg_sheet.toml")
+            .write_str(&format!("pipeline = {pipeline}\n"))
+            .unwrap();
+        let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+        cmd.current_dir(temp.path());
+        cmd.assert()
+    };
+
+    // Listed without their flags, `compact-paths` and `optimize` still run.
+    build(r#"["normalize-dims", "compact-paths", "optimize", "scope-ids"]"#).success();
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(
+        sprite.contains("width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><path data-id=\"b\" d=\"M0 0 1 1\"/></pattern>"),
+        "{sprite}"
+    );
+
+    // Left out, dimensions stay as written and ids are not moved to data-id.
+    build(r#"["optimize"]"#).success();
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.contains("width=\"1.0px\""), "{sprite}");
+    assert!(
+        sprite.contains("<path id=\"b\" d=\"M 0 0 L 1 1\"/>"),
+        "{sprite}"
+    );
+
+    // The sprite-wide collision check still applies to the ids kept.
+    temp.child("svgs/b.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"></svg>")
+        .unwrap();
+    build(r#"["optimize"]"#)
+        .failure()
+        .stderr(predicate::str::contains("E0006"));
+    build(r#"["optimize", "scope-ids"]"#).success();
+
+    // Without the feature, a listed `plugins` stage could never run.
+    #[cfg(not(feature = "plugins"))]
+    build(r#"["plugins", "scope-ids"]"#)
+        .failure()
+        .stderr(predicate::str::contains(
+            "stage 'plugins' requires the plugins feature",
+        ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn chunk_by_dir_writes_one_sheet_per_directory() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let svg = "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>";
+    temp.child("svgs/social/like.svg").write_str(svg).unwrap();
+    temp.child("svgs/editor/bold.svg").write_str(svg).unwrap();
+    temp.child("svgs/logo.svg").write_str(svg).unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--recursive", "--chunk-by", "dir"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("(3 chunks)"));
+
+    let social = fs::read_to_string(temp.child("sprite-social.svg").path()).unwrap();
+    assert!(social.contains("<pattern id=\"social-like\""));
+    assert!(!social.contains("editor-bold"));
+    temp.child("sprite-editor.svg")
+        .assert(predicate::str::contains("<pattern id=\"editor-bold\""));
+    temp.child("sprite-root.svg")
+        .assert(predicate::str::contains("<pattern id=\"logo\""));
+    temp.child("sprite.svg").assert(predicate::path::missing());
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp.child("sprite.chunks.json").path()).unwrap())
+            .unwrap();
+    assert_eq!(manifest["icons"]["social-like"], "sprite-social.svg");
+    assert_eq!(manifest["chunks"]["editor"], "sprite-editor.svg");
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn max_output_size_warns_and_fails_on_warn() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/big.svg")
+        .write_str(&format!(
+            "<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><path d=\"{}\"/></svg>",
+            "M0 0L1 1".repeat(200)
+        ))
+        .unwrap();
+    temp.child("svgs/small.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--max-output-size", "1KB"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1 warning"))
+        .stderr(predicate::str::contains("over the 1.0 KiB budget"))
+        .stderr(predicate::str::contains("largest icons: big ("));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--max-output-size", "1KB", "--fail-on-warn"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("aborting due to"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn config_schema_prints_json_schema() {
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    let output = cmd.args(["config", "schema"]).assert().success();
+    let schema: serde_json::Value =
+        serde_json::from_slice(&output.get_output().stdout).expect("valid JSON");
+    assert_eq!(schema["title"], "svg_sheet.toml");
+    for key in ["emit", "colors", "roles"] {
+        assert!(schema["properties"][key].is_object(), "missing {key}");
+    }
+}
+
+#[test]
+fn environment_variables_sit_between_defaults_and_flags() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("icons/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .env("SVG_SHEET_DIRECTORY", "icons")
+        .env("SVG_SHEET_FILE", "env.svg")
+        .env("SVG_SHEET_QUIET", "1");
+    cmd.assert().success().stdout(predicate::str::is_empty());
+    temp.child("env.svg").assert(predicate::path::exists());
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .env("SVG_SHEET_DIRECTORY", "icons")
+        .env("SVG_SHEET_FILE", "env.svg")
+        .env("SVG_SHEET_QUIET", "false")
+        .args(["-f", "flag.svg"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Built flag.svg"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn log_format_selects_json_or_compact_lines() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/w.svg")
+        .write_str("<svg ><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .env_remove("RUST_LOG")
+        .args(["--log-format", "compact"]);
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "WARN Missing width on root <svg> path=",
+        ))
+        .stderr(predicate::str::contains("\"level\"").not());
+
+    // Without a terminal the default stays JSON.
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).env_remove("RUST_LOG");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("\"level\":\"WARN\""));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn debug_logs_trace_per_file_spans_with_timings() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\" viewBox=\"0 0 1 1\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).env_remove("RUST_LOG").args([
+        "--log-level",
+        "debug",
+        "--log-format",
+        "compact",
+    ]);
+    cmd.assert()
+        .success()
+        .stderr(
+            predicate::str::is_match(
+                r#"parse_document: close time.busy=\S+ .*file="svgs/a.svg" icon="a" bytes=\d+"#,
+            )
+            .unwrap(),
+        )
+        .stderr(predicate::str::contains("render_sprite: close"))
+        .stderr(predicate::str::contains("write_output: close"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn emit_storybook_writes_stories_for_every_icon() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    for name in ["arrow", "close"] {
+        temp.child(format!("svgs/{name}.svg"))
+            .write_str("<svg width=\"16\" height=\"16\" viewBox=\"0 0 16 16\"><g/></svg>")
+            .unwrap();
+    }
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--emit", "storybook=src/Icons.stories.tsx"]);
+    cmd.assert().success();
+
+    let stories = fs::read_to_string(temp.child("src/Icons.stories.tsx").path()).unwrap();
+    assert!(stories.contains("<pattern id=\\\"arrow\\\""));
+    assert!(stories.contains("{ name: \"close\", width: \"16\", height: \"16\" }"));
+    assert!(stories.contains("export default meta;"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn emit_esm_exports_icon_markup() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/arrow-left.svg")
+        .write_str("<svg width=\"16\" height=\"16\" viewBox=\"0 0 16 16\"><g/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--emit", "esm=src/icons.mjs"]);
+    cmd.assert().success();
+
+    let module = fs::read_to_string(temp.child("src/icons.mjs").path()).unwrap();
+    assert!(
+        module.contains("export const arrowLeft = \"<svg xmlns=\\\"http://www.w3.org/2000/svg\\\"")
+    );
+    assert!(module.contains("  \"arrow-left\": arrowLeft,\n"));
+    assert!(module.contains("export default icons;"));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn input_iconify_builds_collection_icons_alongside_files() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/logo.svg")
+        .write_str("<svg width=\"8\" height=\"8\" viewBox=\"0 0 8 8\"><g/></svg>")
+        .unwrap();
+    temp.child("mdi.json")
+        .write_str(
+            r#"{
+                "prefix": "mdi",
+                "width": 24,
+                "height": 24,
+                "icons": {
+                    "home": { "body": "<path d=\"M10 20v-6h4v6\"/>" },
+                    "account": { "body": "<circle cx=\"12\" cy=\"8\" r=\"4\"/>" }
+                },
+                "aliases": { "house": { "parent": "home", "hFlip": true } }
+            }"#,
+        )
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).args([
+        "--input-iconify",
+        "mdi.json",
+        "--only",
+        "logo",
+        "--only",
+        "home",
+        "--only",
+        "house",
+    ]);
+    cmd.assert().success();
+
+    let sprite = fs::read_to_string(temp.child("sprite.svg").path()).unwrap();
+    assert!(sprite.contains("<pattern id=\"logo\""));
+    assert!(sprite.contains(
+        "<pattern id=\"home\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><path d=\"M10 20v-6h4v6\"/></pattern>"
+    ));
+    assert!(sprite.contains("<g transform=\"translate(24 0) scale(-1 1)\">"));
+    assert!(!sprite.contains("account"));
+
+    // Without an icon directory, only the collection is read
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).args([
+        "-d",
+        "missing",
+        "--input-iconify",
+        "mdi.json",
+        "-f",
+        "only-mdi.svg",
+    ]);
+    cmd.assert().success();
+    let sprite = fs::read_to_string(temp.child("only-mdi.svg").path()).unwrap();
+    assert!(sprite.contains("<pattern id=\"account\""));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn emit_iconify_round_trips_through_input_iconify() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("svgs/arrow.svg")
+        .write_str("<svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\" fill=\"none\"><path stroke=\"currentColor\" d=\"M5 12h14\"/></svg>")
+        .unwrap();
+    temp.child("svgs/dot.svg")
+        .write_str("<svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><circle cx=\"12\" cy=\"12\" r=\"2\"/></svg>")
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path())
+        .args(["--emit", "iconify=dist/acme.json"]);
+    cmd.assert().success();
+
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp.child("dist/acme.json").path()).unwrap())
+            .unwrap();
+    assert_eq!(json["prefix"], "acme");
+    assert_eq!(json["width"], 24);
+    assert_eq!(
+        json["icons"]["arrow"]["body"],
+        "<g fill=\"none\"><path stroke=\"currentColor\" d=\"M5 12h14\"/></g>"
+    );
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path()).args([
+        "-d",
+        "none",
+        "--input-iconify",
+        "dist/acme.json",
+        "-f",
+        "again.svg",
+    ]);
+    cmd.assert().success();
+    let sprite = fs::read_to_string(temp.child("again.svg").path()).unwrap();
+    assert!(sprite.contains(
+        "<pattern id=\"dot\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><circle cx=\"12\" cy=\"12\" r=\"2\"/></pattern>"
+    ));
+
+    temp.close().unwrap();
+}
+
+#[test]
+fn from_package_builds_prefixed_icons_from_node_modules() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let icon = "<svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\"><g/></svg>";
+    temp.child("web/svgs/logo.svg").write_str(icon).unwrap();
+    temp.child("node_modules/heroicons/24/outline/bell.svg")
+        .write_str(icon)
+        .unwrap();
+    temp.child("node_modules/heroicons/24/outline/home.svg")
+        .write_str(icon)
+        .unwrap();
+    temp.child("node_modules/@tabler/icons/icons/star.svg")
+        .write_str(icon)
+        .unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.child("web").path()).args([
+        "--from-package",
+        "heroicons/24/outline",
+        "--from-package",
+        "@tabler/icons/icons=tb",
+        "--only",
+        "logo",
+        "--only",
+        "heroicons-bell",
+        "--only",
+        "tb-star",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("3 icons"));
+    let sprite = fs::read_to_string(temp.child("web/sprite.svg").path()).unwrap();
+    for id in ["logo", "heroicons-bell", "tb-star"] {
+        assert!(sprite.contains(&format!("<pattern id=\"{id}\"")), "{id}");
+    }
+    assert!(!sprite.contains("home"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.child("web").path())
+        .args(["--from-package", "lucide-static/icons"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "package path 'lucide-static/icons' not found in any node_modules above",
+    ));
+
+    temp.close().unwrap();
+}
+
+/// Start `svg_sheet --poll watch --events json` in `dir` and wait for its
+/// first build.
+#[cfg(unix)]
+#[test]
+fn symlink_loops_are_logged_once_per_build() {
+    use std::os::unix::fs::symlink;
+    let temp = assert_fs::TempDir::new().expect("tempdir");
+    temp.child("svgs/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\"><g/></svg>")
+        .unwrap();
+    symlink(temp.child("svgs").path(), temp.child("svgs/loop").path()).unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("svg_sheet").expect("binary");
+    cmd.current_dir(temp.path());
+    let output = cmd
+        .args(["--recursive", "--follow-symlinks", "build"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(
+        stderr.matches("Skipping symlink loop").count(),
+        1,
+        "{stderr}"
+    );
+
+    temp.close().unwrap();
+}
+
+#[cfg(unix)]
+fn spawn_watch(dir: &std::path::Path) -> std::process::Child {
+    let child = std::process::Command::new(assert_cmd::cargo::cargo_bin("svg_sheet"))
+        .current_dir(dir)
+        .args(["--poll", "watch", "--events", "json"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("spawn watch");
+    let sprite = dir.join("sprite.svg");
+    for _ in 0..100 {
+        if sprite.exists() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    child
+}
+
+/// Send SIGINT to a watch started by `spawn_watch`; returns its stdout after
+/// checking it exited successfully.
+#[cfg(unix)]
+fn interrupt_watch(child: std::process::Child) -> String {
+    let killed = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .expect("kill");
+    assert!(killed.success());
+    let output = child.wait_with_output().expect("wait");
+    assert!(output.status.success(), "{:?}", output.status);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[cfg(unix)]
+#[test]
+fn watch_stops_on_sigint_with_a_session_summary() {
+    let temp = assert_fs::TempDir::new().expect("tempdir");
+    temp.child("svgs/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\"><g/></svg>")
+        .unwrap();
+
+    let stdout = interrupt_watch(spawn_watch(temp.path()));
+    assert!(
+        stdout.contains(r#"{"event":"watch_stopped","rebuilds":1,"failures":0,"#),
+        "{stdout}"
+    );
+
+    temp.close().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn watch_reloads_the_config_file() {
+    let temp = assert_fs::TempDir::new().expect("tempdir");
+    temp.child("svgs/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\"><path fill=\"#111111\" d=\"M0 0\"/></svg>")
+        .unwrap();
+
+    let child = spawn_watch(temp.path());
+    temp.child("svg_sheet.toml")
+        .write_str("[colors]\n\"#111111\" = \"currentColor\"\n")
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+    let stdout = interrupt_watch(child);
+
+    assert!(
+        stdout.contains(
+            r#"{"event":"config_reloaded","path":"svg_sheet.toml","changed":["colors"]}"#
+        ),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains(r#"{"event":"watch_stopped","rebuilds":2,"#),
+        "{stdout}"
+    );
+    let sprite = fs::read_to_string(temp.path().join("sprite.svg")).unwrap();
+    assert!(sprite.contains("fill=\"currentColor\""), "{sprite}");
+
+    temp.close().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn watch_rebuilds_on_sigusr1() {
+    let temp = assert_fs::TempDir::new().expect("tempdir");
+    temp.child("svgs/a.svg")
+        .write_str("<svg width=\"1\" height=\"1\"><g/></svg>")
+        .unwrap();
+
+    let child = spawn_watch(temp.path());
+    let signalled = std::process::Command::new("kill")
+        .args(["-USR1", &child.id().to_string()])
+        .status()
+        .expect("kill");
+    assert!(signalled.success());
+    std::thread::sleep(std::time::Duration::from_millis(1000));
+    let stdout = interrupt_watch(child);
+    assert!(
+        stdout.contains(r#"{"event":"watch_stopped","rebuilds":2,"failures":0,"#),
+        "{stdout}"
+    );
+
+    temp.close().unwrap();
+}